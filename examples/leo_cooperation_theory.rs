@@ -193,6 +193,7 @@ Possible solutions:
                  Use your memory blocks to track insights and build on previous discussions.",
             )
             .client(client.clone() as Arc<dyn LlmClient>)
+            .memory(game_theorist_memory.clone())
             .react_config(ReActConfig {
                 enable_reasoning_traces: true,
                 reasoning_format: ReasoningFormat::ThoughtAction,
@@ -254,6 +255,7 @@ Possible solutions:
                  Reference TCAS and real-world collision avoidance when discussing solutions.",
             )
             .client(client.clone() as Arc<dyn LlmClient>)
+            .memory(engineer_memory.clone())
             .react_config(ReActConfig {
                 enable_reasoning_traces: true,
                 reasoning_format: ReasoningFormat::ThoughtAction,
@@ -316,6 +318,7 @@ Possible solutions:
                  Consider international cooperation, liability, and enforcement mechanisms.",
             )
             .client(client.clone() as Arc<dyn LlmClient>)
+            .memory(policy_memory.clone())
             .react_config(ReActConfig {
                 enable_reasoning_traces: true,
                 reasoning_format: ReasoningFormat::ThoughtAction,
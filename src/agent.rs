@@ -1,16 +1,39 @@
 //! Agent implementation with ReAct loop
 
+use crate::budget::{self, BudgetLedger};
 use crate::config::ModelConfig;
 use crate::error::{Error, Result};
 use crate::guardrails::{GuardrailContext, InputGuardrail, OutputGuardrail};
+use crate::handoffs::{AgentRegistry, Handoff, HandoffContext, HandoffStrategy};
 use crate::llm_client::LlmClient;
-use crate::openrouter::{CompletionRequest, Message};
-use crate::react::{Action, Observation, ReActConfig, ReActTrace, Thought};
+use crate::memory::AgentMemory;
+use crate::memory_tools::create_memory_tools;
+use crate::openrouter::{
+    CompletionRequest, CompletionResponse, ContentPart, Message, ResponseFormat, StreamAccumulator,
+    ToolDefinition,
+};
+use crate::react::{Action, Observation, ReActConfig, ReActTrace, Thought, ToolCallingMode};
+use crate::react_parser::{self, ParsedStep};
 use crate::tools::{Tool, ToolContext};
+use crate::tracing_ext::{self, SpanType, Tracer};
 use crate::types::{AgentId, TokenUsage};
+use futures::future::BoxFuture;
+use futures::stream::{Stream, StreamExt};
 use parking_lot::RwLock;
+use serde::de::DeserializeOwned;
 use serde::{Deserialize, Serialize};
 use std::sync::Arc;
+use std::time::Instant;
+use tokio::sync::mpsc;
+
+/// Channel used to surface [`AgentEvent`]s while the ReAct loop runs
+#[derive(Clone)]
+struct EventSender {
+    tx: mpsc::UnboundedSender<AgentEvent>,
+    /// Whether completions are streamed to forward token deltas; off when
+    /// the events only serve as a progress signal
+    stream: bool,
+}
 
 /// Agent structure
 pub struct Agent<TContext = ()> {
@@ -26,6 +49,10 @@ pub struct Agent<TContext = ()> {
     pub tools: Vec<Arc<dyn Tool>>,
     /// Agents this agent can hand off to
     pub handoff_targets: Vec<AgentId>,
+    /// How control is transferred to handoff targets
+    pub handoff_strategy: HandoffStrategy,
+    /// Whether control returns to this agent after a direct or cascading handoff
+    pub handoff_return_control: bool,
     /// Input guardrails (run before processing)
     pub input_guardrails: Vec<Arc<dyn InputGuardrail>>,
     /// Output guardrails (run on final output)
@@ -36,12 +63,24 @@ pub struct Agent<TContext = ()> {
     pub temperature: f32,
     /// ReAct configuration for this agent
     pub react_config: ReActConfig,
+    /// How tools are offered to the model
+    pub tool_calling: ToolCallingMode,
     /// Shared context accessible across agent runs
     pub context: Arc<RwLock<TContext>>,
     /// Agent lifecycle hooks
     pub hooks: AgentHooks,
+    /// Re-prompts allowed when a typed answer fails validation
+    pub output_retries: u32,
+    /// Memory rendered into every prompt and edited through memory tools
+    pub memory: Option<Arc<AgentMemory>>,
     /// LLM client (OpenRouter, vLLM, etc.)
     client: Arc<dyn LlmClient>,
+    /// Registry used to resolve handoff targets
+    registry: Option<Arc<AgentRegistry>>,
+    /// Tracer that records runs started outside an existing trace
+    tracer: Option<Arc<Tracer>>,
+    /// Budget charged for every LLM call this agent makes
+    budget: Option<Arc<BudgetLedger>>,
 }
 
 impl Agent<()> {
@@ -65,10 +104,181 @@ where
 
     /// Execute the ReAct loop for the given input
     pub async fn react_loop(&self, input: &str) -> Result<AgentOutput> {
+        self.run_react(input, &[], None, HandoffScope::root(self.id), None, None).await
+    }
+
+    /// Execute the ReAct loop with prior conversation placed before the input
+    ///
+    /// `history` is inserted between the system prompt and the new user
+    /// message, so earlier turns are visible to the model.
+    pub async fn react_with_history(&self, history: &[Message], input: &str) -> Result<AgentOutput> {
+        self.run_react(input, history, None, HandoffScope::root(self.id), None, None).await
+    }
+
+    /// Execute the ReAct loop and parse the final answer as `T`
+    ///
+    /// The JSON Schema of `T` is sent as a `json_schema` response format, or
+    /// spelled out in the prompt under the text protocol. Answers that are
+    /// not valid JSON or break the schema are sent back with the violations,
+    /// up to [`Agent::output_retries`] times, before failing with
+    /// [`Error::JsonSchema`].
+    pub async fn run_typed<T>(&self, input: &str) -> Result<T>
+    where
+        T: schemars::JsonSchema + DeserializeOwned,
+    {
+        let schema = serde_json::to_value(schemars::schema_for!(T))?;
+        let validator = jsonschema::validator_for(&schema)
+            .map_err(|e| Error::JsonSchema(format!("invalid output schema: {}", e)))?;
+
+        let (mut prompt, format) = match self.tool_calling {
+            ToolCallingMode::Native => {
+                let name: String = T::schema_name()
+                    .chars()
+                    .map(|c| if c.is_ascii_alphanumeric() || c == '-' { c } else { '_' })
+                    .collect();
+                (input.to_string(), Some(ResponseFormat::json_schema(name, schema.clone())))
+            }
+            // A response format would also constrain the Thought/Action steps
+            ToolCallingMode::TextProtocol => (
+                format!(
+                    "{}\n\nGive your final answer as JSON matching this schema:\n{}",
+                    input, schema
+                ),
+                None,
+            ),
+        };
+
+        // Retries continue the same conversation, so tool results from the
+        // first attempt are reused rather than fetched again
+        let mut history = Vec::new();
+        let mut retries = 0;
+        loop {
+            let mut transcript = Vec::new();
+            let output = self
+                .run_react(
+                    &prompt,
+                    &history,
+                    None,
+                    HandoffScope::root(self.id),
+                    format.as_ref(),
+                    Some(&mut transcript),
+                )
+                .await?;
+
+            let violations = match parse_json_answer(&output.content) {
+                Ok(value) => {
+                    let violations = crate::tools::schema_violations(&validator, &value);
+                    if violations.is_empty() {
+                        return serde_json::from_value(value)
+                            .map_err(|e| Error::JsonSchema(e.to_string()));
+                    }
+                    violations.join("; ")
+                }
+                Err(e) => format!("invalid JSON: {}", e),
+            };
+
+            if retries >= self.output_retries {
+                return Err(Error::JsonSchema(violations));
+            }
+            retries += 1;
+
+            history = transcript;
+            prompt = format!(
+                "Your answer does not match the required JSON schema: {}\n\nReply again with only the corrected JSON.",
+                violations
+            );
+        }
+    }
+
+    /// LLM client used by this agent
+    pub fn client(&self) -> Arc<dyn LlmClient> {
+        self.client.clone()
+    }
+
+    /// Execute the ReAct loop, streaming progress as it happens
+    ///
+    /// LLM calls are made with [`LlmClient::stream`] so token deltas arrive as
+    /// they are generated. The stream ends with [`AgentEvent::FinalAnswer`], or
+    /// with the error that stopped the run.
+    pub fn react_stream<'a>(
+        &'a self,
+        input: &'a str,
+    ) -> impl Stream<Item = Result<AgentEvent>> + Send + 'a {
+        async_stream::stream! {
+            let (tx, mut rx) = mpsc::unbounded_channel();
+            let events = EventSender { tx, stream: true };
+            let run = self.run_react(input, &[], Some(events), HandoffScope::root(self.id), None, None);
+            futures::pin_mut!(run);
+
+            let result = loop {
+                tokio::select! {
+                    biased;
+                    Some(event) = rx.recv() => yield Ok(event),
+                    result = &mut run => break result,
+                }
+            };
+
+            // Flush anything emitted just before the run finished
+            while let Ok(event) = rx.try_recv() {
+                yield Ok(event);
+            }
+
+            match result {
+                Ok(output) => yield Ok(AgentEvent::FinalAnswer { output }),
+                Err(e) => yield Err(e),
+            }
+        }
+    }
+
+    /// Run the loop inside an agent-run span
+    ///
+    /// A run started outside any trace becomes the root of a new trace when
+    /// the agent has a tracer; otherwise it nests under the active span.
+    /// When `transcript` is given it receives the conversation that led to
+    /// the final answer, history included and system prompt excluded.
+    async fn run_react(
+        &self,
+        input: &str,
+        history: &[Message],
+        events: Option<EventSender>,
+        scope: HandoffScope,
+        response_format: Option<&ResponseFormat>,
+        transcript: Option<&mut Vec<Message>>,
+    ) -> Result<AgentOutput> {
+        let run = async {
+            tracing_ext::record("agent_id", serde_json::json!(self.id));
+            tracing_ext::record("model", serde_json::json!(self.model.model));
+            self.execute_react(input, history, events, scope, response_format, transcript).await
+        };
+
+        match &self.tracer {
+            Some(tracer) if tracing_ext::current_span_id().is_none() => {
+                tracer.trace(SpanType::AgentRun, &self.name, run).await
+            }
+            _ => tracing_ext::in_span(SpanType::AgentRun, &self.name, run).await,
+        }
+    }
+
+    async fn execute_react(
+        &self,
+        input: &str,
+        history: &[Message],
+        events: Option<EventSender>,
+        scope: HandoffScope,
+        response_format: Option<&ResponseFormat>,
+        transcript: Option<&mut Vec<Message>>,
+    ) -> Result<AgentOutput> {
+        let events = events.as_ref();
+
         // Check input guardrails
         let guardrail_ctx = GuardrailContext::new(self.id);
         for guardrail in &self.input_guardrails {
-            let result = guardrail.check(input, &guardrail_ctx).await?;
+            let result = tracing_ext::in_span(SpanType::GuardrailCheck, guardrail.id(), async {
+                let result = guardrail.check(input, &guardrail_ctx).await?;
+                tracing_ext::record("passed", serde_json::json!(result.passed));
+                Ok(result)
+            })
+            .await?;
             if !result.passed {
                 return Err(Error::guardrail_violation(
                     guardrail.id(),
@@ -77,61 +287,191 @@ where
             }
         }
 
+        if let Some(memory) = &self.memory {
+            memory.add_message("user".to_string(), input.to_string()).await;
+        }
+
+        let routes = self.handoff_routes()?;
         let mut trace = ReActTrace::new();
-        let mut messages = vec![
-            Message::system(&self.system_prompt),
-            Message::user(input),
-        ];
+        let mut messages = Vec::with_capacity(history.len() + 2);
+        messages.push(Message::system(self.effective_system_prompt(&routes).await));
+        messages.extend_from_slice(history);
+        messages.push(Message::user(input));
+
+        let mut wrapping_up = false;
+        for iteration in 0..self.max_loops {
+            if iteration > 0 && self.memory.is_some() {
+                // Memory tools may have edited blocks since the last call
+                messages[0] = Message::system(self.effective_system_prompt(&routes).await);
+            }
+            if !wrapping_up && self.ledgers().iter().any(|l| l.soft_limit_reached()) {
+                wrapping_up = true;
+                messages.push(Message::user(WRAP_UP_PROMPT));
+            }
 
-        for _iteration in 0..self.max_loops {
             // THOUGHT: Generate reasoning about current state
-            let thought = self.generate_thought(&messages).await?;
-            trace.add_thought(thought.clone());
-
-            // Parse the thought to determine the next action
-            let action = self.decide_action(&thought, &messages).await?;
-            trace.add_action(action.clone());
-
-            match action {
-                Action::ToolCall { tool_id, params, .. } => {
-                    // Execute tool and capture observation
-                    let observation = self.execute_tool(&tool_id, params).await?;
-                    trace.add_observation(observation.clone());
-
-                    // Add tool result to messages
-                    messages.push(Message::assistant(&thought.content));
-                    messages.push(Message::user(&observation.content));
+            let (thought, reply) = self
+                .generate_thought(&messages, &routes, events, response_format)
+                .await?;
+
+            // Parse the model's reply to determine the next actions
+            let actions = match self.tool_calling {
+                ToolCallingMode::Native => {
+                    emit(events, AgentEvent::ThoughtComplete { thought: thought.clone() });
+                    trace.add_thought(thought.clone());
+                    let actions = self.decide_actions(&reply, &routes);
+                    if reply.tool_calls.as_ref().is_some_and(|calls| !calls.is_empty()) {
+                        // The assistant turn must precede the tool results that answer it
+                        messages.push(reply);
+                    }
+                    actions
                 }
-                Action::Handoff { target_agent, reason, .. } => {
-                    // TODO: Implement handoff to another agent
-                    return Err(Error::handoff(format!(
-                        "Handoff to agent {} not yet implemented: {}",
-                        target_agent, reason
-                    )));
+                ToolCallingMode::TextProtocol => {
+                    let raw = react_parser::truncate_at_observation(&reply.content.text()).to_string();
+                    messages.push(Message::assistant(&raw));
+
+                    match self.parse_text_step(&raw, &routes) {
+                        Ok((step, action)) => {
+                            let thought = Thought::new(step.thought()).with_tokens(thought.tokens);
+                            emit(events, AgentEvent::ThoughtComplete { thought: thought.clone() });
+                            trace.add_thought(thought);
+                            vec![(action, None)]
+                        }
+                        Err(e) => {
+                            // Malformed output: ask the model to try again in the expected format
+                            emit(events, AgentEvent::ThoughtComplete { thought: thought.clone() });
+                            trace.add_thought(thought);
+                            messages.push(Message::user(react_parser::corrective_prompt(
+                                self.react_config.reasoning_format,
+                                &e,
+                            )));
+                            continue;
+                        }
+                    }
                 }
-                Action::FinalAnswer { answer, .. } => {
-                    // Complete the loop with final output
-                    trace.complete();
-                    let output = AgentOutput {
-                        agent_id: self.id,
-                        content: answer,
-                        trace,
-                        metadata: serde_json::json!({}),
-                    };
+            };
 
-                    // Check output guardrails
-                    for guardrail in &self.output_guardrails {
-                        let result = guardrail.check(&output, &guardrail_ctx).await?;
-                        if !result.passed {
-                            return Err(Error::guardrail_violation(
-                                guardrail.id(),
-                                result.reasoning,
-                            ));
+            let mut images = Vec::new();
+            for (action, rejection) in actions {
+                trace.add_action(action.clone());
+
+                let (observation, call_id) = match action {
+                    Action::ToolCall { tool_id, params, call_id, .. } => {
+                        emit(events, AgentEvent::ToolCallStarted {
+                            tool_id: tool_id.clone(),
+                            call_id: call_id.clone(),
+                            params: params.clone(),
+                        });
+
+                        // Execute tool and capture observation
+                        let started = Instant::now();
+                        let observation = match rejection {
+                            Some(observation) => observation,
+                            None => self.execute_tool(&tool_id, params).await?,
+                        };
+                        emit(events, AgentEvent::ToolCallFinished {
+                            tool_id: tool_id.clone(),
+                            call_id: call_id.clone(),
+                            observation: observation.clone(),
+                            duration_ms: started.elapsed().as_millis() as u64,
+                        });
+                        (observation, call_id)
+                    }
+                    Action::Handoff { target_agent, reason, call_id, .. } => {
+                        let route = routes
+                            .iter()
+                            .find(|route| route.target.id.to_string() == target_agent)
+                            .ok_or_else(|| {
+                                Error::handoff(format!("Unknown handoff target: {}", target_agent))
+                            })?;
+
+                        let context = trace.observations.iter().cloned().fold(
+                            HandoffContext::new(input)
+                                .with_trace(trace.clone())
+                                .with_metadata("source_agent", serde_json::json!(self.name)),
+                            HandoffContext::with_observation,
+                        );
+                        let handoff = Handoff::new(self.id, route.target.id, reason, context)
+                        .with_return_control(self.handoff_return_control);
+                        let returns_control = handoff.return_control
+                            || !matches!(
+                                self.handoff_strategy,
+                                HandoffStrategy::Direct | HandoffStrategy::Cascading { .. }
+                            );
+
+                        let span_name = format!("handoff to {}", route.target.name);
+                        let delegated = tracing_ext::in_span(SpanType::Handoff, span_name, async {
+                            tracing_ext::record("target", serde_json::json!(route.target.id));
+                            tracing_ext::record("reason", serde_json::json!(handoff.reason));
+                            self.perform_handoff(handoff, route.target.clone(), &scope, events)
+                                .await
+                        })
+                        .await?;
+
+                        if !returns_control {
+                            // The target owns the conversation from here on
+                            trace.complete();
+                            let output = AgentOutput {
+                                agent_id: delegated.agent_id,
+                                content: delegated.content,
+                                trace,
+                                metadata: serde_json::json!({
+                                    "handoff": {
+                                        "source": self.id,
+                                        "target": delegated.agent_id,
+                                        "target_name": route.target.name,
+                                        "target_trace": delegated.trace,
+                                    }
+                                }),
+                            };
+                            return self.finalize(output, &guardrail_ctx).await;
                         }
+
+                        let observation = Observation::new(format!(
+                            "{} responded: {}",
+                            route.target.name, delegated.content
+                        ));
+                        (observation, call_id)
                     }
+                    Action::FinalAnswer { answer, .. } => {
+                        // Complete the loop with final output
+                        trace.complete();
+                        if let Some(transcript) = transcript {
+                            if self.tool_calling == ToolCallingMode::Native {
+                                // The text protocol already recorded the raw reply
+                                messages.push(Message::assistant(&answer));
+                            }
+                            messages.remove(0);
+                            *transcript = messages;
+                        }
+                        let output = AgentOutput {
+                            agent_id: self.id,
+                            content: answer,
+                            trace,
+                            metadata: serde_json::json!({}),
+                        };
 
-                    return Ok(output);
-                }
+                        return self.finalize(output, &guardrail_ctx).await;
+                    }
+                };
+
+                emit(events, AgentEvent::Observation { observation: observation.clone() });
+                trace.add_observation(observation.clone());
+
+                // Return the result against the originating tool call, or as
+                // an observation line for the text protocol
+                messages.push(match call_id {
+                    Some(call_id) => Message::tool(&observation.content, call_id),
+                    None => Message::user(react_parser::observation(&observation.content)),
+                });
+                images.extend(observation.images.into_iter().map(|image_url| ContentPart::ImageUrl { image_url }));
+            }
+
+            // Tool results carry text only, so images follow in a user turn
+            if !images.is_empty() {
+                let mut parts = vec![ContentPart::text("Images returned by the tools above:")];
+                parts.extend(images);
+                messages.push(Message::user_parts(parts));
             }
         }
 
@@ -140,72 +480,299 @@ where
         Err(Error::MaxLoopsExceeded(self.max_loops))
     }
 
+    /// Run output guardrails over a final output
+    async fn finalize(&self, output: AgentOutput, guardrail_ctx: &GuardrailContext) -> Result<AgentOutput> {
+        for guardrail in &self.output_guardrails {
+            let result = tracing_ext::in_span(SpanType::GuardrailCheck, guardrail.id(), async {
+                let result = guardrail.check(&output, guardrail_ctx).await?;
+                tracing_ext::record("passed", serde_json::json!(result.passed));
+                Ok(result)
+            })
+            .await?;
+            if !result.passed {
+                return Err(Error::guardrail_violation(
+                    guardrail.id(),
+                    result.reasoning,
+                ));
+            }
+        }
+
+        if let Some(memory) = &self.memory {
+            memory.add_message("assistant".to_string(), output.content.clone()).await;
+        }
+
+        tracing_ext::record("iterations", serde_json::json!(output.trace.iteration_count()));
+        Ok(output)
+    }
+
     /// Generate a thought based on the current state
-    async fn generate_thought(&self, messages: &[Message]) -> Result<Thought> {
-        let request = CompletionRequest::new(&self.model.model, messages.to_vec())
+    ///
+    /// Returns the thought alongside the raw assistant message so that any
+    /// native tool calls can be resolved and echoed back to the model.
+    /// When `events` wants token deltas the completion is streamed and each
+    /// content delta is forwarded as an [`AgentEvent::TokenDelta`].
+    async fn generate_thought(
+        &self,
+        messages: &[Message],
+        routes: &[HandoffRoute],
+        events: Option<&EventSender>,
+        response_format: Option<&ResponseFormat>,
+    ) -> Result<(Thought, Message)> {
+        let mut request = CompletionRequest::new(&self.model.model, messages.to_vec())
             .with_temperature(self.temperature)
             .with_max_tokens(self.react_config.max_reasoning_tokens);
+        if let Some(format) = response_format {
+            request = request.with_response_format(format.clone());
+        }
 
-        let response = self.client.complete(request).await?;
+        match self.tool_calling {
+            ToolCallingMode::Native if !self.tools.is_empty() || !routes.is_empty() => {
+                request = request.with_tools(self.tool_definitions(routes));
+            }
+            ToolCallingMode::Native => {}
+            ToolCallingMode::TextProtocol => {
+                request = request.with_stop(vec![react_parser::OBSERVATION_STOP.to_string()]);
+            }
+        }
+
+        let ledgers = self.ledgers();
+        budget::check_all(&ledgers)?;
+
+        let generation = async {
+            tracing_ext::record("messages", serde_json::json!(request.messages.len()));
+            let response = match events.filter(|events| events.stream) {
+                Some(events) => self.stream_completion(request, events).await?,
+                None => self.client.complete(request).await?,
+            };
+            tracing_ext::record_tokens(TokenUsage::from(response.usage.clone()));
+            Ok((response, tracing_ext::current_span_id()))
+        };
+        let (response, span_id) =
+            tracing_ext::in_span(SpanType::LlmGeneration, &self.model.model, generation).await?;
 
-        let content = response
+        let reply = response
             .choices
-            .first()
-            .map(|choice| choice.message.content.clone())
-            .unwrap_or_default();
+            .into_iter()
+            .next()
+            .map(|choice| choice.message)
+            .unwrap_or_else(|| Message::assistant(""));
 
         let tokens = TokenUsage::from(response.usage);
 
-        Ok(Thought::new(content).with_tokens(tokens))
-    }
+        budget::charge_all(&ledgers, self.id, &self.name, &self.model.model, tokens)?;
 
-    /// Decide the next action based on the thought
-    async fn decide_action(&self, thought: &Thought, _messages: &[Message]) -> Result<Action> {
-        // Simple parsing logic - in production, this would be more sophisticated
-        let content = thought.content.to_lowercase();
+        // Models that reason separately from their answer keep it out of the content
+        let reasoning = reply.reasoning.clone().filter(|r| !r.trim().is_empty());
+        let mut thought = Thought::new(reasoning.unwrap_or_else(|| reply.content.to_string())).with_tokens(tokens);
+        if let Some(span_id) = span_id {
+            thought = thought.with_span_id(span_id);
+        }
 
-        // Check for final answer
-        if content.contains("final answer:") || content.contains("answer:") {
-            // Extract the answer after "final answer:" or "answer:"
-            let answer = if let Some(idx) = content.find("final answer:") {
-                thought.content[idx + 13..].trim().to_string()
-            } else if let Some(idx) = content.find("answer:") {
-                thought.content[idx + 7..].trim().to_string()
-            } else {
-                thought.content.clone()
-            };
+        Ok((thought, reply))
+    }
+
+    /// Stream a completion, forwarding content deltas as they arrive
+    async fn stream_completion(
+        &self,
+        request: CompletionRequest,
+        events: &EventSender,
+    ) -> Result<CompletionResponse> {
+        let mut stream = self.client.stream(request).await?;
+        let mut accumulator = StreamAccumulator::new();
 
-            return Ok(Action::final_answer(answer));
+        while let Some(chunk) = stream.next().await {
+            let chunk = chunk?;
+            for delta in chunk
+                .choices
+                .iter()
+                .filter(|c| c.index == 0)
+                .filter_map(|c| c.delta.content.as_deref())
+                .filter(|content| !content.is_empty())
+            {
+                emit(Some(events), AgentEvent::TokenDelta { content: delta.to_string() });
+            }
+            accumulator.push(&chunk);
         }
 
-        // Check for tool calls
-        if content.contains("action:") {
-            // Simple parsing - in production, would use function calling
-            if let Some(tool) = self.tools.first() {
-                return Ok(Action::tool_call(
-                    tool.id(),
-                    serde_json::json!({ "message": "test" }),
-                ));
+        Ok(accumulator.finish())
+    }
+
+    /// Decide the next actions based on the model's reply
+    ///
+    /// Native tool calls take precedence; a reply without tool calls is the
+    /// final answer. A call whose arguments are not valid JSON comes with the
+    /// error observation to return in place of running it, so the model can
+    /// correct itself like it does after a schema violation.
+    fn decide_actions(&self, reply: &Message, routes: &[HandoffRoute]) -> Vec<(Action, Option<Observation>)> {
+        let tool_calls = match reply.tool_calls.as_deref() {
+            Some(calls) if !calls.is_empty() => calls,
+            _ => return vec![(Action::final_answer(extract_final_answer(&reply.content.text())), None)],
+        };
+
+        tool_calls
+            .iter()
+            .map(|call| {
+                let arguments = call.function.arguments.trim();
+                let params = if arguments.is_empty() {
+                    serde_json::json!({})
+                } else {
+                    match serde_json::from_str(arguments) {
+                        Ok(params) => params,
+                        Err(e) => {
+                            let action = Action::tool_call_with_id(
+                                &call.id,
+                                &call.function.name,
+                                serde_json::Value::String(arguments.to_string()),
+                            );
+                            let observation = Observation::error(format!(
+                                "Invalid arguments for tool '{}': not valid JSON ({})",
+                                call.function.name, e
+                            ));
+                            return (action, Some(observation));
+                        }
+                    }
+                };
+
+                if let Some(route) = routes.iter().find(|r| r.tool_name == call.function.name) {
+                    let reason = handoff_reason(&params);
+                    return (Action::handoff_with_id(&call.id, route.target.id.to_string(), reason), None);
+                }
+
+                (Action::tool_call_with_id(&call.id, &call.function.name, params), None)
+            })
+            .collect()
+    }
+
+    /// System prompt sent to the model, including protocol instructions when
+    /// tools are driven through the text protocol
+    async fn effective_system_prompt(&self, routes: &[HandoffRoute]) -> String {
+        let mut prompt = self.system_prompt.clone();
+        if let Some(memory) = &self.memory {
+            let blocks = memory.render_blocks().await;
+            if !blocks.is_empty() {
+                prompt = format!("{}\n\n{}", prompt, blocks);
             }
         }
 
-        // Default to final answer if no action detected
-        Ok(Action::final_answer(&thought.content))
+        match self.tool_calling {
+            ToolCallingMode::Native => prompt,
+            ToolCallingMode::TextProtocol => format!(
+                "{}\n\n{}",
+                prompt,
+                react_parser::instructions(
+                    self.react_config.reasoning_format,
+                    &self.tool_definitions(routes)
+                )
+            ),
+        }
+    }
+
+    /// Ledgers charged for this agent's LLM calls
+    pub(crate) fn ledgers(&self) -> Vec<Arc<BudgetLedger>> {
+        budget::active_with(self.budget.as_ref())
+    }
+
+    /// Tool definitions offered to the model, including one transfer
+    /// function per handoff target
+    fn tool_definitions(&self, routes: &[HandoffRoute]) -> Vec<ToolDefinition> {
+        self.tools
+            .iter()
+            .map(|t| t.definition())
+            .chain(routes.iter().map(HandoffRoute::definition))
+            .collect()
+    }
+
+    /// Resolve `handoff_targets` against the registry
+    fn handoff_routes(&self) -> Result<Vec<HandoffRoute>> {
+        if self.handoff_targets.is_empty() {
+            return Ok(Vec::new());
+        }
+
+        let registry = self.registry.as_ref().ok_or_else(|| {
+            Error::handoff(format!("Agent '{}' has handoff targets but no registry", self.name))
+        })?;
+
+        self.handoff_targets
+            .iter()
+            .map(|id| {
+                let target = registry
+                    .get(*id)
+                    .ok_or_else(|| Error::handoff(format!("Handoff target {} is not registered", id)))?;
+                Ok(HandoffRoute {
+                    tool_name: format!("transfer_to_{}", slug(&target.name)),
+                    target,
+                })
+            })
+            .collect()
+    }
+
+    /// Parse a text-protocol reply into an action against a known tool
+    fn parse_text_step(&self, text: &str, routes: &[HandoffRoute]) -> Result<(ParsedStep, Action)> {
+        let step = react_parser::parse(self.react_config.reasoning_format, text)?;
+
+        let action = match &step {
+            ParsedStep::ToolCall { tool, input, .. } => {
+                if let Some(route) = routes.iter().find(|r| &r.tool_name == tool) {
+                    let action = Action::handoff(route.target.id.to_string(), handoff_reason(input));
+                    return Ok((step.clone(), action));
+                }
+                if !self.tools.iter().any(|t| t.id() == tool) {
+                    let available: Vec<&str> = self
+                        .tools
+                        .iter()
+                        .map(|t| t.id())
+                        .chain(routes.iter().map(|r| r.tool_name.as_str()))
+                        .collect();
+                    return Err(Error::InvalidInput(format!(
+                        "unknown tool '{}'; available tools: {}",
+                        tool,
+                        available.join(", ")
+                    )));
+                }
+                Action::tool_call(tool, input.clone())
+            }
+            ParsedStep::FinalAnswer { answer, .. } => Action::final_answer(answer),
+        };
+
+        Ok((step, action))
     }
 
     /// Execute a tool with the given parameters
+    ///
+    /// Parameters that fail the tool's input schema are reported back to the
+    /// model as an error observation instead of reaching the tool.
     async fn execute_tool(&self, tool_id: &str, params: serde_json::Value) -> Result<Observation> {
+        tracing_ext::in_span(SpanType::ToolCall, tool_id, async {
+            let observation = self.run_tool(tool_id, params).await?;
+            tracing_ext::record("is_error", serde_json::json!(observation.is_error));
+            Ok(observation)
+        })
+        .await
+    }
+
+    async fn run_tool(&self, tool_id: &str, params: serde_json::Value) -> Result<Observation> {
         let tool = self
             .tools
             .iter()
             .find(|t| t.id() == tool_id)
             .ok_or_else(|| Error::tool_execution(tool_id, "Tool not found"))?;
 
+        if let Err(e) = tool
+            .input_schema()
+            .validate(&params)
+            .and_then(|_| tool.validate(&params))
+        {
+            return Ok(Observation::error(format!(
+                "Invalid arguments for tool '{}': {}",
+                tool_id, e
+            )));
+        }
+
         let ctx = ToolContext::new(self.id);
         let output = tool.execute(params, &ctx).await?;
 
         if output.success {
-            Ok(Observation::new(&output.content))
+            Ok(Observation::new(&output.content).with_images(output.images))
         } else {
             Ok(Observation::error(
                 output.error.unwrap_or_else(|| "Unknown error".to_string()),
@@ -214,10 +781,249 @@ where
     }
 
     /// Perform a handoff to another agent
-    async fn perform_handoff(&self, _target_agent: AgentId, _trace: &ReActTrace) -> Result<AgentOutput> {
-        // TODO: Implement handoff logic
-        Err(Error::handoff("Handoff not yet implemented"))
+    ///
+    /// Runs the target on the rendered handoff context, enforcing loop and
+    /// depth limits from `scope`. Under [`HandoffStrategy::Supervised`] the
+    /// target is abandoned if it makes no progress for a full `check_interval`.
+    /// Its completions are only streamed if the caller streams too, so token
+    /// deltas count as progress only then.
+    fn perform_handoff<'a>(
+        &'a self,
+        handoff: Handoff,
+        target: Arc<Agent>,
+        scope: &'a HandoffScope,
+        events: Option<&'a EventSender>,
+    ) -> BoxFuture<'a, Result<AgentOutput>> {
+        Box::pin(async move {
+            let child_scope = scope.descend(self.id, &self.handoff_strategy, target.id)?;
+            let prompt = handoff.prompt(&self.name);
+
+            tracing::debug!(
+                source = %self.name,
+                target = %target.name,
+                depth = child_scope.depth(),
+                "performing handoff"
+            );
+
+            match self.handoff_strategy {
+                HandoffStrategy::Supervised { check_interval } => {
+                    let (tx, mut rx) = mpsc::unbounded_channel();
+                    let progress = EventSender {
+                        tx,
+                        stream: events.is_some_and(|events| events.stream),
+                    };
+                    let run = target.run_react(&prompt, &[], Some(progress), child_scope, None, None);
+                    futures::pin_mut!(run);
+
+                    let mut deadline = tokio::time::Instant::now() + check_interval;
+                    loop {
+                        tokio::select! {
+                            biased;
+                            result = &mut run => break result,
+                            Some(event) = rx.recv() => {
+                                // Progress resets the supervision window
+                                deadline = tokio::time::Instant::now() + check_interval;
+                                emit(events, event);
+                            }
+                            _ = tokio::time::sleep_until(deadline) => {
+                                break Err(Error::handoff(format!(
+                                    "Supervised handoff to '{}' made no progress within {:?}",
+                                    target.name, check_interval
+                                )));
+                            }
+                        }
+                    }
+                }
+                _ => target.run_react(&prompt, &[], events.cloned(), child_scope, None, None).await,
+            }
+        })
+    }
+}
+
+/// A handoff target resolved for the current run
+struct HandoffRoute {
+    /// Function name offered to the model
+    tool_name: String,
+    /// Agent receiving control
+    target: Arc<Agent>,
+}
+
+impl HandoffRoute {
+    fn definition(&self) -> ToolDefinition {
+        ToolDefinition::function(
+            &self.tool_name,
+            format!(
+                "Hand off the task to {}. Use when the request is better handled by that agent.",
+                self.target.name
+            ),
+            serde_json::json!({
+                "type": "object",
+                "properties": {
+                    "reason": {
+                        "type": "string",
+                        "description": "Why the task is being handed off"
+                    }
+                },
+                "required": ["reason"]
+            }),
+        )
+    }
+}
+
+/// Position of a run within a chain of handoffs
+#[derive(Debug, Clone)]
+struct HandoffScope {
+    /// Agents visited so far, starting with the root
+    chain: Vec<AgentId>,
+    /// Deepest handoff allowed for this run and its descendants
+    max_depth: u32,
+}
+
+impl HandoffScope {
+    fn root(id: AgentId) -> Self {
+        Self {
+            chain: vec![id],
+            max_depth: u32::MAX,
+        }
+    }
+
+    /// Number of handoffs that led to the current run
+    fn depth(&self) -> u32 {
+        self.chain.len().saturating_sub(1) as u32
+    }
+
+    /// Scope for a target, or the typed error if the hop is not allowed
+    ///
+    /// Only [`HandoffStrategy::Cascading`] lets the target delegate further.
+    fn descend(&self, source: AgentId, strategy: &HandoffStrategy, target: AgentId) -> Result<Self> {
+        if self.chain.contains(&target) {
+            let chain: Vec<String> = self
+                .chain
+                .iter()
+                .chain(std::iter::once(&target))
+                .map(|id| id.to_string())
+                .collect();
+            return Err(Error::HandoffLoop(chain.join(" -> ")));
+        }
+
+        let depth = self.depth() + 1;
+        let limit = match strategy {
+            HandoffStrategy::Cascading { max_depth } => self.max_depth.min(*max_depth),
+            _ => self.max_depth.min(depth),
+        };
+        if depth > limit {
+            return Err(Error::HandoffDepthExceeded { depth, max: limit });
+        }
+
+        let mut chain = self.chain.clone();
+        debug_assert_eq!(chain.last(), Some(&source));
+        chain.push(target);
+
+        Ok(Self {
+            chain,
+            max_depth: match strategy {
+                HandoffStrategy::Cascading { .. } => limit,
+                _ => depth,
+            },
+        })
+    }
+}
+
+/// Lowercase a name into a function-name-safe slug
+fn slug(name: &str) -> String {
+    let mut slug = String::with_capacity(name.len());
+    for c in name.chars() {
+        if c.is_ascii_alphanumeric() {
+            slug.push(c.to_ascii_lowercase());
+        } else if !slug.ends_with('_') {
+            slug.push('_');
+        }
+    }
+    slug.trim_matches('_').to_string()
+}
+
+/// Reason argument of a transfer call
+fn handoff_reason(params: &serde_json::Value) -> String {
+    params
+        .get("reason")
+        .and_then(|r| r.as_str())
+        .unwrap_or("No reason given")
+        .to_string()
+}
+
+/// Sent once when a token budget passes its soft limit
+const WRAP_UP_PROMPT: &str = "The token budget for this task is nearly used up. Do not start new \
+tool calls; give your final answer now based on what you already know.";
+
+/// Forward an event to a streaming consumer, if there is one
+fn emit(events: Option<&EventSender>, event: AgentEvent) {
+    if let Some(events) = events {
+        // A dropped receiver just means nobody is listening any more
+        let _ = events.tx.send(event);
+    }
+}
+
+/// Parse an answer as JSON, ignoring a surrounding Markdown code fence
+pub(crate) fn parse_json_answer(content: &str) -> serde_json::Result<serde_json::Value> {
+    let content = content.trim();
+    let unfenced = content
+        .strip_prefix("```")
+        .and_then(|rest| rest.strip_suffix("```"))
+        .map(|body| body.trim_start_matches("json").trim())
+        .unwrap_or(content);
+    serde_json::from_str(unfenced)
+}
+
+/// Strip a leading "Final Answer:" marker that some models emit out of habit
+fn extract_final_answer(content: &str) -> String {
+    const MARKER: &str = "final answer:";
+
+    content
+        .char_indices()
+        .find(|(idx, _)| {
+            content
+                .get(*idx..idx + MARKER.len())
+                .is_some_and(|candidate| candidate.eq_ignore_ascii_case(MARKER))
+        })
+        .map(|(idx, _)| content[idx + MARKER.len()..].trim().to_string())
+        .unwrap_or_else(|| content.trim().to_string())
+}
+
+/// Client configured by environment variables, if any, with its default model
+///
+/// OpenRouter is preferred, then local vLLM, Ollama and llama.cpp servers.
+/// Local servers are only used when their variable is set explicitly. They
+/// serve the models they have loaded, so their default model comes from
+/// `VLLM_MODEL`, `OLLAMA_MODEL` or `LLAMACPP_MODEL`; without it the default
+/// model is a configuration error.
+fn client_from_env() -> Option<(Arc<dyn LlmClient>, Result<String>)> {
+    if let Ok(client) = crate::openrouter::OpenRouterClient::from_env() {
+        return Some((Arc::new(client), Ok(crate::config::presets::BALANCED.to_string())));
+    }
+
+    let configured = |name: &str| std::env::var(name).ok().filter(|v| !v.trim().is_empty());
+    let model = |backend: &str, var: &str| {
+        configured(var).ok_or_else(|| {
+            Error::config(format!(
+                "No model set for the {} backend (call AgentBuilder::model or set {})",
+                backend, var
+            ))
+        })
+    };
+
+    if configured("VLLM_BASE_URL").is_some() {
+        let client = crate::vllm::VllmClient::from_env().ok()?;
+        return Some((Arc::new(client), model("vLLM", "VLLM_MODEL")));
     }
+    if configured("OLLAMA_HOST").is_some() {
+        let client = crate::ollama::OllamaClient::from_env().ok()?;
+        return Some((Arc::new(client), model("Ollama", "OLLAMA_MODEL")));
+    }
+    if configured("LLAMACPP_BASE_URL").is_some() {
+        let client = crate::llamacpp::LlamaCppClient::from_env().ok()?;
+        return Some((Arc::new(client), model("llama.cpp", "LLAMACPP_MODEL")));
+    }
+    None
 }
 
 /// Agent builder
@@ -227,13 +1033,21 @@ pub struct AgentBuilder<TContext = ()> {
     model: Option<String>,
     tools: Vec<Arc<dyn Tool>>,
     handoff_targets: Vec<AgentId>,
+    handoff_strategy: HandoffStrategy,
+    handoff_return_control: bool,
+    registry: Option<Arc<AgentRegistry>>,
+    tracer: Option<Arc<Tracer>>,
+    budget: Option<Arc<BudgetLedger>>,
     input_guardrails: Vec<Arc<dyn InputGuardrail>>,
     output_guardrails: Vec<Arc<dyn OutputGuardrail>>,
     max_loops: u32,
     temperature: f32,
     react_config: Option<ReActConfig>,
+    tool_calling: ToolCallingMode,
     context: Option<Arc<RwLock<TContext>>>,
     hooks: AgentHooks,
+    output_retries: u32,
+    memory: Option<Arc<AgentMemory>>,
     client: Option<Arc<dyn LlmClient>>,
 }
 
@@ -249,13 +1063,21 @@ where
             model: None,
             tools: Vec::new(),
             handoff_targets: Vec::new(),
+            handoff_strategy: HandoffStrategy::default(),
+            handoff_return_control: false,
+            registry: None,
+            tracer: None,
+            budget: None,
             input_guardrails: Vec::new(),
             output_guardrails: Vec::new(),
             max_loops: 10,
             temperature: 0.7,
             react_config: None,
+            tool_calling: ToolCallingMode::default(),
             context: None,
             hooks: AgentHooks::default(),
+            output_retries: 2,
+            memory: None,
             client: None,
         }
     }
@@ -296,6 +1118,38 @@ where
         self
     }
 
+    /// Set the handoff strategy
+    pub fn handoff_strategy(mut self, strategy: HandoffStrategy) -> Self {
+        self.handoff_strategy = strategy;
+        self
+    }
+
+    /// Set whether control returns to this agent after a handoff
+    ///
+    /// Collaborative and supervised handoffs always return control.
+    pub fn handoff_return_control(mut self, return_control: bool) -> Self {
+        self.handoff_return_control = return_control;
+        self
+    }
+
+    /// Set the registry used to resolve handoff targets
+    pub fn registry(mut self, registry: Arc<AgentRegistry>) -> Self {
+        self.registry = Some(registry);
+        self
+    }
+
+    /// Set the tracer used to record and export runs
+    pub fn tracer(mut self, tracer: Arc<Tracer>) -> Self {
+        self.tracer = Some(tracer);
+        self
+    }
+
+    /// Set a token budget charged for this agent's LLM calls
+    pub fn budget(mut self, budget: Arc<BudgetLedger>) -> Self {
+        self.budget = Some(budget);
+        self
+    }
+
     /// Add an input guardrail
     pub fn input_guardrail(mut self, guardrail: Arc<dyn InputGuardrail>) -> Self {
         self.input_guardrails.push(guardrail);
@@ -326,6 +1180,15 @@ where
         self
     }
 
+    /// Set how tools are offered to the model
+    ///
+    /// Use [`ToolCallingMode::TextProtocol`] for models served without
+    /// OpenAI-style tool calling support.
+    pub fn tool_calling(mut self, mode: ToolCallingMode) -> Self {
+        self.tool_calling = mode;
+        self
+    }
+
     /// Set the context
     pub fn context(mut self, context: Arc<RwLock<TContext>>) -> Self {
         self.context = Some(context);
@@ -338,6 +1201,24 @@ where
         self
     }
 
+    /// Set how many times a typed answer is re-prompted after failing validation
+    pub fn output_retries(mut self, retries: u32) -> Self {
+        self.output_retries = retries;
+        self
+    }
+
+    /// Give the agent memory
+    ///
+    /// In-context blocks are rendered into the system prompt on every LLM
+    /// call, the memory tools are registered, and each user input and final
+    /// answer is logged to the memory's message history. The agent takes the
+    /// memory's `agent_id` as its own id, so budget, audit and access records
+    /// refer to the same agent as the memory.
+    pub fn memory(mut self, memory: Arc<AgentMemory>) -> Self {
+        self.memory = Some(memory);
+        self
+    }
+
     /// Set the LLM client (OpenRouter, vLLM, etc.)
     pub fn client(mut self, client: Arc<dyn LlmClient>) -> Self {
         self.client = Some(client);
@@ -350,33 +1231,60 @@ where
         let system_prompt = self
             .system_prompt
             .ok_or_else(|| Error::config("System prompt is required"))?;
-        let model_name = self.model.unwrap_or_else(|| crate::config::presets::BALANCED.to_string());
-
-        let client = self
-            .client
-            .or_else(|| {
-                // Try OpenRouter as default
-                crate::openrouter::OpenRouterClient::from_env()
-                    .ok()
-                    .map(|c| Arc::new(c) as Arc<dyn LlmClient>)
-            })
-            .ok_or_else(|| Error::config("LLM client not configured (set OPENROUTER_API_KEY or VLLM_BASE_URL)"))?;
+
+        let (client, model_name) = match self.client {
+            Some(client) => {
+                let model = self.model.unwrap_or_else(|| crate::config::presets::BALANCED.to_string());
+                (client, model)
+            }
+            None => {
+                let (client, default_model) = client_from_env().ok_or_else(|| {
+                    Error::config(
+                        "LLM client not configured (set OPENROUTER_API_KEY, VLLM_BASE_URL, OLLAMA_HOST or LLAMACPP_BASE_URL)",
+                    )
+                })?;
+                let model = match self.model {
+                    Some(model) => model,
+                    None => default_model?,
+                };
+                (client, model)
+            }
+        };
+
+        let mut tools = self.tools;
+        if let Some(memory) = &self.memory {
+            for tool in create_memory_tools(memory.clone()) {
+                if !tools.iter().any(|t| t.id() == tool.id()) {
+                    tools.push(tool);
+                }
+            }
+        }
+
+        let id = self.memory.as_ref().map(|memory| memory.agent_id).unwrap_or_default();
 
         Ok(Agent {
-            id: AgentId::new(),
+            id,
             name,
             system_prompt,
             model: ModelConfig::new(model_name),
-            tools: self.tools,
+            tools,
             handoff_targets: self.handoff_targets,
+            handoff_strategy: self.handoff_strategy,
+            handoff_return_control: self.handoff_return_control,
             input_guardrails: self.input_guardrails,
             output_guardrails: self.output_guardrails,
             max_loops: self.max_loops,
             temperature: self.temperature,
             react_config: self.react_config.unwrap_or_default(),
+            tool_calling: self.tool_calling,
             context: self.context.unwrap_or_else(|| Arc::new(RwLock::new(TContext::default()))),
             hooks: self.hooks,
+            output_retries: self.output_retries,
+            memory: self.memory,
             client,
+            registry: self.registry,
+            tracer: self.tracer,
+            budget: self.budget,
         })
     }
 }
@@ -421,6 +1329,52 @@ impl AgentOutput {
     }
 }
 
+/// Progress event emitted by [`Agent::react_stream`]
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(tag = "type", rename_all = "snake_case")]
+pub enum AgentEvent {
+    /// Incremental content generated by the model
+    TokenDelta {
+        /// Content fragment
+        content: String,
+    },
+    /// A reasoning step finished generating
+    ThoughtComplete {
+        /// The completed thought
+        thought: Thought,
+    },
+    /// A tool is about to run
+    ToolCallStarted {
+        /// Tool identifier
+        tool_id: String,
+        /// Provider-assigned tool call ID (native tool calling only)
+        call_id: Option<String>,
+        /// Tool parameters
+        params: serde_json::Value,
+    },
+    /// A tool finished running
+    ToolCallFinished {
+        /// Tool identifier
+        tool_id: String,
+        /// Provider-assigned tool call ID (native tool calling only)
+        call_id: Option<String>,
+        /// What the tool returned
+        observation: Observation,
+        /// Wall-clock execution time in milliseconds
+        duration_ms: u64,
+    },
+    /// An observation was added to the conversation
+    Observation {
+        /// The observation
+        observation: Observation,
+    },
+    /// The agent produced its final output
+    FinalAnswer {
+        /// The final output, after output guardrails
+        output: AgentOutput,
+    },
+}
+
 /// Agent lifecycle hooks
 #[derive(Clone, Default)]
 pub struct AgentHooks {
@@ -441,3 +1395,798 @@ impl std::fmt::Debug for AgentHooks {
             .finish()
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::llm_client::testing::ScriptedClient;
+    use crate::tracing_ext::testing::CollectingExporter;
+    use crate::openrouter::{FunctionCall, Role, ToolCall};
+    use crate::openrouter::ImageUrl;
+    use crate::tools::{calculator_tool, echo_tool, JsonSchema, Tool, ToolContext, ToolOutput};
+    use std::time::Duration;
+    use async_trait::async_trait;
+
+    fn tool_call(id: &str, name: &str, arguments: &str) -> ToolCall {
+        ToolCall {
+            id: id.to_string(),
+            tool_type: "function".to_string(),
+            function: FunctionCall {
+                name: name.to_string(),
+                arguments: arguments.to_string(),
+            },
+        }
+    }
+
+    fn agent_with(client: Arc<ScriptedClient>) -> Agent {
+        Agent::builder()
+            .name("Test Agent")
+            .system_prompt("You are a test agent.")
+            .model("test")
+            .tool(echo_tool())
+            .tool(calculator_tool())
+            .client(client)
+            .build()
+            .unwrap()
+    }
+
+    #[tokio::test]
+    async fn test_native_tool_call_round_trip() {
+        let client = Arc::new(ScriptedClient::new(vec![
+            Message::assistant_with_tool_calls(
+                "",
+                vec![tool_call(
+                    "call_1",
+                    "calculator",
+                    r#"{"operation":"multiply","a":6,"b":7}"#,
+                )],
+            ),
+            Message::assistant("The product is 42."),
+        ]));
+        let agent = agent_with(client.clone());
+
+        let output = agent.react_loop("What is 6 * 7?").await.unwrap();
+        assert_eq!(output.content, "The product is 42.");
+        assert_eq!(output.trace.observations.len(), 1);
+        assert!(output.trace.observations[0].content.contains("42"));
+
+        let requests = client.requests();
+        let tools = requests[0].tools.as_ref().unwrap();
+        let names: Vec<&str> = tools.iter().map(|t| t.function.name.as_str()).collect();
+        assert_eq!(names, vec!["echo", "calculator"]);
+
+        // Second request carries the assistant tool call and the matching tool result
+        let follow_up = &requests[1].messages;
+        let assistant = &follow_up[follow_up.len() - 2];
+        assert!(matches!(assistant.role, Role::Assistant));
+        assert_eq!(assistant.tool_calls.as_ref().unwrap()[0].id, "call_1");
+        let result = follow_up.last().unwrap();
+        assert!(matches!(result.role, Role::Tool));
+        assert_eq!(result.tool_call_id.as_deref(), Some("call_1"));
+    }
+
+    #[tokio::test]
+    async fn test_parallel_tool_calls_dispatch_to_named_tools() {
+        let client = Arc::new(ScriptedClient::new(vec![
+            Message::assistant_with_tool_calls(
+                "",
+                vec![
+                    tool_call("call_a", "echo", r#"{"message":"hello"}"#),
+                    tool_call("call_b", "calculator", r#"{"operation":"add","a":1,"b":2}"#),
+                ],
+            ),
+            Message::assistant("Final Answer: done"),
+        ]));
+        let agent = agent_with(client.clone());
+
+        let output = agent.react_loop("Do both").await.unwrap();
+        assert_eq!(output.content, "done");
+        assert_eq!(output.trace.observations[0].content, "Echo: hello");
+        assert!(output.trace.observations[1].content.contains("= 3"));
+
+        let requests = client.requests();
+        let ids: Vec<_> = requests[1]
+            .messages
+            .iter()
+            .filter_map(|m| m.tool_call_id.as_deref())
+            .collect();
+        assert_eq!(ids, vec!["call_a", "call_b"]);
+    }
+
+    #[tokio::test]
+    async fn test_malformed_tool_arguments_reported_as_observation() {
+        let client = Arc::new(ScriptedClient::new(vec![
+            Message::assistant_with_tool_calls("", vec![tool_call("call_1", "echo", r#"{"message": "hi"#)]),
+            Message::assistant_with_tool_calls("", vec![tool_call("call_2", "echo", r#"{"message": "hi"}"#)]),
+            Message::assistant("Final Answer: Echo: hi"),
+        ]));
+        let agent = agent_with(client.clone());
+
+        let output = agent.react_loop("Echo hi").await.unwrap();
+        assert_eq!(output.content, "Echo: hi");
+        let observations = &output.trace.observations;
+        assert!(observations[0].is_error);
+        assert!(observations[0].content.contains("not valid JSON"));
+        assert_eq!(observations[1].content, "Echo: hi");
+
+        // The rejection answers the malformed call so the model can retry
+        let requests = client.requests();
+        let result = requests[1].messages.last().unwrap();
+        assert_eq!(result.tool_call_id.as_deref(), Some("call_1"));
+        assert!(result.content.text().contains("Invalid arguments for tool 'echo'"));
+    }
+
+    #[tokio::test]
+    async fn test_schema_violation_reported_as_observation() {
+        let client = Arc::new(ScriptedClient::new(vec![
+            Message::assistant_with_tool_calls(
+                "",
+                vec![tool_call("call_1", "calculator", r#"{"operation":"add","a":1}"#)],
+            ),
+            Message::assistant("I need both operands."),
+        ]));
+        let agent = agent_with(client);
+
+        let output = agent.react_loop("Add one").await.unwrap();
+        let observation = &output.trace.observations[0];
+        assert!(observation.is_error);
+        assert!(observation.content.contains("\"b\" is a required property"));
+    }
+
+    #[tokio::test]
+    async fn test_text_protocol_recovers_from_malformed_output() {
+        let client = Arc::new(ScriptedClient::new(vec![
+            Message::assistant("Let me think about this."),
+            Message::assistant(
+                "Thought: I should echo it\nAction: echo\nAction Input: {\"message\": \"hi\"}",
+            ),
+            Message::assistant("Thought: I have the echo\nFinal Answer: Echo: hi"),
+        ]));
+        let agent = Agent::builder()
+            .name("Text Agent")
+            .system_prompt("You are a test agent.")
+            .model("test")
+            .tool(echo_tool())
+            .tool_calling(ToolCallingMode::TextProtocol)
+            .client(client.clone())
+            .build()
+            .unwrap();
+
+        let output = agent.react_loop("Echo hi").await.unwrap();
+        assert_eq!(output.content, "Echo: hi");
+        assert_eq!(output.trace.observations[0].content, "Echo: hi");
+        assert_eq!(output.trace.thoughts[1].content, "I should echo it");
+
+        let requests = client.requests();
+        assert!(requests[0].tools.is_none());
+        assert!(requests[0].messages[0].content.text().contains("Action Input:"));
+        assert!(requests[1]
+            .messages
+            .last()
+            .unwrap()
+            .content
+            .text()
+            .starts_with("Your previous response could not be processed"));
+        assert_eq!(
+            requests[2].messages.last().unwrap().content,
+            "Observation: Echo: hi"
+        );
+    }
+
+    #[tokio::test]
+    async fn test_react_stream_events() {
+        let client = Arc::new(ScriptedClient::new(vec![
+            Message::assistant_with_tool_calls(
+                "Let me echo that.",
+                vec![tool_call("call_1", "echo", r#"{"message":"hi"}"#)],
+            ),
+            Message::assistant("All done."),
+        ]));
+        let agent = agent_with(client);
+
+        let events: Vec<AgentEvent> = agent
+            .react_stream("Echo hi")
+            .map(|event| event.unwrap())
+            .collect()
+            .await;
+
+        let kinds: Vec<&str> = events
+            .iter()
+            .map(|event| match event {
+                AgentEvent::TokenDelta { .. } => "token",
+                AgentEvent::ThoughtComplete { .. } => "thought",
+                AgentEvent::ToolCallStarted { .. } => "tool_started",
+                AgentEvent::ToolCallFinished { .. } => "tool_finished",
+                AgentEvent::Observation { .. } => "observation",
+                AgentEvent::FinalAnswer { .. } => "final",
+            })
+            .collect();
+        assert_eq!(
+            kinds,
+            vec![
+                "token",
+                "thought",
+                "tool_started",
+                "tool_finished",
+                "observation",
+                "token",
+                "thought",
+                "final"
+            ]
+        );
+
+        match &events[3] {
+            AgentEvent::ToolCallFinished { call_id, observation, .. } => {
+                assert_eq!(call_id.as_deref(), Some("call_1"));
+                assert_eq!(observation.content, "Echo: hi");
+            }
+            other => panic!("unexpected event: {:?}", other),
+        }
+        match events.last().unwrap() {
+            AgentEvent::FinalAnswer { output } => assert_eq!(output.content, "All done."),
+            other => panic!("unexpected event: {:?}", other),
+        }
+    }
+
+    #[tokio::test]
+    async fn test_react_stream_surfaces_errors() {
+        let client = Arc::new(ScriptedClient::new(vec![Message::assistant_with_tool_calls(
+            "",
+            vec![tool_call("call_1", "missing_tool", "{}")],
+        )]));
+        let agent = agent_with(client);
+
+        let events: Vec<Result<AgentEvent>> = agent.react_stream("Use it").collect().await;
+        assert!(matches!(events.last(), Some(Err(Error::ToolExecution { .. }))));
+    }
+
+    fn transfer(id: &str, target: &str) -> Message {
+        Message::assistant_with_tool_calls(
+            "",
+            vec![tool_call(id, &format!("transfer_to_{}", target), r#"{"reason":"specialist"}"#)],
+        )
+    }
+
+    fn plain_agent(name: &str, client: Arc<ScriptedClient>) -> AgentBuilder {
+        Agent::builder()
+            .name(name)
+            .system_prompt("You are a test agent.")
+            .model("test")
+            .client(client)
+    }
+
+    #[tokio::test]
+    async fn test_direct_handoff_transfers_control() {
+        let registry = Arc::new(AgentRegistry::new());
+        let billing_client = Arc::new(ScriptedClient::new(vec![Message::assistant("Refund issued.")]));
+        let billing = plain_agent("Billing", billing_client.clone()).build().unwrap();
+        let billing_id = registry.register(Arc::new(billing));
+
+        let triage_client = Arc::new(ScriptedClient::new(vec![transfer("call_1", "billing")]));
+        let triage = plain_agent("Triage", triage_client.clone())
+            .handoff_target(billing_id)
+            .registry(registry)
+            .build()
+            .unwrap();
+
+        let output = triage.react_loop("I want a refund").await.unwrap();
+        assert_eq!(output.content, "Refund issued.");
+        assert_eq!(output.agent_id, billing_id);
+        assert!(matches!(output.trace.actions[0], Action::Handoff { .. }));
+
+        let offered = triage_client.requests()[0].tools.clone().unwrap();
+        assert_eq!(offered[0].function.name, "transfer_to_billing");
+
+        let prompt = billing_client.requests()[0].messages[1].content.to_string();
+        assert!(prompt.starts_with("I want a refund"));
+        assert!(prompt.contains("## Handoff from Triage"));
+    }
+
+    #[tokio::test]
+    async fn test_handoff_returns_control() {
+        let registry = Arc::new(AgentRegistry::new());
+        let billing = plain_agent(
+            "Billing",
+            Arc::new(ScriptedClient::new(vec![Message::assistant("Refund issued.")])),
+        )
+        .build()
+        .unwrap();
+        let billing_id = registry.register(Arc::new(billing));
+
+        let triage_client = Arc::new(ScriptedClient::new(vec![
+            transfer("call_1", "billing"),
+            Message::assistant("Billing has issued your refund."),
+        ]));
+        let triage = plain_agent("Triage", triage_client.clone())
+            .handoff_target(billing_id)
+            .handoff_return_control(true)
+            .registry(registry)
+            .build()
+            .unwrap();
+
+        let output = triage.react_loop("I want a refund").await.unwrap();
+        assert_eq!(output.content, "Billing has issued your refund.");
+        assert_eq!(output.agent_id, triage.id);
+        assert_eq!(output.trace.observations[0].content, "Billing responded: Refund issued.");
+
+        let requests = triage_client.requests();
+        let result = requests[1].messages.last().unwrap();
+        assert_eq!(result.tool_call_id.as_deref(), Some("call_1"));
+    }
+
+    #[tokio::test]
+    async fn test_handoff_loop_detected() {
+        let registry = Arc::new(AgentRegistry::new());
+        let strategy = HandoffStrategy::Cascading { max_depth: 5 };
+
+        let mut a = plain_agent("Alpha", Arc::new(ScriptedClient::new(vec![transfer("call_1", "beta")])))
+            .handoff_strategy(strategy.clone())
+            .registry(registry.clone())
+            .build()
+            .unwrap();
+        let mut b = plain_agent("Beta", Arc::new(ScriptedClient::new(vec![transfer("call_2", "alpha")])))
+            .handoff_strategy(strategy)
+            .registry(registry.clone())
+            .build()
+            .unwrap();
+        a.handoff_targets.push(b.id);
+        b.handoff_targets.push(a.id);
+        let a = Arc::new(a);
+        registry.register(a.clone());
+        registry.register(Arc::new(b));
+
+        let err = a.react_loop("Go").await.unwrap_err();
+        assert!(matches!(err, Error::HandoffLoop(_)), "unexpected error: {err}");
+    }
+
+    #[tokio::test]
+    async fn test_handoff_depth_exceeded() {
+        let registry = Arc::new(AgentRegistry::new());
+        let leaf = plain_agent("Leaf", Arc::new(ScriptedClient::new(vec![Message::assistant("done")])))
+            .build()
+            .unwrap();
+        let leaf_id = registry.register(Arc::new(leaf));
+
+        // Direct handoffs do not allow the target to delegate again
+        let middle = plain_agent("Middle", Arc::new(ScriptedClient::new(vec![transfer("call_2", "leaf")])))
+            .handoff_target(leaf_id)
+            .registry(registry.clone())
+            .build()
+            .unwrap();
+        let middle_id = registry.register(Arc::new(middle));
+
+        let root = plain_agent("Root", Arc::new(ScriptedClient::new(vec![transfer("call_1", "middle")])))
+            .handoff_target(middle_id)
+            .registry(registry)
+            .build()
+            .unwrap();
+
+        let err = root.react_loop("Go").await.unwrap_err();
+        assert!(matches!(err, Error::HandoffDepthExceeded { depth: 2, max: 1 }), "unexpected error: {err}");
+    }
+
+    #[tokio::test]
+    async fn test_supervised_handoff_times_out() {
+        let registry = Arc::new(AgentRegistry::new());
+        let slow_client = ScriptedClient::new(vec![Message::assistant("late")])
+            .with_delay(Duration::from_millis(500));
+        let slow = plain_agent("Slow", Arc::new(slow_client)).build().unwrap();
+        let slow_id = registry.register(Arc::new(slow));
+
+        let supervisor = plain_agent("Supervisor", Arc::new(ScriptedClient::new(vec![transfer("call_1", "slow")])))
+            .handoff_target(slow_id)
+            .handoff_strategy(HandoffStrategy::Supervised {
+                check_interval: Duration::from_millis(20),
+            })
+            .registry(registry)
+            .build()
+            .unwrap();
+
+        let err = supervisor.react_loop("Go").await.unwrap_err();
+        assert!(matches!(err, Error::Handoff(_)), "unexpected error: {err}");
+    }
+
+    #[tokio::test]
+    async fn test_supervised_handoff_streams_only_when_the_caller_does() {
+        let registry = Arc::new(AgentRegistry::new());
+        let target_client = Arc::new(ScriptedClient::repeating("Final Answer: done"));
+        let target = plain_agent("Target", target_client.clone()).build().unwrap();
+        let target_id = registry.register(Arc::new(target));
+
+        // Supervised handoffs return control, so each run ends with the supervisor's answer
+        let supervisor_client = ScriptedClient::new(vec![
+            transfer("call_1", "target"),
+            Message::assistant("Final Answer: checked"),
+            transfer("call_2", "target"),
+            Message::assistant("Final Answer: checked"),
+        ]);
+        let supervisor = plain_agent("Supervisor", Arc::new(supervisor_client))
+            .handoff_target(target_id)
+            .handoff_strategy(HandoffStrategy::Supervised {
+                check_interval: Duration::from_secs(5),
+            })
+            .registry(registry)
+            .build()
+            .unwrap();
+
+        supervisor.react_loop("Go").await.unwrap();
+        assert_eq!((target_client.calls(), target_client.streams()), (1, 0));
+
+        let events: Vec<Result<AgentEvent>> = supervisor.react_stream("Go").collect().await;
+        assert!(matches!(events.last(), Some(Ok(AgentEvent::FinalAnswer { .. }))));
+        assert_eq!((target_client.calls(), target_client.streams()), (2, 1));
+    }
+
+    #[tokio::test]
+    async fn test_run_records_nested_spans() {
+        let exporter = Arc::new(CollectingExporter::default());
+        let tracer = Arc::new(Tracer::new().with_exporter(exporter.clone()));
+        let client = Arc::new(ScriptedClient::new(vec![
+            Message::assistant_with_tool_calls("", vec![tool_call("call_1", "echo", r#"{"message":"hi"}"#)]),
+            Message::assistant("Done."),
+        ]));
+        let agent = Agent::builder()
+            .name("Traced")
+            .system_prompt("You are a test agent.")
+            .model("test")
+            .tool(echo_tool())
+            .tracer(tracer)
+            .client(client)
+            .build()
+            .unwrap();
+
+        let output = agent.react_loop("Echo hi").await.unwrap();
+
+        let traces = exporter.traces.lock();
+        assert_eq!(traces.len(), 1);
+        let trace = &traces[0];
+        assert_eq!(trace.total_tokens.total_tokens, 30);
+
+        let root = &trace.root_span;
+        assert!(matches!(root.span_type, SpanType::AgentRun));
+        assert_eq!(root.data.data["iterations"], 2);
+        let kinds: Vec<String> = root
+            .children
+            .iter()
+            .map(|span| serde_json::to_value(&span.span_type).unwrap().to_string())
+            .collect();
+        assert_eq!(kinds, [r#""llm_generation""#, r#""tool_call""#, r#""llm_generation""#]);
+        assert_eq!(output.trace.thoughts[0].span_id, Some(root.children[0].span_id));
+    }
+
+    #[tokio::test]
+    async fn test_hard_budget_aborts_run() {
+        let ledger = Arc::new(BudgetLedger::new().with_hard_limit(20));
+        let client = Arc::new(ScriptedClient::new(vec![
+            Message::assistant_with_tool_calls("", vec![tool_call("call_1", "echo", r#"{"message":"a"}"#)]),
+            Message::assistant_with_tool_calls("", vec![tool_call("call_2", "echo", r#"{"message":"b"}"#)]),
+        ]));
+        let agent = Agent::builder()
+            .name("Spender")
+            .system_prompt("You are a test agent.")
+            .model("test")
+            .tool(echo_tool())
+            .budget(ledger.clone())
+            .client(client.clone())
+            .build()
+            .unwrap();
+
+        let err = agent.react_loop("Go").await.unwrap_err();
+        assert!(matches!(err, Error::BudgetExceeded { used: 30, limit: 20 }), "unexpected error: {err}");
+        assert_eq!(client.requests().len(), 2);
+        assert_eq!(ledger.report().by_name("Spender").unwrap().calls, 2);
+    }
+
+    #[tokio::test]
+    async fn test_soft_budget_asks_to_wrap_up() {
+        let ledger = Arc::new(BudgetLedger::new().with_soft_limit(10));
+        let client = Arc::new(ScriptedClient::new(vec![
+            Message::assistant_with_tool_calls("", vec![tool_call("call_1", "echo", r#"{"message":"a"}"#)]),
+            Message::assistant("Wrapping up."),
+        ]));
+        let agent = Agent::builder()
+            .name("Spender")
+            .system_prompt("You are a test agent.")
+            .model("test")
+            .tool(echo_tool())
+            .client(client.clone())
+            .build()
+            .unwrap();
+
+        let output = budget::scope(ledger, agent.react_loop("Go")).await.unwrap();
+        assert_eq!(output.content, "Wrapping up.");
+
+        let requests = client.requests();
+        assert_eq!(requests[1].messages.last().unwrap().content, WRAP_UP_PROMPT);
+    }
+
+    struct ScreenshotTool;
+
+    #[async_trait]
+    impl Tool for ScreenshotTool {
+        fn id(&self) -> &str {
+            "screenshot"
+        }
+
+        fn name(&self) -> &str {
+            "Screenshot"
+        }
+
+        fn description(&self) -> &str {
+            "Captures the screen"
+        }
+
+        fn input_schema(&self) -> JsonSchema {
+            JsonSchema::object(Default::default())
+        }
+
+        async fn execute(&self, _params: serde_json::Value, _ctx: &ToolContext) -> Result<ToolOutput> {
+            Ok(ToolOutput::success("Captured the screen")
+                .with_image(ImageUrl::base64("image/png", "iVBORw0KGgo=")))
+        }
+    }
+
+    #[tokio::test]
+    async fn test_tool_images_forwarded_to_next_turn() {
+        let client = Arc::new(ScriptedClient::new(vec![
+            Message::assistant_with_tool_calls("", vec![tool_call("call_1", "screenshot", "{}")]),
+            Message::assistant("The screen is blank."),
+        ]));
+        let agent = Agent::builder()
+            .name("Viewer")
+            .system_prompt("Describe the screen.")
+            .model("test")
+            .tool(Arc::new(ScreenshotTool))
+            .client(client.clone())
+            .build()
+            .unwrap();
+
+        agent.react_loop("What is on screen?").await.unwrap();
+
+        let requests = client.requests();
+        let follow_up = &requests[1].messages;
+        assert_eq!(follow_up[follow_up.len() - 2].content, "Captured the screen");
+        let parts = follow_up.last().unwrap().content.parts();
+        assert_eq!(parts.len(), 2);
+        let ContentPart::ImageUrl { image_url } = &parts[1] else {
+            panic!("expected an image part");
+        };
+        assert_eq!(image_url.url, "data:image/png;base64,iVBORw0KGgo=");
+    }
+
+    #[derive(Debug, Deserialize, schemars::JsonSchema)]
+    struct Invoice {
+        number: String,
+        total: f64,
+    }
+
+    #[tokio::test]
+    async fn test_run_typed_reprompts_until_valid() {
+        let client = Arc::new(ScriptedClient::new(vec![
+            Message::assistant_with_tool_calls(
+                "",
+                vec![tool_call("call_1", "echo", r#"{"message": "INV-7 for 12.5"}"#)],
+            ),
+            Message::assistant(r#"{"number": "INV-7"}"#),
+            Message::assistant("```json\n{\"number\": \"INV-7\", \"total\": 12.5}\n```"),
+        ]));
+        let agent = agent_with(client.clone());
+
+        let invoice: Invoice = agent.run_typed("Extract the invoice").await.unwrap();
+        assert_eq!(invoice.number, "INV-7");
+        assert_eq!(invoice.total, 12.5);
+
+        let requests = client.requests();
+        assert_eq!(requests.len(), 3);
+        let format = requests[0].response_format.as_ref().unwrap();
+        assert_eq!(format.schema().unwrap()["required"], serde_json::json!(["number", "total"]));
+
+        // The retry continues the conversation instead of starting over
+        let retry = &requests[2].messages;
+        let tool_results = retry.iter().filter(|m| matches!(m.role, Role::Tool)).count();
+        assert_eq!(tool_results, 1);
+        assert_eq!(retry[retry.len() - 2].content.to_string(), r#"{"number": "INV-7"}"#);
+        let reprompt = retry.last().unwrap().content.to_string();
+        assert!(reprompt.contains("total"), "{}", reprompt);
+    }
+
+    #[tokio::test]
+    async fn test_run_typed_gives_up_with_violations() {
+        let client = Arc::new(ScriptedClient::new(vec![
+            Message::assistant("not json"),
+            Message::assistant(r#"{"number": 7, "total": 1}"#),
+        ]));
+        let agent = Agent::builder()
+            .name("Extractor")
+            .system_prompt("Extract invoices.")
+            .model("test")
+            .output_retries(1)
+            .client(client.clone())
+            .build()
+            .unwrap();
+
+        let err = agent.run_typed::<Invoice>("Extract the invoice").await.unwrap_err();
+        let Error::JsonSchema(violations) = err else {
+            panic!("expected a schema error, got {:?}", err);
+        };
+        assert!(violations.contains("/number"), "{}", violations);
+        assert_eq!(client.requests().len(), 2);
+    }
+
+    #[tokio::test]
+    async fn test_memory_blocks_rendered_and_tools_registered() {
+        use crate::memory::{MemoryBlock, MemoryConfig};
+
+        let memory = Arc::new(AgentMemory::new(AgentId::new(), MemoryConfig::default()));
+        let id = memory.add_block(MemoryBlock::new("human", "Name unknown")).await.unwrap();
+
+        let arguments = serde_json::json!({ "block_id": id.to_string(), "new_value": "Name: Ada" });
+        let client = Arc::new(ScriptedClient::new(vec![
+            Message::assistant_with_tool_calls(
+                "",
+                vec![tool_call("call_1", "update_memory", &arguments.to_string())],
+            ),
+            Message::assistant("Nice to meet you, Ada."),
+        ]));
+        let agent = Agent::builder()
+            .name("Companion")
+            .system_prompt("You are a companion.")
+            .model("test")
+            .memory(memory.clone())
+            .client(client.clone())
+            .build()
+            .unwrap();
+        assert_eq!(agent.id, memory.agent_id);
+
+        agent.react_loop("I'm Ada").await.unwrap();
+
+        let history = memory.get_recent_messages(10).await;
+        let logged: Vec<(&str, &str)> =
+            history.iter().map(|m| (m.role.as_str(), m.content.as_str())).collect();
+        assert_eq!(logged, vec![("user", "I'm Ada"), ("assistant", "Nice to meet you, Ada.")]);
+
+        let requests = client.requests();
+        let names: Vec<&str> = requests[0]
+            .tools
+            .as_ref()
+            .unwrap()
+            .iter()
+            .map(|t| t.function.name.as_str())
+            .collect();
+        assert!(names.contains(&"update_memory") && names.contains(&"search_messages"));
+
+        let first = requests[0].messages[0].content.to_string();
+        assert!(first.starts_with("You are a companion.\n\n<memory_blocks>"));
+        assert!(first.contains("Name unknown"));
+        let second = requests[1].messages[0].content.to_string();
+        assert!(second.contains("Name: Ada") && !second.contains("Name unknown"));
+    }
+
+    #[tokio::test]
+    async fn test_memory_tool_results_reach_the_model() {
+        use crate::memory::{EditAuthor, MemoryBlock, MemoryConfig};
+
+        let memory = Arc::new(AgentMemory::new(AgentId::new(), MemoryConfig::default()));
+        let id = memory.add_block(MemoryBlock::new("human", "Name: Ada")).await.unwrap();
+        let author = EditAuthor::Agent { agent_id: memory.agent_id };
+        memory
+            .update_block_as(id, "Name: Ada. Has a cat.".to_string(), author, "Learned about the cat")
+            .await
+            .unwrap();
+        memory.add_message("user".to_string(), "My cat is called Turing".to_string()).await;
+
+        let history = serde_json::json!({ "block_id": id.to_string() });
+        let client = Arc::new(ScriptedClient::new(vec![
+            Message::assistant_with_tool_calls(
+                "",
+                vec![
+                    tool_call("call_1", "list_memory_blocks", "{}"),
+                    tool_call("call_2", "search_messages", r#"{"query": "cat"}"#),
+                    tool_call("call_3", "memory_block_history", &history.to_string()),
+                ],
+            ),
+            Message::assistant("Your cat is Turing, Ada."),
+        ]));
+        let agent = Agent::builder()
+            .name("Companion")
+            .system_prompt("You are a companion.")
+            .model("test")
+            .memory(memory)
+            .client(client.clone())
+            .build()
+            .unwrap();
+
+        agent.react_loop("What is my cat called?").await.unwrap();
+
+        let requests = client.requests();
+        let results: Vec<String> = requests[1]
+            .messages
+            .iter()
+            .filter(|m| matches!(m.role, Role::Tool))
+            .map(|m| m.content.to_string())
+            .collect();
+        assert!(results[0].contains("[human]") && results[0].contains("Name: Ada"), "{}", results[0]);
+        assert!(results[1].contains("user: My cat is called Turing"), "{}", results[1]);
+        assert!(results[2].contains("Block created\nName: Ada\n"), "{}", results[2]);
+        assert!(results[2].contains(": Learned about the cat\nName: Ada. Has a cat."), "{}", results[2]);
+        assert!(results[2].contains("Version 2 ("), "{}", results[2]);
+    }
+
+    #[tokio::test]
+    async fn test_archival_search_hits_reach_the_model() {
+        use crate::archival::{ArchivalMemory, FlatIndex, PassageSource};
+        use crate::embeddings::HashingEmbedder;
+        use crate::memory::MemoryConfig;
+
+        let agent_id = AgentId::new();
+        let archival = Arc::new(ArchivalMemory::new(
+            agent_id,
+            Arc::new(HashingEmbedder::new(64)),
+            Arc::new(FlatIndex::new()),
+        ));
+        archival
+            .insert("The Orion deploy key rotates every March", PassageSource::Insert, Default::default())
+            .await
+            .unwrap();
+        let memory = Arc::new(AgentMemory::new(agent_id, MemoryConfig::default()).with_archival(archival));
+
+        let client = Arc::new(ScriptedClient::new(vec![
+            Message::assistant_with_tool_calls(
+                "",
+                vec![tool_call("call_1", "archival_search", r#"{"query": "Orion deploy key"}"#)],
+            ),
+            Message::assistant("It rotates in March."),
+        ]));
+        let agent = Agent::builder()
+            .name("Archivist")
+            .system_prompt("You remember things.")
+            .model("test")
+            .memory(memory)
+            .client(client.clone())
+            .build()
+            .unwrap();
+
+        agent.react_loop("When does the Orion key rotate?").await.unwrap();
+
+        let requests = client.requests();
+        let result = requests[1].messages.last().unwrap().content.to_string();
+        // The question itself was archived as a message too
+        assert!(result.starts_with("Found 2 archival passages"), "{}", result);
+        assert!(result.contains("1. [score ") && result.contains("inserted"), "{}", result);
+        assert!(result.contains("The Orion deploy key rotates every March"), "{}", result);
+        assert!(result.contains("user message"), "{}", result);
+    }
+
+    #[test]
+    fn test_local_backend_from_env_needs_a_model() {
+        if std::env::var("OPENROUTER_API_KEY").is_ok() {
+            return;
+        }
+        std::env::set_var("LLAMACPP_BASE_URL", "http://localhost:8080");
+        for var in ["VLLM_MODEL", "OLLAMA_MODEL", "LLAMACPP_MODEL"] {
+            std::env::remove_var(var);
+        }
+        let builder = || Agent::builder().name("Local").system_prompt("Be brief.");
+
+        let err = builder().build().err().unwrap();
+        assert!(matches!(&err, Error::Config(msg) if msg.contains("_MODEL")), "{}", err);
+        assert_eq!(builder().model("qwen3-8b").build().unwrap().model.model, "qwen3-8b");
+    }
+
+    #[test]
+    fn test_null_content_deserializes() {
+        let message: Message = serde_json::from_value(serde_json::json!({
+            "role": "assistant",
+            "content": null,
+            "tool_calls": [{
+                "id": "call_1",
+                "type": "function",
+                "function": { "name": "echo", "arguments": "{}" }
+            }]
+        }))
+        .unwrap();
+
+        assert!(message.content.is_empty());
+        assert_eq!(message.tool_calls.unwrap().len(), 1);
+    }
+}
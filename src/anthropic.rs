@@ -0,0 +1,1230 @@
+//! Native Anthropic Messages API client
+//!
+//! [`AnthropicClient`] translates OpenAI-shaped [`CompletionRequest`]s to the
+//! Messages API and back, so agents keep the same message types while
+//! gaining Anthropic-only features:
+//!
+//! - System messages become top-level system blocks
+//! - Tool calls and tool results become `tool_use` / `tool_result` blocks
+//! - Prompt caching marks the tools, system prompt and latest message as
+//!   cache breakpoints
+//! - Extended thinking is returned in [`Message::reasoning`], which agents
+//!   record as the [`Thought`](crate::react::Thought) instead of the answer.
+//!   Each thinking and redacted thinking block is also kept in
+//!   [`Message::reasoning_details`] and sent back unchanged on later turns
+//!
+//! ```rust,ignore
+//! let client = AnthropicClient::new(
+//!     AnthropicConfig::from_env()?.with_prompt_caching(true).with_thinking(4096),
+//! )?;
+//! let agent = Agent::builder()
+//!     .model("claude-sonnet-4-0")
+//!     .client(Arc::new(client))
+//!     .build()?;
+//! ```
+
+use crate::error::{Error, Result};
+use crate::llm_client::LlmClient;
+use crate::openrouter::{
+    Choice, CompletionRequest, CompletionResponse, CompletionStream, ContentPart, Delta,
+    FunctionCall, FunctionCallDelta, Message, MessageContent, ReasoningDetail, ResponseFormat,
+    Role, SseDecoder, StreamChoice, StreamChunk, ToolCall, ToolCallDelta, ToolChoice, Usage,
+};
+use async_trait::async_trait;
+use futures::StreamExt;
+use reqwest::Client;
+use secrecy::{ExposeSecret, SecretString};
+use serde::{Deserialize, Serialize};
+use std::collections::HashMap;
+use std::time::Duration;
+
+/// Messages API version sent with every request
+const ANTHROPIC_VERSION: &str = "2023-06-01";
+
+/// Anthropic client configuration
+#[derive(Clone)]
+pub struct AnthropicConfig {
+    /// API key
+    pub api_key: SecretString,
+    /// Base URL of the API (e.g., "https://api.anthropic.com")
+    pub base_url: String,
+    /// Request timeout
+    pub timeout: Duration,
+    /// Completion tokens for requests that do not set `max_tokens`
+    pub max_tokens: u32,
+    /// Token budget for extended thinking, if enabled
+    pub thinking_budget: Option<u32>,
+    /// Whether to mark prompt-caching breakpoints
+    pub prompt_caching: bool,
+}
+
+impl AnthropicConfig {
+    /// Create a new Anthropic configuration
+    pub fn new(api_key: impl Into<String>) -> Self {
+        Self {
+            api_key: SecretString::from(api_key.into()),
+            base_url: "https://api.anthropic.com".to_string(),
+            timeout: Duration::from_secs(300),
+            max_tokens: 4096,
+            thinking_budget: None,
+            prompt_caching: false,
+        }
+    }
+
+    /// Create configuration from environment variables
+    pub fn from_env() -> Result<Self> {
+        let api_key = std::env::var("ANTHROPIC_API_KEY")
+            .map_err(|_| Error::config("ANTHROPIC_API_KEY environment variable not set"))?;
+
+        let mut config = Self::new(api_key);
+        if let Ok(base_url) = std::env::var("ANTHROPIC_BASE_URL") {
+            config.base_url = base_url;
+        }
+        Ok(config)
+    }
+
+    /// Set the base URL
+    pub fn with_base_url(mut self, base_url: impl Into<String>) -> Self {
+        self.base_url = base_url.into();
+        self
+    }
+
+    /// Set the timeout
+    pub fn with_timeout(mut self, timeout: Duration) -> Self {
+        self.timeout = timeout;
+        self
+    }
+
+    /// Set the completion tokens used when a request does not say
+    pub fn with_max_tokens(mut self, max_tokens: u32) -> Self {
+        self.max_tokens = max_tokens;
+        self
+    }
+
+    /// Enable extended thinking with a token budget
+    ///
+    /// The budget is added on top of each request's `max_tokens`.
+    pub fn with_thinking(mut self, budget_tokens: u32) -> Self {
+        self.thinking_budget = Some(budget_tokens);
+        self
+    }
+
+    /// Enable or disable prompt-caching breakpoints
+    pub fn with_prompt_caching(mut self, enabled: bool) -> Self {
+        self.prompt_caching = enabled;
+        self
+    }
+
+    /// Get the API key as a string
+    pub fn api_key(&self) -> &str {
+        self.api_key.expose_secret()
+    }
+}
+
+impl std::fmt::Debug for AnthropicConfig {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.debug_struct("AnthropicConfig")
+            .field("api_key", &"***REDACTED***")
+            .field("base_url", &self.base_url)
+            .field("timeout", &self.timeout)
+            .field("max_tokens", &self.max_tokens)
+            .field("thinking_budget", &self.thinking_budget)
+            .field("prompt_caching", &self.prompt_caching)
+            .finish()
+    }
+}
+
+/// Client for the Anthropic Messages API
+pub struct AnthropicClient {
+    /// HTTP client
+    client: Client,
+    /// Configuration
+    config: AnthropicConfig,
+}
+
+impl AnthropicClient {
+    /// Create a new Anthropic client from environment variables
+    pub fn from_env() -> Result<Self> {
+        Self::new(AnthropicConfig::from_env()?)
+    }
+
+    /// Create a new Anthropic client with the given configuration
+    pub fn new(config: AnthropicConfig) -> Result<Self> {
+        let client = Client::builder().timeout(config.timeout).build()?;
+        Ok(Self { client, config })
+    }
+
+    /// Get the configuration
+    pub fn config(&self) -> &AnthropicConfig {
+        &self.config
+    }
+
+    async fn send(&self, body: &MessagesRequest, what: &str) -> Result<reqwest::Response> {
+        let response = self
+            .client
+            .post(format!(
+                "{}/v1/messages",
+                self.config.base_url.trim_end_matches('/')
+            ))
+            .header("x-api-key", self.config.api_key())
+            .header("anthropic-version", ANTHROPIC_VERSION)
+            .json(body)
+            .send()
+            .await?;
+
+        if !response.status().is_success() {
+            let status = response.status();
+            let error_text = response
+                .text()
+                .await
+                .unwrap_or_else(|_| "Unknown error".to_string());
+            return Err(Error::api(
+                status.as_u16(),
+                format!("Anthropic {} failed: {}", what, error_text),
+            ));
+        }
+
+        Ok(response)
+    }
+
+    /// Translate a request into the Messages API shape
+    fn messages_request(&self, request: CompletionRequest, stream: bool) -> MessagesRequest {
+        let mut system = Vec::new();
+        let mut messages: Vec<WireMessage> = Vec::new();
+
+        for message in request.messages {
+            let (role, blocks) = match message.role {
+                Role::System => {
+                    if !message.content.is_empty() {
+                        system.push(ContentBlock::text(message.content.text()));
+                    }
+                    continue;
+                }
+                Role::User => ("user", content_blocks(message.content)),
+                Role::Tool => (
+                    "user",
+                    vec![ContentBlock::ToolResult {
+                        tool_use_id: message.tool_call_id.unwrap_or_default(),
+                        content: message.content.to_string(),
+                        cache_control: None,
+                    }],
+                ),
+                Role::Assistant => ("assistant", assistant_blocks(message)),
+            };
+
+            if blocks.is_empty() {
+                continue;
+            }
+            // Tool results and any following user text share one user turn
+            match messages.last_mut() {
+                Some(last) if last.role == role => last.content.extend(blocks),
+                _ => messages.push(WireMessage {
+                    role: role.to_string(),
+                    content: blocks,
+                }),
+            }
+        }
+
+        // The Messages API has no response format, so the schema joins the system prompt
+        if let Some(schema) = request
+            .response_format
+            .as_ref()
+            .and_then(ResponseFormat::schema)
+        {
+            system.push(ContentBlock::text(format!(
+                "Respond only with JSON matching this schema:\n{}",
+                schema
+            )));
+        }
+
+        let mut tools: Vec<WireTool> = request
+            .tools
+            .unwrap_or_default()
+            .into_iter()
+            .map(|tool| WireTool {
+                name: tool.function.name,
+                description: tool.function.description,
+                input_schema: tool.function.parameters,
+                cache_control: None,
+            })
+            .collect();
+
+        if self.config.prompt_caching {
+            if let Some(tool) = tools.last_mut() {
+                tool.cache_control = Some(CacheControl::ephemeral());
+            }
+            if let Some(block) = system.last_mut() {
+                block.mark_cached();
+            }
+            if let Some(block) = messages.last_mut().and_then(|m| m.content.last_mut()) {
+                block.mark_cached();
+            }
+        }
+
+        let max_tokens = request.max_tokens.unwrap_or(self.config.max_tokens);
+        let thinking = self.config.thinking_budget.map(|budget_tokens| Thinking {
+            kind: "enabled",
+            budget_tokens,
+        });
+
+        MessagesRequest {
+            model: model_id(&request.model).to_string(),
+            max_tokens: max_tokens + self.config.thinking_budget.unwrap_or(0),
+            system,
+            messages,
+            // Extended thinking only runs at the default sampling settings
+            temperature: request.temperature.filter(|_| thinking.is_none()),
+            top_p: request.top_p.filter(|_| thinking.is_none()),
+            stop_sequences: request.stop,
+            tools,
+            tool_choice: request.tool_choice.map(tool_choice),
+            thinking,
+            stream,
+        }
+    }
+}
+
+/// Model ID without an OpenRouter-style `anthropic/` prefix
+fn model_id(model: &str) -> &str {
+    model.strip_prefix("anthropic/").unwrap_or(model)
+}
+
+/// Content blocks for an assistant turn
+///
+/// Thinking blocks are replayed from [`Message::reasoning_details`] in their
+/// original order. Unsigned thinking, e.g. from another provider, is not
+/// sent back, since the API rejects it.
+fn assistant_blocks(message: Message) -> Vec<ContentBlock> {
+    let mut blocks: Vec<ContentBlock> = message
+        .reasoning_details
+        .into_iter()
+        .filter_map(|detail| match detail {
+            ReasoningDetail::Text {
+                text,
+                signature: Some(signature),
+            } => Some(ContentBlock::Thinking {
+                thinking: text,
+                signature,
+            }),
+            ReasoningDetail::Text {
+                signature: None, ..
+            } => None,
+            ReasoningDetail::Encrypted { data } => Some(ContentBlock::RedactedThinking { data }),
+        })
+        .collect();
+    blocks.extend(content_blocks(message.content));
+    for call in message.tool_calls.unwrap_or_default() {
+        let input = serde_json::from_str(&call.function.arguments)
+            .unwrap_or_else(|_| serde_json::json!({}));
+        blocks.push(ContentBlock::ToolUse {
+            id: call.id,
+            name: call.function.name,
+            input,
+            cache_control: None,
+        });
+    }
+    blocks
+}
+
+/// Text, image and document blocks for message content
+///
+/// PDFs become document blocks; other files are not supported by the API
+/// and are dropped.
+fn content_blocks(content: MessageContent) -> Vec<ContentBlock> {
+    let parts = match content {
+        MessageContent::Text(text) if text.is_empty() => return Vec::new(),
+        MessageContent::Text(text) => return vec![ContentBlock::text(text)],
+        MessageContent::Parts(parts) => parts,
+    };
+
+    parts
+        .into_iter()
+        .filter_map(|part| match part {
+            ContentPart::Text { text } if text.is_empty() => None,
+            ContentPart::Text { text } => Some(ContentBlock::text(text)),
+            ContentPart::ImageUrl { image_url } => Some(ContentBlock::Image {
+                source: match image_url.as_base64() {
+                    Some((media_type, data)) => Source::Base64 {
+                        media_type: media_type.to_string(),
+                        data: data.to_string(),
+                    },
+                    None => Source::Url { url: image_url.url },
+                },
+                cache_control: None,
+            }),
+            ContentPart::File { file } => match file.as_base64() {
+                Some((media_type @ "application/pdf", data)) => Some(ContentBlock::Document {
+                    source: Source::Base64 {
+                        media_type: media_type.to_string(),
+                        data: data.to_string(),
+                    },
+                    cache_control: None,
+                }),
+                _ => None,
+            },
+        })
+        .collect()
+}
+
+fn tool_choice(choice: ToolChoice) -> serde_json::Value {
+    match choice {
+        ToolChoice::Auto => serde_json::json!({ "type": "auto" }),
+        ToolChoice::None => serde_json::json!({ "type": "none" }),
+        ToolChoice::Required => serde_json::json!({ "type": "any" }),
+        ToolChoice::Function { function } => {
+            serde_json::json!({ "type": "tool", "name": function.name })
+        }
+    }
+}
+
+/// OpenAI-style finish reason for a Messages API stop reason
+fn finish_reason(stop_reason: &str) -> String {
+    match stop_reason {
+        "end_turn" | "stop_sequence" => "stop",
+        "max_tokens" => "length",
+        "tool_use" => "tool_calls",
+        other => other,
+    }
+    .to_string()
+}
+
+fn completion_response(response: MessagesResponse) -> CompletionResponse {
+    let mut content = String::new();
+    let mut reasoning = String::new();
+    let mut reasoning_details = Vec::new();
+    let mut tool_calls = Vec::new();
+
+    for block in response.content {
+        match block {
+            ContentBlock::Text { text, .. } => content.push_str(&text),
+            ContentBlock::Thinking {
+                thinking,
+                signature,
+            } => {
+                reasoning.push_str(&thinking);
+                reasoning_details.push(ReasoningDetail::Text {
+                    text: thinking,
+                    signature: Some(signature).filter(|s| !s.is_empty()),
+                });
+            }
+            ContentBlock::RedactedThinking { data } => {
+                reasoning_details.push(ReasoningDetail::Encrypted { data });
+            }
+            ContentBlock::ToolUse {
+                id, name, input, ..
+            } => tool_calls.push(ToolCall {
+                id,
+                tool_type: "function".to_string(),
+                function: FunctionCall {
+                    name,
+                    arguments: input.to_string(),
+                },
+            }),
+            _ => {}
+        }
+    }
+
+    let mut message = if tool_calls.is_empty() {
+        Message::assistant(content)
+    } else {
+        Message::assistant_with_tool_calls(content, tool_calls)
+    };
+    if !reasoning.is_empty() {
+        message = message.with_reasoning(reasoning);
+    }
+    message = message.with_reasoning_details(reasoning_details);
+
+    CompletionResponse {
+        id: response.id,
+        model: response.model,
+        provider: Some("anthropic".to_string()),
+        choices: vec![Choice {
+            index: 0,
+            message,
+            finish_reason: response.stop_reason.as_deref().map(finish_reason),
+        }],
+        usage: response.usage.into(),
+    }
+}
+
+#[async_trait]
+impl LlmClient for AnthropicClient {
+    async fn complete(&self, request: CompletionRequest) -> Result<CompletionResponse> {
+        let body = self.messages_request(request, false);
+        let response: MessagesResponse = self.send(&body, "request").await?.json().await?;
+        Ok(completion_response(response))
+    }
+
+    async fn stream(&self, request: CompletionRequest) -> Result<CompletionStream> {
+        let body = self.messages_request(request, true);
+        let mut bytes = self.send(&body, "stream request").await?.bytes_stream();
+
+        let chunks = async_stream::stream! {
+            let mut decoder = SseDecoder::default();
+            let mut translator = StreamTranslator::default();
+
+            loop {
+                while let Some(data) = decoder.next_event() {
+                    match translator.translate(&data) {
+                        Ok(Some(chunk)) => yield Ok(chunk),
+                        Ok(None) => {}
+                        Err(e) => {
+                            yield Err(e);
+                            return;
+                        }
+                    }
+                    if translator.stopped {
+                        return;
+                    }
+                }
+
+                match bytes.next().await {
+                    Some(Ok(data)) => decoder.push(&data),
+                    Some(Err(e)) => {
+                        yield Err(e.into());
+                        return;
+                    }
+                    None => {
+                        if let Some(data) = decoder.finish() {
+                            if let Some(chunk) = translator.translate(&data).transpose() {
+                                yield chunk;
+                            }
+                        }
+                        return;
+                    }
+                }
+            }
+        };
+
+        Ok(CompletionStream::from_chunks(chunks))
+    }
+
+    fn client_type(&self) -> &str {
+        "anthropic"
+    }
+
+    fn endpoint(&self) -> &str {
+        &self.config.base_url
+    }
+}
+
+/// Turns Messages API stream events into [`StreamChunk`]s
+#[derive(Default)]
+struct StreamTranslator {
+    id: String,
+    model: String,
+    prompt_tokens: u64,
+    /// Tool call index for each `tool_use` content block index
+    tool_calls: HashMap<u32, u32>,
+    /// Text and signature of each open thinking block, by content block index
+    thinking: HashMap<u32, (String, String)>,
+    stopped: bool,
+}
+
+impl StreamTranslator {
+    fn translate(&mut self, data: &str) -> Result<Option<StreamChunk>> {
+        let event: StreamEvent = serde_json::from_str(data)?;
+        let delta = match event {
+            StreamEvent::MessageStart { message } => {
+                self.id = message.id;
+                self.model = message.model;
+                self.prompt_tokens = Usage::from(message.usage).prompt_tokens;
+                Delta {
+                    role: Some(Role::Assistant),
+                    ..Default::default()
+                }
+            }
+            StreamEvent::ContentBlockStart {
+                index,
+                content_block,
+            } => match content_block {
+                ContentBlock::ToolUse { id, name, .. } => {
+                    let position = self.tool_calls.len() as u32;
+                    self.tool_calls.insert(index, position);
+                    Delta {
+                        tool_calls: Some(vec![ToolCallDelta {
+                            index: position,
+                            id: Some(id),
+                            tool_type: Some("function".to_string()),
+                            function: Some(FunctionCallDelta {
+                                name: Some(name),
+                                arguments: None,
+                            }),
+                        }]),
+                        ..Default::default()
+                    }
+                }
+                ContentBlock::Text { text, .. } if !text.is_empty() => Delta {
+                    content: Some(text),
+                    ..Default::default()
+                },
+                ContentBlock::Thinking {
+                    thinking,
+                    signature,
+                } => {
+                    self.thinking.insert(index, (String::new(), signature));
+                    if thinking.is_empty() {
+                        return Ok(None);
+                    }
+                    return self.thinking_delta(index, thinking);
+                }
+                ContentBlock::RedactedThinking { data } => Delta {
+                    reasoning_details: Some(vec![ReasoningDetail::Encrypted { data }]),
+                    ..Default::default()
+                },
+                _ => return Ok(None),
+            },
+            StreamEvent::ContentBlockDelta { index, delta } => match delta {
+                BlockDelta::TextDelta { text } => Delta {
+                    content: Some(text),
+                    ..Default::default()
+                },
+                BlockDelta::ThinkingDelta { thinking } => {
+                    return self.thinking_delta(index, thinking)
+                }
+                BlockDelta::SignatureDelta { signature } => {
+                    if let Some((_, block_signature)) = self.thinking.get_mut(&index) {
+                        block_signature.push_str(&signature);
+                    }
+                    return Ok(None);
+                }
+                BlockDelta::InputJsonDelta { partial_json } => {
+                    let Some(&position) = self.tool_calls.get(&index) else {
+                        return Ok(None);
+                    };
+                    Delta {
+                        tool_calls: Some(vec![ToolCallDelta {
+                            index: position,
+                            function: Some(FunctionCallDelta {
+                                name: None,
+                                arguments: Some(partial_json),
+                            }),
+                            ..Default::default()
+                        }]),
+                        ..Default::default()
+                    }
+                }
+                BlockDelta::Other => return Ok(None),
+            },
+            StreamEvent::MessageDelta { delta, usage } => {
+                let completion_tokens = usage.output_tokens;
+                return Ok(Some(StreamChunk {
+                    id: self.id.clone(),
+                    model: self.model.clone(),
+                    choices: vec![StreamChoice {
+                        index: 0,
+                        delta: Delta::default(),
+                        finish_reason: delta.stop_reason.as_deref().map(finish_reason),
+                    }],
+                    usage: Some(Usage {
+                        prompt_tokens: self.prompt_tokens,
+                        completion_tokens,
+                        total_tokens: self.prompt_tokens + completion_tokens,
+                    }),
+                }));
+            }
+            // A finished thinking block is forwarded whole, with its signature
+            StreamEvent::ContentBlockStop { index } => match self.thinking.remove(&index) {
+                Some((text, signature)) => Delta {
+                    reasoning_details: Some(vec![ReasoningDetail::Text {
+                        text,
+                        signature: Some(signature).filter(|s| !s.is_empty()),
+                    }]),
+                    ..Default::default()
+                },
+                None => return Ok(None),
+            },
+            StreamEvent::MessageStop => {
+                self.stopped = true;
+                return Ok(None);
+            }
+            StreamEvent::Error { error } => {
+                return Err(Error::openrouter(format!(
+                    "Stream error: {}",
+                    error.message
+                )));
+            }
+            StreamEvent::Other => return Ok(None),
+        };
+
+        Ok(Some(self.chunk(delta)))
+    }
+
+    /// Forward thinking text, remembering it for the block's final detail
+    fn thinking_delta(&mut self, index: u32, thinking: String) -> Result<Option<StreamChunk>> {
+        if let Some((text, _)) = self.thinking.get_mut(&index) {
+            text.push_str(&thinking);
+        }
+        Ok(Some(self.chunk(Delta {
+            reasoning: Some(thinking),
+            ..Default::default()
+        })))
+    }
+
+    fn chunk(&self, delta: Delta) -> StreamChunk {
+        StreamChunk {
+            id: self.id.clone(),
+            model: self.model.clone(),
+            choices: vec![StreamChoice {
+                index: 0,
+                delta,
+                finish_reason: None,
+            }],
+            usage: None,
+        }
+    }
+}
+
+#[derive(Debug, Serialize)]
+struct MessagesRequest {
+    model: String,
+    max_tokens: u32,
+    #[serde(skip_serializing_if = "Vec::is_empty")]
+    system: Vec<ContentBlock>,
+    messages: Vec<WireMessage>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    temperature: Option<f32>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    top_p: Option<f32>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    stop_sequences: Option<Vec<String>>,
+    #[serde(skip_serializing_if = "Vec::is_empty")]
+    tools: Vec<WireTool>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    tool_choice: Option<serde_json::Value>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    thinking: Option<Thinking>,
+    #[serde(skip_serializing_if = "std::ops::Not::not")]
+    stream: bool,
+}
+
+#[derive(Debug, Serialize)]
+struct WireMessage {
+    role: String,
+    content: Vec<ContentBlock>,
+}
+
+#[derive(Debug, Serialize)]
+struct WireTool {
+    name: String,
+    description: String,
+    input_schema: serde_json::Value,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    cache_control: Option<CacheControl>,
+}
+
+#[derive(Debug, Serialize)]
+struct Thinking {
+    #[serde(rename = "type")]
+    kind: &'static str,
+    budget_tokens: u32,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+struct CacheControl {
+    #[serde(rename = "type")]
+    kind: String,
+}
+
+impl CacheControl {
+    fn ephemeral() -> Self {
+        Self {
+            kind: "ephemeral".to_string(),
+        }
+    }
+}
+
+#[derive(Debug, Serialize, Deserialize)]
+#[serde(tag = "type", rename_all = "snake_case")]
+enum ContentBlock {
+    Text {
+        text: String,
+        #[serde(default, skip_serializing_if = "Option::is_none")]
+        cache_control: Option<CacheControl>,
+    },
+    Thinking {
+        thinking: String,
+        #[serde(default)]
+        signature: String,
+    },
+    RedactedThinking {
+        data: String,
+    },
+    ToolUse {
+        id: String,
+        name: String,
+        #[serde(default)]
+        input: serde_json::Value,
+        #[serde(default, skip_serializing_if = "Option::is_none")]
+        cache_control: Option<CacheControl>,
+    },
+    ToolResult {
+        tool_use_id: String,
+        content: String,
+        #[serde(default, skip_serializing_if = "Option::is_none")]
+        cache_control: Option<CacheControl>,
+    },
+    Image {
+        source: Source,
+        #[serde(default, skip_serializing_if = "Option::is_none")]
+        cache_control: Option<CacheControl>,
+    },
+    Document {
+        source: Source,
+        #[serde(default, skip_serializing_if = "Option::is_none")]
+        cache_control: Option<CacheControl>,
+    },
+    /// Server-side blocks, which have no equivalent
+    #[serde(other)]
+    Other,
+}
+
+impl ContentBlock {
+    fn text(text: impl Into<String>) -> Self {
+        Self::Text {
+            text: text.into(),
+            cache_control: None,
+        }
+    }
+
+    /// Make this block the end of a cached prefix, where the API allows it
+    fn mark_cached(&mut self) {
+        match self {
+            Self::Text { cache_control, .. }
+            | Self::ToolUse { cache_control, .. }
+            | Self::ToolResult { cache_control, .. }
+            | Self::Image { cache_control, .. }
+            | Self::Document { cache_control, .. } => {
+                *cache_control = Some(CacheControl::ephemeral())
+            }
+            Self::Thinking { .. } | Self::RedactedThinking { .. } | Self::Other => {}
+        }
+    }
+}
+
+/// Image or document data
+#[derive(Debug, Serialize, Deserialize)]
+#[serde(tag = "type", rename_all = "snake_case")]
+enum Source {
+    Base64 { media_type: String, data: String },
+    Url { url: String },
+}
+
+#[derive(Debug, Deserialize)]
+struct MessagesResponse {
+    id: String,
+    model: String,
+    #[serde(default)]
+    content: Vec<ContentBlock>,
+    #[serde(default)]
+    stop_reason: Option<String>,
+    #[serde(default)]
+    usage: WireUsage,
+}
+
+#[derive(Debug, Default, Deserialize)]
+struct WireUsage {
+    #[serde(default)]
+    input_tokens: u64,
+    #[serde(default)]
+    output_tokens: u64,
+    #[serde(default)]
+    cache_creation_input_tokens: Option<u64>,
+    #[serde(default)]
+    cache_read_input_tokens: Option<u64>,
+}
+
+impl From<WireUsage> for Usage {
+    /// Cache writes and reads count as prompt tokens
+    fn from(usage: WireUsage) -> Self {
+        let prompt_tokens = usage.input_tokens
+            + usage.cache_creation_input_tokens.unwrap_or(0)
+            + usage.cache_read_input_tokens.unwrap_or(0);
+        Usage {
+            prompt_tokens,
+            completion_tokens: usage.output_tokens,
+            total_tokens: prompt_tokens + usage.output_tokens,
+        }
+    }
+}
+
+#[derive(Debug, Deserialize)]
+#[serde(tag = "type", rename_all = "snake_case")]
+enum StreamEvent {
+    MessageStart {
+        message: MessagesResponse,
+    },
+    ContentBlockStart {
+        index: u32,
+        content_block: ContentBlock,
+    },
+    ContentBlockDelta {
+        index: u32,
+        delta: BlockDelta,
+    },
+    ContentBlockStop {
+        index: u32,
+    },
+    MessageDelta {
+        delta: MessageDeltaBody,
+        #[serde(default)]
+        usage: WireUsage,
+    },
+    MessageStop,
+    Error {
+        error: ApiError,
+    },
+    /// Pings carry nothing to forward
+    #[serde(other)]
+    Other,
+}
+
+#[derive(Debug, Deserialize)]
+#[serde(tag = "type", rename_all = "snake_case")]
+enum BlockDelta {
+    TextDelta {
+        text: String,
+    },
+    InputJsonDelta {
+        partial_json: String,
+    },
+    ThinkingDelta {
+        thinking: String,
+    },
+    SignatureDelta {
+        signature: String,
+    },
+    #[serde(other)]
+    Other,
+}
+
+#[derive(Debug, Deserialize)]
+struct MessageDeltaBody {
+    #[serde(default)]
+    stop_reason: Option<String>,
+}
+
+#[derive(Debug, Deserialize)]
+struct ApiError {
+    message: String,
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::openrouter::ToolDefinition;
+    use crate::Agent;
+    use std::sync::Arc;
+
+    fn client(server: &mockito::ServerGuard, config: AnthropicConfig) -> AnthropicClient {
+        AnthropicClient::new(config.with_base_url(server.url())).unwrap()
+    }
+
+    #[tokio::test]
+    async fn test_request_translation_with_cache_breakpoints() {
+        let mut server = mockito::Server::new_async().await;
+        let mock = server
+            .mock("POST", "/v1/messages")
+            .match_header("x-api-key", "test-key")
+            .match_header("anthropic-version", ANTHROPIC_VERSION)
+            .match_body(mockito::Matcher::Json(serde_json::json!({
+                "model": "claude-sonnet-4",
+                "max_tokens": 100,
+                "system": [
+                    { "type": "text", "text": "Be brief.", "cache_control": { "type": "ephemeral" } }
+                ],
+                "messages": [
+                    { "role": "user", "content": [{ "type": "text", "text": "What is 2 + 2?" }] },
+                    { "role": "assistant", "content": [
+                        { "type": "tool_use", "id": "toolu_1", "name": "calculator", "input": { "a": 2, "b": 2 } }
+                    ] },
+                    { "role": "user", "content": [
+                        { "type": "tool_result", "tool_use_id": "toolu_1", "content": "4",
+                          "cache_control": { "type": "ephemeral" } }
+                    ] }
+                ],
+                "temperature": 0.0,
+                "tools": [{
+                    "name": "calculator",
+                    "description": "Add numbers",
+                    "input_schema": { "type": "object" },
+                    "cache_control": { "type": "ephemeral" }
+                }],
+                "tool_choice": { "type": "auto" }
+            })))
+            .with_body(
+                serde_json::json!({
+                    "id": "msg_1",
+                    "model": "claude-sonnet-4",
+                    "content": [{ "type": "text", "text": "4" }],
+                    "stop_reason": "end_turn",
+                    "usage": { "input_tokens": 5, "cache_read_input_tokens": 20, "output_tokens": 1 }
+                })
+                .to_string(),
+            )
+            .create_async()
+            .await;
+
+        let call = ToolCall {
+            id: "toolu_1".to_string(),
+            tool_type: "function".to_string(),
+            function: FunctionCall {
+                name: "calculator".to_string(),
+                arguments: r#"{"a":2,"b":2}"#.to_string(),
+            },
+        };
+        let request = CompletionRequest::new(
+            "anthropic/claude-sonnet-4",
+            vec![
+                Message::system("Be brief."),
+                Message::user("What is 2 + 2?"),
+                Message::assistant_with_tool_calls("", vec![call]),
+                Message::tool("4", "toolu_1"),
+            ],
+        )
+        .with_temperature(0.0)
+        .with_max_tokens(100)
+        .with_tools(vec![ToolDefinition::function(
+            "calculator",
+            "Add numbers",
+            serde_json::json!({ "type": "object" }),
+        )])
+        .with_tool_choice(ToolChoice::Auto);
+
+        let client = client(
+            &server,
+            AnthropicConfig::new("test-key").with_prompt_caching(true),
+        );
+        let response = client.complete(request).await.unwrap();
+
+        mock.assert_async().await;
+        assert_eq!(response.choices[0].message.content, "4");
+        assert_eq!(response.choices[0].finish_reason.as_deref(), Some("stop"));
+        assert_eq!(response.usage.prompt_tokens, 25);
+        assert_eq!(response.usage.total_tokens, 26);
+    }
+
+    #[tokio::test]
+    async fn test_thinking_becomes_the_thought() {
+        let mut server = mockito::Server::new_async().await;
+        server
+            .mock("POST", "/v1/messages")
+            .match_body(mockito::Matcher::PartialJson(serde_json::json!({
+                "max_tokens": 2048 + 1000,
+                "thinking": { "type": "enabled", "budget_tokens": 2048 }
+            })))
+            .with_body(
+                serde_json::json!({
+                    "id": "msg_1",
+                    "model": "claude-sonnet-4",
+                    "content": [
+                        { "type": "thinking", "thinking": "Two plus two is four.", "signature": "sig" },
+                        { "type": "redacted_thinking", "data": "opaque" },
+                        { "type": "text", "text": "4" }
+                    ],
+                    "stop_reason": "end_turn",
+                    "usage": { "input_tokens": 10, "output_tokens": 12 }
+                })
+                .to_string(),
+            )
+            .create_async()
+            .await;
+
+        let client = client(
+            &server,
+            AnthropicConfig::new("test-key").with_thinking(2048),
+        );
+        let agent = Agent::builder()
+            .name("Calculator")
+            .model("claude-sonnet-4")
+            .system_prompt("Answer arithmetic questions.")
+            .client(Arc::new(client))
+            .build()
+            .unwrap();
+
+        let output = agent.react_loop("What is 2 + 2?").await.unwrap();
+        assert_eq!(output.content, "4");
+        assert_eq!(output.trace.thoughts[0].content, "Two plus two is four.");
+    }
+
+    #[tokio::test]
+    async fn test_stream_translates_events() {
+        let mut server = mockito::Server::new_async().await;
+        let events = [
+            (
+                "message_start",
+                r#"{"type":"message_start","message":{"id":"msg_1","model":"claude-sonnet-4","content":[],"stop_reason":null,"usage":{"input_tokens":7,"output_tokens":1}}}"#,
+            ),
+            (
+                "content_block_start",
+                r#"{"type":"content_block_start","index":0,"content_block":{"type":"thinking","thinking":"","signature":""}}"#,
+            ),
+            (
+                "content_block_delta",
+                r#"{"type":"content_block_delta","index":0,"delta":{"type":"thinking_delta","thinking":"Need the weather."}}"#,
+            ),
+            (
+                "content_block_delta",
+                r#"{"type":"content_block_delta","index":0,"delta":{"type":"signature_delta","signature":"sig-a"}}"#,
+            ),
+            (
+                "content_block_stop",
+                r#"{"type":"content_block_stop","index":0}"#,
+            ),
+            (
+                "content_block_start",
+                r#"{"type":"content_block_start","index":1,"content_block":{"type":"redacted_thinking","data":"opaque"}}"#,
+            ),
+            (
+                "content_block_stop",
+                r#"{"type":"content_block_stop","index":1}"#,
+            ),
+            (
+                "content_block_start",
+                r#"{"type":"content_block_start","index":2,"content_block":{"type":"thinking","thinking":"","signature":""}}"#,
+            ),
+            (
+                "content_block_delta",
+                r#"{"type":"content_block_delta","index":2,"delta":{"type":"thinking_delta","thinking":" Paris it is."}}"#,
+            ),
+            (
+                "content_block_delta",
+                r#"{"type":"content_block_delta","index":2,"delta":{"type":"signature_delta","signature":"sig-b"}}"#,
+            ),
+            (
+                "content_block_stop",
+                r#"{"type":"content_block_stop","index":2}"#,
+            ),
+            (
+                "content_block_start",
+                r#"{"type":"content_block_start","index":3,"content_block":{"type":"text","text":""}}"#,
+            ),
+            (
+                "content_block_delta",
+                r#"{"type":"content_block_delta","index":3,"delta":{"type":"text_delta","text":"Checking."}}"#,
+            ),
+            ("ping", r#"{"type":"ping"}"#),
+            (
+                "content_block_start",
+                r#"{"type":"content_block_start","index":4,"content_block":{"type":"tool_use","id":"toolu_1","name":"weather","input":{}}}"#,
+            ),
+            (
+                "content_block_delta",
+                r#"{"type":"content_block_delta","index":4,"delta":{"type":"input_json_delta","partial_json":"{\"city\":"}}"#,
+            ),
+            (
+                "content_block_delta",
+                r#"{"type":"content_block_delta","index":4,"delta":{"type":"input_json_delta","partial_json":"\"Paris\"}"}}"#,
+            ),
+            (
+                "message_delta",
+                r#"{"type":"message_delta","delta":{"stop_reason":"tool_use"},"usage":{"output_tokens":30}}"#,
+            ),
+            ("message_stop", r#"{"type":"message_stop"}"#),
+        ];
+        let body: String = events
+            .iter()
+            .map(|(event, data)| format!("event: {}\ndata: {}\n\n", event, data))
+            .collect();
+        server
+            .mock("POST", "/v1/messages")
+            .match_body(mockito::Matcher::PartialJson(
+                serde_json::json!({ "stream": true }),
+            ))
+            .with_header("content-type", "text/event-stream")
+            .with_body(body)
+            .create_async()
+            .await;
+
+        let client = client(&server, AnthropicConfig::new("test-key"));
+        let response = client
+            .stream(CompletionRequest::new(
+                "claude-sonnet-4",
+                vec![Message::user("Weather in Paris?")],
+            ))
+            .await
+            .unwrap()
+            .collect_response()
+            .await
+            .unwrap();
+
+        let message = &response.choices[0].message;
+        assert_eq!(message.content, "Checking.");
+        assert_eq!(
+            message.reasoning.as_deref(),
+            Some("Need the weather. Paris it is.")
+        );
+        assert_eq!(
+            message.reasoning_details,
+            vec![
+                ReasoningDetail::Text {
+                    text: "Need the weather.".to_string(),
+                    signature: Some("sig-a".to_string()),
+                },
+                ReasoningDetail::Encrypted {
+                    data: "opaque".to_string(),
+                },
+                ReasoningDetail::Text {
+                    text: " Paris it is.".to_string(),
+                    signature: Some("sig-b".to_string()),
+                },
+            ]
+        );
+        let call = &message.tool_calls.as_ref().unwrap()[0];
+        assert_eq!(call.id, "toolu_1");
+        assert_eq!(call.function.arguments, r#"{"city":"Paris"}"#);
+        assert_eq!(
+            response.choices[0].finish_reason.as_deref(),
+            Some("tool_calls")
+        );
+        assert_eq!(response.usage.total_tokens, 37);
+
+        // Every thinking block goes back unchanged ahead of the tool call on the next turn
+        let next = client.messages_request(
+            CompletionRequest::new(
+                "claude-sonnet-4",
+                vec![message.clone(), Message::tool("Sunny", "toolu_1")],
+            ),
+            false,
+        );
+        let blocks = serde_json::to_value(&next.messages[0].content).unwrap();
+        assert_eq!(
+            blocks[0],
+            serde_json::json!({"type": "thinking", "thinking": "Need the weather.", "signature": "sig-a"})
+        );
+        assert_eq!(
+            blocks[1],
+            serde_json::json!({"type": "redacted_thinking", "data": "opaque"})
+        );
+        assert_eq!(
+            blocks[2],
+            serde_json::json!({"type": "thinking", "thinking": " Paris it is.", "signature": "sig-b"})
+        );
+        assert_eq!(blocks[3]["type"], "text");
+        assert_eq!(blocks[4]["type"], "tool_use");
+    }
+
+    #[tokio::test]
+    async fn test_api_errors_report_status() {
+        let mut server = mockito::Server::new_async().await;
+        server
+            .mock("POST", "/v1/messages")
+            .with_status(529)
+            .with_body(
+                r#"{"type":"error","error":{"type":"overloaded_error","message":"Overloaded"}}"#,
+            )
+            .create_async()
+            .await;
+
+        let client = client(&server, AnthropicConfig::new("test-key"));
+        let err = client
+            .complete(CompletionRequest::new(
+                "claude-sonnet-4",
+                vec![Message::user("hi")],
+            ))
+            .await
+            .unwrap_err();
+        assert!(err.is_transient());
+        assert!(err.to_string().contains("Overloaded"));
+    }
+}
@@ -0,0 +1,1082 @@
+//! Archival memory: long-term recall outside the context window
+//!
+//! Text archived by an agent (explicit inserts, logged messages and blocks
+//! moved out of context) is split into overlapping chunks, embedded with an
+//! [`EmbeddingClient`] and stored as [`ArchivalPassage`]s in a
+//! [`VectorIndex`]. Searches take the best keyword matches and the nearest
+//! vectors from the index and fuse the two rankings, so exact names and
+//! loose paraphrases are both found.
+//!
+//! Indexes are pluggable: [`FlatIndex`] scans every passage, [`HnswIndex`]
+//! keeps an approximate nearest-neighbour graph and an inverted index in
+//! process, and `PostgresStorage` stores passages in pgvector with full-text
+//! search (`storage` feature).
+//!
+//! ```rust,ignore
+//! let archival = Arc::new(ArchivalMemory::new(agent_id, embedder, Arc::new(HnswIndex::new())));
+//! let memory = Arc::new(AgentMemory::new(agent_id, MemoryConfig::default()).with_archival(archival));
+//! let agent = Agent::builder().memory(memory) /* archival_insert and archival_search included */;
+//! ```
+
+use crate::embeddings::{cosine_similarity, EmbeddingClient};
+use crate::error::{Error, Result};
+use crate::memory::{MemoryBlock, MemoryBlockId, MessageEntry};
+use crate::types::AgentId;
+use async_trait::async_trait;
+use chrono::{DateTime, Utc};
+use parking_lot::RwLock;
+use serde::{Deserialize, Serialize};
+use std::cmp::{Ordering, Reverse};
+use std::collections::{BinaryHeap, HashMap, HashSet};
+use uuid::Uuid;
+
+/// Where an archived passage came from
+#[derive(Debug, Clone, PartialEq, Eq, Hash, Serialize, Deserialize)]
+#[serde(tag = "type", rename_all = "snake_case")]
+pub enum PassageSource {
+    /// Inserted directly, e.g. by the `archival_insert` tool
+    Insert,
+    /// A message from the agent's history
+    Message {
+        /// Message ID
+        message_id: Uuid,
+    },
+    /// A memory block moved out of context
+    Block {
+        /// Block ID
+        block_id: MemoryBlockId,
+    },
+}
+
+/// One embedded chunk of archived text
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct ArchivalPassage {
+    /// Unique passage ID
+    pub id: Uuid,
+    /// Agent that owns the passage
+    pub agent_id: AgentId,
+    /// Where the text came from
+    pub source: PassageSource,
+    /// Chunk text
+    pub text: String,
+    /// When the source was archived
+    pub created_at: DateTime<Utc>,
+    /// Custom fields, e.g. message role or block label
+    pub metadata: HashMap<String, String>,
+    /// Embedding of `text`
+    pub embedding: Vec<f32>,
+}
+
+/// Passage returned by a search with its vector or keyword score
+#[derive(Debug, Clone)]
+pub struct ScoredPassage {
+    /// Matching passage
+    pub passage: ArchivalPassage,
+    /// Cosine similarity to the query, or keyword relevance for
+    /// [`VectorIndex::keyword`]
+    pub score: f32,
+}
+
+/// Storage for embedded passages with nearest-neighbour search
+#[async_trait]
+pub trait VectorIndex: Send + Sync {
+    /// Add passages to the index
+    async fn insert(&self, passages: Vec<ArchivalPassage>) -> Result<()>;
+
+    /// Remove every passage of an agent that came from `source`
+    async fn remove_source(&self, agent_id: AgentId, source: &PassageSource) -> Result<()>;
+
+    /// Up to `limit` passages of an agent most similar to `vector`, best first
+    async fn nearest(
+        &self,
+        agent_id: AgentId,
+        vector: &[f32],
+        limit: usize,
+    ) -> Result<Vec<ScoredPassage>>;
+
+    /// Up to `limit` passages of an agent that share terms with `query`,
+    /// best keyword match first
+    async fn keyword(
+        &self,
+        agent_id: AgentId,
+        query: &str,
+        limit: usize,
+    ) -> Result<Vec<ScoredPassage>>;
+
+    /// Every passage of an agent
+    async fn passages(&self, agent_id: AgentId) -> Result<Vec<ArchivalPassage>>;
+}
+
+/// Exact search over every passage
+///
+/// Both vector and keyword searches score every passage of the agent. Fine
+/// for tens of thousands of passages; beyond that use [`HnswIndex`].
+#[derive(Default)]
+pub struct FlatIndex {
+    passages: RwLock<Vec<ArchivalPassage>>,
+}
+
+impl FlatIndex {
+    /// Create an empty index
+    pub fn new() -> Self {
+        Self::default()
+    }
+}
+
+#[async_trait]
+impl VectorIndex for FlatIndex {
+    async fn insert(&self, passages: Vec<ArchivalPassage>) -> Result<()> {
+        self.passages.write().extend(passages);
+        Ok(())
+    }
+
+    async fn remove_source(&self, agent_id: AgentId, source: &PassageSource) -> Result<()> {
+        self.passages
+            .write()
+            .retain(|p| p.agent_id != agent_id || &p.source != source);
+        Ok(())
+    }
+
+    async fn nearest(
+        &self,
+        agent_id: AgentId,
+        vector: &[f32],
+        limit: usize,
+    ) -> Result<Vec<ScoredPassage>> {
+        let mut scored: Vec<ScoredPassage> = self
+            .passages
+            .read()
+            .iter()
+            .filter(|p| p.agent_id == agent_id)
+            .map(|p| ScoredPassage {
+                score: cosine_similarity(vector, &p.embedding),
+                passage: p.clone(),
+            })
+            .collect();
+        scored.sort_by(|a, b| b.score.total_cmp(&a.score));
+        scored.truncate(limit);
+        Ok(scored)
+    }
+
+    async fn keyword(
+        &self,
+        agent_id: AgentId,
+        query: &str,
+        limit: usize,
+    ) -> Result<Vec<ScoredPassage>> {
+        let passages = self.passages(agent_id).await?;
+        let texts: Vec<&str> = passages.iter().map(|p| p.text.as_str()).collect();
+        let mut scored: Vec<ScoredPassage> = bm25_scores(query, &texts)
+            .into_iter()
+            .zip(&passages)
+            .filter(|(score, _)| *score > 0.0)
+            .map(|(score, passage)| ScoredPassage {
+                passage: passage.clone(),
+                score,
+            })
+            .collect();
+        scored.sort_by(|a, b| b.score.total_cmp(&a.score));
+        scored.truncate(limit);
+        Ok(scored)
+    }
+
+    async fn passages(&self, agent_id: AgentId) -> Result<Vec<ArchivalPassage>> {
+        Ok(self
+            .passages
+            .read()
+            .iter()
+            .filter(|p| p.agent_id == agent_id)
+            .cloned()
+            .collect())
+    }
+}
+
+/// Graph distance, ordered so heaps can hold it
+#[derive(Debug, Clone, Copy, PartialEq)]
+struct Candidate {
+    distance: f32,
+    node: usize,
+}
+
+impl Eq for Candidate {}
+
+impl PartialOrd for Candidate {
+    fn partial_cmp(&self, other: &Self) -> Option<Ordering> {
+        Some(self.cmp(other))
+    }
+}
+
+impl Ord for Candidate {
+    fn cmp(&self, other: &Self) -> Ordering {
+        self.distance
+            .total_cmp(&other.distance)
+            .then_with(|| self.node.cmp(&other.node))
+    }
+}
+
+/// Item stored in an HNSW graph
+pub(crate) trait Embedded {
+    /// Vector the item is indexed by
+    fn embedding(&self) -> &[f32];
+}
+
+impl Embedded for ArchivalPassage {
+    fn embedding(&self) -> &[f32] {
+        &self.embedding
+    }
+}
+
+struct HnswNode<T> {
+    item: T,
+    /// Neighbours on each layer the node belongs to
+    neighbors: Vec<Vec<usize>>,
+    /// Removed nodes stay in the graph for routing but are never returned
+    deleted: bool,
+}
+
+/// Navigable small-world graph over embedded items
+pub(crate) struct HnswGraph<T> {
+    nodes: Vec<HnswNode<T>>,
+    entry: Option<usize>,
+    max_level: usize,
+    rng: u64,
+}
+
+impl<T> Default for HnswGraph<T> {
+    fn default() -> Self {
+        Self {
+            nodes: Vec::new(),
+            entry: None,
+            max_level: 0,
+            rng: 0,
+        }
+    }
+}
+
+impl<T: Embedded + Clone> HnswGraph<T> {
+    fn distance(&self, vector: &[f32], node: usize) -> f32 {
+        1.0 - cosine_similarity(vector, self.nodes[node].item.embedding())
+    }
+
+    /// Level for a new node, geometrically distributed
+    fn random_level(&mut self, m: usize) -> usize {
+        // splitmix64, so graphs are reproducible
+        self.rng = self.rng.wrapping_add(0x9e3779b97f4a7c15);
+        let mut z = self.rng;
+        z = (z ^ (z >> 30)).wrapping_mul(0xbf58476d1ce4e5b9);
+        z = (z ^ (z >> 27)).wrapping_mul(0x94d049bb133111eb);
+        z ^= z >> 31;
+        let uniform = ((z >> 11) as f64 + 1.0) / (1u64 << 53) as f64;
+        (-uniform.ln() / (m as f64).ln()).floor() as usize
+    }
+
+    /// Best-first search of one layer, returning up to `ef` nodes nearest first
+    fn search_layer(
+        &self,
+        vector: &[f32],
+        entry_points: &[usize],
+        ef: usize,
+        level: usize,
+    ) -> Vec<Candidate> {
+        let mut visited: HashSet<usize> = entry_points.iter().copied().collect();
+        let mut frontier = BinaryHeap::new();
+        let mut best = BinaryHeap::new();
+        for &node in entry_points {
+            let candidate = Candidate {
+                distance: self.distance(vector, node),
+                node,
+            };
+            frontier.push(Reverse(candidate));
+            best.push(candidate);
+        }
+
+        while let Some(Reverse(current)) = frontier.pop() {
+            if best.len() >= ef
+                && best
+                    .peek()
+                    .is_some_and(|worst: &Candidate| current.distance > worst.distance)
+            {
+                break;
+            }
+            for &neighbor in &self.nodes[current.node].neighbors[level] {
+                if !visited.insert(neighbor) {
+                    continue;
+                }
+                let candidate = Candidate {
+                    distance: self.distance(vector, neighbor),
+                    node: neighbor,
+                };
+                if best.len() < ef
+                    || best
+                        .peek()
+                        .is_some_and(|worst| candidate.distance < worst.distance)
+                {
+                    frontier.push(Reverse(candidate));
+                    best.push(candidate);
+                    if best.len() > ef {
+                        best.pop();
+                    }
+                }
+            }
+        }
+
+        best.into_sorted_vec()
+    }
+
+    /// Add an item, linking it to its nearest neighbours, and return its node
+    pub(crate) fn insert(&mut self, item: T, m: usize, ef_construction: usize) -> usize {
+        let level = self.random_level(m);
+        let vector = item.embedding().to_vec();
+        let node = self.nodes.len();
+        self.nodes.push(HnswNode {
+            item,
+            neighbors: vec![Vec::new(); level + 1],
+            deleted: false,
+        });
+
+        let Some(mut entry) = self.entry else {
+            self.entry = Some(node);
+            self.max_level = level;
+            return node;
+        };
+
+        // Descend greedily through layers above the new node's level
+        for layer in (level + 1..=self.max_level).rev() {
+            entry = self.search_layer(&vector, &[entry], 1, layer)[0].node;
+        }
+
+        let mut entry_points = vec![entry];
+        for layer in (0..=level.min(self.max_level)).rev() {
+            let candidates = self.search_layer(&vector, &entry_points, ef_construction, layer);
+            let max_links = if layer == 0 { 2 * m } else { m };
+            let links: Vec<usize> = candidates.iter().take(max_links).map(|c| c.node).collect();
+
+            for &link in &links {
+                self.nodes[link].neighbors[layer].push(node);
+                if self.nodes[link].neighbors[layer].len() > max_links {
+                    self.prune(link, layer, max_links);
+                }
+            }
+            self.nodes[node].neighbors[layer] = links;
+            entry_points = candidates.into_iter().map(|c| c.node).collect();
+        }
+
+        if level > self.max_level {
+            self.entry = Some(node);
+            self.max_level = level;
+        }
+        node
+    }
+
+    /// Keep only the `max_links` nearest neighbours of `node` on `layer`
+    fn prune(&mut self, node: usize, layer: usize, max_links: usize) {
+        let vector = self.nodes[node].item.embedding().to_vec();
+        let mut neighbors: Vec<Candidate> = self.nodes[node].neighbors[layer]
+            .iter()
+            .map(|&n| Candidate {
+                distance: self.distance(&vector, n),
+                node: n,
+            })
+            .collect();
+        neighbors.sort();
+        neighbors.truncate(max_links);
+        self.nodes[node].neighbors[layer] = neighbors.into_iter().map(|c| c.node).collect();
+    }
+
+    /// Up to `limit` live items nearest to `vector` with their cosine
+    /// similarity, best first
+    pub(crate) fn search(&self, vector: &[f32], limit: usize, ef: usize) -> Vec<(T, f32)> {
+        let Some(mut entry) = self.entry else {
+            return Vec::new();
+        };
+        for layer in (1..=self.max_level).rev() {
+            entry = self.search_layer(vector, &[entry], 1, layer)[0].node;
+        }
+
+        // Widen the search by the number of removed nodes it may run into
+        let deleted = self.nodes.iter().filter(|n| n.deleted).count();
+        self.search_layer(vector, &[entry], ef.max(limit) + deleted.min(ef), 0)
+            .into_iter()
+            .filter(|c| !self.nodes[c.node].deleted)
+            .take(limit)
+            .map(|c| (self.nodes[c.node].item.clone(), 1.0 - c.distance))
+            .collect()
+    }
+
+    /// Tombstone every live item matching `predicate`, returning their nodes
+    pub(crate) fn remove(&mut self, predicate: impl Fn(&T) -> bool) -> Vec<usize> {
+        let mut removed = Vec::new();
+        for (index, node) in self.nodes.iter_mut().enumerate() {
+            if !node.deleted && predicate(&node.item) {
+                node.deleted = true;
+                removed.push(index);
+            }
+        }
+        removed
+    }
+
+    /// Live item at `node`
+    pub(crate) fn get(&self, node: usize) -> Option<&T> {
+        self.nodes.get(node).filter(|n| !n.deleted).map(|n| &n.item)
+    }
+
+    /// Live items in insertion order
+    pub(crate) fn items(&self) -> impl Iterator<Item = &T> {
+        self.nodes.iter().filter(|n| !n.deleted).map(|n| &n.item)
+    }
+}
+
+/// Term postings for BM25 ranking without scanning every document
+#[derive(Default)]
+struct InvertedIndex {
+    /// Term frequency of each term per document
+    postings: HashMap<String, HashMap<usize, u32>>,
+    /// Number of terms in each document
+    lengths: HashMap<usize, usize>,
+    total_len: usize,
+}
+
+impl InvertedIndex {
+    fn insert(&mut self, doc: usize, text: &str) {
+        let terms = tokenize(text);
+        self.lengths.insert(doc, terms.len());
+        self.total_len += terms.len();
+        for term in terms {
+            *self
+                .postings
+                .entry(term)
+                .or_default()
+                .entry(doc)
+                .or_default() += 1;
+        }
+    }
+
+    fn remove(&mut self, doc: usize, text: &str) {
+        let Some(len) = self.lengths.remove(&doc) else {
+            return;
+        };
+        self.total_len -= len;
+        for term in tokenize(text) {
+            if let Some(postings) = self.postings.get_mut(&term) {
+                postings.remove(&doc);
+                if postings.is_empty() {
+                    self.postings.remove(&term);
+                }
+            }
+        }
+    }
+
+    /// Up to `limit` documents containing a query term with their BM25
+    /// score, scored as in [`bm25_scores`], best first
+    fn search(&self, query: &str, limit: usize) -> Vec<(usize, f32)> {
+        const K1: f32 = 1.2;
+        const B: f32 = 0.75;
+
+        if self.lengths.is_empty() {
+            return Vec::new();
+        }
+        let n = self.lengths.len() as f32;
+        let average_len = self.total_len as f32 / n;
+
+        let terms: HashSet<String> = tokenize(query).into_iter().collect();
+        let mut scores: HashMap<usize, f32> = HashMap::new();
+        for postings in terms.iter().filter_map(|term| self.postings.get(term)) {
+            let df = postings.len() as f32;
+            let idf = (1.0 + (n - df + 0.5) / (df + 0.5)).ln();
+            for (&doc, &tf) in postings {
+                let tf = tf as f32;
+                let len_norm = 1.0 - B + B * self.lengths[&doc] as f32 / average_len.max(1.0);
+                *scores.entry(doc).or_default() += idf * tf * (K1 + 1.0) / (tf + K1 * len_norm);
+            }
+        }
+
+        let mut scores: Vec<(usize, f32)> = scores.into_iter().collect();
+        scores.sort_by(|a, b| b.1.total_cmp(&a.1).then_with(|| a.0.cmp(&b.0)));
+        scores.truncate(limit);
+        scores
+    }
+}
+
+/// One agent's passages in an [`HnswIndex`]
+#[derive(Default)]
+struct HnswShard {
+    graph: HnswGraph<ArchivalPassage>,
+    keywords: InvertedIndex,
+}
+
+/// Approximate nearest-neighbour index using HNSW graphs, one per agent
+///
+/// Searches visit a small part of the graph, so they stay fast as the
+/// archive grows, at the cost of occasionally missing a close match.
+/// Removed passages are tombstoned rather than unlinked. Keyword searches
+/// use an inverted index kept alongside each graph.
+pub struct HnswIndex {
+    graphs: RwLock<HashMap<AgentId, HnswShard>>,
+    m: usize,
+    ef_construction: usize,
+    ef_search: usize,
+}
+
+impl HnswIndex {
+    /// Create an index with 16 links per node
+    pub fn new() -> Self {
+        Self {
+            graphs: RwLock::new(HashMap::new()),
+            m: 16,
+            ef_construction: 100,
+            ef_search: 64,
+        }
+    }
+
+    /// Set the number of links per node (twice this on the bottom layer)
+    pub fn with_m(mut self, m: usize) -> Self {
+        self.m = m.max(2);
+        self
+    }
+
+    /// Set the candidate list size used while building the graph
+    pub fn with_ef_construction(mut self, ef: usize) -> Self {
+        self.ef_construction = ef.max(1);
+        self
+    }
+
+    /// Set the candidate list size used while searching
+    pub fn with_ef_search(mut self, ef: usize) -> Self {
+        self.ef_search = ef.max(1);
+        self
+    }
+}
+
+impl Default for HnswIndex {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+#[async_trait]
+impl VectorIndex for HnswIndex {
+    async fn insert(&self, passages: Vec<ArchivalPassage>) -> Result<()> {
+        let mut graphs = self.graphs.write();
+        for passage in passages {
+            let shard = graphs.entry(passage.agent_id).or_default();
+            let text = passage.text.clone();
+            let node = shard.graph.insert(passage, self.m, self.ef_construction);
+            shard.keywords.insert(node, &text);
+        }
+        Ok(())
+    }
+
+    async fn remove_source(&self, agent_id: AgentId, source: &PassageSource) -> Result<()> {
+        if let Some(shard) = self.graphs.write().get_mut(&agent_id) {
+            for node in shard.graph.remove(|passage| &passage.source == source) {
+                let text = &shard.graph.nodes[node].item.text;
+                shard.keywords.remove(node, text);
+            }
+        }
+        Ok(())
+    }
+
+    async fn nearest(
+        &self,
+        agent_id: AgentId,
+        vector: &[f32],
+        limit: usize,
+    ) -> Result<Vec<ScoredPassage>> {
+        Ok(self
+            .graphs
+            .read()
+            .get(&agent_id)
+            .map(|shard| {
+                shard
+                    .graph
+                    .search(vector, limit, self.ef_search)
+                    .into_iter()
+                    .map(|(passage, score)| ScoredPassage { passage, score })
+                    .collect()
+            })
+            .unwrap_or_default())
+    }
+
+    async fn keyword(
+        &self,
+        agent_id: AgentId,
+        query: &str,
+        limit: usize,
+    ) -> Result<Vec<ScoredPassage>> {
+        Ok(self
+            .graphs
+            .read()
+            .get(&agent_id)
+            .map(|shard| {
+                shard
+                    .keywords
+                    .search(query, limit)
+                    .into_iter()
+                    .filter_map(|(node, score)| {
+                        let passage = shard.graph.get(node)?.clone();
+                        Some(ScoredPassage { passage, score })
+                    })
+                    .collect()
+            })
+            .unwrap_or_default())
+    }
+
+    async fn passages(&self, agent_id: AgentId) -> Result<Vec<ArchivalPassage>> {
+        Ok(self
+            .graphs
+            .read()
+            .get(&agent_id)
+            .map(|shard| shard.graph.items().cloned().collect())
+            .unwrap_or_default())
+    }
+}
+
+/// Lowercase alphanumeric terms
+fn tokenize(text: &str) -> Vec<String> {
+    text.split(|c: char| !c.is_alphanumeric())
+        .filter(|t| !t.is_empty())
+        .map(str::to_lowercase)
+        .collect()
+}
+
+/// Okapi BM25 scores of `query` against each document, with k1 = 1.2, b = 0.75
+pub fn bm25_scores(query: &str, documents: &[&str]) -> Vec<f32> {
+    const K1: f32 = 1.2;
+    const B: f32 = 0.75;
+
+    let terms: HashSet<String> = tokenize(query).into_iter().collect();
+    let docs: Vec<Vec<String>> = documents.iter().map(|d| tokenize(d)).collect();
+    if docs.is_empty() {
+        return Vec::new();
+    }
+    let average_len = docs.iter().map(Vec::len).sum::<usize>() as f32 / docs.len() as f32;
+
+    let idf: HashMap<&String, f32> = terms
+        .iter()
+        .map(|term| {
+            let df = docs.iter().filter(|d| d.contains(term)).count() as f32;
+            let n = docs.len() as f32;
+            (term, (1.0 + (n - df + 0.5) / (df + 0.5)).ln())
+        })
+        .collect();
+
+    docs.iter()
+        .map(|doc| {
+            let len_norm = 1.0 - B + B * doc.len() as f32 / average_len.max(1.0);
+            terms
+                .iter()
+                .map(|term| {
+                    let tf = doc.iter().filter(|t| *t == term).count() as f32;
+                    idf[term] * tf * (K1 + 1.0) / (tf + K1 * len_norm)
+                })
+                .sum()
+        })
+        .collect()
+}
+
+/// Split text into chunks of at most `max_chars`, repeating up to `overlap`
+/// characters of each chunk at the start of the next
+///
+/// Chunks break between words; a single word longer than `max_chars` becomes
+/// its own chunk.
+pub fn chunk_text(text: &str, max_chars: usize, overlap: usize) -> Vec<String> {
+    let words: Vec<&str> = text.split_whitespace().collect();
+    let mut chunks = Vec::new();
+    let mut start = 0;
+
+    while start < words.len() {
+        let mut end = start;
+        let mut len = 0;
+        while end < words.len() {
+            let added = words[end].len() + usize::from(end > start);
+            if end > start && len + added > max_chars {
+                break;
+            }
+            len += added;
+            end += 1;
+        }
+        chunks.push(words[start..end].join(" "));
+        if end == words.len() {
+            break;
+        }
+
+        // Step back over trailing words that fit in the overlap
+        let mut next = end;
+        let mut carried = 0;
+        while next > start + 1 && carried + words[next - 1].len() < overlap {
+            carried += words[next - 1].len() + 1;
+            next -= 1;
+        }
+        start = next;
+    }
+
+    chunks
+}
+
+/// Passage found by [`ArchivalMemory::search`]
+#[derive(Debug, Clone)]
+pub struct ArchivalHit {
+    /// Matching passage
+    pub passage: ArchivalPassage,
+    /// Fused rank score; higher is better
+    pub score: f32,
+    /// Cosine similarity, when the passage was a vector candidate
+    pub vector_score: Option<f32>,
+    /// Keyword score, when the passage matched query terms: BM25 in process,
+    /// `ts_rank` in PostgreSQL
+    pub keyword_score: Option<f32>,
+}
+
+/// Chunked, embedded long-term memory for one agent
+pub struct ArchivalMemory {
+    agent_id: AgentId,
+    embedder: std::sync::Arc<dyn EmbeddingClient>,
+    index: std::sync::Arc<dyn VectorIndex>,
+    chunk_chars: usize,
+    chunk_overlap: usize,
+}
+
+/// Constant in reciprocal rank fusion; larger values flatten rank differences
+const RRF_K: f32 = 60.0;
+
+impl ArchivalMemory {
+    /// Archive for `agent_id`, embedding with `embedder` and storing in `index`
+    pub fn new(
+        agent_id: AgentId,
+        embedder: std::sync::Arc<dyn EmbeddingClient>,
+        index: std::sync::Arc<dyn VectorIndex>,
+    ) -> Self {
+        Self {
+            agent_id,
+            embedder,
+            index,
+            chunk_chars: 1000,
+            chunk_overlap: 100,
+        }
+    }
+
+    /// Set the chunk size and the overlap between neighbouring chunks, in characters
+    pub fn with_chunking(mut self, chunk_chars: usize, overlap: usize) -> Self {
+        self.chunk_chars = chunk_chars.max(1);
+        self.chunk_overlap = overlap.min(chunk_chars / 2);
+        self
+    }
+
+    /// Agent this archive belongs to
+    pub fn agent_id(&self) -> AgentId {
+        self.agent_id
+    }
+
+    /// Chunk, embed and store `text`, returning the new passage IDs
+    pub async fn insert(
+        &self,
+        text: &str,
+        source: PassageSource,
+        metadata: HashMap<String, String>,
+    ) -> Result<Vec<Uuid>> {
+        let chunks = chunk_text(text, self.chunk_chars, self.chunk_overlap);
+        if chunks.is_empty() {
+            return Ok(Vec::new());
+        }
+
+        let embeddings = self.embedder.embed_batch(&chunks).await?;
+        if embeddings.len() != chunks.len() {
+            return Err(Error::other(format!(
+                "Expected {} embeddings, got {}",
+                chunks.len(),
+                embeddings.len()
+            )));
+        }
+
+        let created_at = Utc::now();
+        let passages: Vec<ArchivalPassage> = chunks
+            .into_iter()
+            .zip(embeddings)
+            .map(|(text, embedding)| ArchivalPassage {
+                id: Uuid::new_v4(),
+                agent_id: self.agent_id,
+                source: source.clone(),
+                text,
+                created_at,
+                metadata: metadata.clone(),
+                embedding,
+            })
+            .collect();
+        let ids = passages.iter().map(|p| p.id).collect();
+        self.index.insert(passages).await?;
+        Ok(ids)
+    }
+
+    /// Archive a message from the agent's history
+    pub async fn archive_message(&self, message: &MessageEntry) -> Result<Vec<Uuid>> {
+        let mut metadata = message.metadata.clone();
+        metadata.insert("role".to_string(), message.role.clone());
+        self.insert(
+            &message.content,
+            PassageSource::Message {
+                message_id: message.id,
+            },
+            metadata,
+        )
+        .await
+    }
+
+    /// Archive a memory block, replacing any earlier copy of it
+    pub async fn archive_block(&self, block: &MemoryBlock) -> Result<Vec<Uuid>> {
+        let source = PassageSource::Block { block_id: block.id };
+        self.index.remove_source(self.agent_id, &source).await?;
+
+        let mut metadata = block.metadata.clone();
+        metadata.insert("label".to_string(), block.label.clone());
+        let text = if block.description.is_empty() {
+            format!("{}: {}", block.label, block.value)
+        } else {
+            format!("{} ({}): {}", block.label, block.description, block.value)
+        };
+        self.insert(&text, source, metadata).await
+    }
+
+    /// Passages most relevant to `query`, best first
+    ///
+    /// Vector and BM25 rankings are combined with reciprocal rank fusion, so
+    /// a passage ranked well by either appears near the top.
+    pub async fn search(&self, query: &str, limit: usize) -> Result<Vec<ArchivalHit>> {
+        if limit == 0 {
+            return Ok(Vec::new());
+        }
+        let candidates = limit * 4;
+
+        let vector = self.embedder.embed_one(query).await?;
+        let nearest = self
+            .index
+            .nearest(self.agent_id, &vector, candidates)
+            .await?;
+        let keyword = self.index.keyword(self.agent_id, query, candidates).await?;
+
+        let mut hits: HashMap<Uuid, ArchivalHit> = HashMap::new();
+        for (rank, scored) in nearest.into_iter().enumerate() {
+            let hit = hits
+                .entry(scored.passage.id)
+                .or_insert_with(|| ArchivalHit {
+                    passage: scored.passage,
+                    score: 0.0,
+                    vector_score: None,
+                    keyword_score: None,
+                });
+            hit.score += 1.0 / (RRF_K + rank as f32 + 1.0);
+            hit.vector_score = Some(scored.score);
+        }
+        for (rank, scored) in keyword.into_iter().enumerate() {
+            let hit = hits
+                .entry(scored.passage.id)
+                .or_insert_with(|| ArchivalHit {
+                    passage: scored.passage,
+                    score: 0.0,
+                    vector_score: None,
+                    keyword_score: None,
+                });
+            hit.score += 1.0 / (RRF_K + rank as f32 + 1.0);
+            hit.keyword_score = Some(scored.score);
+        }
+
+        let mut hits: Vec<ArchivalHit> = hits.into_values().collect();
+        hits.sort_by(|a, b| {
+            b.score
+                .total_cmp(&a.score)
+                .then_with(|| b.passage.created_at.cmp(&a.passage.created_at))
+        });
+        hits.truncate(limit);
+        Ok(hits)
+    }
+}
+
+impl std::fmt::Debug for ArchivalMemory {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.debug_struct("ArchivalMemory")
+            .field("agent_id", &self.agent_id)
+            .field("embedder", &self.embedder.model_id())
+            .field("chunk_chars", &self.chunk_chars)
+            .field("chunk_overlap", &self.chunk_overlap)
+            .finish()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::embeddings::HashingEmbedder;
+    use std::sync::Arc;
+
+    fn passage(agent_id: AgentId, embedding: Vec<f32>) -> ArchivalPassage {
+        ArchivalPassage {
+            id: Uuid::new_v4(),
+            agent_id,
+            source: PassageSource::Insert,
+            text: String::new(),
+            created_at: Utc::now(),
+            metadata: HashMap::new(),
+            embedding,
+        }
+    }
+
+    #[test]
+    fn test_chunk_text_overlaps() {
+        let text = "one two three four five six seven eight nine ten";
+        let chunks = chunk_text(text, 14, 6);
+        assert_eq!(chunks[0], "one two three");
+        assert_eq!(chunks[1], "three four");
+        assert!(chunks.iter().all(|c| c.len() <= 14));
+        assert_eq!(chunks.last().unwrap(), "eight nine ten");
+        assert_eq!(chunk_text("  ", 10, 2), Vec::<String>::new());
+    }
+
+    #[test]
+    fn test_bm25_prefers_rare_terms() {
+        let docs = ["the cat sat", "the dog sat", "the zebra"];
+        let scores = bm25_scores("zebra sat", &docs);
+        assert!(scores[2] > scores[0]);
+        assert_eq!(scores[0], scores[1]);
+        assert_eq!(bm25_scores("unrelated", &docs), vec![0.0; 3]);
+    }
+
+    #[tokio::test]
+    async fn test_hnsw_matches_flat_search() {
+        let agent_id = AgentId::new();
+        let mut seed = 7u64;
+        let mut random = move || {
+            seed ^= seed << 13;
+            seed ^= seed >> 7;
+            seed ^= seed << 17;
+            (seed % 2000) as f32 / 1000.0 - 1.0
+        };
+        let passages: Vec<ArchivalPassage> = (0..300)
+            .map(|_| passage(agent_id, (0..16).map(|_| random()).collect()))
+            .collect();
+
+        let flat = FlatIndex::new();
+        let hnsw = HnswIndex::new().with_m(8);
+        flat.insert(passages.clone()).await.unwrap();
+        hnsw.insert(passages.clone()).await.unwrap();
+
+        let mut found = 0;
+        for query in passages.iter().take(20) {
+            let exact: HashSet<Uuid> = flat
+                .nearest(agent_id, &query.embedding, 5)
+                .await
+                .unwrap()
+                .into_iter()
+                .map(|s| s.passage.id)
+                .collect();
+            let approximate = hnsw.nearest(agent_id, &query.embedding, 5).await.unwrap();
+            assert_eq!(approximate[0].passage.id, query.id);
+            found += approximate
+                .iter()
+                .filter(|s| exact.contains(&s.passage.id))
+                .count();
+        }
+        assert!(found >= 90, "recall {}/100", found);
+
+        assert!(hnsw
+            .nearest(AgentId::new(), &passages[0].embedding, 5)
+            .await
+            .unwrap()
+            .is_empty());
+    }
+
+    #[tokio::test]
+    async fn test_keyword_search_uses_inverted_index() {
+        let agent_id = AgentId::new();
+        let texts = [
+            "the cat sat",
+            "the dog sat",
+            "the zebra",
+            "zebra crossing ahead",
+        ];
+        let passages: Vec<ArchivalPassage> = texts
+            .iter()
+            .map(|text| ArchivalPassage {
+                text: text.to_string(),
+                ..passage(agent_id, vec![1.0, 0.0])
+            })
+            .collect();
+
+        let flat = FlatIndex::new();
+        let hnsw = HnswIndex::new();
+        flat.insert(passages.clone()).await.unwrap();
+        hnsw.insert(passages.clone()).await.unwrap();
+
+        for query in ["zebra sat", "dog", "crossing the zebra"] {
+            let expected = flat.keyword(agent_id, query, 3).await.unwrap();
+            let found = hnsw.keyword(agent_id, query, 3).await.unwrap();
+            assert_eq!(found.len(), expected.len(), "{}", query);
+            for (found, expected) in found.iter().zip(&expected) {
+                assert!((found.score - expected.score).abs() < 1e-5, "{}", query);
+            }
+            assert_eq!(found[0].passage.id, expected[0].passage.id, "{}", query);
+        }
+        assert!(hnsw
+            .keyword(agent_id, "unrelated", 3)
+            .await
+            .unwrap()
+            .is_empty());
+
+        let mut block_passage = passage(agent_id, vec![0.0, 1.0]);
+        block_passage.text = "zebra block".to_string();
+        block_passage.source = PassageSource::Block {
+            block_id: MemoryBlockId::new(),
+        };
+        let source = block_passage.source.clone();
+        hnsw.insert(vec![block_passage]).await.unwrap();
+        hnsw.remove_source(agent_id, &source).await.unwrap();
+        let found = hnsw.keyword(agent_id, "block", 3).await.unwrap();
+        assert!(found.is_empty());
+    }
+
+    #[tokio::test]
+    async fn test_hybrid_search_and_block_replacement() {
+        let agent_id = AgentId::new();
+        let index: Arc<dyn VectorIndex> = Arc::new(HnswIndex::new());
+        let archival =
+            ArchivalMemory::new(agent_id, Arc::new(HashingEmbedder::new(128)), index.clone())
+                .with_chunking(60, 10);
+
+        archival
+            .insert(
+                "The deploy key for project Orion rotates every March",
+                PassageSource::Insert,
+                HashMap::new(),
+            )
+            .await
+            .unwrap();
+        archival
+            .insert(
+                "Lunch options near the office include ramen and tacos",
+                PassageSource::Insert,
+                HashMap::new(),
+            )
+            .await
+            .unwrap();
+
+        let hits = archival
+            .search("when does the Orion deploy key rotate", 2)
+            .await
+            .unwrap();
+        assert!(hits[0].passage.text.contains("Orion"));
+        assert!(hits[0].keyword_score.is_some() && hits[0].vector_score.is_some());
+
+        let mut block = MemoryBlock::new("project", "Orion ships in May");
+        archival.archive_block(&block).await.unwrap();
+        block.update_value("Orion ships in June").unwrap();
+        archival.archive_block(&block).await.unwrap();
+
+        let texts: Vec<String> = index
+            .passages(agent_id)
+            .await
+            .unwrap()
+            .into_iter()
+            .map(|p| p.text)
+            .collect();
+        assert!(texts.iter().any(|t| t.contains("June")));
+        assert!(!texts.iter().any(|t| t.contains("May")));
+    }
+}
@@ -7,9 +7,11 @@
 //! - Connection recovery and state management
 //! - Background job tracking
 
-use crate::agent::{Agent, AgentOutput};
+use crate::agent::{Agent, AgentEvent, AgentOutput};
+use crate::budget::{self, BudgetLedger};
 use crate::error::{Error, Result};
 use chrono::{DateTime, Utc};
+use futures::StreamExt;
 use serde::{Deserialize, Serialize};
 use std::collections::HashMap;
 use std::sync::Arc;
@@ -178,10 +180,125 @@ struct BackgroundRun {
     task_handle: Option<tokio::task::JoinHandle<Result<AgentOutput>>>,
 }
 
+impl BackgroundRun {
+    /// Append an event with the next sequence ID
+    fn push_event(&mut self, event_type: RunEventType, data: serde_json::Value) {
+        self.events.push(RunEvent {
+            seq_id: self.metadata.last_seq_id,
+            timestamp: Utc::now(),
+            event_type,
+            data,
+        });
+        self.metadata.last_seq_id = self.metadata.last_seq_id.next();
+        self.metadata.total_events += 1;
+    }
+}
+
+/// Map an agent progress event onto the run event log
+///
+/// Token deltas are recorded as `Progress` events; observations are already
+/// covered by the matching `ToolResult`.
+fn forwarded_event(event: AgentEvent) -> Option<(RunEventType, serde_json::Value)> {
+    match event {
+        AgentEvent::TokenDelta { content } => Some((
+            RunEventType::Progress,
+            serde_json::json!({ "token": content }),
+        )),
+        AgentEvent::ThoughtComplete { thought } => Some((
+            RunEventType::Thought,
+            serde_json::json!({
+                "content": thought.content,
+                "tokens": thought.tokens.total_tokens
+            }),
+        )),
+        AgentEvent::ToolCallStarted { tool_id, call_id, params } => Some((
+            RunEventType::ToolCall,
+            serde_json::json!({
+                "tool": tool_id,
+                "call_id": call_id,
+                "params": params
+            }),
+        )),
+        AgentEvent::ToolCallFinished { tool_id, call_id, observation, duration_ms } => Some((
+            RunEventType::ToolResult,
+            serde_json::json!({
+                "tool": tool_id,
+                "call_id": call_id,
+                "content": observation.content,
+                "is_error": observation.is_error,
+                "duration_ms": duration_ms
+            }),
+        )),
+        AgentEvent::Observation { .. } | AgentEvent::FinalAnswer { .. } => None,
+    }
+}
+
+/// Upper bound on the progress events recorded under one lock acquisition
+const PROGRESS_BATCH: usize = 64;
+
+/// Drive `react_stream` and append its progress to the run's event log
+///
+/// Events that are already available are collected into a batch and
+/// consecutive token deltas merged, so the run lock is taken once per batch
+/// rather than once per token.
+async fn run_streaming(
+    agent: &Agent,
+    input: &str,
+    runs: &RwLock<HashMap<RunId, BackgroundRun>>,
+    run_id: RunId,
+) -> Result<AgentOutput> {
+    let mut result = Err(Error::agent("Agent stream ended without a final answer"));
+    let stream = agent.react_stream(input).ready_chunks(PROGRESS_BATCH);
+    futures::pin_mut!(stream);
+
+    while let Some(chunk) = stream.next().await {
+        let mut batch: Vec<(RunEventType, serde_json::Value)> = Vec::new();
+        let mut pending_tokens = String::new();
+
+        for event in chunk {
+            match event {
+                Ok(AgentEvent::FinalAnswer { output }) => result = Ok(output),
+                Ok(AgentEvent::TokenDelta { content }) => pending_tokens.push_str(&content),
+                Ok(event) => {
+                    if let Some(forwarded) = forwarded_event(event) {
+                        flush_tokens(&mut pending_tokens, &mut batch);
+                        batch.push(forwarded);
+                    }
+                }
+                Err(e) => result = Err(e),
+            }
+        }
+        flush_tokens(&mut pending_tokens, &mut batch);
+
+        if batch.is_empty() {
+            continue;
+        }
+        if let Some(run) = runs.write().await.get_mut(&run_id) {
+            for (event_type, data) in batch {
+                run.push_event(event_type, data);
+            }
+        }
+    }
+
+    result
+}
+
+/// Move buffered token deltas into the batch as a single `Progress` event
+fn flush_tokens(pending: &mut String, batch: &mut Vec<(RunEventType, serde_json::Value)>) {
+    if !pending.is_empty() {
+        let token = std::mem::take(pending);
+        batch.push((RunEventType::Progress, serde_json::json!({ "token": token })));
+    }
+}
+
 /// Manager for background runs
 pub struct BackgroundExecutor {
     /// All active and completed runs
     runs: Arc<RwLock<HashMap<RunId, BackgroundRun>>>,
+    /// Budget charged by every run
+    budget: Option<Arc<BudgetLedger>>,
+    /// Run agents through `react_stream` and record progress events
+    stream_progress: bool,
 }
 
 impl BackgroundExecutor {
@@ -189,9 +306,26 @@ impl BackgroundExecutor {
     pub fn new() -> Self {
         Self {
             runs: Arc::new(RwLock::new(HashMap::new())),
+            budget: None,
+            stream_progress: false,
         }
     }
 
+    /// Charge every run's LLM calls to a shared budget
+    pub fn with_budget(mut self, budget: Arc<BudgetLedger>) -> Self {
+        self.budget = Some(budget);
+        self
+    }
+
+    /// Record token, thought and tool progress while runs execute
+    ///
+    /// Runs go through [`Agent::react_stream`] instead of
+    /// [`Agent::react_loop`], so the client must support streaming.
+    pub fn with_progress_streaming(mut self, enabled: bool) -> Self {
+        self.stream_progress = enabled;
+        self
+    }
+
     /// Start an agent execution in the background
     pub async fn execute_async(
         &self,
@@ -213,34 +347,41 @@ impl BackgroundExecutor {
             metadata: HashMap::new(),
         };
 
-        // Spawn background task
+        // Register the run before spawning so no early events are lost
+        self.runs.write().await.insert(
+            run_id,
+            BackgroundRun {
+                metadata,
+                events: Vec::new(),
+                task_handle: None,
+            },
+        );
+
         let runs = self.runs.clone();
-        let handle = tokio::spawn(async move {
+        let stream_progress = self.stream_progress;
+        let task = async move {
             // Update status to Running
             {
                 let mut runs_lock = runs.write().await;
                 if let Some(run) = runs_lock.get_mut(&run_id) {
                     run.metadata.status = RunStatus::Running;
                     run.metadata.started_at = Some(Utc::now());
-
-                    // Add started event
-                    let event = RunEvent {
-                        seq_id: run.metadata.last_seq_id,
-                        timestamp: Utc::now(),
-                        event_type: RunEventType::Started,
-                        data: serde_json::json!({
+                    run.push_event(
+                        RunEventType::Started,
+                        serde_json::json!({
                             "agent": agent.name,
                             "input": input
                         }),
-                    };
-                    run.events.push(event);
-                    run.metadata.last_seq_id = run.metadata.last_seq_id.next();
-                    run.metadata.total_events += 1;
+                    );
                 }
             }
 
-            // Execute the agent
-            let result = agent.react_loop(&input).await;
+            // Execute the agent, forwarding progress as it happens when asked to
+            let result = if stream_progress {
+                run_streaming(&agent, &input, &runs, run_id).await
+            } else {
+                agent.react_loop(&input).await
+            };
 
             // Update status based on result
             {
@@ -261,64 +402,41 @@ impl BackgroundExecutor {
                                 })
                                 .count();
 
-                            // Add output event
-                            let event = RunEvent {
-                                seq_id: run.metadata.last_seq_id,
-                                timestamp: Utc::now(),
-                                event_type: RunEventType::Output,
-                                data: serde_json::json!({
+                            run.push_event(
+                                RunEventType::Output,
+                                serde_json::json!({
                                     "content": output.content,
                                     "tool_calls": tool_calls
                                 }),
-                            };
-                            run.events.push(event);
-                            run.metadata.last_seq_id = run.metadata.last_seq_id.next();
-                            run.metadata.total_events += 1;
-
-                            // Add completed event
-                            let event = RunEvent {
-                                seq_id: run.metadata.last_seq_id,
-                                timestamp: Utc::now(),
-                                event_type: RunEventType::Completed,
-                                data: serde_json::json!({}),
-                            };
-                            run.events.push(event);
-                            run.metadata.last_seq_id = run.metadata.last_seq_id.next();
-                            run.metadata.total_events += 1;
+                            );
+                            run.push_event(RunEventType::Completed, serde_json::json!({}));
                         }
                         Err(e) => {
                             run.metadata.status = RunStatus::Failed {
                                 error: e.to_string(),
                             };
-
-                            // Add failed event
-                            let event = RunEvent {
-                                seq_id: run.metadata.last_seq_id,
-                                timestamp: Utc::now(),
-                                event_type: RunEventType::Failed,
-                                data: serde_json::json!({
+                            run.push_event(
+                                RunEventType::Failed,
+                                serde_json::json!({
                                     "error": e.to_string()
                                 }),
-                            };
-                            run.events.push(event);
-                            run.metadata.last_seq_id = run.metadata.last_seq_id.next();
-                            run.metadata.total_events += 1;
+                            );
                         }
                     }
                 }
             }
 
             result
-        });
+        };
 
-        let run = BackgroundRun {
-            metadata,
-            events: Vec::new(),
-            task_handle: Some(handle),
+        let handle = match self.budget.clone() {
+            Some(ledger) => tokio::spawn(budget::scope(ledger, task)),
+            None => tokio::spawn(task),
         };
 
-        let mut runs = self.runs.write().await;
-        runs.insert(run_id, run);
+        if let Some(run) = self.runs.write().await.get_mut(&run_id) {
+            run.task_handle = Some(handle);
+        }
 
         Ok(run_id)
     }
@@ -428,17 +546,12 @@ impl BackgroundExecutor {
             run.metadata.completed_at = Some(Utc::now());
 
             // Add cancelled event
-            let event = RunEvent {
-                seq_id: run.metadata.last_seq_id,
-                timestamp: Utc::now(),
-                event_type: RunEventType::Failed,
-                data: serde_json::json!({
+            run.push_event(
+                RunEventType::Failed,
+                serde_json::json!({
                     "error": "Cancelled by user"
                 }),
-            };
-            run.events.push(event);
-            run.metadata.last_seq_id = run.metadata.last_seq_id.next();
-            run.metadata.total_events += 1;
+            );
         }
 
         Ok(())
@@ -505,51 +618,7 @@ pub struct PaginatedEvents {
 mod tests {
     use super::*;
     use crate::agent::AgentBuilder;
-    use crate::llm_client::LlmClient;
-    use crate::openrouter::CompletionRequest;
-    use crate::types::AgentId;
-    use async_trait::async_trait;
-
-    // Mock client for testing
-    struct MockClient;
-
-    #[async_trait]
-    impl LlmClient for MockClient {
-        async fn complete(
-            &self,
-            _request: CompletionRequest,
-        ) -> Result<crate::openrouter::CompletionResponse> {
-            Ok(crate::openrouter::CompletionResponse {
-                id: "test".to_string(),
-                model: "test".to_string(),
-                choices: vec![crate::openrouter::Choice {
-                    message: crate::openrouter::Message {
-                        role: "assistant".to_string(),
-                        content: Some("Test response".to_string()),
-                        tool_calls: None,
-                    },
-                    finish_reason: Some("stop".to_string()),
-                    index: 0,
-                }],
-                usage: None,
-            })
-        }
-
-        async fn stream(
-            &self,
-            _request: CompletionRequest,
-        ) -> Result<crate::openrouter::CompletionStream> {
-            Err(Error::config("Streaming not supported in mock".to_string()))
-        }
-
-        fn client_type(&self) -> &str {
-            "mock"
-        }
-
-        fn endpoint(&self) -> &str {
-            "http://localhost"
-        }
-    }
+    use crate::llm_client::testing::ScriptedClient;
 
     #[tokio::test]
     async fn test_background_execution() {
@@ -558,8 +627,9 @@ mod tests {
         let agent = Arc::new(
             AgentBuilder::new()
                 .name("Test Agent")
+                .system_prompt("You are a test agent.")
                 .model("test")
-                .client(Arc::new(MockClient))
+                .client(Arc::new(ScriptedClient::repeating("Test response")))
                 .build()
                 .unwrap(),
         );
@@ -576,6 +646,31 @@ mod tests {
         assert_eq!(metadata.agent_name, "Test Agent");
     }
 
+    #[tokio::test]
+    async fn test_runs_charge_executor_budget() {
+        let ledger = Arc::new(BudgetLedger::new());
+        let executor = BackgroundExecutor::new().with_budget(ledger.clone());
+
+        let agent = Arc::new(
+            AgentBuilder::new()
+                .name("Test Agent")
+                .system_prompt("You are a test agent.")
+                .model("test")
+                .client(Arc::new(ScriptedClient::repeating("Test response")))
+                .build()
+                .unwrap(),
+        );
+
+        let run_id = executor
+            .execute_async(agent, "Test input".to_string())
+            .await
+            .unwrap();
+        executor.wait_for_completion(run_id).await.unwrap();
+
+        let report = ledger.report();
+        assert_eq!(report.by_name("Test Agent").unwrap().calls, 1);
+    }
+
     #[tokio::test]
     async fn test_event_streaming() {
         let executor = BackgroundExecutor::new();
@@ -583,8 +678,9 @@ mod tests {
         let agent = Arc::new(
             AgentBuilder::new()
                 .name("Test Agent")
+                .system_prompt("You are a test agent.")
                 .model("test")
-                .client(Arc::new(MockClient))
+                .client(Arc::new(ScriptedClient::repeating("Test response")))
                 .build()
                 .unwrap(),
         );
@@ -608,8 +704,9 @@ mod tests {
         let agent = Arc::new(
             AgentBuilder::new()
                 .name("Test Agent")
+                .system_prompt("You are a test agent.")
                 .model("test")
-                .client(Arc::new(MockClient))
+                .client(Arc::new(ScriptedClient::repeating("Test response")))
                 .build()
                 .unwrap(),
         );
@@ -628,4 +725,69 @@ mod tests {
 
         assert!(page1.events.len() <= 2);
     }
+
+    #[tokio::test]
+    async fn test_progress_not_recorded_by_default() {
+        let executor = BackgroundExecutor::new();
+
+        let agent = Arc::new(
+            AgentBuilder::new()
+                .name("Test Agent")
+                .system_prompt("You are a test agent.")
+                .model("test")
+                .client(Arc::new(ScriptedClient::repeating("Test response")))
+                .build()
+                .unwrap(),
+        );
+
+        let run_id = executor
+            .execute_async(agent, "Test".to_string())
+            .await
+            .unwrap();
+        executor.wait_for_completion(run_id).await.unwrap();
+
+        let events = executor.stream_events(run_id, None).await.unwrap();
+        let types: Vec<RunEventType> = events.iter().map(|e| e.event_type.clone()).collect();
+        assert_eq!(
+            types,
+            vec![RunEventType::Started, RunEventType::Output, RunEventType::Completed]
+        );
+    }
+
+    #[tokio::test]
+    async fn test_progress_events_forwarded() {
+        let executor = BackgroundExecutor::new().with_progress_streaming(true);
+
+        let agent = Arc::new(
+            AgentBuilder::new()
+                .name("Test Agent")
+                .system_prompt("You are a test agent.")
+                .model("test")
+                .client(Arc::new(ScriptedClient::repeating("Test response")))
+                .build()
+                .unwrap(),
+        );
+
+        let run_id = executor
+            .execute_async(agent, "Test".to_string())
+            .await
+            .unwrap();
+        let output = executor.wait_for_completion(run_id).await.unwrap();
+        assert_eq!(output.content, "Test response");
+
+        let events = executor.stream_events(run_id, None).await.unwrap();
+        let types: Vec<RunEventType> = events.iter().map(|e| e.event_type.clone()).collect();
+        assert_eq!(
+            types,
+            vec![
+                RunEventType::Started,
+                RunEventType::Progress,
+                RunEventType::Thought,
+                RunEventType::Output,
+                RunEventType::Completed,
+            ]
+        );
+        assert_eq!(events[1].data["token"], "Test response");
+        assert!(events.windows(2).all(|w| w[0].seq_id < w[1].seq_id));
+    }
 }
@@ -0,0 +1,395 @@
+//! Token budgets and cost accounting
+//!
+//! A [`BudgetLedger`] records token usage per agent and estimates dollar cost
+//! from a [`PriceTable`]. Agents charge every ledger that is active for the
+//! current task: their own (see [`AgentBuilder::budget`]) plus any entered
+//! with [`scope`] by an orchestrator or the background executor. Nested scopes
+//! stack, so a run is charged once to each ledger above it.
+//!
+//! LLM calls made outside an agent's ReAct loop, such as session summaries
+//! and sleep-time consolidation, go through [`complete_charged`] so they are
+//! charged the same way.
+//!
+//! [`AgentBuilder::budget`]: crate::agent::AgentBuilder::budget
+
+use crate::error::{Error, Result};
+use crate::llm_client::LlmClient;
+use crate::openrouter::{CompletionRequest, CompletionResponse};
+use crate::types::{AgentId, TokenUsage};
+use parking_lot::Mutex;
+use serde::{Deserialize, Serialize};
+use std::collections::{BTreeMap, HashMap};
+use std::future::Future;
+use std::sync::Arc;
+
+/// Price of a model in US dollars per million tokens
+#[derive(Debug, Clone, Copy, PartialEq, Serialize, Deserialize)]
+pub struct ModelPrice {
+    /// Price per million prompt tokens
+    pub prompt_per_million: f64,
+    /// Price per million completion tokens
+    pub completion_per_million: f64,
+}
+
+impl ModelPrice {
+    /// Create a price from per-million-token rates
+    pub fn new(prompt_per_million: f64, completion_per_million: f64) -> Self {
+        Self {
+            prompt_per_million,
+            completion_per_million,
+        }
+    }
+
+    /// Dollar cost of the given usage
+    pub fn cost(&self, usage: TokenUsage) -> f64 {
+        (usage.prompt_tokens as f64 * self.prompt_per_million
+            + usage.completion_tokens as f64 * self.completion_per_million)
+            / 1_000_000.0
+    }
+}
+
+/// Per-model prices used for cost estimation
+///
+/// Models without an entry are still counted in token usage but add nothing
+/// to the estimated cost.
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+pub struct PriceTable {
+    prices: HashMap<String, ModelPrice>,
+}
+
+impl PriceTable {
+    /// Create an empty price table
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Set the price of a model
+    pub fn with_price(mut self, model: impl Into<String>, price: ModelPrice) -> Self {
+        self.prices.insert(model.into(), price);
+        self
+    }
+
+    /// Price of a model, if known
+    pub fn get(&self, model: &str) -> Option<&ModelPrice> {
+        self.prices.get(model)
+    }
+
+    /// Dollar cost of usage on a model, if the model is priced
+    pub fn cost(&self, model: &str, usage: TokenUsage) -> Option<f64> {
+        self.get(model).map(|price| price.cost(usage))
+    }
+}
+
+/// Usage charged by one agent
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+pub struct AgentUsage {
+    /// Name of the agent when it was first charged
+    pub agent_name: String,
+    /// Tokens used
+    pub usage: TokenUsage,
+    /// Estimated cost in US dollars
+    pub cost_usd: f64,
+    /// Number of LLM calls
+    pub calls: u64,
+}
+
+/// Snapshot of a ledger
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+pub struct UsageReport {
+    /// Tokens used across all agents
+    pub total: TokenUsage,
+    /// Estimated cost across all agents in US dollars
+    pub cost_usd: f64,
+    /// Usage keyed by agent ID, so agents sharing a name are counted apart
+    pub by_agent: BTreeMap<AgentId, AgentUsage>,
+}
+
+impl UsageReport {
+    /// Usage of every agent called `name`, combined
+    pub fn by_name(&self, name: &str) -> Option<AgentUsage> {
+        self.by_agent
+            .values()
+            .filter(|usage| usage.agent_name == name)
+            .fold(None, |total: Option<AgentUsage>, usage| {
+                let mut total = total.unwrap_or_else(|| AgentUsage {
+                    agent_name: name.to_string(),
+                    ..AgentUsage::default()
+                });
+                total.usage.add(usage.usage);
+                total.cost_usd += usage.cost_usd;
+                total.calls += usage.calls;
+                Some(total)
+            })
+    }
+}
+
+/// Shared token budget with per-agent accounting
+///
+/// The hard limit aborts further LLM calls with [`Error::BudgetExceeded`];
+/// the soft limit only signals that agents should wrap up.
+#[derive(Debug, Default)]
+pub struct BudgetLedger {
+    hard_limit: Option<u64>,
+    soft_limit: Option<u64>,
+    prices: PriceTable,
+    report: Mutex<UsageReport>,
+}
+
+impl BudgetLedger {
+    /// Create an unlimited ledger
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Abort once total tokens exceed `limit`
+    pub fn with_hard_limit(mut self, limit: u64) -> Self {
+        self.hard_limit = Some(limit);
+        self
+    }
+
+    /// Ask agents to wrap up once total tokens reach `limit`
+    pub fn with_soft_limit(mut self, limit: u64) -> Self {
+        self.soft_limit = Some(limit);
+        self
+    }
+
+    /// Set the prices used for cost estimation
+    pub fn with_prices(mut self, prices: PriceTable) -> Self {
+        self.prices = prices;
+        self
+    }
+
+    /// Prices used for cost estimation
+    pub fn prices(&self) -> &PriceTable {
+        &self.prices
+    }
+
+    /// Total tokens charged so far
+    pub fn used(&self) -> u64 {
+        self.report.lock().total.total_tokens
+    }
+
+    /// Tokens left before the hard limit, if there is one
+    pub fn remaining(&self) -> Option<u64> {
+        self.hard_limit
+            .map(|limit| limit.saturating_sub(self.used()))
+    }
+
+    /// Whether usage has reached the soft limit
+    pub fn soft_limit_reached(&self) -> bool {
+        self.soft_limit.is_some_and(|limit| self.used() >= limit)
+    }
+
+    /// Fail if the hard limit is already used up
+    pub fn check(&self) -> Result<()> {
+        match self.hard_limit {
+            Some(limit) if self.used() >= limit => Err(Error::BudgetExceeded {
+                used: self.used(),
+                limit,
+            }),
+            _ => Ok(()),
+        }
+    }
+
+    /// Record usage of `model` by the agent `agent_id`, named `agent_name`
+    ///
+    /// The usage is always recorded; the error reports that this charge took
+    /// the ledger past its hard limit.
+    pub fn charge(
+        &self,
+        agent_id: AgentId,
+        agent_name: &str,
+        model: &str,
+        usage: TokenUsage,
+    ) -> Result<()> {
+        let cost = self.prices.cost(model, usage).unwrap_or(0.0);
+        let used = {
+            let mut report = self.report.lock();
+            report.total.add(usage);
+            report.cost_usd += cost;
+            let entry = report
+                .by_agent
+                .entry(agent_id)
+                .or_insert_with(|| AgentUsage {
+                    agent_name: agent_name.to_string(),
+                    ..AgentUsage::default()
+                });
+            entry.usage.add(usage);
+            entry.cost_usd += cost;
+            entry.calls += 1;
+            report.total.total_tokens
+        };
+
+        match self.hard_limit {
+            Some(limit) if used > limit => Err(Error::BudgetExceeded { used, limit }),
+            _ => Ok(()),
+        }
+    }
+
+    /// Snapshot of usage so far
+    pub fn report(&self) -> UsageReport {
+        self.report.lock().clone()
+    }
+}
+
+tokio::task_local! {
+    static ACTIVE: Vec<Arc<BudgetLedger>>;
+}
+
+/// Run `fut` with `ledger` charged for every LLM call made inside it
+pub async fn scope<F: Future>(ledger: Arc<BudgetLedger>, fut: F) -> F::Output {
+    let mut ledgers = active();
+    if !ledgers.iter().any(|l| Arc::ptr_eq(l, &ledger)) {
+        ledgers.push(ledger);
+    }
+    ACTIVE.scope(ledgers, fut).await
+}
+
+/// Ledgers entered with [`scope`] by the current task, outermost first
+pub fn active() -> Vec<Arc<BudgetLedger>> {
+    ACTIVE
+        .try_with(|ledgers| ledgers.clone())
+        .unwrap_or_default()
+}
+
+/// Active ledgers plus `own`, if it is not already among them
+pub fn active_with(own: Option<&Arc<BudgetLedger>>) -> Vec<Arc<BudgetLedger>> {
+    let mut ledgers = active();
+    if let Some(own) = own {
+        if !ledgers.iter().any(|l| Arc::ptr_eq(l, own)) {
+            ledgers.push(own.clone());
+        }
+    }
+    ledgers
+}
+
+/// Fail if any of `ledgers` has used up its hard limit
+pub fn check_all(ledgers: &[Arc<BudgetLedger>]) -> Result<()> {
+    ledgers.iter().try_for_each(|ledger| ledger.check())
+}
+
+/// Charge every ledger before reporting an exceeded limit
+pub fn charge_all(
+    ledgers: &[Arc<BudgetLedger>],
+    agent_id: AgentId,
+    agent_name: &str,
+    model: &str,
+    usage: TokenUsage,
+) -> Result<()> {
+    let charged: Vec<Result<()>> = ledgers
+        .iter()
+        .map(|ledger| ledger.charge(agent_id, agent_name, model, usage))
+        .collect();
+    charged.into_iter().collect()
+}
+
+/// Complete `request` for an agent, checking and charging `ledgers`
+pub async fn complete_charged(
+    client: &dyn LlmClient,
+    ledgers: &[Arc<BudgetLedger>],
+    agent_id: AgentId,
+    agent_name: &str,
+    request: CompletionRequest,
+) -> Result<CompletionResponse> {
+    check_all(ledgers)?;
+    let model = request.model.clone();
+    let response = client.complete(request).await?;
+    charge_all(
+        ledgers,
+        agent_id,
+        agent_name,
+        &model,
+        TokenUsage::from(response.usage.clone()),
+    )?;
+    Ok(response)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_charges_accumulate_per_agent_with_cost() {
+        let prices = PriceTable::new().with_price("small", ModelPrice::new(1.0, 2.0));
+        let ledger = BudgetLedger::new().with_prices(prices);
+        let (a, b) = (AgentId::new(), AgentId::new());
+
+        ledger
+            .charge(a, "a", "small", TokenUsage::new(1_000_000, 500_000))
+            .unwrap();
+        ledger
+            .charge(a, "a", "unpriced", TokenUsage::new(10, 10))
+            .unwrap();
+        ledger
+            .charge(b, "b", "small", TokenUsage::new(0, 1_000_000))
+            .unwrap();
+
+        let report = ledger.report();
+        assert_eq!(report.total.total_tokens, 2_500_020);
+        assert_eq!(report.by_agent[&a].calls, 2);
+        assert_eq!(report.by_agent[&a].agent_name, "a");
+        assert!((report.by_agent[&a].cost_usd - 2.0).abs() < 1e-9);
+        assert!((report.cost_usd - 4.0).abs() < 1e-9);
+    }
+
+    #[test]
+    fn test_agents_sharing_a_name_are_counted_apart() {
+        let ledger = BudgetLedger::new();
+        let (first, second) = (AgentId::new(), AgentId::new());
+
+        ledger
+            .charge(first, "worker", "m", TokenUsage::new(10, 0))
+            .unwrap();
+        ledger
+            .charge(second, "worker", "m", TokenUsage::new(5, 0))
+            .unwrap();
+
+        let report = ledger.report();
+        assert_eq!(report.by_agent.len(), 2);
+        assert_eq!(report.by_agent[&second].usage.total_tokens, 5);
+        let combined = report.by_name("worker").unwrap();
+        assert_eq!((combined.calls, combined.usage.total_tokens), (2, 15));
+    }
+
+    #[test]
+    fn test_limits() {
+        let ledger = BudgetLedger::new().with_soft_limit(50).with_hard_limit(100);
+        let agent = AgentId::new();
+
+        ledger
+            .charge(agent, "a", "m", TokenUsage::new(40, 20))
+            .unwrap();
+        assert!(ledger.soft_limit_reached());
+        assert_eq!(ledger.remaining(), Some(40));
+
+        let err = ledger
+            .charge(agent, "a", "m", TokenUsage::new(40, 20))
+            .unwrap_err();
+        assert!(matches!(
+            err,
+            Error::BudgetExceeded {
+                used: 120,
+                limit: 100
+            }
+        ));
+        assert!(ledger.check().is_err());
+    }
+
+    #[tokio::test]
+    async fn test_scopes_stack_without_duplicates() {
+        let outer = Arc::new(BudgetLedger::new());
+        let inner = Arc::new(BudgetLedger::new());
+
+        let count = scope(outer.clone(), async {
+            scope(
+                inner.clone(),
+                scope(outer.clone(), async { active().len() }),
+            )
+            .await
+        })
+        .await;
+
+        assert_eq!(count, 2);
+        assert!(active().is_empty());
+    }
+}
@@ -0,0 +1,663 @@
+//! Persistent and semantic response caching for LLM clients
+//!
+//! [`SemanticCache`] is a [`Layer`] that answers repeated requests from a
+//! [`CacheStore`]. Exact hits match the whole normalised request. With an
+//! [`EmbeddingClient`] configured, a request whose prompt is similar enough
+//! to a cached one with the same model and settings is answered too. Prompt
+//! embeddings are kept in an in-process HNSW graph per model and settings,
+//! so similarity lookups do not scan the store.
+//!
+//! Stores are available in memory, on sled (`SledCacheStore`) and in SQLite
+//! (`SqliteStorage`), the latter two with the `storage` feature.
+//!
+//! ```rust,ignore
+//! let cache = SemanticCache::new(Arc::new(SledCacheStore::open("cache.db")?))
+//!     .with_ttl(Duration::from_secs(24 * 3600))
+//!     .with_semantic(embedder, 0.95);
+//! let client = ClientStack::new(client).layer(cache.clone()).build();
+//! // ... later
+//! println!("{:?}", cache.stats());
+//! ```
+
+use crate::archival::{Embedded, HnswGraph};
+use crate::embeddings::EmbeddingClient;
+use crate::error::Result;
+use crate::llm_client::{Layer, LlmClient};
+use crate::openrouter::{
+    CompletionRequest, CompletionResponse, CompletionStream, StreamAccumulator,
+};
+use crate::replay::request_key;
+use async_trait::async_trait;
+use chrono::{DateTime, Utc};
+use parking_lot::RwLock;
+use serde::{Deserialize, Serialize};
+use std::collections::HashMap;
+use std::sync::atomic::{AtomicU64, Ordering};
+use std::sync::Arc;
+use std::time::Duration;
+
+/// Links per node in the prompt embedding graphs
+const GRAPH_M: usize = 16;
+/// Candidate list size while building the graphs
+const GRAPH_EF_CONSTRUCTION: usize = 100;
+/// Candidate list size while searching the graphs
+const GRAPH_EF_SEARCH: usize = 32;
+/// Similar entries checked before giving up on a semantic hit
+const SEMANTIC_CANDIDATES: usize = 4;
+
+/// A cached response
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct CacheEntry {
+    /// `<scope>/<request hash>`
+    pub key: String,
+    /// Hash of the request without its messages; only entries in the same
+    /// scope are considered for similarity hits
+    pub scope: String,
+    /// Embedding of the prompt, when semantic matching is enabled
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub embedding: Option<Vec<f32>>,
+    /// Cached response
+    pub response: CompletionResponse,
+    /// When the response was cached
+    pub created_at: DateTime<Utc>,
+}
+
+/// Storage for cached responses
+#[async_trait]
+pub trait CacheStore: Send + Sync {
+    /// Load an entry by key
+    async fn get(&self, key: &str) -> Result<Option<CacheEntry>>;
+
+    /// Insert or replace an entry
+    async fn put(&self, entry: &CacheEntry) -> Result<()>;
+
+    /// Remove an entry
+    async fn remove(&self, key: &str) -> Result<()>;
+
+    /// All entries in a scope
+    async fn entries(&self, scope: &str) -> Result<Vec<CacheEntry>>;
+}
+
+/// Cache store held in memory
+#[derive(Default)]
+pub struct InMemoryCacheStore {
+    entries: RwLock<HashMap<String, CacheEntry>>,
+}
+
+impl InMemoryCacheStore {
+    /// Create an empty store
+    pub fn new() -> Self {
+        Self::default()
+    }
+}
+
+#[async_trait]
+impl CacheStore for InMemoryCacheStore {
+    async fn get(&self, key: &str) -> Result<Option<CacheEntry>> {
+        Ok(self.entries.read().get(key).cloned())
+    }
+
+    async fn put(&self, entry: &CacheEntry) -> Result<()> {
+        self.entries
+            .write()
+            .insert(entry.key.clone(), entry.clone());
+        Ok(())
+    }
+
+    async fn remove(&self, key: &str) -> Result<()> {
+        self.entries.write().remove(key);
+        Ok(())
+    }
+
+    async fn entries(&self, scope: &str) -> Result<Vec<CacheEntry>> {
+        Ok(self
+            .entries
+            .read()
+            .values()
+            .filter(|entry| entry.scope == scope)
+            .cloned()
+            .collect())
+    }
+}
+
+/// Snapshot of cache effectiveness
+#[derive(Debug, Clone, Copy, Default, PartialEq, Serialize, Deserialize)]
+pub struct CacheStats {
+    /// Requests answered by an exact match
+    pub exact_hits: u64,
+    /// Requests answered by a similar prompt
+    pub semantic_hits: u64,
+    /// Cacheable requests sent to the model
+    pub misses: u64,
+    /// Requests not cached because they sample with temperature above zero
+    pub skipped: u64,
+    /// Tokens the cached responses cost when first generated
+    pub tokens_saved: u64,
+}
+
+impl CacheStats {
+    /// Share of cacheable requests answered from the cache
+    pub fn hit_rate(&self) -> f64 {
+        let hits = self.exact_hits + self.semantic_hits;
+        match hits + self.misses {
+            0 => 0.0,
+            total => hits as f64 / total as f64,
+        }
+    }
+}
+
+#[derive(Default)]
+struct Counters {
+    exact_hits: AtomicU64,
+    semantic_hits: AtomicU64,
+    misses: AtomicU64,
+    skipped: AtomicU64,
+    tokens_saved: AtomicU64,
+}
+
+/// Cache key and prompt embedding of an entry, as held in a graph
+#[derive(Clone)]
+struct CachedPrompt {
+    key: String,
+    embedding: Vec<f32>,
+}
+
+impl Embedded for CachedPrompt {
+    fn embedding(&self) -> &[f32] {
+        &self.embedding
+    }
+}
+
+/// Response cache with optional similarity matching
+///
+/// Requests sampled with a temperature above zero are passed through
+/// uncached unless [`SemanticCache::force`] is set; requests without a
+/// temperature use the provider default and count as sampled. Streamed
+/// responses are cached once the stream completes. Clones share their
+/// store, graphs and statistics. Cache failures are logged and never fail a
+/// request.
+#[derive(Clone)]
+pub struct SemanticCache {
+    store: Arc<dyn CacheStore>,
+    ttl: Option<Duration>,
+    semantic: Option<(Arc<dyn EmbeddingClient>, f32)>,
+    force: bool,
+    counters: Arc<Counters>,
+    /// Prompt embeddings by scope, loaded from the store on first use
+    graphs: Arc<RwLock<HashMap<String, HnswGraph<CachedPrompt>>>>,
+}
+
+impl SemanticCache {
+    /// Cache responses in `store`, matching exact requests only
+    pub fn new(store: Arc<dyn CacheStore>) -> Self {
+        Self {
+            store,
+            ttl: None,
+            semantic: None,
+            force: false,
+            counters: Arc::new(Counters::default()),
+            graphs: Arc::new(RwLock::new(HashMap::new())),
+        }
+    }
+
+    /// Cache responses in memory
+    pub fn in_memory() -> Self {
+        Self::new(Arc::new(InMemoryCacheStore::new()))
+    }
+
+    /// Ignore entries older than `ttl`
+    pub fn with_ttl(mut self, ttl: Duration) -> Self {
+        self.ttl = Some(ttl);
+        self
+    }
+
+    /// Also answer prompts whose embedding has cosine similarity of at least
+    /// `threshold` with a cached one
+    pub fn with_semantic(mut self, embedder: Arc<dyn EmbeddingClient>, threshold: f32) -> Self {
+        self.semantic = Some((embedder, threshold));
+        self
+    }
+
+    /// Cache requests regardless of temperature
+    pub fn force(mut self, force: bool) -> Self {
+        self.force = force;
+        self
+    }
+
+    /// Hit and miss counts so far
+    pub fn stats(&self) -> CacheStats {
+        let c = &self.counters;
+        CacheStats {
+            exact_hits: c.exact_hits.load(Ordering::Relaxed),
+            semantic_hits: c.semantic_hits.load(Ordering::Relaxed),
+            misses: c.misses.load(Ordering::Relaxed),
+            skipped: c.skipped.load(Ordering::Relaxed),
+            tokens_saved: c.tokens_saved.load(Ordering::Relaxed),
+        }
+    }
+
+    fn cacheable(&self, request: &CompletionRequest) -> bool {
+        self.force || request.temperature.unwrap_or(1.0) <= 0.0
+    }
+
+    fn fresh(&self, entry: &CacheEntry) -> bool {
+        self.fresh_at(entry.created_at)
+    }
+
+    fn fresh_at(&self, created_at: DateTime<Utc>) -> bool {
+        match self
+            .ttl
+            .and_then(|ttl| chrono::Duration::from_std(ttl).ok())
+        {
+            Some(ttl) => created_at + ttl > Utc::now(),
+            None => true,
+        }
+    }
+
+    fn hit(&self, counter: &AtomicU64, response: &CompletionResponse) {
+        counter.fetch_add(1, Ordering::Relaxed);
+        self.counters
+            .tokens_saved
+            .fetch_add(response.usage.total_tokens, Ordering::Relaxed);
+    }
+
+    /// Fresh entry stored under `key`, dropping it if expired
+    async fn exact(&self, scope: &str, key: &str) -> Result<Option<CompletionResponse>> {
+        match self.store.get(key).await? {
+            Some(entry) if self.fresh(&entry) => Ok(Some(entry.response)),
+            Some(_) => {
+                self.store.remove(key).await?;
+                self.forget(scope, key);
+                Ok(None)
+            }
+            None => Ok(None),
+        }
+    }
+
+    /// Drop `key` from the scope's graph
+    fn forget(&self, scope: &str, key: &str) {
+        if let Some(graph) = self.graphs.write().get_mut(scope) {
+            graph.remove(|prompt| prompt.key == key);
+        }
+    }
+
+    /// Build the scope's graph from the store, once
+    async fn load_graph(&self, scope: &str) -> Result<()> {
+        if self.graphs.read().contains_key(scope) {
+            return Ok(());
+        }
+
+        let mut graph = HnswGraph::default();
+        for entry in self.store.entries(scope).await? {
+            if let Some(embedding) = entry.embedding.filter(|_| self.fresh_at(entry.created_at)) {
+                let prompt = CachedPrompt {
+                    key: entry.key,
+                    embedding,
+                };
+                graph.insert(prompt, GRAPH_M, GRAPH_EF_CONSTRUCTION);
+            }
+        }
+        self.graphs
+            .write()
+            .entry(scope.to_string())
+            .or_insert(graph);
+        Ok(())
+    }
+
+    /// Best fresh entry whose prompt is at least `threshold` similar
+    async fn similar(
+        &self,
+        scope: &str,
+        embedding: &[f32],
+        threshold: f32,
+    ) -> Result<Option<CompletionResponse>> {
+        self.load_graph(scope).await?;
+        let candidates = match self.graphs.read().get(scope) {
+            Some(graph) => graph.search(embedding, SEMANTIC_CANDIDATES, GRAPH_EF_SEARCH),
+            None => Vec::new(),
+        };
+
+        for (prompt, similarity) in candidates {
+            if similarity < threshold {
+                break;
+            }
+            match self.exact(scope, &prompt.key).await? {
+                Some(response) => return Ok(Some(response)),
+                None => self.forget(scope, &prompt.key),
+            }
+        }
+        Ok(None)
+    }
+
+    /// Store the response to a missed lookup
+    async fn store(&self, miss: Lookup, response: &CompletionResponse) {
+        let Lookup::Miss {
+            key,
+            scope,
+            embedding,
+        } = miss
+        else {
+            return;
+        };
+        let entry = CacheEntry {
+            key,
+            scope,
+            embedding,
+            response: response.clone(),
+            created_at: Utc::now(),
+        };
+        if let Err(e) = self.store.put(&entry).await {
+            tracing::warn!("Failed to cache response: {}", e);
+            return;
+        }
+
+        if let Some(embedding) = entry.embedding {
+            if let Some(graph) = self.graphs.write().get_mut(&entry.scope) {
+                let prompt = CachedPrompt {
+                    key: entry.key,
+                    embedding,
+                };
+                graph.insert(prompt, GRAPH_M, GRAPH_EF_CONSTRUCTION);
+            }
+        }
+    }
+
+    /// Look a request up in the cache
+    async fn lookup(&self, request: &CompletionRequest) -> Result<Lookup> {
+        let scope = request_key(&CompletionRequest {
+            messages: Vec::new(),
+            ..request.clone()
+        })?;
+        let key = format!("{}/{}", scope, request_key(request)?);
+
+        if let Some(response) = self.exact(&scope, &key).await? {
+            self.hit(&self.counters.exact_hits, &response);
+            return Ok(Lookup::Hit(response));
+        }
+
+        let mut embedding = None;
+        if let Some((embedder, threshold)) = &self.semantic {
+            let vector = embedder.embed_one(&prompt_text(request)).await?;
+            if let Some(response) = self.similar(&scope, &vector, *threshold).await? {
+                self.hit(&self.counters.semantic_hits, &response);
+                return Ok(Lookup::Hit(response));
+            }
+            embedding = Some(vector);
+        }
+
+        self.counters.misses.fetch_add(1, Ordering::Relaxed);
+        Ok(Lookup::Miss {
+            key,
+            scope,
+            embedding,
+        })
+    }
+}
+
+/// Outcome of a cache lookup
+enum Lookup {
+    Hit(CompletionResponse),
+    /// Where to store the response once it arrives
+    Miss {
+        key: String,
+        scope: String,
+        embedding: Option<Vec<f32>>,
+    },
+}
+
+impl Layer for SemanticCache {
+    fn layer(&self, inner: Arc<dyn LlmClient>) -> Arc<dyn LlmClient> {
+        Arc::new(SemanticCachingClient {
+            inner,
+            cache: self.clone(),
+        })
+    }
+}
+
+/// Text compared for similarity hits
+fn prompt_text(request: &CompletionRequest) -> String {
+    request
+        .messages
+        .iter()
+        .map(|m| format!("{:?}: {}", m.role, m.content))
+        .collect::<Vec<_>>()
+        .join("\n")
+}
+
+struct SemanticCachingClient {
+    inner: Arc<dyn LlmClient>,
+    cache: SemanticCache,
+}
+
+#[async_trait]
+impl LlmClient for SemanticCachingClient {
+    async fn complete(&self, request: CompletionRequest) -> Result<CompletionResponse> {
+        if !self.cache.cacheable(&request) {
+            self.cache.counters.skipped.fetch_add(1, Ordering::Relaxed);
+            return self.inner.complete(request).await;
+        }
+
+        let miss = match self.cache.lookup(&request).await {
+            Ok(Lookup::Hit(response)) => return Ok(response),
+            Ok(miss) => Some(miss),
+            Err(e) => {
+                tracing::warn!("Response cache lookup failed: {}", e);
+                None
+            }
+        };
+
+        let response = self.inner.complete(request).await?;
+        if let Some(miss) = miss {
+            self.cache.store(miss, &response).await;
+        }
+        Ok(response)
+    }
+
+    async fn stream(&self, request: CompletionRequest) -> Result<CompletionStream> {
+        if !self.cache.cacheable(&request) {
+            self.cache.counters.skipped.fetch_add(1, Ordering::Relaxed);
+            return self.inner.stream(request).await;
+        }
+
+        let miss = match self.cache.lookup(&request).await {
+            Ok(Lookup::Hit(response)) => return Ok(CompletionStream::from_response(response)),
+            Ok(miss) => miss,
+            Err(e) => {
+                tracing::warn!("Response cache lookup failed: {}", e);
+                return self.inner.stream(request).await;
+            }
+        };
+
+        // Pass chunks through, caching the assembled response once the
+        // stream ends without error
+        let inner = self.inner.stream(request).await?;
+        let cache = self.cache.clone();
+        let state = Some((inner, StreamAccumulator::new(), miss));
+        let chunks = futures::stream::unfold(state, move |state| {
+            let cache = cache.clone();
+            async move {
+                let (mut inner, mut accumulator, miss) = state?;
+                match inner.next_chunk().await {
+                    Some(Ok(chunk)) => {
+                        accumulator.push(&chunk);
+                        Some((Ok(chunk), Some((inner, accumulator, miss))))
+                    }
+                    Some(Err(e)) => Some((Err(e), None)),
+                    None => {
+                        cache.store(miss, &accumulator.finish()).await;
+                        None
+                    }
+                }
+            }
+        });
+        Ok(CompletionStream::from_chunks(chunks))
+    }
+
+    fn client_type(&self) -> &str {
+        self.inner.client_type()
+    }
+
+    fn endpoint(&self) -> &str {
+        self.inner.endpoint()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::llm_client::ClientStack;
+    use crate::openrouter::Message;
+    use crate::replay::ReplayClient;
+
+    /// Embeds text as counts of a few marker words
+    struct KeywordEmbedder;
+
+    #[async_trait]
+    impl EmbeddingClient for KeywordEmbedder {
+        async fn embed_batch(&self, texts: &[String]) -> Result<Vec<Vec<f32>>> {
+            Ok(texts
+                .iter()
+                .map(|text| {
+                    let text = text.to_lowercase();
+                    ["capital", "france", "germany"]
+                        .iter()
+                        .map(|word| text.matches(word).count() as f32)
+                        .collect()
+                })
+                .collect())
+        }
+
+        fn dimensions(&self) -> Option<usize> {
+            Some(3)
+        }
+
+        fn model_id(&self) -> &str {
+            "keywords"
+        }
+    }
+
+    fn request(prompt: &str) -> CompletionRequest {
+        CompletionRequest::new("m", vec![Message::user(prompt)]).with_temperature(0.0)
+    }
+
+    #[tokio::test]
+    async fn test_exact_and_semantic_hits() {
+        let cache = SemanticCache::in_memory().with_semantic(Arc::new(KeywordEmbedder), 0.99);
+        let base = Arc::new(
+            ReplayClient::scripted()
+                .with_reply("Paris")
+                .with_reply("Berlin"),
+        );
+        let client = ClientStack::new(base).layer(cache.clone()).build();
+
+        let answer = |r: CompletionResponse| r.choices[0].message.content.clone();
+        assert_eq!(
+            answer(
+                client
+                    .complete(request("Capital of France?"))
+                    .await
+                    .unwrap()
+            ),
+            "Paris"
+        );
+        assert_eq!(
+            answer(
+                client
+                    .complete(request("Capital of France?"))
+                    .await
+                    .unwrap()
+            ),
+            "Paris"
+        );
+        assert_eq!(
+            answer(
+                client
+                    .complete(request("What's the capital of France"))
+                    .await
+                    .unwrap()
+            ),
+            "Paris"
+        );
+        assert_eq!(
+            answer(
+                client
+                    .complete(request("Capital of Germany?"))
+                    .await
+                    .unwrap()
+            ),
+            "Berlin"
+        );
+
+        let stats = cache.stats();
+        assert_eq!(
+            (stats.exact_hits, stats.semantic_hits, stats.misses),
+            (1, 1, 2)
+        );
+        assert!((stats.hit_rate() - 0.5).abs() < 1e-9);
+    }
+
+    #[tokio::test]
+    async fn test_sampled_requests_skip_unless_forced() {
+        let cache = SemanticCache::in_memory();
+        let base = Arc::new(
+            ReplayClient::scripted()
+                .with_reply("a")
+                .with_reply("b")
+                .with_reply("c"),
+        );
+        let client = ClientStack::new(base.clone()).layer(cache.clone()).build();
+        let sampled =
+            CompletionRequest::new("m", vec![Message::user("poem")]).with_temperature(0.7);
+
+        client.complete(sampled.clone()).await.unwrap();
+        let second = client.complete(sampled.clone()).await.unwrap();
+        assert_eq!(second.choices[0].message.content, "b");
+        assert_eq!(cache.stats().skipped, 2);
+
+        let forced = ClientStack::new(base)
+            .layer(cache.clone().force(true))
+            .build();
+        forced.complete(sampled.clone()).await.unwrap();
+        let cached = forced.complete(sampled).await.unwrap();
+        assert_eq!(cached.choices[0].message.content, "c");
+        assert_eq!(cache.stats().exact_hits, 1);
+    }
+
+    #[tokio::test]
+    async fn test_expired_entries_are_refreshed() {
+        let cache = SemanticCache::in_memory().with_ttl(Duration::from_millis(10));
+        let base = Arc::new(ReplayClient::scripted().with_reply("old").with_reply("new"));
+        let client = ClientStack::new(base).layer(cache.clone()).build();
+
+        client.complete(request("q")).await.unwrap();
+        tokio::time::sleep(Duration::from_millis(30)).await;
+        let refreshed = client.complete(request("q")).await.unwrap();
+
+        assert_eq!(refreshed.choices[0].message.content, "new");
+        assert_eq!(cache.stats().misses, 2);
+    }
+
+    #[tokio::test]
+    async fn test_streamed_responses_are_cached() {
+        let cache = SemanticCache::in_memory();
+        let base = Arc::new(
+            ReplayClient::scripted()
+                .with_reply("streamed")
+                .with_reply("unused"),
+        );
+        let client = ClientStack::new(base).layer(cache.clone()).build();
+
+        let stream = client.stream(request("q").with_stream(true)).await.unwrap();
+        assert_eq!(
+            stream.collect_response().await.unwrap().choices[0]
+                .message
+                .content,
+            "streamed"
+        );
+        let cached = client.complete(request("q")).await.unwrap();
+
+        assert_eq!(cached.choices[0].message.content, "streamed");
+        let stats = cache.stats();
+        assert_eq!((stats.exact_hits, stats.misses), (1, 1));
+    }
+}
@@ -104,10 +104,16 @@ pub struct OpenRouterConfig {
     pub fallback_models: Vec<String>,
     /// Maximum retries on failure
     pub max_retries: u32,
+    /// Delay before the first retry, doubled for each further retry
+    pub retry_backoff: Duration,
     /// Request timeout
     pub timeout: Duration,
     /// App name for OpenRouter tracking
     pub app_name: String,
+    /// Model for embedding requests
+    pub embedding_model: String,
+    /// Embedding length to request, for models that support shortening
+    pub embedding_dimensions: Option<usize>,
 }
 
 impl OpenRouterConfig {
@@ -130,8 +136,11 @@ impl OpenRouterConfig {
                 presets::FREE_TIER.to_string(),
             ],
             max_retries: 3,
+            retry_backoff: Duration::from_millis(500),
             timeout: Duration::from_secs(120),
             app_name: "ATHPTTGH Agent Harness".to_string(),
+            embedding_model: presets::EMBEDDING.to_string(),
+            embedding_dimensions: None,
         })
     }
 
@@ -148,8 +157,11 @@ impl OpenRouterConfig {
                 presets::FREE_TIER.to_string(),
             ],
             max_retries: 3,
+            retry_backoff: Duration::from_millis(500),
             timeout: Duration::from_secs(120),
             app_name: "ATHPTTGH Agent Harness".to_string(),
+            embedding_model: presets::EMBEDDING.to_string(),
+            embedding_dimensions: None,
         }
     }
 
@@ -171,12 +183,42 @@ impl OpenRouterConfig {
         self
     }
 
+    /// Set the fallback models tried once the requested model keeps failing
+    pub fn with_fallback_models(mut self, models: Vec<String>) -> Self {
+        self.fallback_models = models;
+        self
+    }
+
+    /// Set the maximum retries per model
+    pub fn with_max_retries(mut self, max_retries: u32) -> Self {
+        self.max_retries = max_retries;
+        self
+    }
+
+    /// Set the delay before the first retry
+    pub fn with_retry_backoff(mut self, backoff: Duration) -> Self {
+        self.retry_backoff = backoff;
+        self
+    }
+
     /// Set the timeout
     pub fn with_timeout(mut self, timeout: Duration) -> Self {
         self.timeout = timeout;
         self
     }
 
+    /// Set the embedding model
+    pub fn with_embedding_model(mut self, model: impl Into<String>) -> Self {
+        self.embedding_model = model.into();
+        self
+    }
+
+    /// Set the embedding length to request
+    pub fn with_embedding_dimensions(mut self, dimensions: usize) -> Self {
+        self.embedding_dimensions = Some(dimensions);
+        self
+    }
+
     /// Set the app name
     pub fn with_app_name(mut self, app_name: impl Into<String>) -> Self {
         self.app_name = app_name.into();
@@ -198,8 +240,11 @@ impl std::fmt::Debug for OpenRouterConfig {
             .field("provider_preferences", &self.provider_preferences)
             .field("fallback_models", &self.fallback_models)
             .field("max_retries", &self.max_retries)
+            .field("retry_backoff", &self.retry_backoff)
             .field("timeout", &self.timeout)
             .field("app_name", &self.app_name)
+            .field("embedding_model", &self.embedding_model)
+            .field("embedding_dimensions", &self.embedding_dimensions)
             .finish()
     }
 }
@@ -229,4 +274,7 @@ pub mod presets {
 
     /// Gemini Pro
     pub const GEMINI_PRO: &str = "google/gemini-pro-1.5";
+
+    /// Text embeddings
+    pub const EMBEDDING: &str = "openai/text-embedding-3-small";
 }
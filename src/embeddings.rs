@@ -0,0 +1,277 @@
+//! Text embeddings for semantic search, caching and similarity checks
+//!
+//! [`EmbeddingClient`] turns batches of text into vectors. It is implemented
+//! by [`VllmClient`](crate::vllm::VllmClient) and
+//! [`OpenRouterClient`](crate::openrouter::OpenRouterClient) over the
+//! OpenAI-compatible `/embeddings` endpoint, and by [`HashingEmbedder`],
+//! which needs no model or network:
+//!
+//! ```rust,ignore
+//! let client = VllmClient::new(
+//!     VllmConfig::new("http://localhost:8001").with_embedding_model("BAAI/bge-m3"),
+//! )?;
+//! let vectors = client.embed_batch(&["first".to_string(), "second".to_string()]).await?;
+//! ```
+//!
+//! Embedding clients are used by [`ArchivalMemory`](crate::archival::ArchivalMemory)
+//! and by [`SemanticCache::with_semantic`](crate::cache::SemanticCache::with_semantic),
+//! which both compare vectors with [`cosine_similarity`].
+
+use crate::error::{Error, Result};
+use async_trait::async_trait;
+use serde::{Deserialize, Serialize};
+
+/// Produces embedding vectors for text
+#[async_trait]
+pub trait EmbeddingClient: Send + Sync {
+    /// Embed a batch of texts, returning one vector per text in order
+    async fn embed_batch(&self, texts: &[String]) -> Result<Vec<Vec<f32>>>;
+
+    /// Embed a single text
+    async fn embed_one(&self, text: &str) -> Result<Vec<f32>> {
+        self.embed_batch(&[text.to_string()])
+            .await?
+            .pop()
+            .ok_or_else(|| Error::other("Embedding response was empty"))
+    }
+
+    /// Length of the vectors, once known
+    fn dimensions(&self) -> Option<usize>;
+
+    /// Embedding model identifier
+    fn model_id(&self) -> &str;
+}
+
+/// Cosine similarity, or 0 when the vectors differ in length or either is zero
+pub fn cosine_similarity(a: &[f32], b: &[f32]) -> f32 {
+    if a.len() != b.len() {
+        return 0.0;
+    }
+    let (mut dot, mut norm_a, mut norm_b) = (0.0f32, 0.0f32, 0.0f32);
+    for (x, y) in a.iter().zip(b) {
+        dot += x * y;
+        norm_a += x * x;
+        norm_b += y * y;
+    }
+    if norm_a == 0.0 || norm_b == 0.0 {
+        0.0
+    } else {
+        dot / (norm_a.sqrt() * norm_b.sqrt())
+    }
+}
+
+/// OpenAI-compatible `/embeddings` request
+#[derive(Debug, Clone, Serialize)]
+pub(crate) struct EmbeddingRequest<'a> {
+    /// Model to use; servers hosting one model may omit it
+    #[serde(skip_serializing_if = "str::is_empty")]
+    pub model: &'a str,
+    /// Texts to embed
+    pub input: &'a [String],
+    /// Requested vector length, for models that can shorten their output
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub dimensions: Option<usize>,
+    /// Always `float`
+    pub encoding_format: &'static str,
+}
+
+/// OpenAI-compatible `/embeddings` response
+#[derive(Debug, Clone, Deserialize)]
+pub(crate) struct EmbeddingResponse {
+    data: Vec<EmbeddingData>,
+}
+
+#[derive(Debug, Clone, Deserialize)]
+struct EmbeddingData {
+    index: usize,
+    embedding: Vec<f32>,
+}
+
+/// Send an embedding request and return its vectors in input order
+pub(crate) async fn send(
+    request: reqwest::RequestBuilder,
+    provider: &str,
+    expected: usize,
+) -> Result<Vec<Vec<f32>>> {
+    let response = request.send().await?;
+
+    if !response.status().is_success() {
+        let status = response.status();
+        let error_text = response
+            .text()
+            .await
+            .unwrap_or_else(|_| "Unknown error".to_string());
+        return Err(Error::api(
+            status.as_u16(),
+            format!("{} embedding request failed: {}", provider, error_text),
+        ));
+    }
+
+    let response: EmbeddingResponse = response.json().await?;
+    response.into_vectors(expected)
+}
+
+impl EmbeddingResponse {
+    /// Vectors in input order, checked against the number of inputs
+    fn into_vectors(mut self, expected: usize) -> Result<Vec<Vec<f32>>> {
+        if self.data.len() != expected {
+            return Err(Error::other(format!(
+                "Expected {} embeddings, got {}",
+                expected,
+                self.data.len()
+            )));
+        }
+        self.data.sort_by_key(|d| d.index);
+        Ok(self.data.into_iter().map(|d| d.embedding).collect())
+    }
+}
+
+/// Deterministic embeddings from hashed word features
+///
+/// Each lowercase word and word bigram is hashed into one of `dimensions`
+/// buckets with a hash-derived sign, and the result is L2-normalised. Texts
+/// sharing words score a high cosine similarity, which is enough for tests
+/// and offline runs; it captures no meaning beyond shared vocabulary.
+#[derive(Debug, Clone)]
+pub struct HashingEmbedder {
+    dimensions: usize,
+}
+
+impl HashingEmbedder {
+    /// Create an embedder producing vectors of `dimensions` values
+    pub fn new(dimensions: usize) -> Self {
+        Self {
+            dimensions: dimensions.max(1),
+        }
+    }
+
+    /// Embed one text synchronously
+    pub fn embed_text(&self, text: &str) -> Vec<f32> {
+        let words: Vec<String> = text
+            .split(|c: char| !c.is_alphanumeric())
+            .filter(|w| !w.is_empty())
+            .map(str::to_lowercase)
+            .collect();
+
+        let mut vector = vec![0.0f32; self.dimensions];
+        let bigrams = words.windows(2).map(|pair| format!("{} {}", pair[0], pair[1]));
+        for feature in words.iter().cloned().chain(bigrams) {
+            let hash = crate::replay::fnv1a(feature.as_bytes());
+            let bucket = (hash % self.dimensions as u64) as usize;
+            vector[bucket] += if hash >> 63 == 0 { 1.0 } else { -1.0 };
+        }
+
+        let norm = vector.iter().map(|v| v * v).sum::<f32>().sqrt();
+        if norm > 0.0 {
+            vector.iter_mut().for_each(|v| *v /= norm);
+        }
+        vector
+    }
+}
+
+impl Default for HashingEmbedder {
+    fn default() -> Self {
+        Self::new(256)
+    }
+}
+
+#[async_trait]
+impl EmbeddingClient for HashingEmbedder {
+    async fn embed_batch(&self, texts: &[String]) -> Result<Vec<Vec<f32>>> {
+        Ok(texts.iter().map(|text| self.embed_text(text)).collect())
+    }
+
+    fn dimensions(&self) -> Option<usize> {
+        Some(self.dimensions)
+    }
+
+    fn model_id(&self) -> &str {
+        "hashing"
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::config::OpenRouterConfig;
+    use crate::openrouter::OpenRouterClient;
+    use crate::vllm::{VllmClient, VllmConfig};
+
+    #[tokio::test]
+    async fn test_hashing_embedder_is_deterministic() {
+        let embedder = HashingEmbedder::new(64);
+        let texts = vec![
+            "The capital of France is Paris".to_string(),
+            "the capital of France is paris!".to_string(),
+            "Rust borrow checker errors".to_string(),
+        ];
+        let vectors = embedder.embed_batch(&texts).await.unwrap();
+
+        assert_eq!(vectors[0], HashingEmbedder::new(64).embed_text(&texts[0]));
+        assert_eq!(vectors[0].len(), 64);
+        assert!((cosine_similarity(&vectors[0], &vectors[1]) - 1.0).abs() < 1e-5);
+        assert!(cosine_similarity(&vectors[0], &vectors[2]) < 0.5);
+        assert_eq!(embedder.embed_one(&texts[0]).await.unwrap(), vectors[0]);
+    }
+
+    #[tokio::test]
+    async fn test_vllm_embeddings_in_input_order() {
+        let mut server = mockito::Server::new_async().await;
+        let mock = server
+            .mock("POST", "/v1/embeddings")
+            .match_body(mockito::Matcher::PartialJson(serde_json::json!({
+                "model": "bge-m3",
+                "input": ["a", "b"],
+            })))
+            .with_body(r#"{"object":"list","data":[
+                {"object":"embedding","index":1,"embedding":[0.0,1.0,0.0]},
+                {"object":"embedding","index":0,"embedding":[1.0,0.0,0.0]}
+            ],"model":"bge-m3","usage":{"prompt_tokens":2,"total_tokens":2}}"#)
+            .create_async()
+            .await;
+
+        let client =
+            VllmClient::new(VllmConfig::new(server.url()).with_embedding_model("bge-m3")).unwrap();
+        assert_eq!(client.dimensions(), None);
+
+        let vectors = client.embed_batch(&["a".to_string(), "b".to_string()]).await.unwrap();
+        mock.assert_async().await;
+        assert_eq!(vectors, vec![vec![1.0, 0.0, 0.0], vec![0.0, 1.0, 0.0]]);
+        assert_eq!(client.dimensions(), Some(3));
+        assert_eq!(client.model_id(), "bge-m3");
+    }
+
+    #[tokio::test]
+    async fn test_openrouter_embeddings() {
+        let mut server = mockito::Server::new_async().await;
+        server
+            .mock("POST", "/api/v1/embeddings")
+            .match_header("authorization", "Bearer test-key")
+            .match_body(mockito::Matcher::PartialJson(serde_json::json!({
+                "model": "openai/text-embedding-3-small",
+                "input": ["hello"],
+                "dimensions": 2,
+            })))
+            .with_body(r#"{"data":[{"index":0,"embedding":[0.6,0.8]}]}"#)
+            .create_async()
+            .await;
+        server
+            .mock("POST", "/api/v1/embeddings")
+            .match_body(mockito::Matcher::PartialJson(serde_json::json!({ "input": ["fail"] })))
+            .with_status(400)
+            .with_body("bad input")
+            .create_async()
+            .await;
+
+        let base_url = url::Url::parse(&format!("{}/api/v1", server.url())).unwrap();
+        let config = OpenRouterConfig::new("test-key")
+            .with_base_url(base_url)
+            .with_embedding_dimensions(2);
+        let client = OpenRouterClient::new(config).unwrap();
+
+        assert_eq!(client.embed_one("hello").await.unwrap(), vec![0.6, 0.8]);
+        assert_eq!(client.dimensions(), Some(2));
+        let err = client.embed_batch(&["fail".to_string()]).await.unwrap_err();
+        assert!(err.to_string().contains("400"));
+    }
+}
@@ -1,5 +1,7 @@
 //! Error types for the ATHPTTGH framework
 
+use crate::orchestrator::FailedAgent;
+use std::time::Duration;
 use thiserror::Error;
 
 /// Result type alias for ATHPTTGH operations
@@ -16,6 +18,24 @@ pub enum Error {
     #[error("HTTP error: {0}")]
     Http(#[from] reqwest::Error),
 
+    /// Provider answered with an unsuccessful HTTP status
+    #[error("API error: status {status}: {message}")]
+    Api {
+        /// HTTP status code
+        status: u16,
+        /// What failed, including the response body
+        message: String,
+    },
+
+    /// Client already retried a transient failure and gave up
+    #[error("Gave up after {attempts} attempts: {last}")]
+    RetriesExhausted {
+        /// Requests sent, across all models tried
+        attempts: u32,
+        /// Error from the final attempt
+        last: Box<Error>,
+    },
+
     /// Serialization/deserialization error
     #[error("Serialization error: {0}")]
     Serialization(#[from] serde_json::Error),
@@ -32,6 +52,19 @@ pub enum Error {
     #[error("Handoff error: {0}")]
     Handoff(String),
 
+    /// Handoff chain revisited an agent
+    #[error("Handoff loop detected: {0}")]
+    HandoffLoop(String),
+
+    /// Handoff chain went deeper than the strategy allows
+    #[error("Handoff depth exceeded: {depth} (max: {max})")]
+    HandoffDepthExceeded {
+        /// Depth the chain would have reached
+        depth: u32,
+        /// Maximum depth allowed by the strategy
+        max: u32,
+    },
+
     /// Guardrail violation
     #[error("Guardrail violation: {guardrail}: {reason}")]
     GuardrailViolation { guardrail: String, reason: String },
@@ -52,6 +85,15 @@ pub enum Error {
     #[error("Context window exceeded: {current} tokens (max: {max})")]
     ContextWindowExceeded { current: u64, max: u64 },
 
+    /// Token budget exhausted
+    #[error("Token budget exceeded: {used} tokens (limit: {limit})")]
+    BudgetExceeded {
+        /// Tokens charged so far
+        used: u64,
+        /// Hard limit of the ledger
+        limit: u64,
+    },
+
     /// Maximum loops exceeded
     #[error("Maximum loops exceeded: {0}")]
     MaxLoopsExceeded(u32),
@@ -88,6 +130,43 @@ pub enum Error {
     #[error("JSON Schema validation error: {0}")]
     JsonSchema(String),
 
+    /// Orchestrator pattern did not finish within its timeout
+    #[error("Pattern execution exceeded {timeout:?} (still running: {})", .running.join(", "))]
+    PatternTimeout {
+        /// Configured timeout
+        timeout: Duration,
+        /// Agents whose step was still running
+        running: Vec<String>,
+        /// Agents whose step had completed
+        completed: Vec<String>,
+        /// Agent steps that had already failed
+        failed_agents: Vec<FailedAgent>,
+    },
+
+    /// Every agent step of an orchestrator pattern failed
+    #[error("No agent produced output in {pattern} pattern ({} failed)", .failed_agents.len())]
+    NoAgentOutput {
+        /// Pattern type
+        pattern: String,
+        /// Agent steps that failed
+        failed_agents: Vec<FailedAgent>,
+    },
+
+    /// Shared memory block changed since the caller read it
+    #[error("Memory conflict: block {block_id} is at revision {actual}, expected {expected}")]
+    MemoryConflict {
+        /// Block that was being updated
+        block_id: String,
+        /// Revision the caller based its change on
+        expected: u64,
+        /// Revision the block is actually at
+        actual: u64,
+    },
+
+    /// Caller lacks access to a resource
+    #[error("Permission denied: {0}")]
+    PermissionDenied(String),
+
     /// Generic error
     #[error("{0}")]
     Other(String),
@@ -99,6 +178,14 @@ impl Error {
         Self::OpenRouter(msg.into())
     }
 
+    /// Create an error for an unsuccessful HTTP status
+    pub fn api(status: u16, message: impl Into<String>) -> Self {
+        Self::Api {
+            status,
+            message: message.into(),
+        }
+    }
+
     /// Create an agent error
     pub fn agent(msg: impl Into<String>) -> Self {
         Self::Agent(msg.into())
@@ -139,4 +226,22 @@ impl Error {
     pub fn other(msg: impl Into<String>) -> Self {
         Self::Other(msg.into())
     }
+
+    /// Whether the error may go away if the request is repeated
+    ///
+    /// Covers timeouts, connection failures, rate limits (429) and server
+    /// errors (5xx). Failures a client has already retried itself
+    /// ([`Error::RetriesExhausted`]) are not transient, so retry layers do
+    /// not multiply the attempts.
+    pub fn is_transient(&self) -> bool {
+        let transient_status = |status: u16| status == 429 || (500..600).contains(&status);
+        match self {
+            Self::Http(e) => {
+                e.is_timeout() || e.is_connect() || e.status().is_some_and(|s| transient_status(s.as_u16()))
+            }
+            Self::Api { status, .. } => transient_status(*status),
+            Self::RateLimitExceeded(_) | Self::Timeout(_) | Self::PatternTimeout { .. } => true,
+            _ => false,
+        }
+    }
 }
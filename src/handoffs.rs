@@ -1,9 +1,12 @@
 //! Handoff protocol and inter-agent delegation
 
+use crate::agent::Agent;
 use crate::react::{Observation, ReActTrace};
 use crate::types::AgentId;
+use parking_lot::RwLock;
 use serde::{Deserialize, Serialize};
 use std::collections::HashMap;
+use std::sync::Arc;
 use std::time::Duration;
 
 /// Handoff request from one agent to another
@@ -43,6 +46,24 @@ impl Handoff {
         self.return_control = return_control;
         self
     }
+
+    /// Render the input given to the target agent
+    pub fn prompt(&self, source_name: &str) -> String {
+        let mut prompt = format!(
+            "{}\n\n## Handoff from {}\nReason: {}\n",
+            self.context.original_query, source_name, self.reason
+        );
+
+        if !self.context.observations.is_empty() {
+            prompt.push_str("\nObservations so far:\n");
+            for observation in &self.context.observations {
+                let prefix = if observation.is_error { "[error] " } else { "" };
+                prompt.push_str(&format!("- {}{}\n", prefix, observation.content));
+            }
+        }
+
+        prompt
+    }
 }
 
 /// Context to transfer during handoff
@@ -113,3 +134,62 @@ impl Default for HandoffStrategy {
         Self::Direct
     }
 }
+
+/// Registry of agents that can receive handoffs
+///
+/// Agents resolve their `handoff_targets` against the registry at run time,
+/// so targets can be registered after the delegating agent is built.
+#[derive(Default)]
+pub struct AgentRegistry {
+    agents: RwLock<HashMap<AgentId, Arc<Agent>>>,
+}
+
+impl AgentRegistry {
+    /// Create an empty registry
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Register an agent, returning its ID
+    pub fn register(&self, agent: Arc<Agent>) -> AgentId {
+        let id = agent.id;
+        self.agents.write().insert(id, agent);
+        id
+    }
+
+    /// Remove an agent from the registry
+    pub fn unregister(&self, id: AgentId) -> Option<Arc<Agent>> {
+        self.agents.write().remove(&id)
+    }
+
+    /// Look up an agent by ID
+    pub fn get(&self, id: AgentId) -> Option<Arc<Agent>> {
+        self.agents.read().get(&id).cloned()
+    }
+
+    /// Look up an agent by name
+    pub fn find_by_name(&self, name: &str) -> Option<Arc<Agent>> {
+        self.agents
+            .read()
+            .values()
+            .find(|agent| agent.name == name)
+            .cloned()
+    }
+
+    /// Number of registered agents
+    pub fn len(&self) -> usize {
+        self.agents.read().len()
+    }
+
+    /// Whether the registry is empty
+    pub fn is_empty(&self) -> bool {
+        self.agents.read().is_empty()
+    }
+}
+
+impl std::fmt::Debug for AgentRegistry {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        let names: Vec<String> = self.agents.read().values().map(|a| a.name.clone()).collect();
+        f.debug_struct("AgentRegistry").field("agents", &names).finish()
+    }
+}
@@ -45,20 +45,31 @@
 
 pub mod agent;
 pub mod agent_file;
+pub mod anthropic;
+pub mod archival;
 pub mod background;
+pub mod budget;
+pub mod cache;
 pub mod config;
+pub mod embeddings;
 pub mod error;
 pub mod filesystem;
 pub mod guardrails;
 pub mod handoffs;
 pub mod hitl;
+pub mod llamacpp;
 pub mod llm_client;
 pub mod memory;
 pub mod memory_tools;
+pub mod middleware;
+pub mod ollama;
 pub mod openrouter;
 pub mod patterns;
 pub mod orchestrator;
+pub mod rate_limit;
 pub mod react;
+pub mod react_parser;
+pub mod replay;
 pub mod sleeptime;
 #[cfg(feature = "storage")]
 pub mod storage;
@@ -74,36 +85,52 @@ pub mod vllm;
 pub mod solid;
 
 // Re-exports for convenience
-pub use agent::{Agent, AgentBuilder, AgentHooks, AgentOutput};
+pub use agent::{Agent, AgentBuilder, AgentEvent, AgentHooks, AgentOutput};
 pub use agent_file::{AgentFile, CheckpointManager};
+pub use anthropic::{AnthropicClient, AnthropicConfig};
+pub use archival::{ArchivalMemory, FlatIndex, HnswIndex, VectorIndex};
 pub use background::{BackgroundExecutor, RunId, SeqId, RunStatus, RunEvent, RunEventType, PaginatedEvents};
+pub use budget::{BudgetLedger, ModelPrice, PriceTable, UsageReport};
+pub use cache::{CacheStats, CacheStore, InMemoryCacheStore, SemanticCache};
 pub use config::{ModelConfig, OpenRouterConfig};
+pub use embeddings::{EmbeddingClient, HashingEmbedder};
 pub use error::{Error, Result};
 pub use filesystem::{FilesystemManager, AttachedFolder};
 pub use guardrails::{GuardrailContext, GuardrailResult, InputGuardrail, OutputGuardrail};
-pub use handoffs::{Handoff, HandoffContext, HandoffStrategy};
+pub use handoffs::{AgentRegistry, Handoff, HandoffContext, HandoffStrategy};
 pub use hitl::{ApprovalDecision, ApprovalHandler, ApprovalRequest};
-pub use llm_client::LlmClient;
-pub use memory::{AgentMemory, MemoryBlock, MemoryConfig, SharedMemoryManager};
-pub use openrouter::{OpenRouterClient, CompletionRequest, StreamChunk};
-pub use sleeptime::{SleepTimeAgent, SleepTimeConfig};
+pub use llm_client::{ClientStack, Layer, LlmClient};
+pub use memory::{
+    AgentMemory, BlockAccess, BlockDiff, BlockVersion, EditAuthor, MemoryBlock, MemoryConfig, SharedBlock,
+    SharedBlockEvent, SharedBlockUpdate, SharedMemoryManager,
+};
+pub use openrouter::{OpenRouterClient, CompletionRequest, ContentPart, ImageUrl, MessageContent, ProviderRouting, ReasoningDetail, ResponseFormat, StreamAccumulator, StreamChunk};
+pub use sleeptime::{MemoryEdit, MemoryEditKind, SleepTimeAgent, SleepTimeConfig};
 #[cfg(feature = "storage")]
-pub use storage::{MemoryStorage, PostgresStorage, SqliteStorage};
-pub use patterns::{PatternConfig, WorkflowPattern};
+pub use storage::{MemoryStorage, PostgresStorage, SledCacheStore, SledTurnStorage, SqliteStorage};
+pub use patterns::{FailureMode, PatternConfig, WorkflowPattern};
 pub use orchestrator::{
     OrchestratorConfig, OrchestratorPattern, OrchestratorResult,
     PatternType, AgentConfig, SubagentConfig,
     SequentialOrchestrator, ConcurrentOrchestrator, HierarchicalOrchestrator,
     DebateOrchestrator, RouterOrchestrator, ConsensusOrchestrator,
 };
-pub use react::{ReActConfig, ReActTrace, ReasoningFormat};
+pub use middleware::{AuditEntry, AuditLayer, AuditSink, JsonLinesAuditSink, RedactionLayer, RetryLayer};
+pub use rate_limit::{RateLimit, RateLimitedClient, RateLimiter};
+pub use replay::{Cassette, ReplayClient};
+pub use react::{ReActConfig, ReActTrace, ReasoningFormat, ToolCallingMode};
 pub use tools::{Tool, ToolContext, ToolOutput};
 #[cfg(feature = "mcp-tools")]
 pub use tools::McpSubprocessTool;
 pub use security_tools::{SecurityToolRegistry, SecurityTool, SecurityCategory, ListSecurityTools, RunSecurityTool, TaggedSecurityTools};
-pub use turns::{Session, Turn, TurnManager};
+pub use tracing_ext::{JsonLinesExporter, Span, SpanType, Trace, TraceExporter, Tracer};
+#[cfg(feature = "telemetry")]
+pub use tracing_ext::OtlpExporter;
+pub use turns::{InMemoryTurnStorage, Session, Turn, TurnManager};
 pub use types::{AgentId, SessionId, SpanId, TraceId, TurnId};
 pub use vllm::{VllmClient, VllmConfig};
+pub use ollama::{OllamaClient, OllamaConfig};
+pub use llamacpp::{LlamaCppClient, LlamaCppConfig};
 
 /// Prelude module for common imports
 pub mod prelude {
@@ -111,7 +138,7 @@ pub mod prelude {
     pub use crate::error::{Error, Result};
     pub use crate::llm_client::LlmClient;
     pub use crate::openrouter::OpenRouterClient;
-    pub use crate::react::{ReActConfig, ReasoningFormat};
+    pub use crate::react::{ReActConfig, ReasoningFormat, ToolCallingMode};
     pub use crate::tools::Tool;
     #[cfg(feature = "mcp-tools")]
     pub use crate::tools::McpSubprocessTool;
@@ -0,0 +1,267 @@
+//! llama.cpp server client
+//!
+//! `llama-server` serves one GGUF model over an OpenAI-compatible API:
+//!
+//! ```bash
+//! llama-server -m models/qwen2.5-7b-instruct-q4_k_m.gguf --port 8080 --jinja
+//! ```
+//!
+//! Models are loaded when the server starts, so there is no pull; use
+//! [`LlamaCppClient::show_model`] to see what is loaded. Reasoning returned
+//! in `reasoning_content` (with `--reasoning-format deepseek`) is kept out of
+//! the answer, like other thinking models.
+
+use crate::error::{Error, Result};
+use crate::llm_client::LlmClient;
+use crate::openrouter::{CompletionRequest, CompletionResponse, CompletionStream, StreamOptions};
+use crate::vllm::ModelsResponse;
+use async_trait::async_trait;
+use reqwest::Client;
+use serde::{Deserialize, Serialize};
+use std::time::Duration;
+
+/// llama.cpp server configuration
+#[derive(Debug, Clone)]
+pub struct LlamaCppConfig {
+    /// Base URL of the server (e.g., "http://localhost:8080")
+    pub base_url: String,
+    /// Request timeout
+    pub timeout: Duration,
+    /// Optional API key (for servers started with `--api-key`)
+    pub api_key: Option<String>,
+}
+
+impl LlamaCppConfig {
+    /// Create a new llama.cpp configuration
+    pub fn new(base_url: impl Into<String>) -> Self {
+        Self {
+            base_url: base_url.into(),
+            timeout: Duration::from_secs(300),
+            api_key: None,
+        }
+    }
+
+    /// Create configuration from environment variables
+    pub fn from_env() -> Result<Self> {
+        let base_url = std::env::var("LLAMACPP_BASE_URL")
+            .unwrap_or_else(|_| "http://localhost:8080".to_string());
+        let api_key = std::env::var("LLAMACPP_API_KEY").ok();
+
+        Ok(Self {
+            base_url,
+            timeout: Duration::from_secs(300),
+            api_key,
+        })
+    }
+
+    /// Set the timeout
+    pub fn with_timeout(mut self, timeout: Duration) -> Self {
+        self.timeout = timeout;
+        self
+    }
+
+    /// Set the API key
+    pub fn with_api_key(mut self, api_key: impl Into<String>) -> Self {
+        self.api_key = Some(api_key.into());
+        self
+    }
+}
+
+/// llama.cpp server client
+pub struct LlamaCppClient {
+    /// HTTP client
+    client: Client,
+    /// Configuration
+    config: LlamaCppConfig,
+}
+
+impl LlamaCppClient {
+    /// Create a new llama.cpp client from environment variables
+    pub fn from_env() -> Result<Self> {
+        Self::new(LlamaCppConfig::from_env()?)
+    }
+
+    /// Create a new llama.cpp client with the given configuration
+    pub fn new(config: LlamaCppConfig) -> Result<Self> {
+        let client = Client::builder().timeout(config.timeout).build()?;
+        Ok(Self { client, config })
+    }
+
+    /// Get the configuration
+    pub fn config(&self) -> &LlamaCppConfig {
+        &self.config
+    }
+
+    fn get(&self, path: &str) -> reqwest::RequestBuilder {
+        let mut request = self
+            .client
+            .get(format!("{}{}", self.config.base_url.trim_end_matches('/'), path));
+        if let Some(ref api_key) = self.config.api_key {
+            request = request.header("Authorization", format!("Bearer {}", api_key));
+        }
+        request
+    }
+
+    /// Check if the server is up and its model is loaded
+    pub async fn health_check(&self) -> Result<LlamaCppHealth> {
+        let response = self.get("/health").send().await?;
+
+        // The server answers 503 while the model is still loading
+        if !response.status().is_success() {
+            return Err(Error::config(format!(
+                "llama.cpp health check failed: {}",
+                response.status()
+            )));
+        }
+
+        Ok(response.json().await?)
+    }
+
+    /// List the models the server exposes
+    pub async fn list_models(&self) -> Result<ModelsResponse> {
+        let response = self.get("/v1/models").send().await?;
+
+        if !response.status().is_success() {
+            return Err(Error::config(format!(
+                "Failed to get models: {}",
+                response.status()
+            )));
+        }
+
+        Ok(response.json().await?)
+    }
+
+    /// Get the loaded model and server settings
+    pub async fn show_model(&self) -> Result<LlamaCppProps> {
+        let response = self.get("/props").send().await?;
+
+        if !response.status().is_success() {
+            return Err(Error::config(format!(
+                "Failed to get server properties: {}",
+                response.status()
+            )));
+        }
+
+        Ok(response.json().await?)
+    }
+
+    async fn send(&self, request: &CompletionRequest, what: &str) -> Result<reqwest::Response> {
+        let url = format!("{}/v1/chat/completions", self.config.base_url.trim_end_matches('/'));
+        let mut http_request = self.client.post(&url).json(request);
+
+        if let Some(ref api_key) = self.config.api_key {
+            http_request = http_request.header("Authorization", format!("Bearer {}", api_key));
+        }
+
+        let response = http_request.send().await?;
+
+        if !response.status().is_success() {
+            let status = response.status();
+            let error_text = response
+                .text()
+                .await
+                .unwrap_or_else(|_| "Unknown error".to_string());
+            return Err(Error::api(
+                status.as_u16(),
+                format!("llama.cpp {} failed: {}", what, error_text),
+            ));
+        }
+
+        Ok(response)
+    }
+}
+
+#[async_trait]
+impl LlmClient for LlamaCppClient {
+    async fn complete(&self, request: CompletionRequest) -> Result<CompletionResponse> {
+        Ok(self.send(&request, "request").await?.json().await?)
+    }
+
+    async fn stream(&self, mut request: CompletionRequest) -> Result<CompletionStream> {
+        request.stream = true;
+        request.stream_options = Some(StreamOptions { include_usage: true });
+
+        let response = self.send(&request, "stream request").await?;
+        Ok(CompletionStream::new(response.bytes_stream()))
+    }
+
+    fn client_type(&self) -> &str {
+        "llamacpp"
+    }
+
+    fn endpoint(&self) -> &str {
+        &self.config.base_url
+    }
+}
+
+/// llama.cpp health check response
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct LlamaCppHealth {
+    /// Server status
+    pub status: String,
+}
+
+/// Loaded model and server settings
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct LlamaCppProps {
+    /// Path of the loaded GGUF file
+    #[serde(default)]
+    pub model_path: String,
+    /// Number of parallel request slots
+    #[serde(default)]
+    pub total_slots: u32,
+    /// Chat template applied to messages
+    #[serde(default)]
+    pub chat_template: String,
+    /// Sampling defaults and context size
+    #[serde(default)]
+    pub default_generation_settings: serde_json::Value,
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::openrouter::Message;
+
+    #[tokio::test]
+    async fn test_management_and_reasoning_stream() {
+        let mut server = mockito::Server::new_async().await;
+        server
+            .mock("GET", "/health")
+            .with_body(r#"{"status":"ok"}"#)
+            .create_async()
+            .await;
+        server
+            .mock("GET", "/props")
+            .with_body(r#"{"model_path":"models/qwen.gguf","total_slots":4,"default_generation_settings":{"n_ctx":8192}}"#)
+            .create_async()
+            .await;
+        server
+            .mock("POST", "/v1/chat/completions")
+            .with_header("content-type", "text/event-stream")
+            .with_body(concat!(
+                "data: {\"id\":\"c\",\"model\":\"qwen\",\"choices\":[{\"index\":0,\"delta\":{\"reasoning_content\":\"Add them.\"},\"finish_reason\":null}]}\n\n",
+                "data: {\"id\":\"c\",\"model\":\"qwen\",\"choices\":[{\"index\":0,\"delta\":{\"content\":\"4\"},\"finish_reason\":\"stop\"}]}\n\n",
+                "data: [DONE]\n\n",
+            ))
+            .create_async()
+            .await;
+
+        let client = LlamaCppClient::new(LlamaCppConfig::new(server.url())).unwrap();
+        assert_eq!(client.health_check().await.unwrap().status, "ok");
+        let props = client.show_model().await.unwrap();
+        assert_eq!(props.model_path, "models/qwen.gguf");
+        assert_eq!(props.default_generation_settings["n_ctx"], 8192);
+
+        let response = client
+            .stream(CompletionRequest::new("qwen", vec![Message::user("2 + 2?")]))
+            .await
+            .unwrap()
+            .collect_response()
+            .await
+            .unwrap();
+        let message = &response.choices[0].message;
+        assert_eq!(message.content, "4");
+        assert_eq!(message.reasoning.as_deref(), Some("Add them."));
+    }
+}
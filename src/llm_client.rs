@@ -3,6 +3,7 @@
 use crate::error::Result;
 use crate::openrouter::{CompletionRequest, CompletionResponse, CompletionStream};
 use async_trait::async_trait;
+use std::sync::Arc;
 
 /// Unified trait for LLM clients (both remote and local)
 #[async_trait]
@@ -19,3 +20,221 @@ pub trait LlmClient: Send + Sync {
     /// Get the base URL (for local models) or endpoint (for remote)
     fn endpoint(&self) -> &str;
 }
+
+/// Wraps a client in another, tower-style
+///
+/// Layers are applied with [`ClientStack`]. Any
+/// `Fn(Arc<dyn LlmClient>) -> C` closure is a layer too.
+pub trait Layer {
+    /// Wrap `inner`
+    fn layer(&self, inner: Arc<dyn LlmClient>) -> Arc<dyn LlmClient>;
+}
+
+impl<F, C> Layer for F
+where
+    F: Fn(Arc<dyn LlmClient>) -> C,
+    C: LlmClient + 'static,
+{
+    fn layer(&self, inner: Arc<dyn LlmClient>) -> Arc<dyn LlmClient> {
+        Arc::new(self(inner))
+    }
+}
+
+/// Builds a client from a base client and layers
+///
+/// Each layer wraps everything added before it, so the last layer sees
+/// requests first and responses last.
+///
+/// ```rust,ignore
+/// let client = ClientStack::new(Arc::new(vllm))
+///     .layer(SemanticCache::in_memory())
+///     .layer(RetryLayer::new(3))
+///     .layer(RedactionLayer::new())
+///     .layer(AuditLayer::new(Arc::new(JsonLinesAuditSink::new("audit.jsonl"))))
+///     .build();
+/// let agent = Agent::builder().client(client) /* ... */;
+/// ```
+pub struct ClientStack {
+    client: Arc<dyn LlmClient>,
+}
+
+impl ClientStack {
+    /// Start from a base client
+    pub fn new(client: Arc<dyn LlmClient>) -> Self {
+        Self { client }
+    }
+
+    /// Wrap the stack so far in another layer
+    pub fn layer(self, layer: impl Layer) -> Self {
+        Self {
+            client: layer.layer(self.client),
+        }
+    }
+
+    /// The composed client
+    pub fn build(self) -> Arc<dyn LlmClient> {
+        self.client
+    }
+}
+
+/// Scripted client shared by the crate's tests
+#[cfg(test)]
+pub(crate) mod testing {
+    use super::LlmClient;
+    use crate::error::{Error, Result};
+    use crate::openrouter::{Choice, CompletionRequest, CompletionResponse, CompletionStream, Message, Usage};
+    use async_trait::async_trait;
+    use parking_lot::{Mutex, MutexGuard};
+    use std::collections::VecDeque;
+    use std::time::Duration;
+
+    /// Replies with scripted assistant messages in order and records every
+    /// request it answers
+    ///
+    /// Once the script runs out it repeats the fallback reply, or echoes the
+    /// last request message when built with [`ScriptedClient::echoing`].
+    pub(crate) struct ScriptedClient {
+        replies: Mutex<VecDeque<Message>>,
+        fallback: Option<Message>,
+        requests: Mutex<Vec<CompletionRequest>>,
+        calls: Mutex<usize>,
+        streams: Mutex<usize>,
+        failures: Mutex<u32>,
+        failure: fn() -> Error,
+        delay: Option<Duration>,
+        usage: Usage,
+    }
+
+    impl ScriptedClient {
+        /// Answer with `replies`, then with "Final Answer: out of script"
+        pub(crate) fn new(replies: Vec<Message>) -> Self {
+            Self {
+                replies: Mutex::new(replies.into()),
+                fallback: Some(Message::assistant("Final Answer: out of script")),
+                requests: Mutex::new(Vec::new()),
+                calls: Mutex::new(0),
+                streams: Mutex::new(0),
+                failures: Mutex::new(0),
+                failure: || Error::openrouter("scripted failure"),
+                delay: None,
+                usage: Usage {
+                    prompt_tokens: 10,
+                    completion_tokens: 5,
+                    total_tokens: 15,
+                },
+            }
+        }
+
+        /// Answer every request with `reply`
+        pub(crate) fn repeating(reply: &str) -> Self {
+            Self::new(Vec::new()).with_fallback(reply)
+        }
+
+        /// Answer with the text of the last request message
+        pub(crate) fn echoing() -> Self {
+            Self {
+                fallback: None,
+                ..Self::new(Vec::new())
+            }
+        }
+
+        /// Reply with `reply` once the script runs out
+        pub(crate) fn with_fallback(mut self, reply: &str) -> Self {
+            self.fallback = Some(Message::assistant(reply));
+            self
+        }
+
+        /// Fail the first `calls` requests with a non-transient error
+        pub(crate) fn failing_first(self, calls: u32) -> Self {
+            *self.failures.lock() = calls;
+            self
+        }
+
+        /// Fail the first `calls` requests with errors built by `failure`
+        pub(crate) fn failing_with(mut self, calls: u32, failure: fn() -> Error) -> Self {
+            self.failure = failure;
+            self.failing_first(calls)
+        }
+
+        /// Wait `delay` before answering
+        pub(crate) fn with_delay(mut self, delay: Duration) -> Self {
+            self.delay = Some(delay);
+            self
+        }
+
+        /// Report `total_tokens` used by every reply
+        pub(crate) fn with_usage(mut self, total_tokens: u64) -> Self {
+            self.usage = Usage {
+                prompt_tokens: 10,
+                completion_tokens: total_tokens.saturating_sub(10),
+                total_tokens,
+            };
+            self
+        }
+
+        /// Requests answered so far, excluding failed ones
+        pub(crate) fn requests(&self) -> MutexGuard<'_, Vec<CompletionRequest>> {
+            self.requests.lock()
+        }
+
+        /// Number of requests received, including failed ones
+        pub(crate) fn calls(&self) -> usize {
+            *self.calls.lock()
+        }
+
+        /// Number of requests received through [`LlmClient::stream`]
+        pub(crate) fn streams(&self) -> usize {
+            *self.streams.lock()
+        }
+    }
+
+    #[async_trait]
+    impl LlmClient for ScriptedClient {
+        async fn complete(&self, request: CompletionRequest) -> Result<CompletionResponse> {
+            *self.calls.lock() += 1;
+            if let Some(delay) = self.delay {
+                tokio::time::sleep(delay).await;
+            }
+            {
+                let mut failures = self.failures.lock();
+                if *failures > 0 {
+                    *failures -= 1;
+                    return Err((self.failure)());
+                }
+            }
+
+            let message = self.replies.lock().pop_front().unwrap_or_else(|| match &self.fallback {
+                Some(fallback) => fallback.clone(),
+                None => Message::assistant(
+                    request.messages.last().map(|m| m.content.to_string()).unwrap_or_default(),
+                ),
+            });
+            self.requests.lock().push(request);
+
+            Ok(CompletionResponse {
+                id: "scripted".to_string(),
+                model: "test".to_string(),
+                provider: None,
+                choices: vec![Choice {
+                    index: 0,
+                    message,
+                    finish_reason: Some("stop".to_string()),
+                }],
+                usage: self.usage.clone(),
+            })
+        }
+
+        async fn stream(&self, request: CompletionRequest) -> Result<CompletionStream> {
+            *self.streams.lock() += 1;
+            Ok(CompletionStream::from_response(self.complete(request).await?))
+        }
+
+        fn client_type(&self) -> &str {
+            "scripted"
+        }
+
+        fn endpoint(&self) -> &str {
+            "http://localhost"
+        }
+    }
+}
@@ -3,17 +3,21 @@
 //! Inspired by Letta's memory architecture, this module implements:
 //! - Memory hierarchy (in-context vs out-of-context)
 //! - Editable memory blocks with persistence
+//! - Version history of every block edit, with diffs and rollback
 //! - Agentic context engineering (agents control their memory)
-//! - Shared memory blocks for multi-agent coordination
+//! - Shared memory blocks for multi-agent coordination, with access lists,
+//!   compare-and-swap updates and change notifications
 //! - Perpetual message history with Agent File (.af) format
+//! - Archival memory with semantic recall (see [`crate::archival`])
 
+use crate::archival::ArchivalMemory;
 use crate::error::{Error, Result};
 use crate::types::AgentId;
 use chrono::{DateTime, Utc};
 use serde::{Deserialize, Serialize};
 use std::collections::HashMap;
 use std::sync::Arc;
-use tokio::sync::RwLock;
+use tokio::sync::{broadcast, RwLock};
 use uuid::Uuid;
 
 #[cfg(feature = "storage")]
@@ -71,6 +75,166 @@ pub struct MemoryBlock {
 
     /// Metadata for custom fields
     pub metadata: HashMap<String, String>,
+
+    /// Revision number, starting at 1 and incremented on every value change
+    #[serde(default = "first_version")]
+    pub version: u64,
+}
+
+fn first_version() -> u64 {
+    1
+}
+
+/// Who changed a memory block
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(tag = "type", rename_all = "snake_case")]
+pub enum EditAuthor {
+    /// An agent, usually through its memory tools
+    Agent {
+        /// Agent that made the edit
+        agent_id: AgentId,
+    },
+    /// The sleep-time agent consolidating an agent's memory
+    SleepTime {
+        /// Agent whose memory was consolidated
+        agent_id: AgentId,
+    },
+    /// A person, e.g. an operator correcting a block
+    Human {
+        /// Name or handle of the person
+        name: String,
+    },
+    /// Application code acting on nobody's behalf
+    System,
+}
+
+impl std::fmt::Display for EditAuthor {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            Self::Agent { agent_id } => write!(f, "agent {}", agent_id),
+            Self::SleepTime { agent_id } => write!(f, "sleep-time agent of {}", agent_id),
+            Self::Human { name } => write!(f, "human {}", name),
+            Self::System => write!(f, "system"),
+        }
+    }
+}
+
+/// Value of a memory block at one revision
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct BlockVersion {
+    /// Block this version belongs to
+    pub block_id: MemoryBlockId,
+    /// Revision number, matching [`MemoryBlock::version`] after the edit
+    pub version: u64,
+    /// Block value at this revision
+    pub value: String,
+    /// Who made the edit
+    pub author: EditAuthor,
+    /// Why the edit was made
+    pub reason: String,
+    /// When the edit was made
+    pub timestamp: DateTime<Utc>,
+}
+
+impl BlockVersion {
+    /// Version capturing the current value of `block`
+    pub fn of(block: &MemoryBlock, author: EditAuthor, reason: impl Into<String>) -> Self {
+        Self {
+            block_id: block.id,
+            version: block.version,
+            value: block.value.clone(),
+            author,
+            reason: reason.into(),
+            timestamp: block.updated_at,
+        }
+    }
+}
+
+/// One line of a [`BlockDiff`]
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(tag = "op", content = "line", rename_all = "snake_case")]
+pub enum DiffLine {
+    /// Present in both versions
+    Unchanged(String),
+    /// Only in the newer version
+    Added(String),
+    /// Only in the older version
+    Removed(String),
+}
+
+/// Line diff between two versions of a block
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct BlockDiff {
+    /// Version compared from
+    pub from: u64,
+    /// Version compared to
+    pub to: u64,
+    /// Lines of both versions in order
+    pub lines: Vec<DiffLine>,
+}
+
+impl BlockDiff {
+    /// Diff the value of `from` against `to` line by line
+    pub fn between(from: &BlockVersion, to: &BlockVersion) -> Self {
+        let old: Vec<&str> = from.value.lines().collect();
+        let new: Vec<&str> = to.value.lines().collect();
+
+        // Longest common subsequence table, filled from the end
+        let mut lcs = vec![vec![0usize; new.len() + 1]; old.len() + 1];
+        for i in (0..old.len()).rev() {
+            for j in (0..new.len()).rev() {
+                lcs[i][j] = if old[i] == new[j] {
+                    lcs[i + 1][j + 1] + 1
+                } else {
+                    lcs[i + 1][j].max(lcs[i][j + 1])
+                };
+            }
+        }
+
+        let (mut i, mut j) = (0, 0);
+        let mut lines = Vec::new();
+        while i < old.len() || j < new.len() {
+            if i < old.len() && j < new.len() && old[i] == new[j] {
+                lines.push(DiffLine::Unchanged(old[i].to_string()));
+                i += 1;
+                j += 1;
+            } else if i < old.len() && (j == new.len() || lcs[i + 1][j] >= lcs[i][j + 1]) {
+                lines.push(DiffLine::Removed(old[i].to_string()));
+                i += 1;
+            } else {
+                lines.push(DiffLine::Added(new[j].to_string()));
+                j += 1;
+            }
+        }
+
+        Self {
+            from: from.version,
+            to: to.version,
+            lines,
+        }
+    }
+
+    /// Whether the two versions have the same value
+    pub fn is_empty(&self) -> bool {
+        self.lines
+            .iter()
+            .all(|l| matches!(l, DiffLine::Unchanged(_)))
+    }
+}
+
+impl std::fmt::Display for BlockDiff {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        writeln!(f, "--- version {}", self.from)?;
+        write!(f, "+++ version {}", self.to)?;
+        for line in &self.lines {
+            match line {
+                DiffLine::Unchanged(l) => write!(f, "\n  {}", l)?,
+                DiffLine::Added(l) => write!(f, "\n+ {}", l)?,
+                DiffLine::Removed(l) => write!(f, "\n- {}", l)?,
+            }
+        }
+        Ok(())
+    }
 }
 
 impl MemoryBlock {
@@ -87,6 +251,7 @@ impl MemoryBlock {
             created_at: now,
             updated_at: now,
             metadata: HashMap::new(),
+            version: 1,
         }
     }
 
@@ -119,6 +284,7 @@ impl MemoryBlock {
 
         self.value = new_val;
         self.updated_at = Utc::now();
+        self.version += 1;
         Ok(())
     }
 
@@ -199,6 +365,19 @@ pub struct AgentMemory {
 
     /// Message history (for perpetual agents)
     message_history: Arc<RwLock<Vec<MessageEntry>>>,
+
+    /// Value history of owned blocks, oldest first
+    versions: Arc<RwLock<HashMap<MemoryBlockId, Vec<BlockVersion>>>>,
+
+    /// Latest version of each block already written to storage
+    #[cfg(feature = "storage")]
+    persisted_versions: Arc<RwLock<HashMap<MemoryBlockId, u64>>>,
+
+    /// Long-term store for messages and blocks moved out of context
+    archival: Option<Arc<ArchivalMemory>>,
+
+    /// Manager holding the shared blocks this agent attaches
+    shared_memory: Option<SharedMemoryManager>,
 }
 
 /// A single message in the agent's perpetual history
@@ -232,40 +411,237 @@ impl AgentMemory {
             shared_blocks: Arc::new(RwLock::new(Vec::new())),
             config,
             message_history: Arc::new(RwLock::new(Vec::new())),
+            versions: Arc::new(RwLock::new(HashMap::new())),
+            #[cfg(feature = "storage")]
+            persisted_versions: Arc::new(RwLock::new(HashMap::new())),
+            archival: None,
+            shared_memory: None,
+        }
+    }
+
+    /// Read and update attached shared blocks through `manager`
+    pub fn with_shared_memory(mut self, manager: SharedMemoryManager) -> Self {
+        self.shared_memory = Some(manager);
+        self
+    }
+
+    /// Shared memory manager, if configured
+    pub fn shared_memory(&self) -> Option<&SharedMemoryManager> {
+        self.shared_memory.as_ref()
+    }
+
+    /// Attached shared blocks this agent can read, with its access to each
+    pub async fn attached_shared_blocks(&self) -> Vec<(MemoryBlock, BlockAccess)> {
+        let Some(manager) = &self.shared_memory else {
+            return Vec::new();
+        };
+        let attached = self.shared_blocks.read().await.clone();
+        let mut blocks = Vec::new();
+        for id in attached {
+            if let Ok(block) = manager.read_block(id, self.agent_id).await {
+                blocks.push(block);
+            }
         }
+        blocks
     }
 
-    /// Add a new memory block
+    /// Update a shared block if it is still at `expected_revision`
+    ///
+    /// See [`SharedMemoryManager::compare_and_swap`]. Returns the new revision.
+    pub async fn update_shared_block(
+        &self,
+        id: MemoryBlockId,
+        expected_revision: u64,
+        new_value: String,
+    ) -> Result<u64> {
+        let manager = self
+            .shared_memory
+            .as_ref()
+            .ok_or_else(|| Error::config("No shared memory manager configured"))?;
+        manager
+            .compare_and_swap(id, self.agent_id, expected_revision, new_value)
+            .await
+    }
+
+    /// Receive changes other agents make to attached shared blocks this
+    /// agent can read
+    pub fn subscribe_shared(&self) -> Option<SharedBlockSubscription> {
+        self.shared_memory.as_ref().map(|manager| {
+            let mut subscription = manager.subscribe(self.agent_id);
+            subscription.attached = Some(self.shared_blocks.clone());
+            subscription
+        })
+    }
+
+    /// Archive messages and out-of-context blocks in `archival`
+    pub fn with_archival(mut self, archival: Arc<ArchivalMemory>) -> Self {
+        self.archival = Some(archival);
+        self
+    }
+
+    /// Archival memory, if configured
+    pub fn archival(&self) -> Option<&Arc<ArchivalMemory>> {
+        self.archival.as_ref()
+    }
+
+    /// Add a new memory block, recorded as created by this agent
     pub async fn add_block(&self, block: MemoryBlock) -> Result<MemoryBlockId> {
+        let author = EditAuthor::Agent {
+            agent_id: self.agent_id,
+        };
+        self.add_block_as(block, author, "Block created").await
+    }
+
+    /// Add a new memory block, recording its first version under `author`
+    pub async fn add_block_as(
+        &self,
+        block: MemoryBlock,
+        author: EditAuthor,
+        reason: impl Into<String>,
+    ) -> Result<MemoryBlockId> {
         let id = block.id;
+        let version = BlockVersion::of(&block, author, reason);
         let mut blocks = self.blocks.write().await;
         blocks.insert(id, block);
+        self.record_version(version).await;
         Ok(id)
     }
 
+    async fn record_version(&self, version: BlockVersion) {
+        let mut versions = self.versions.write().await;
+        let history = versions.entry(version.block_id).or_default();
+        if history
+            .last()
+            .is_none_or(|last| last.version < version.version)
+        {
+            history.push(version);
+        }
+    }
+
     /// Get a memory block by ID
     pub async fn get_block(&self, id: MemoryBlockId) -> Option<MemoryBlock> {
         let blocks = self.blocks.read().await;
         blocks.get(&id).cloned()
     }
 
-    /// Update a memory block's value
+    /// Update a memory block's value, recorded as an edit by this agent
     pub async fn update_block(&self, id: MemoryBlockId, new_value: String) -> Result<()> {
+        let author = EditAuthor::Agent {
+            agent_id: self.agent_id,
+        };
+        self.update_block_as(id, new_value, author, "")
+            .await
+            .map(|_| ())
+    }
+
+    /// Update a memory block's value, recording the new version under
+    /// `author`, and return the new version number
+    pub async fn update_block_as(
+        &self,
+        id: MemoryBlockId,
+        new_value: String,
+        author: EditAuthor,
+        reason: impl Into<String>,
+    ) -> Result<u64> {
         let mut blocks = self.blocks.write().await;
 
         if let Some(block) = blocks.get_mut(&id) {
             block.update_value(new_value)?;
-            Ok(())
+            let version = BlockVersion::of(block, author, reason);
+            let number = version.version;
+            self.record_version(version).await;
+            Ok(number)
         } else {
             Err(Error::config(format!("Memory block {} not found", id)))
         }
     }
 
-    /// Delete a memory block
+    /// Every recorded version of an owned block or a readable attached
+    /// shared block, oldest first
+    ///
+    /// History outlives the block, so deleted blocks can still be inspected.
+    pub async fn block_history(&self, id: MemoryBlockId) -> Result<Vec<BlockVersion>> {
+        if let Some(history) = self.versions.read().await.get(&id) {
+            return Ok(history.clone());
+        }
+        match &self.shared_memory {
+            Some(manager) if self.shared_blocks.read().await.contains(&id) => {
+                manager.read_block(id, self.agent_id).await?;
+                manager.block_history(id).await
+            }
+            _ => Err(Error::config(format!("No history for memory block {}", id))),
+        }
+    }
+
+    /// One recorded version of a block with history, see [`Self::block_history`]
+    pub async fn block_version(&self, id: MemoryBlockId, version: u64) -> Result<BlockVersion> {
+        self.block_history(id)
+            .await?
+            .into_iter()
+            .find(|v| v.version == version)
+            .ok_or_else(|| Error::config(format!("Memory block {} has no version {}", id, version)))
+    }
+
+    /// Line diff between two versions of a block with history
+    pub async fn diff_block_versions(
+        &self,
+        id: MemoryBlockId,
+        from: u64,
+        to: u64,
+    ) -> Result<BlockDiff> {
+        let from = self.block_version(id, from).await?;
+        let to = self.block_version(id, to).await?;
+        Ok(BlockDiff::between(&from, &to))
+    }
+
+    /// Restore the value an owned block had at `version`
+    ///
+    /// The restored value is saved as a new version, so the rollback itself
+    /// can be undone. Returns the new version number.
+    pub async fn rollback_block(
+        &self,
+        id: MemoryBlockId,
+        version: u64,
+        author: EditAuthor,
+    ) -> Result<u64> {
+        let target = self.block_version(id, version).await?;
+        self.update_block_as(
+            id,
+            target.value,
+            author,
+            format!("Rolled back to version {}", version),
+        )
+        .await
+    }
+
+    /// Delete a memory block, recorded as deleted by this agent
     pub async fn delete_block(&self, id: MemoryBlockId) -> Result<()> {
+        let author = EditAuthor::Agent {
+            agent_id: self.agent_id,
+        };
+        self.delete_block_as(id, author, "Block deleted").await
+    }
+
+    /// Delete a memory block, recording an empty final version under
+    /// `author` so the history shows when and why it was removed
+    pub async fn delete_block_as(
+        &self,
+        id: MemoryBlockId,
+        author: EditAuthor,
+        reason: impl Into<String>,
+    ) -> Result<()> {
         let mut blocks = self.blocks.write().await;
 
-        if blocks.remove(&id).is_some() {
+        if let Some(block) = blocks.remove(&id) {
+            self.record_version(BlockVersion {
+                block_id: id,
+                version: block.version + 1,
+                value: String::new(),
+                author,
+                reason: reason.into(),
+                timestamp: Utc::now(),
+            })
+            .await;
             Ok(())
         } else {
             Err(Error::config(format!("Memory block {} not found", id)))
@@ -283,21 +659,77 @@ impl AgentMemory {
     /// Get all in-context memory blocks
     pub async fn in_context_blocks(&self) -> Vec<MemoryBlock> {
         let blocks = self.blocks.read().await;
-        blocks
-            .values()
-            .filter(|b| b.in_context)
-            .cloned()
-            .collect()
+        blocks.values().filter(|b| b.in_context).cloned().collect()
     }
 
     /// Get all out-of-context memory blocks
     pub async fn out_of_context_blocks(&self) -> Vec<MemoryBlock> {
         let blocks = self.blocks.read().await;
-        blocks
-            .values()
-            .filter(|b| !b.in_context)
-            .cloned()
-            .collect()
+        blocks.values().filter(|b| !b.in_context).cloned().collect()
+    }
+
+    /// Render in-context blocks as a prompt section
+    ///
+    /// Blocks appear oldest first, each with its label, description, ID, and
+    /// size against its limit, so the model can edit them with the memory
+    /// tools. Attached shared blocks the agent can read are included with
+    /// their revision and access. Returns an empty string when no block is
+    /// in context.
+    pub async fn render_blocks(&self) -> String {
+        let mut blocks: Vec<(MemoryBlock, Option<BlockAccess>)> = self
+            .in_context_blocks()
+            .await
+            .into_iter()
+            .map(|b| (b, None))
+            .collect();
+        blocks.extend(
+            self.attached_shared_blocks()
+                .await
+                .into_iter()
+                .map(|(b, access)| (b, Some(access))),
+        );
+        if blocks.is_empty() {
+            return String::new();
+        }
+        blocks.sort_by(|(a, _), (b, _)| {
+            a.created_at
+                .cmp(&b.created_at)
+                .then_with(|| a.label.cmp(&b.label))
+        });
+
+        let used: usize = blocks.iter().map(|(b, _)| b.size()).sum();
+        let mut out = format!(
+            "<memory_blocks>\nYour core memory, {}/{} characters in use. \
+             Keep it current with the memory tools.\n",
+            used, self.config.max_context_size
+        );
+        for (block, access) in &blocks {
+            let limit = block
+                .max_size
+                .map_or_else(|| "none".to_string(), |max| max.to_string());
+            // Shared blocks carry the revision that updates must quote
+            let shared = access
+                .map(|access| format!(" shared revision={} access={}", block.version, access))
+                .unwrap_or_default();
+            out.push_str(&format!("\n<{}>\n", block.label));
+            if !block.description.is_empty() {
+                out.push_str(&format!(
+                    "<description>{}</description>\n",
+                    block.description
+                ));
+            }
+            out.push_str(&format!(
+                "<metadata>id={} chars={} limit={}{}</metadata>\n<value>\n{}\n</value>\n</{}>\n",
+                block.id,
+                block.size(),
+                limit,
+                shared,
+                block.value,
+                block.label
+            ));
+        }
+        out.push_str("</memory_blocks>");
+        out
     }
 
     /// Calculate total in-context memory size
@@ -307,15 +739,25 @@ impl AgentMemory {
     }
 
     /// Move a block out of context (to save context window space)
+    ///
+    /// With archival memory configured, the block is archived so it can
+    /// still be found by `archival_search`.
     pub async fn move_out_of_context(&self, id: MemoryBlockId) -> Result<()> {
-        let mut blocks = self.blocks.write().await;
-
-        if let Some(block) = blocks.get_mut(&id) {
+        let block = {
+            let mut blocks = self.blocks.write().await;
+            let block = blocks
+                .get_mut(&id)
+                .ok_or_else(|| Error::config(format!("Memory block {} not found", id)))?;
             block.set_in_context(false);
-            Ok(())
-        } else {
-            Err(Error::config(format!("Memory block {} not found", id)))
+            block.clone()
+        };
+
+        if let Some(archival) = &self.archival {
+            if let Err(e) = archival.archive_block(&block).await {
+                tracing::warn!("Failed to archive memory block '{}': {}", block.label, e);
+            }
         }
+        Ok(())
     }
 
     /// Move a block into context
@@ -360,6 +802,12 @@ impl AgentMemory {
             metadata: HashMap::new(),
         };
 
+        if let Some(archival) = &self.archival {
+            if let Err(e) = archival.archive_message(&message).await {
+                tracing::warn!("Failed to archive message {}: {}", message.id, e);
+            }
+        }
+
         let id = message.id;
         let mut history = self.message_history.write().await;
         history.push(message);
@@ -374,6 +822,9 @@ impl AgentMemory {
     }
 
     /// Search message history by content
+    ///
+    /// Plain substring match over the in-memory history; use
+    /// [`ArchivalMemory::search`] for ranked semantic recall.
     pub async fn search_messages(&self, query: &str) -> Vec<MessageEntry> {
         let history = self.message_history.read().await;
         history
@@ -393,6 +844,12 @@ impl AgentMemory {
         let blocks = storage.load_agent_blocks(self.agent_id).await?;
         let messages = storage.load_messages(self.agent_id, message_limit).await?;
 
+        // Deleted blocks keep their history, so load by agent rather than by block
+        let mut versions: HashMap<MemoryBlockId, Vec<BlockVersion>> = HashMap::new();
+        for version in storage.load_agent_block_versions(self.agent_id).await? {
+            versions.entry(version.block_id).or_default().push(version);
+        }
+
         {
             let mut blocks_map = self.blocks.write().await;
             blocks_map.clear();
@@ -407,6 +864,12 @@ impl AgentMemory {
             history.extend(messages);
         }
 
+        *self.persisted_versions.write().await = versions
+            .iter()
+            .filter_map(|(id, history)| Some((*id, history.last()?.version)))
+            .collect();
+        *self.versions.write().await = versions;
+
         Ok(())
     }
 
@@ -422,6 +885,29 @@ impl AgentMemory {
             storage.save_block(self.agent_id, block).await?;
         }
 
+        // Versions are immutable, so only those not yet stored are written
+        let mut persisted = self.persisted_versions.write().await;
+        let versions: Vec<BlockVersion> = {
+            let versions = self.versions.read().await;
+            versions
+                .values()
+                .flatten()
+                .filter(|v| {
+                    persisted
+                        .get(&v.block_id)
+                        .is_none_or(|&stored| v.version > stored)
+                })
+                .cloned()
+                .collect()
+        };
+
+        for version in &versions {
+            storage.save_block_version(self.agent_id, version).await?;
+            let stored = persisted.entry(version.block_id).or_default();
+            *stored = (*stored).max(version.version);
+        }
+        drop(persisted);
+
         let messages: Vec<MessageEntry> = {
             let history = self.message_history.read().await;
             history.clone()
@@ -435,7 +921,10 @@ impl AgentMemory {
     }
 
     /// Get all memory blocks (owned + shared)
-    pub async fn all_blocks(&self, shared_memory_manager: Option<&SharedMemoryManager>) -> Vec<MemoryBlock> {
+    pub async fn all_blocks(
+        &self,
+        shared_memory_manager: Option<&SharedMemoryManager>,
+    ) -> Vec<MemoryBlock> {
         let mut all_blocks = Vec::new();
 
         // Add owned blocks
@@ -456,22 +945,97 @@ impl AgentMemory {
     }
 }
 
+/// Access an agent has to a shared memory block
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum BlockAccess {
+    /// May read the block
+    ReadOnly,
+    /// May read and update the block
+    ReadWrite,
+}
+
+impl BlockAccess {
+    /// Whether this access allows updates
+    pub fn can_write(self) -> bool {
+        self == Self::ReadWrite
+    }
+}
+
+impl std::fmt::Display for BlockAccess {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            Self::ReadOnly => write!(f, "read_only"),
+            Self::ReadWrite => write!(f, "read_write"),
+        }
+    }
+}
+
+/// Shared memory block with the agents allowed to use it
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct SharedBlock {
+    /// The block; its `version` is the revision compared by updates
+    pub block: MemoryBlock,
+    /// Access granted to each agent
+    pub acl: HashMap<AgentId, BlockAccess>,
+}
+
+/// Change to a shared block, sent to subscribers
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct SharedBlockEvent {
+    /// Block that changed
+    pub block_id: MemoryBlockId,
+    /// Block label
+    pub label: String,
+    /// Revision after the change
+    pub revision: u64,
+    /// Value after the change
+    pub value: String,
+    /// Who made the change
+    pub author: EditAuthor,
+    /// When the change was made
+    pub timestamp: DateTime<Utc>,
+}
+
 /// Shared memory manager - manages blocks shared across multiple agents
+///
+/// Agents read and write shared blocks through an access list, and updates
+/// are compare-and-swap on the block revision, so concurrent writers get a
+/// [`Error::MemoryConflict`] instead of silently overwriting each other.
+/// Every change is broadcast to [`SharedBlockSubscription`]s and recorded
+/// as a [`BlockVersion`].
 #[derive(Debug, Clone)]
 pub struct SharedMemoryManager {
     /// All shared memory blocks
-    blocks: Arc<RwLock<HashMap<MemoryBlockId, MemoryBlock>>>,
+    blocks: Arc<RwLock<HashMap<MemoryBlockId, SharedBlock>>>,
+
+    /// Value history of each shared block, oldest first
+    versions: Arc<RwLock<HashMap<MemoryBlockId, Vec<BlockVersion>>>>,
+
+    /// Revision of each block last written to storage
+    #[cfg(feature = "storage")]
+    persisted: Arc<RwLock<HashMap<MemoryBlockId, u64>>>,
+
+    /// Change notifications
+    events: broadcast::Sender<SharedBlockEvent>,
 }
 
 impl SharedMemoryManager {
     /// Create a new shared memory manager
     pub fn new() -> Self {
+        let (events, _) = broadcast::channel(256);
         Self {
             blocks: Arc::new(RwLock::new(HashMap::new())),
+            versions: Arc::new(RwLock::new(HashMap::new())),
+            #[cfg(feature = "storage")]
+            persisted: Arc::new(RwLock::new(HashMap::new())),
+            events,
         }
     }
 
     /// Create a new shared block
+    ///
+    /// No agent has access until granted with [`Self::grant`].
     pub async fn create_block(
         &self,
         label: impl Into<String>,
@@ -482,27 +1046,327 @@ impl SharedMemoryManager {
         let id = block.id;
 
         let mut blocks = self.blocks.write().await;
-        blocks.insert(id, block);
+        self.record_version(BlockVersion::of(
+            &block,
+            EditAuthor::System,
+            "Block created",
+        ))
+        .await;
+        blocks.insert(
+            id,
+            SharedBlock {
+                block,
+                acl: HashMap::new(),
+            },
+        );
         id
     }
 
-    /// Get a shared block by ID
+    /// Get a shared block by ID, ignoring access lists
     pub async fn get_block(&self, id: MemoryBlockId) -> Option<MemoryBlock> {
         let blocks = self.blocks.read().await;
-        blocks.get(&id).cloned()
+        blocks.get(&id).map(|shared| shared.block.clone())
     }
 
-    /// Update a shared block
+    /// Update a shared block, ignoring access lists and revisions
+    ///
+    /// Meant for application code; agents should use
+    /// [`Self::compare_and_swap`]. Subscribers are still notified.
     pub async fn update_block(&self, id: MemoryBlockId, new_value: String) -> Result<()> {
         let mut blocks = self.blocks.write().await;
 
-        if let Some(block) = blocks.get_mut(&id) {
-            block.update_value(new_value)?;
+        if let Some(shared) = blocks.get_mut(&id) {
+            shared.block.update_value(new_value)?;
+            self.changed(
+                &shared.block,
+                EditAuthor::System,
+                "Updated by the application",
+            )
+            .await;
             Ok(())
         } else {
             Err(Error::config(format!("Shared block {} not found", id)))
         }
     }
+
+    /// Give `agent_id` access to a shared block, replacing any earlier grant
+    pub async fn grant(
+        &self,
+        id: MemoryBlockId,
+        agent_id: AgentId,
+        access: BlockAccess,
+    ) -> Result<()> {
+        let mut blocks = self.blocks.write().await;
+        let shared = blocks
+            .get_mut(&id)
+            .ok_or_else(|| Error::config(format!("Shared block {} not found", id)))?;
+        shared.acl.insert(agent_id, access);
+        Ok(())
+    }
+
+    /// Remove the access of `agent_id` to a shared block
+    pub async fn revoke(&self, id: MemoryBlockId, agent_id: AgentId) -> Result<()> {
+        let mut blocks = self.blocks.write().await;
+        let shared = blocks
+            .get_mut(&id)
+            .ok_or_else(|| Error::config(format!("Shared block {} not found", id)))?;
+        shared.acl.remove(&agent_id);
+        Ok(())
+    }
+
+    /// Access `agent_id` has to a shared block, if any
+    pub async fn access(&self, id: MemoryBlockId, agent_id: AgentId) -> Option<BlockAccess> {
+        let blocks = self.blocks.read().await;
+        blocks
+            .get(&id)
+            .and_then(|shared| shared.acl.get(&agent_id).copied())
+    }
+
+    /// Read a shared block as `agent_id`, with the agent's access
+    pub async fn read_block(
+        &self,
+        id: MemoryBlockId,
+        agent_id: AgentId,
+    ) -> Result<(MemoryBlock, BlockAccess)> {
+        let blocks = self.blocks.read().await;
+        let shared = blocks
+            .get(&id)
+            .ok_or_else(|| Error::config(format!("Shared block {} not found", id)))?;
+        let access = shared.acl.get(&agent_id).copied().ok_or_else(|| {
+            Error::PermissionDenied(format!(
+                "agent {} cannot read shared block {}",
+                agent_id, id
+            ))
+        })?;
+        Ok((shared.block.clone(), access))
+    }
+
+    /// Update a shared block as `agent_id` if it is still at `expected_revision`
+    ///
+    /// Fails with [`Error::MemoryConflict`] when another agent updated the
+    /// block first, and with [`Error::PermissionDenied`] without read-write
+    /// access. Returns the new revision.
+    pub async fn compare_and_swap(
+        &self,
+        id: MemoryBlockId,
+        agent_id: AgentId,
+        expected_revision: u64,
+        new_value: String,
+    ) -> Result<u64> {
+        let mut blocks = self.blocks.write().await;
+        let shared = blocks
+            .get_mut(&id)
+            .ok_or_else(|| Error::config(format!("Shared block {} not found", id)))?;
+
+        if !shared
+            .acl
+            .get(&agent_id)
+            .is_some_and(|access| access.can_write())
+        {
+            return Err(Error::PermissionDenied(format!(
+                "agent {} cannot write shared block {}",
+                agent_id, id
+            )));
+        }
+        if shared.block.version != expected_revision {
+            return Err(Error::MemoryConflict {
+                block_id: id.to_string(),
+                expected: expected_revision,
+                actual: shared.block.version,
+            });
+        }
+
+        shared.block.update_value(new_value)?;
+        let reason = format!("Updated from revision {}", expected_revision);
+        self.changed(&shared.block, EditAuthor::Agent { agent_id }, reason)
+            .await;
+        Ok(shared.block.version)
+    }
+
+    /// Every recorded version of a shared block, oldest first, ignoring
+    /// access lists
+    pub async fn block_history(&self, id: MemoryBlockId) -> Result<Vec<BlockVersion>> {
+        self.versions
+            .read()
+            .await
+            .get(&id)
+            .cloned()
+            .ok_or_else(|| Error::config(format!("No history for shared block {}", id)))
+    }
+
+    /// Shared blocks `agent_id` can read, with its access to each
+    pub async fn blocks_for(&self, agent_id: AgentId) -> Vec<(MemoryBlock, BlockAccess)> {
+        let blocks = self.blocks.read().await;
+        blocks
+            .values()
+            .filter_map(|shared| {
+                shared
+                    .acl
+                    .get(&agent_id)
+                    .map(|access| (shared.block.clone(), *access))
+            })
+            .collect()
+    }
+
+    /// Receive changes to the blocks `agent_id` can read, made by others
+    pub fn subscribe(&self, agent_id: AgentId) -> SharedBlockSubscription {
+        SharedBlockSubscription {
+            agent_id,
+            events: self.events.subscribe(),
+            blocks: self.blocks.clone(),
+            attached: None,
+        }
+    }
+
+    async fn record_version(&self, version: BlockVersion) {
+        self.versions
+            .write()
+            .await
+            .entry(version.block_id)
+            .or_default()
+            .push(version);
+    }
+
+    /// Record a new value of `block` and tell subscribers about it
+    async fn changed(&self, block: &MemoryBlock, author: EditAuthor, reason: impl Into<String>) {
+        self.record_version(BlockVersion::of(block, author.clone(), reason))
+            .await;
+        self.notify(block, author);
+    }
+
+    fn notify(&self, block: &MemoryBlock, author: EditAuthor) {
+        // Sending only fails when nobody is subscribed
+        let _ = self.events.send(SharedBlockEvent {
+            block_id: block.id,
+            label: block.label.clone(),
+            revision: block.version,
+            value: block.value.clone(),
+            author,
+            timestamp: block.updated_at,
+        });
+    }
+
+    /// Load shared blocks and their history from storage, keeping newer
+    /// revisions held in memory
+    #[cfg(feature = "storage")]
+    pub async fn load_from_storage(&self, storage: &dyn MemoryStorage) -> Result<()> {
+        let stored = storage.load_shared_blocks().await?;
+        let stored_versions = storage.load_shared_block_versions().await?;
+        {
+            let mut versions = self.versions.write().await;
+            for version in stored_versions {
+                let history = versions.entry(version.block_id).or_default();
+                if !history.iter().any(|v| v.version == version.version) {
+                    history.push(version);
+                }
+            }
+            for history in versions.values_mut() {
+                history.sort_by_key(|v| v.version);
+            }
+        }
+
+        let mut blocks = self.blocks.write().await;
+        let mut persisted = self.persisted.write().await;
+        for shared in stored {
+            persisted.insert(shared.block.id, shared.block.version);
+            let newer_in_memory = blocks
+                .get(&shared.block.id)
+                .is_some_and(|current| current.block.version > shared.block.version);
+            if !newer_in_memory {
+                blocks.insert(shared.block.id, shared);
+            }
+        }
+        Ok(())
+    }
+
+    /// Persist all shared blocks, their access lists and new versions
+    ///
+    /// A block's value is only written if storage still holds the revision
+    /// this manager last loaded or saved; otherwise another process changed
+    /// it and this fails with [`Error::MemoryConflict`]. Versions are written
+    /// once their revision is saved.
+    #[cfg(feature = "storage")]
+    pub async fn persist_to_storage(&self, storage: &dyn MemoryStorage) -> Result<()> {
+        let blocks: Vec<SharedBlock> = self.blocks.read().await.values().cloned().collect();
+        let mut persisted = self.persisted.write().await;
+        for shared in &blocks {
+            let id = shared.block.id;
+            let stored = persisted.get(&id).copied();
+            if stored != Some(shared.block.version) {
+                storage.save_shared_block(&shared.block, stored).await?;
+                persisted.insert(id, shared.block.version);
+                let versions = self.block_history(id).await.unwrap_or_default();
+                for version in versions
+                    .iter()
+                    .filter(|v| stored.is_none_or(|stored| v.version > stored))
+                {
+                    storage.save_shared_block_version(version).await?;
+                }
+            }
+            storage.save_shared_block_acl(id, &shared.acl).await?;
+        }
+        Ok(())
+    }
+}
+
+/// What a [`SharedBlockSubscription`] receives next
+#[derive(Debug, Clone)]
+pub enum SharedBlockUpdate {
+    /// A shared block changed
+    Changed(SharedBlockEvent),
+    /// The receiver fell behind and this many changes to shared blocks,
+    /// including ones it would have skipped, were dropped; re-read the
+    /// blocks to catch up
+    Lagged(u64),
+}
+
+/// Stream of [`SharedBlockEvent`]s for one agent
+///
+/// Skips changes the agent made itself and blocks it cannot read, and when
+/// created by [`AgentMemory::subscribe_shared`], blocks the agent has not
+/// attached. Missed events are reported as [`SharedBlockUpdate::Lagged`].
+pub struct SharedBlockSubscription {
+    agent_id: AgentId,
+    events: broadcast::Receiver<SharedBlockEvent>,
+    blocks: Arc<RwLock<HashMap<MemoryBlockId, SharedBlock>>>,
+    /// Blocks attached by the subscribing agent, if filtered on attachment
+    attached: Option<Arc<RwLock<Vec<MemoryBlockId>>>>,
+}
+
+impl SharedBlockSubscription {
+    /// Next change, or `None` once the manager is dropped
+    pub async fn recv(&mut self) -> Option<SharedBlockUpdate> {
+        loop {
+            let event = match self.events.recv().await {
+                Ok(event) => event,
+                Err(broadcast::error::RecvError::Lagged(missed)) => {
+                    return Some(SharedBlockUpdate::Lagged(missed));
+                }
+                Err(broadcast::error::RecvError::Closed) => return None,
+            };
+            if event.author
+                == (EditAuthor::Agent {
+                    agent_id: self.agent_id,
+                })
+            {
+                continue;
+            }
+            if let Some(attached) = &self.attached {
+                if !attached.read().await.contains(&event.block_id) {
+                    continue;
+                }
+            }
+            let readable = self
+                .blocks
+                .read()
+                .await
+                .get(&event.block_id)
+                .is_some_and(|shared| shared.acl.contains_key(&self.agent_id));
+            if readable {
+                return Some(SharedBlockUpdate::Changed(event));
+            }
+        }
+    }
 }
 
 impl Default for SharedMemoryManager {
@@ -557,6 +1421,98 @@ mod tests {
         assert_eq!(memory.out_of_context_blocks().await.len(), 1);
     }
 
+    #[tokio::test]
+    async fn test_render_blocks() {
+        let memory = AgentMemory::new(AgentId::new(), MemoryConfig::default());
+        assert_eq!(memory.render_blocks().await, "");
+
+        let mut persona =
+            MemoryBlock::with_description("persona", "Who you are", "A careful analyst");
+        persona.max_size = Some(500);
+        let persona_id = memory.add_block(persona).await.unwrap();
+        let notes_id = memory
+            .add_block(MemoryBlock::new("notes", "archived"))
+            .await
+            .unwrap();
+        memory.move_out_of_context(notes_id).await.unwrap();
+
+        let rendered = memory.render_blocks().await;
+        assert!(rendered.starts_with("<memory_blocks>\nYour core memory, 17/8000 characters"));
+        assert!(rendered.contains("<description>Who you are</description>"));
+        assert!(rendered.contains(&format!(
+            "<metadata>id={} chars=17 limit=500</metadata>",
+            persona_id
+        )));
+        assert!(rendered.contains("<value>\nA careful analyst\n</value>\n</persona>"));
+        assert!(!rendered.contains("archived"));
+    }
+
+    #[tokio::test]
+    async fn test_block_history_diff_and_rollback() {
+        let agent_id = AgentId::new();
+        let memory = AgentMemory::new(agent_id, MemoryConfig::default());
+        let id = memory
+            .add_block(MemoryBlock::new("persona", "Helpful\nConcise"))
+            .await
+            .unwrap();
+
+        let operator = EditAuthor::Human {
+            name: "ops".to_string(),
+        };
+        memory
+            .update_block(id, "Helpful\nVerbose\nSarcastic".to_string())
+            .await
+            .unwrap();
+        let version = memory
+            .update_block_as(id, "Sarcastic".to_string(), EditAuthor::System, "Clobbered")
+            .await
+            .unwrap();
+        assert_eq!(version, 3);
+
+        let history = memory.block_history(id).await.unwrap();
+        let numbers: Vec<u64> = history.iter().map(|v| v.version).collect();
+        assert_eq!(numbers, [1, 2, 3]);
+        assert_eq!(history[0].author, EditAuthor::Agent { agent_id });
+        assert_eq!(history[2].reason, "Clobbered");
+
+        let diff = memory.diff_block_versions(id, 1, 2).await.unwrap();
+        assert_eq!(
+            diff.lines,
+            [
+                DiffLine::Unchanged("Helpful".to_string()),
+                DiffLine::Removed("Concise".to_string()),
+                DiffLine::Added("Verbose".to_string()),
+                DiffLine::Added("Sarcastic".to_string()),
+            ]
+        );
+        assert_eq!(
+            diff.to_string(),
+            "--- version 1\n+++ version 2\n  Helpful\n- Concise\n+ Verbose\n+ Sarcastic"
+        );
+
+        assert_eq!(
+            memory
+                .rollback_block(id, 1, operator.clone())
+                .await
+                .unwrap(),
+            4
+        );
+        let block = memory.get_block(id).await.unwrap();
+        assert_eq!(
+            (block.value.as_str(), block.version),
+            ("Helpful\nConcise", 4)
+        );
+        let restored = memory.block_version(id, 4).await.unwrap();
+        assert_eq!(restored.author, operator);
+        assert_eq!(restored.reason, "Rolled back to version 1");
+        assert!(memory
+            .diff_block_versions(id, 1, 4)
+            .await
+            .unwrap()
+            .is_empty());
+        assert!(memory.block_version(id, 9).await.is_err());
+    }
+
     #[tokio::test]
     async fn test_shared_memory() {
         let shared_manager = SharedMemoryManager::new();
@@ -574,4 +1530,144 @@ mod tests {
         let block = shared_manager.get_block(block_id).await.unwrap();
         assert_eq!(block.value, "Acme Corp");
     }
+
+    #[tokio::test]
+    async fn test_shared_block_revisions_access_and_notifications() {
+        let manager = SharedMemoryManager::new();
+        let block_id = manager.create_block("plan", "Team plan", "draft").await;
+
+        let writer = AgentMemory::new(AgentId::new(), MemoryConfig::default())
+            .with_shared_memory(manager.clone());
+        let reader = AgentMemory::new(AgentId::new(), MemoryConfig::default())
+            .with_shared_memory(manager.clone());
+        writer.attach_shared_block(block_id).await;
+        reader.attach_shared_block(block_id).await;
+        assert!(reader.attached_shared_blocks().await.is_empty());
+
+        manager
+            .grant(block_id, writer.agent_id, BlockAccess::ReadWrite)
+            .await
+            .unwrap();
+        manager
+            .grant(block_id, reader.agent_id, BlockAccess::ReadOnly)
+            .await
+            .unwrap();
+        let mut writer_events = writer.subscribe_shared().unwrap();
+        let mut reader_events = reader.subscribe_shared().unwrap();
+
+        assert_eq!(
+            writer
+                .update_shared_block(block_id, 1, "v2".to_string())
+                .await
+                .unwrap(),
+            2
+        );
+        let stale = writer
+            .update_shared_block(block_id, 1, "lost".to_string())
+            .await;
+        assert!(matches!(
+            stale,
+            Err(Error::MemoryConflict {
+                expected: 1,
+                actual: 2,
+                ..
+            })
+        ));
+        let denied = reader
+            .update_shared_block(block_id, 2, "v3".to_string())
+            .await;
+        assert!(matches!(denied, Err(Error::PermissionDenied(_))));
+
+        // The reader hears about the writer's change; the writer skips its own
+        let Some(SharedBlockUpdate::Changed(event)) = reader_events.recv().await else {
+            panic!("expected a change");
+        };
+        assert_eq!((event.revision, event.value.as_str()), (2, "v2"));
+        assert_eq!(
+            event.author,
+            EditAuthor::Agent {
+                agent_id: writer.agent_id
+            }
+        );
+        manager
+            .update_block(block_id, "v3".to_string())
+            .await
+            .unwrap();
+        let Some(SharedBlockUpdate::Changed(event)) = writer_events.recv().await else {
+            panic!("expected a change");
+        };
+        assert_eq!((event.revision, event.author), (3, EditAuthor::System));
+
+        // Compare-and-swap edits are versioned under the writing agent
+        let history = reader.block_history(block_id).await.unwrap();
+        let authors: Vec<&EditAuthor> = history.iter().map(|v| &v.author).collect();
+        let writer_author = EditAuthor::Agent {
+            agent_id: writer.agent_id,
+        };
+        assert_eq!(
+            authors,
+            [&EditAuthor::System, &writer_author, &EditAuthor::System]
+        );
+        assert_eq!(history[1].value, "v2");
+        assert_eq!(history[1].reason, "Updated from revision 1");
+
+        let rendered = reader.render_blocks().await;
+        assert!(rendered.contains("v3"));
+        assert!(rendered.contains("shared revision=3 access=read_only"));
+
+        manager.revoke(block_id, reader.agent_id).await.unwrap();
+        assert!(!reader.render_blocks().await.contains("v3"));
+        assert!(matches!(
+            reader.block_history(block_id).await,
+            Err(Error::PermissionDenied(_))
+        ));
+    }
+
+    #[tokio::test]
+    async fn test_shared_subscription_filters_attachments_and_reports_lag() {
+        let manager = SharedMemoryManager::new();
+        let attached = manager.create_block("plan", "Team plan", "draft").await;
+        let other = manager.create_block("notes", "Team notes", "").await;
+
+        let reader = AgentMemory::new(AgentId::new(), MemoryConfig::default())
+            .with_shared_memory(manager.clone());
+        reader.attach_shared_block(attached).await;
+        for id in [attached, other] {
+            manager
+                .grant(id, reader.agent_id, BlockAccess::ReadOnly)
+                .await
+                .unwrap();
+        }
+        let mut events = reader.subscribe_shared().unwrap();
+
+        // Readable but not attached, so skipped
+        manager
+            .update_block(other, "ignored".to_string())
+            .await
+            .unwrap();
+        manager
+            .update_block(attached, "v2".to_string())
+            .await
+            .unwrap();
+        let Some(SharedBlockUpdate::Changed(event)) = events.recv().await else {
+            panic!("expected a change");
+        };
+        assert_eq!((event.block_id, event.value.as_str()), (attached, "v2"));
+
+        // Overflow the channel so the subscriber falls behind
+        for i in 0..300 {
+            manager
+                .update_block(attached, format!("v{}", i + 3))
+                .await
+                .unwrap();
+        }
+        let Some(SharedBlockUpdate::Lagged(missed)) = events.recv().await else {
+            panic!("expected lag to be reported");
+        };
+        assert_eq!(missed, 300 - 256);
+        let Some(SharedBlockUpdate::Changed(event)) = events.recv().await else {
+            panic!("expected a change");
+        };
+        assert_eq!(event.revision, 2 + 300 - 256 + 1);
+    }
 }
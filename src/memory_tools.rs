@@ -5,9 +5,13 @@
 //! - Move blocks in/out of context
 //! - Search their memory
 //! - Manage the context window
+//! - Store and recall long-term facts in archival memory
+//! - Review the edit history of their blocks
+//! - Update shared blocks without overwriting other agents' changes
 
-use crate::error::Result;
-use crate::memory::{AgentMemory, MemoryBlockId};
+use crate::archival::{ArchivalMemory, PassageSource};
+use crate::error::{Error, Result};
+use crate::memory::{AgentMemory, EditAuthor, MemoryBlockId};
 use crate::tools::{JsonSchema, Tool, ToolContext, ToolOutput};
 use async_trait::async_trait;
 use serde_json::{json, Value};
@@ -56,6 +60,13 @@ impl Tool for UpdateMemoryTool {
                 "description": "The new content for this memory block"
             }),
         );
+        properties.insert(
+            "reason".to_string(),
+            json!({
+                "type": "string",
+                "description": "Why you are making this change, kept in the block's history"
+            }),
+        );
 
         JsonSchema::object(properties)
             .with_required(vec!["block_id".to_string(), "new_value".to_string()])
@@ -75,12 +86,15 @@ impl Tool for UpdateMemoryTool {
         // Parse block ID
         let id: MemoryBlockId = serde_json::from_str(&format!("\"{}\"", block_id))?;
 
+        let reason = params["reason"].as_str().unwrap_or_default();
+
         // Update the block
-        self.memory.update_block(id, new_value).await?;
+        let author = EditAuthor::Agent { agent_id: self.memory.agent_id };
+        let version = self.memory.update_block_as(id, new_value, author, reason).await?;
 
         Ok(ToolOutput::success_with_data(
-            format!("Successfully updated memory block {}", block_id),
-            json!({"block_id": block_id}),
+            format!("Successfully updated memory block {} (version {})", block_id, version),
+            json!({"block_id": block_id, "version": version}),
         ))
     }
 }
@@ -239,49 +253,50 @@ impl Tool for ListMemoryBlocksTool {
         let out_of_context_len = out_of_context.len();
 
         let mut blocks_info = Vec::new();
-
-        for block in in_context {
-            blocks_info.push(json!({
-                "id": block.id.to_string(),
-                "label": block.label,
-                "description": block.description,
-                "in_context": true,
-                "size": block.size(),
-                "value_preview": if block.value.len() > 100 {
-                    format!("{}...", &block.value[..100])
-                } else {
-                    block.value.clone()
-                }
-            }));
-        }
-
-        for block in out_of_context {
+        let mut lines = Vec::new();
+
+        let blocks = in_context
+            .into_iter()
+            .map(|block| (block, true))
+            .chain(out_of_context.into_iter().map(|block| (block, false)));
+        for (block, in_context) in blocks {
+            let value_preview = preview(&block.value, 100);
+            lines.push(format!(
+                "- {} [{}] ({}, {} chars): {}",
+                block.id,
+                block.label,
+                if in_context { "in context" } else { "archived" },
+                block.size(),
+                value_preview
+            ));
             blocks_info.push(json!({
                 "id": block.id.to_string(),
                 "label": block.label,
                 "description": block.description,
-                "in_context": false,
+                "in_context": in_context,
                 "size": block.size(),
-                "value_preview": if block.value.len() > 100 {
-                    format!("{}...", &block.value[..100])
-                } else {
-                    block.value.clone()
-                }
+                "value_preview": value_preview
             }));
         }
 
         let context_size = self.memory.context_size().await;
         let max_size = self.memory.config.max_context_size;
 
+        let mut text = format!(
+            "Found {} memory blocks ({} in context, {} archived)\nContext usage: {}/{}",
+            blocks_info.len(),
+            in_context_len,
+            out_of_context_len,
+            context_size,
+            max_size
+        );
+        for line in lines {
+            text.push('\n');
+            text.push_str(&line);
+        }
+
         Ok(ToolOutput::success_with_data(
-            format!(
-                "Found {} memory blocks ({} in context, {} archived)\nContext usage: {}/{}",
-                blocks_info.len(),
-                in_context_len,
-                out_of_context_len,
-                context_size,
-                max_size
-            ),
+            text,
             json!({
                 "blocks": blocks_info,
                 "context_size": context_size,
@@ -337,23 +352,25 @@ impl Tool for SearchMessagesTool {
 
         let results = self.memory.search_messages(query).await;
 
-        let messages: Vec<Value> = results
-            .iter()
-            .map(|m| {
-                json!({
-                    "timestamp": m.timestamp.to_rfc3339(),
-                    "role": m.role,
-                    "content_preview": if m.content.len() > 200 {
-                        format!("{}...", &m.content[..200])
-                    } else {
-                        m.content.clone()
-                    }
-                })
-            })
-            .collect();
+        let mut text = format!("Found {} messages matching '{}'", results.len(), query);
+        let mut messages = Vec::with_capacity(results.len());
+        for m in &results {
+            let content_preview = preview(&m.content, 200);
+            text.push_str(&format!(
+                "\n- [{}] {}: {}",
+                m.timestamp.to_rfc3339(),
+                m.role,
+                content_preview
+            ));
+            messages.push(json!({
+                "timestamp": m.timestamp.to_rfc3339(),
+                "role": m.role,
+                "content_preview": content_preview
+            }));
+        }
 
         Ok(ToolOutput::success_with_data(
-            format!("Found {} messages matching '{}'", results.len(), query),
+            text,
             json!({
                 "query": query,
                 "results": messages,
@@ -363,13 +380,443 @@ impl Tool for SearchMessagesTool {
     }
 }
 
+/// Tool for viewing the edit history of a memory block
+pub struct MemoryBlockHistoryTool {
+    memory: Arc<AgentMemory>,
+}
+
+impl MemoryBlockHistoryTool {
+    /// Create the tool over an agent's memory
+    pub fn new(memory: Arc<AgentMemory>) -> Self {
+        Self { memory }
+    }
+}
+
+#[async_trait]
+impl Tool for MemoryBlockHistoryTool {
+    fn id(&self) -> &str {
+        "memory_block_history"
+    }
+
+    fn description(&self) -> &str {
+        "Show the edit history of one of your memory blocks: every version with who changed \
+         it, when and why. Give from_version and to_version to see what changed between two \
+         versions. Restore an old value with update_memory."
+    }
+
+    fn name(&self) -> &str {
+        self.id()
+    }
+
+    fn input_schema(&self) -> JsonSchema {
+        let mut properties = HashMap::new();
+        properties.insert(
+            "block_id".to_string(),
+            json!({
+                "type": "string",
+                "description": "The ID of the memory block"
+            }),
+        );
+        properties.insert(
+            "from_version".to_string(),
+            json!({
+                "type": "integer",
+                "description": "Older version to diff from"
+            }),
+        );
+        properties.insert(
+            "to_version".to_string(),
+            json!({
+                "type": "integer",
+                "description": "Newer version to diff to (default: latest)"
+            }),
+        );
+
+        JsonSchema::object(properties).with_required(vec!["block_id".to_string()])
+    }
+
+    async fn execute(&self, params: Value, _ctx: &ToolContext) -> Result<ToolOutput> {
+        let block_id = params["block_id"].as_str().ok_or_else(|| {
+            crate::error::Error::tool_execution("memory_block_history", "Missing block_id")
+        })?;
+
+        let id: MemoryBlockId = serde_json::from_str(&format!("\"{}\"", block_id))?;
+
+        let history = self.memory.block_history(id).await?;
+        let latest = history.last().map(|v| v.version).unwrap_or_default();
+
+        let mut text = format!("Memory block {} has {} versions", block_id, history.len());
+        let mut versions = Vec::with_capacity(history.len());
+        for v in &history {
+            let reason = if v.reason.is_empty() { "no reason given" } else { v.reason.as_str() };
+            text.push_str(&format!(
+                "\n\nVersion {} ({}) by {}: {}\n{}",
+                v.version,
+                v.timestamp.format("%Y-%m-%d %H:%M"),
+                v.author,
+                reason,
+                v.value
+            ));
+            versions.push(json!({
+                "version": v.version,
+                "timestamp": v.timestamp.to_rfc3339(),
+                "author": v.author.to_string(),
+                "reason": v.reason,
+                "value": v.value
+            }));
+        }
+
+        let mut data = json!({"block_id": block_id, "versions": versions});
+
+        if let Some(from) = params["from_version"].as_u64() {
+            let to = params["to_version"].as_u64().unwrap_or(latest);
+            let diff = self.memory.diff_block_versions(id, from, to).await?;
+            text = format!("{}\n\nChanges from version {} to {}:\n{}", text, from, to, diff);
+            data["diff"] = json!(diff.to_string());
+        }
+
+        Ok(ToolOutput::success_with_data(text, data))
+    }
+}
+
+/// Tool for updating a shared memory block with a revision check
+pub struct UpdateSharedMemoryTool {
+    memory: Arc<AgentMemory>,
+}
+
+impl UpdateSharedMemoryTool {
+    /// Create the tool over an agent's memory
+    pub fn new(memory: Arc<AgentMemory>) -> Self {
+        Self { memory }
+    }
+}
+
+#[async_trait]
+impl Tool for UpdateSharedMemoryTool {
+    fn id(&self) -> &str {
+        "update_shared_memory"
+    }
+
+    fn description(&self) -> &str {
+        "Update a memory block shared with other agents. Give the revision shown in the \
+         block's metadata; if another agent changed the block since, the update is rejected \
+         with the current value so you can merge your change and retry."
+    }
+
+    fn name(&self) -> &str {
+        self.id()
+    }
+
+    fn input_schema(&self) -> JsonSchema {
+        let mut properties = HashMap::new();
+        properties.insert(
+            "block_id".to_string(),
+            json!({
+                "type": "string",
+                "description": "The ID of the shared memory block"
+            }),
+        );
+        properties.insert(
+            "expected_revision".to_string(),
+            json!({
+                "type": "integer",
+                "description": "The revision your change is based on"
+            }),
+        );
+        properties.insert(
+            "new_value".to_string(),
+            json!({
+                "type": "string",
+                "description": "The new content for the block"
+            }),
+        );
+
+        JsonSchema::object(properties).with_required(vec![
+            "block_id".to_string(),
+            "expected_revision".to_string(),
+            "new_value".to_string(),
+        ])
+    }
+
+    async fn execute(&self, params: Value, _ctx: &ToolContext) -> Result<ToolOutput> {
+        let block_id = params["block_id"].as_str().ok_or_else(|| {
+            Error::tool_execution("update_shared_memory", "Missing block_id")
+        })?;
+        let expected_revision = params["expected_revision"].as_u64().ok_or_else(|| {
+            Error::tool_execution("update_shared_memory", "Missing expected_revision")
+        })?;
+        let new_value = params["new_value"].as_str().ok_or_else(|| {
+            Error::tool_execution("update_shared_memory", "Missing new_value")
+        })?;
+
+        let id: MemoryBlockId = serde_json::from_str(&format!("\"{}\"", block_id))?;
+
+        match self
+            .memory
+            .update_shared_block(id, expected_revision, new_value.to_string())
+            .await
+        {
+            Ok(revision) => Ok(ToolOutput::success_with_data(
+                format!("Updated shared block {} to revision {}", block_id, revision),
+                json!({"block_id": block_id, "revision": revision}),
+            )),
+            Err(Error::MemoryConflict { actual, .. }) => {
+                let current = match self.memory.shared_memory() {
+                    Some(manager) => manager.get_block(id).await.map(|b| b.value),
+                    None => None,
+                };
+                Ok(ToolOutput::failure(format!(
+                    "Shared block {} was changed by another agent and is now at revision {}. \
+                     Current value:\n{}\nApply your change to this value and retry with \
+                     expected_revision {}.",
+                    block_id,
+                    actual,
+                    current.unwrap_or_default(),
+                    actual
+                )))
+            }
+            Err(Error::PermissionDenied(message)) => Ok(ToolOutput::failure(message)),
+            Err(e) => Err(e),
+        }
+    }
+}
+
+/// Tool for saving text to archival memory
+pub struct ArchivalInsertTool {
+    archival: Arc<ArchivalMemory>,
+}
+
+impl ArchivalInsertTool {
+    /// Create the tool over an agent's archival memory
+    pub fn new(archival: Arc<ArchivalMemory>) -> Self {
+        Self { archival }
+    }
+}
+
+#[async_trait]
+impl Tool for ArchivalInsertTool {
+    fn id(&self) -> &str {
+        "archival_insert"
+    }
+
+    fn description(&self) -> &str {
+        "Save information to your long-term archival memory. Archival memory is unlimited \
+         but not shown in context; use archival_search to recall it later. Write \
+         self-contained text so it makes sense when found on its own."
+    }
+
+    fn name(&self) -> &str {
+        self.id()
+    }
+
+    fn input_schema(&self) -> JsonSchema {
+        let mut properties = HashMap::new();
+        properties.insert(
+            "content".to_string(),
+            json!({
+                "type": "string",
+                "description": "The text to store"
+            }),
+        );
+
+        JsonSchema::object(properties).with_required(vec!["content".to_string()])
+    }
+
+    async fn execute(&self, params: Value, _ctx: &ToolContext) -> Result<ToolOutput> {
+        let content = params["content"]
+            .as_str()
+            .ok_or_else(|| crate::error::Error::tool_execution("archival_insert", "Missing content"))?;
+
+        let ids = self
+            .archival
+            .insert(content, PassageSource::Insert, HashMap::new())
+            .await?;
+
+        Ok(ToolOutput::success_with_data(
+            format!("Stored {} passage(s) in archival memory", ids.len()),
+            json!({"passage_ids": ids}),
+        ))
+    }
+}
+
+/// Tool for semantic search over archival memory
+pub struct ArchivalSearchTool {
+    archival: Arc<ArchivalMemory>,
+}
+
+impl ArchivalSearchTool {
+    /// Create the tool over an agent's archival memory
+    pub fn new(archival: Arc<ArchivalMemory>) -> Self {
+        Self { archival }
+    }
+}
+
+#[async_trait]
+impl Tool for ArchivalSearchTool {
+    fn id(&self) -> &str {
+        "archival_search"
+    }
+
+    fn description(&self) -> &str {
+        "Search your archival memory, which holds past messages, archived memory blocks \
+         and anything saved with archival_insert. Matches by meaning as well as keywords, \
+         so describe what you are looking for."
+    }
+
+    fn name(&self) -> &str {
+        self.id()
+    }
+
+    fn input_schema(&self) -> JsonSchema {
+        let mut properties = HashMap::new();
+        properties.insert(
+            "query".to_string(),
+            json!({
+                "type": "string",
+                "description": "What to look for"
+            }),
+        );
+        properties.insert(
+            "limit".to_string(),
+            json!({
+                "type": "integer",
+                "description": "Maximum number of results (default 5)"
+            }),
+        );
+
+        JsonSchema::object(properties).with_required(vec!["query".to_string()])
+    }
+
+    async fn execute(&self, params: Value, _ctx: &ToolContext) -> Result<ToolOutput> {
+        let query = params["query"]
+            .as_str()
+            .ok_or_else(|| crate::error::Error::tool_execution("archival_search", "Missing query"))?;
+        let limit = params["limit"].as_u64().unwrap_or(5).clamp(1, 50) as usize;
+
+        let hits = self.archival.search(query, limit).await?;
+
+        let mut text = format!("Found {} archival passages for '{}'", hits.len(), query);
+        let mut results = Vec::with_capacity(hits.len());
+        for (rank, hit) in hits.iter().enumerate() {
+            let source = match &hit.passage.source {
+                PassageSource::Insert => "inserted".to_string(),
+                PassageSource::Message { .. } => match hit.passage.metadata.get("role") {
+                    Some(role) => format!("{} message", role),
+                    None => "message".to_string(),
+                },
+                PassageSource::Block { .. } => match hit.passage.metadata.get("label") {
+                    Some(label) => format!("block '{}'", label),
+                    None => "block".to_string(),
+                },
+            };
+            text.push_str(&format!(
+                "\n{}. [score {:.3}, {}, {}] {}",
+                rank + 1,
+                hit.score,
+                source,
+                hit.passage.created_at.format("%Y-%m-%d %H:%M"),
+                preview(&hit.passage.text, 500)
+            ));
+            results.push(json!({
+                "created_at": hit.passage.created_at.to_rfc3339(),
+                "source": hit.passage.source,
+                "metadata": hit.passage.metadata,
+                "score": hit.score,
+                "content": hit.passage.text
+            }));
+        }
+
+        Ok(ToolOutput::success_with_data(
+            text,
+            json!({
+                "query": query,
+                "results": results,
+                "count": hits.len()
+            }),
+        ))
+    }
+}
+
+/// First `max` characters of `text`, with an ellipsis when cut short
+fn preview(text: &str, max: usize) -> String {
+    match text.char_indices().nth(max) {
+        Some((end, _)) => format!("{}...", &text[..end]),
+        None => text.to_string(),
+    }
+}
+
 /// Create all standard memory tools for an agent
+///
+/// Archival tools are included when the memory has archival storage, and
+/// `update_shared_memory` when it has a shared memory manager.
 pub fn create_memory_tools(memory: Arc<AgentMemory>) -> Vec<Arc<dyn Tool>> {
-    vec![
+    let archival = memory.archival().cloned();
+    let shared = memory.shared_memory().is_some();
+    let mut tools: Vec<Arc<dyn Tool>> = vec![
         Arc::new(UpdateMemoryTool::new(memory.clone())),
         Arc::new(MoveOutOfContextTool::new(memory.clone())),
         Arc::new(MoveIntoContextTool::new(memory.clone())),
         Arc::new(ListMemoryBlocksTool::new(memory.clone())),
-        Arc::new(SearchMessagesTool::new(memory)),
-    ]
+        Arc::new(MemoryBlockHistoryTool::new(memory.clone())),
+        Arc::new(SearchMessagesTool::new(memory.clone())),
+    ];
+    if shared {
+        tools.push(Arc::new(UpdateSharedMemoryTool::new(memory)));
+    }
+    if let Some(archival) = archival {
+        tools.push(Arc::new(ArchivalInsertTool::new(archival.clone())));
+        tools.push(Arc::new(ArchivalSearchTool::new(archival)));
+    }
+    tools
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::memory::{BlockAccess, MemoryConfig, SharedMemoryManager};
+    use crate::types::AgentId;
+
+    #[tokio::test]
+    async fn test_update_shared_memory_reports_conflicts() {
+        let manager = SharedMemoryManager::new();
+        let block_id = manager.create_block("plan", "Team plan", "1. research").await;
+
+        let writer = Arc::new(
+            AgentMemory::new(AgentId::new(), MemoryConfig::default()).with_shared_memory(manager.clone()),
+        );
+        let reader = Arc::new(
+            AgentMemory::new(AgentId::new(), MemoryConfig::default()).with_shared_memory(manager.clone()),
+        );
+        manager.grant(block_id, writer.agent_id, BlockAccess::ReadWrite).await.unwrap();
+        manager.grant(block_id, reader.agent_id, BlockAccess::ReadOnly).await.unwrap();
+
+        let tool = create_memory_tools(writer.clone())
+            .into_iter()
+            .find(|t| t.id() == "update_shared_memory")
+            .unwrap();
+        let ctx = ToolContext::new(writer.agent_id);
+        let params = |revision: u64, value: &str| {
+            json!({"block_id": block_id.to_string(), "expected_revision": revision, "new_value": value})
+        };
+
+        let output = tool.execute(params(1, "1. research\n2. draft"), &ctx).await.unwrap();
+        assert!(output.success);
+
+        // A stale revision is rejected with the current value
+        let output = tool.execute(params(1, "1. research\n2. review"), &ctx).await.unwrap();
+        assert!(!output.success);
+        let error = output.error.unwrap();
+        assert!(error.contains("revision 2"));
+        assert!(error.contains("2. draft"));
+        assert_eq!(manager.get_block(block_id).await.unwrap().value, "1. research\n2. draft");
+
+        let denied = UpdateSharedMemoryTool::new(reader.clone())
+            .execute(params(2, "overwritten"), &ToolContext::new(reader.agent_id))
+            .await
+            .unwrap();
+        assert!(denied.error.unwrap().contains("cannot write"));
+        assert!(create_memory_tools(Arc::new(AgentMemory::new(AgentId::new(), MemoryConfig::default())))
+            .iter()
+            .all(|t| t.id() != "update_shared_memory"));
+    }
 }
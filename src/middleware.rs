@@ -0,0 +1,589 @@
+//! Composable [`LlmClient`] middleware
+//!
+//! Each layer wraps a client in another client, so a stack built with
+//! [`ClientStack`] can be handed to [`AgentBuilder::client`] like any other.
+//!
+//! - [`RetryLayer`]: retry transient failures with exponential backoff
+//! - [`RedactionLayer`]: mask PII before it leaves the process
+//! - [`AuditLayer`]: record every request and response, redacted, to an
+//!   [`AuditSink`]
+//!
+//! Response caching is provided by [`SemanticCache`], which is a layer too.
+//!
+//! [`ClientStack`]: crate::llm_client::ClientStack
+//! [`AgentBuilder::client`]: crate::agent::AgentBuilder::client
+//! [`SemanticCache`]: crate::cache::SemanticCache
+
+use crate::error::{Error, Result};
+use crate::llm_client::{Layer, LlmClient};
+use crate::openrouter::{
+    CompletionRequest, CompletionResponse, CompletionStream, ContentPart, Message, MessageContent,
+    ReasoningDetail,
+};
+use async_trait::async_trait;
+use chrono::{DateTime, Utc};
+use regex::Regex;
+use serde::{Deserialize, Serialize};
+use std::path::PathBuf;
+use std::sync::Arc;
+use std::time::{Duration, Instant};
+use tokio::io::AsyncWriteExt;
+
+/// Retries failed requests with exponential backoff
+///
+/// By default only [transient](Error::is_transient) errors are retried.
+/// Clients that retry on their own, like
+/// [`OpenRouterClient`](crate::openrouter::OpenRouterClient), report
+/// [`Error::RetriesExhausted`] once they give up, which is not retried again.
+#[derive(Clone)]
+pub struct RetryLayer {
+    max_retries: u32,
+    backoff: Duration,
+    retry_if: Arc<dyn Fn(&Error) -> bool + Send + Sync>,
+}
+
+impl RetryLayer {
+    /// Retry up to `max_retries` times, starting with a 500ms delay
+    pub fn new(max_retries: u32) -> Self {
+        Self {
+            max_retries,
+            backoff: Duration::from_millis(500),
+            retry_if: Arc::new(Error::is_transient),
+        }
+    }
+
+    /// Set the delay before the first retry, doubled for each further retry
+    pub fn with_backoff(mut self, backoff: Duration) -> Self {
+        self.backoff = backoff;
+        self
+    }
+
+    /// Decide which errors are worth retrying
+    pub fn retry_if(mut self, predicate: impl Fn(&Error) -> bool + Send + Sync + 'static) -> Self {
+        self.retry_if = Arc::new(predicate);
+        self
+    }
+
+    async fn run<T, F, Fut>(&self, mut attempt: F) -> Result<T>
+    where
+        F: FnMut() -> Fut,
+        Fut: std::future::Future<Output = Result<T>>,
+    {
+        let mut retries = 0;
+        loop {
+            match attempt().await {
+                Err(e) if retries < self.max_retries && (self.retry_if)(&e) => {
+                    let delay = self.backoff.saturating_mul(1 << retries.min(16));
+                    tracing::warn!("LLM request failed: {}; retrying in {:?}", e, delay);
+                    tokio::time::sleep(delay).await;
+                    retries += 1;
+                }
+                result => return result,
+            }
+        }
+    }
+}
+
+impl Layer for RetryLayer {
+    fn layer(&self, inner: Arc<dyn LlmClient>) -> Arc<dyn LlmClient> {
+        Arc::new(RetryingClient {
+            inner,
+            policy: self.clone(),
+        })
+    }
+}
+
+struct RetryingClient {
+    inner: Arc<dyn LlmClient>,
+    policy: RetryLayer,
+}
+
+#[async_trait]
+impl LlmClient for RetryingClient {
+    async fn complete(&self, request: CompletionRequest) -> Result<CompletionResponse> {
+        self.policy
+            .run(|| self.inner.complete(request.clone()))
+            .await
+    }
+
+    async fn stream(&self, request: CompletionRequest) -> Result<CompletionStream> {
+        self.policy.run(|| self.inner.stream(request.clone())).await
+    }
+
+    fn client_type(&self) -> &str {
+        self.inner.client_type()
+    }
+
+    fn endpoint(&self) -> &str {
+        self.inner.endpoint()
+    }
+}
+
+/// Masks personally identifiable information in messages
+///
+/// Request messages are redacted before they are sent and completed
+/// responses before they are returned; streamed responses pass through.
+/// Message text, reasoning and the string values of tool call arguments are
+/// redacted, and matches are replaced with `[REDACTED_<LABEL>]`. Signed
+/// reasoning that contained PII no longer matches its signature.
+#[derive(Debug, Clone)]
+pub struct RedactionLayer {
+    patterns: Vec<(String, Regex)>,
+}
+
+impl RedactionLayer {
+    /// Redact email addresses, US social security numbers, card numbers and
+    /// phone numbers
+    pub fn new() -> Self {
+        let defaults = [
+            ("EMAIL", r"[A-Za-z0-9._%+-]+@[A-Za-z0-9.-]+\.[A-Za-z]{2,}"),
+            ("SSN", r"\b\d{3}-\d{2}-\d{4}\b"),
+            ("CARD", r"\b\d(?:[ -]?\d){12,18}\b"),
+            (
+                "PHONE",
+                r"(?:\+\d{1,3}[ .-]?)?(?:\(\d{3}\)|\b\d{3})[ .-]?\d{3}[ .-]?\d{4}\b",
+            ),
+        ];
+        Self {
+            patterns: defaults
+                .into_iter()
+                .map(|(label, pattern)| {
+                    (
+                        label.to_string(),
+                        Regex::new(pattern).expect("valid PII pattern"),
+                    )
+                })
+                .collect(),
+        }
+    }
+
+    /// Also redact matches of `pattern`, labelled `label`
+    pub fn with_pattern(mut self, label: impl Into<String>, pattern: &str) -> Result<Self> {
+        let regex = Regex::new(pattern)
+            .map_err(|e| Error::config(format!("Invalid redaction pattern: {}", e)))?;
+        self.patterns.push((label.into().to_uppercase(), regex));
+        Ok(self)
+    }
+
+    /// Redact all patterns in `text`
+    pub fn redact(&self, text: &str) -> String {
+        self.patterns
+            .iter()
+            .fold(text.to_string(), |text, (label, regex)| {
+                regex
+                    .replace_all(&text, format!("[REDACTED_{}]", label))
+                    .into_owned()
+            })
+    }
+
+    /// Redact every message of a request
+    pub fn redact_request(&self, request: &mut CompletionRequest) {
+        for message in &mut request.messages {
+            self.redact_message(message);
+        }
+    }
+
+    /// Redact every choice of a response
+    pub fn redact_response(&self, response: &mut CompletionResponse) {
+        for choice in &mut response.choices {
+            self.redact_message(&mut choice.message);
+        }
+    }
+
+    /// Redact the content, reasoning and tool call arguments of a message
+    fn redact_message(&self, message: &mut Message) {
+        self.redact_content(&mut message.content);
+        if let Some(reasoning) = &mut message.reasoning {
+            *reasoning = self.redact(reasoning);
+        }
+        for detail in &mut message.reasoning_details {
+            if let ReasoningDetail::Text { text, .. } = detail {
+                *text = self.redact(text);
+            }
+        }
+        for call in message.tool_calls.iter_mut().flatten() {
+            call.function.arguments = self.redact_arguments(&call.function.arguments);
+        }
+    }
+
+    /// Redact the string values of JSON arguments, or the raw text if they
+    /// are not valid JSON
+    fn redact_arguments(&self, arguments: &str) -> String {
+        match serde_json::from_str::<serde_json::Value>(arguments) {
+            Ok(mut value) => {
+                self.redact_json(&mut value);
+                value.to_string()
+            }
+            Err(_) => self.redact(arguments),
+        }
+    }
+
+    fn redact_json(&self, value: &mut serde_json::Value) {
+        match value {
+            serde_json::Value::String(text) => *text = self.redact(text),
+            serde_json::Value::Array(values) => values.iter_mut().for_each(|v| self.redact_json(v)),
+            serde_json::Value::Object(map) => map.values_mut().for_each(|v| self.redact_json(v)),
+            _ => {}
+        }
+    }
+
+    /// Redact the text of a message, leaving images and files as they are
+    fn redact_content(&self, content: &mut MessageContent) {
+        match content {
+            MessageContent::Text(text) => *text = self.redact(text),
+            MessageContent::Parts(parts) => {
+                for part in parts {
+                    if let ContentPart::Text { text } = part {
+                        *text = self.redact(text);
+                    }
+                }
+            }
+        }
+    }
+}
+
+impl Default for RedactionLayer {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl Layer for RedactionLayer {
+    fn layer(&self, inner: Arc<dyn LlmClient>) -> Arc<dyn LlmClient> {
+        Arc::new(RedactingClient {
+            inner,
+            redaction: self.clone(),
+        })
+    }
+}
+
+struct RedactingClient {
+    inner: Arc<dyn LlmClient>,
+    redaction: RedactionLayer,
+}
+
+impl RedactingClient {
+    fn redact_request(&self, mut request: CompletionRequest) -> CompletionRequest {
+        self.redaction.redact_request(&mut request);
+        request
+    }
+}
+
+#[async_trait]
+impl LlmClient for RedactingClient {
+    async fn complete(&self, request: CompletionRequest) -> Result<CompletionResponse> {
+        let mut response = self.inner.complete(self.redact_request(request)).await?;
+        self.redaction.redact_response(&mut response);
+        Ok(response)
+    }
+
+    async fn stream(&self, request: CompletionRequest) -> Result<CompletionStream> {
+        self.inner.stream(self.redact_request(request)).await
+    }
+
+    fn client_type(&self) -> &str {
+        self.inner.client_type()
+    }
+
+    fn endpoint(&self) -> &str {
+        self.inner.endpoint()
+    }
+}
+
+/// One audited LLM call
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct AuditEntry {
+    /// When the request was made
+    pub timestamp: DateTime<Utc>,
+    /// Type of the wrapped client
+    pub client_type: String,
+    /// Request as received by the audit layer, redacted
+    pub request: CompletionRequest,
+    /// Response, redacted, for completed requests that succeeded
+    pub response: Option<CompletionResponse>,
+    /// Error message, redacted, if the request failed
+    pub error: Option<String>,
+    /// Time until the response (or stream) was returned
+    pub duration_ms: u64,
+}
+
+/// Destination for audit entries
+#[async_trait]
+pub trait AuditSink: Send + Sync {
+    /// Record one entry
+    async fn record(&self, entry: &AuditEntry) -> Result<()>;
+}
+
+/// Appends audit entries to a file, one JSON object per line
+pub struct JsonLinesAuditSink {
+    path: PathBuf,
+    lock: tokio::sync::Mutex<()>,
+}
+
+impl JsonLinesAuditSink {
+    /// Create a sink writing to `path`
+    pub fn new(path: impl Into<PathBuf>) -> Self {
+        Self {
+            path: path.into(),
+            lock: tokio::sync::Mutex::new(()),
+        }
+    }
+}
+
+#[async_trait]
+impl AuditSink for JsonLinesAuditSink {
+    async fn record(&self, entry: &AuditEntry) -> Result<()> {
+        let mut line = serde_json::to_string(entry)?;
+        line.push('\n');
+
+        let _guard = self.lock.lock().await;
+        let mut file = tokio::fs::OpenOptions::new()
+            .create(true)
+            .append(true)
+            .open(&self.path)
+            .await?;
+        file.write_all(line.as_bytes()).await?;
+        file.flush().await?;
+
+        Ok(())
+    }
+}
+
+/// Records every request and its outcome to an [`AuditSink`]
+///
+/// Entries are redacted before they reach the sink, with the default
+/// [`RedactionLayer`] patterns unless [`AuditLayer::with_redaction`] sets
+/// others, so the audit trail never holds PII the model did not see either.
+/// Sink errors are logged rather than failing the request.
+#[derive(Clone)]
+pub struct AuditLayer {
+    sink: Arc<dyn AuditSink>,
+    redaction: RedactionLayer,
+}
+
+impl AuditLayer {
+    /// Record to `sink`
+    pub fn new(sink: Arc<dyn AuditSink>) -> Self {
+        Self {
+            sink,
+            redaction: RedactionLayer::new(),
+        }
+    }
+
+    /// Redact entries with `redaction` instead of the default patterns
+    pub fn with_redaction(mut self, redaction: RedactionLayer) -> Self {
+        self.redaction = redaction;
+        self
+    }
+}
+
+impl Layer for AuditLayer {
+    fn layer(&self, inner: Arc<dyn LlmClient>) -> Arc<dyn LlmClient> {
+        Arc::new(AuditingClient {
+            inner,
+            config: self.clone(),
+        })
+    }
+}
+
+struct AuditingClient {
+    inner: Arc<dyn LlmClient>,
+    config: AuditLayer,
+}
+
+impl AuditingClient {
+    async fn record(
+        &self,
+        mut request: CompletionRequest,
+        mut response: Option<CompletionResponse>,
+        error: Option<&Error>,
+        timestamp: DateTime<Utc>,
+        started: Instant,
+    ) {
+        let redaction = &self.config.redaction;
+        redaction.redact_request(&mut request);
+        if let Some(response) = &mut response {
+            redaction.redact_response(response);
+        }
+
+        let entry = AuditEntry {
+            timestamp,
+            client_type: self.inner.client_type().to_string(),
+            request,
+            response,
+            error: error.map(|e| redaction.redact(&e.to_string())),
+            duration_ms: started.elapsed().as_millis() as u64,
+        };
+        if let Err(e) = self.config.sink.record(&entry).await {
+            tracing::warn!("Failed to record audit entry: {}", e);
+        }
+    }
+}
+
+#[async_trait]
+impl LlmClient for AuditingClient {
+    async fn complete(&self, request: CompletionRequest) -> Result<CompletionResponse> {
+        let (timestamp, started) = (Utc::now(), Instant::now());
+        let result = self.inner.complete(request.clone()).await;
+        self.record(
+            request,
+            result.as_ref().ok().cloned(),
+            result.as_ref().err(),
+            timestamp,
+            started,
+        )
+        .await;
+        result
+    }
+
+    async fn stream(&self, request: CompletionRequest) -> Result<CompletionStream> {
+        let (timestamp, started) = (Utc::now(), Instant::now());
+        let result = self.inner.stream(request.clone()).await;
+        self.record(request, None, result.as_ref().err(), timestamp, started)
+            .await;
+        result
+    }
+
+    fn client_type(&self) -> &str {
+        self.inner.client_type()
+    }
+
+    fn endpoint(&self) -> &str {
+        self.inner.endpoint()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::cache::SemanticCache;
+    use crate::llm_client::testing::ScriptedClient;
+    use crate::llm_client::ClientStack;
+    use crate::openrouter::{FunctionCall, ToolCall};
+    use parking_lot::Mutex;
+
+    #[derive(Default)]
+    struct CollectingSink(Mutex<Vec<AuditEntry>>);
+
+    #[async_trait]
+    impl AuditSink for CollectingSink {
+        async fn record(&self, entry: &AuditEntry) -> Result<()> {
+            self.0.lock().push(entry.clone());
+            Ok(())
+        }
+    }
+
+    #[tokio::test]
+    async fn test_full_stack() {
+        let base = Arc::new(
+            ScriptedClient::echoing().failing_with(1, || Error::Timeout("upstream".to_string())),
+        );
+        let sink = Arc::new(CollectingSink::default());
+        let client = ClientStack::new(base.clone())
+            .layer(SemanticCache::in_memory().force(true))
+            .layer(RetryLayer::new(2).with_backoff(Duration::from_millis(1)))
+            .layer(RedactionLayer::new())
+            .layer(AuditLayer::new(sink.clone()))
+            .build();
+
+        let request = CompletionRequest::new(
+            "m",
+            vec![Message::user("Mail jane@example.com or call 555-123-4567")],
+        );
+        let first = client.complete(request.clone()).await.unwrap();
+        let second = client.complete(request).await.unwrap();
+
+        let redacted = "Mail [REDACTED_EMAIL] or call [REDACTED_PHONE]";
+        assert_eq!(first.choices[0].message.content, redacted);
+        assert_eq!(second.choices[0].message.content, redacted);
+        // One failure retried, then the second call is served from the cache
+        assert_eq!(base.calls(), 2);
+        assert_eq!(base.requests()[0].messages[0].content, redacted);
+
+        let entries = sink.0.lock();
+        assert_eq!(entries.len(), 2);
+        // Audit sits outside redaction here but still never records raw PII
+        assert!(entries
+            .iter()
+            .all(|e| e.request.messages[0].content == redacted));
+        assert!(entries.iter().all(|e| e.error.is_none()));
+        assert_eq!(client.client_type(), "scripted");
+    }
+
+    #[tokio::test]
+    async fn test_retry_skips_permanent_errors() {
+        let base = Arc::new(
+            ScriptedClient::echoing().failing_with(3, || Error::api(400, "Request failed: nope")),
+        );
+        let client = ClientStack::new(base.clone())
+            .layer(RetryLayer::new(3).with_backoff(Duration::from_millis(1)))
+            .build();
+
+        assert!(client
+            .complete(CompletionRequest::new("m", vec![]))
+            .await
+            .is_err());
+        assert_eq!(base.calls(), 1);
+    }
+
+    #[test]
+    fn test_redaction_patterns() {
+        let redaction = RedactionLayer::new()
+            .with_pattern("ticket", r"TICKET-\d+")
+            .unwrap();
+        assert_eq!(
+            redaction.redact("SSN 123-45-6789, card 4111 1111 1111 1111, TICKET-42"),
+            "SSN [REDACTED_SSN], card [REDACTED_CARD], [REDACTED_TICKET]"
+        );
+    }
+
+    #[tokio::test]
+    async fn test_redaction_covers_tool_calls_and_reasoning() {
+        let call = ToolCall {
+            id: "call_1".to_string(),
+            tool_type: "function".to_string(),
+            function: FunctionCall {
+                name: "send_mail".to_string(),
+                arguments: r#"{"to":["jane@example.com"],"retries":2}"#.to_string(),
+            },
+        };
+        let mut reply = Message::assistant_with_tool_calls("", vec![call]);
+        reply.reasoning = Some("Jane wrote from jane@example.com".to_string());
+        let base = Arc::new(ScriptedClient::new(vec![reply.clone()]));
+        let sink = Arc::new(CollectingSink::default());
+        let client = ClientStack::new(base.clone())
+            .layer(RedactionLayer::new())
+            .layer(AuditLayer::new(sink.clone()))
+            .build();
+
+        let response = client
+            .complete(CompletionRequest::new("m", vec![reply]))
+            .await
+            .unwrap();
+
+        let arguments = r#"{"retries":2,"to":["[REDACTED_EMAIL]"]}"#;
+        let reasoning = "Jane wrote from [REDACTED_EMAIL]";
+        let sent = &base.requests()[0].messages[0];
+        let entry = sink.0.lock()[0].clone();
+        for message in [
+            sent,
+            &response.choices[0].message,
+            &entry.request.messages[0],
+        ] {
+            let call = &message.tool_calls.as_ref().unwrap()[0];
+            assert_eq!(call.function.arguments, arguments);
+            assert_eq!(message.reasoning.as_deref(), Some(reasoning));
+        }
+    }
+
+    #[test]
+    fn test_transient_errors() {
+        assert!(!Error::api(400, "Bad Request").is_transient());
+        assert!(Error::api(503, "Service Unavailable").is_transient());
+        let exhausted = Error::RetriesExhausted {
+            attempts: 3,
+            last: Box::new(Error::api(503, "Service Unavailable")),
+        };
+        assert!(!exhausted.is_transient());
+    }
+}
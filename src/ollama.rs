@@ -0,0 +1,806 @@
+//! Ollama client for models served on developer machines
+//!
+//! Talks to Ollama's native `/api/chat` endpoint, translating requests and
+//! its newline-delimited JSON stream to the OpenAI-shaped types used by
+//! agents. Model management mirrors the `ollama` CLI:
+//!
+//! ```rust,ignore
+//! let client = OllamaClient::new(OllamaConfig::new("http://localhost:11434"))?;
+//! client.pull_model("llama3.2").await?;
+//! let agent = Agent::builder()
+//!     .model("llama3.2")
+//!     .client(Arc::new(client))
+//!     .build()?;
+//! ```
+
+use crate::error::{Error, Result};
+use crate::llm_client::LlmClient;
+use crate::openrouter::{
+    Choice, CompletionRequest, CompletionResponse, CompletionStream, ContentPart, Delta,
+    FunctionCall, FunctionCallDelta, Message, MessageContent, ResponseFormat, Role, StreamChoice,
+    StreamChunk, ToolCall, ToolCallDelta, ToolDefinition, Usage,
+};
+use async_trait::async_trait;
+use futures::StreamExt;
+use reqwest::Client;
+use serde::{Deserialize, Serialize};
+use std::collections::HashMap;
+use std::time::Duration;
+
+/// Ollama client configuration
+#[derive(Debug, Clone)]
+pub struct OllamaConfig {
+    /// Base URL of the Ollama server (e.g., "http://localhost:11434")
+    pub base_url: String,
+    /// Request timeout
+    pub timeout: Duration,
+    /// Timeout for model downloads
+    pub pull_timeout: Duration,
+    /// How long the server keeps the model loaded after a request (e.g., "10m")
+    pub keep_alive: Option<String>,
+    /// Ask thinking models to return their reasoning separately
+    pub think: bool,
+}
+
+impl OllamaConfig {
+    /// Create a new Ollama configuration
+    pub fn new(base_url: impl Into<String>) -> Self {
+        Self {
+            base_url: base_url.into(),
+            timeout: Duration::from_secs(300),
+            pull_timeout: Duration::from_secs(3600),
+            keep_alive: None,
+            think: false,
+        }
+    }
+
+    /// Create configuration from `OLLAMA_HOST`
+    ///
+    /// Accepts the same forms as the Ollama CLI, such as `127.0.0.1:11434`
+    /// without a scheme.
+    pub fn from_env() -> Result<Self> {
+        let host = std::env::var("OLLAMA_HOST").unwrap_or_else(|_| "127.0.0.1:11434".to_string());
+        Ok(Self::new(host_url(&host)))
+    }
+
+    /// Set the timeout
+    pub fn with_timeout(mut self, timeout: Duration) -> Self {
+        self.timeout = timeout;
+        self
+    }
+
+    /// Set the timeout for model downloads
+    pub fn with_pull_timeout(mut self, timeout: Duration) -> Self {
+        self.pull_timeout = timeout;
+        self
+    }
+
+    /// Set how long the model stays loaded after a request
+    pub fn with_keep_alive(mut self, keep_alive: impl Into<String>) -> Self {
+        self.keep_alive = Some(keep_alive.into());
+        self
+    }
+
+    /// Request separate reasoning from thinking models
+    pub fn with_think(mut self, think: bool) -> Self {
+        self.think = think;
+        self
+    }
+}
+
+/// Base URL for an `OLLAMA_HOST` value
+fn host_url(host: &str) -> String {
+    let host = host.trim().trim_end_matches('/');
+    if host.contains("://") {
+        host.to_string()
+    } else if host.contains(':') {
+        format!("http://{}", host)
+    } else {
+        format!("http://{}:11434", host)
+    }
+}
+
+/// Ollama client for local model inference
+pub struct OllamaClient {
+    /// HTTP client
+    client: Client,
+    /// Configuration
+    config: OllamaConfig,
+}
+
+impl OllamaClient {
+    /// Create a new Ollama client from environment variables
+    pub fn from_env() -> Result<Self> {
+        Self::new(OllamaConfig::from_env()?)
+    }
+
+    /// Create a new Ollama client with the given configuration
+    pub fn new(config: OllamaConfig) -> Result<Self> {
+        let client = Client::builder().timeout(config.timeout).build()?;
+        Ok(Self { client, config })
+    }
+
+    /// Get the configuration
+    pub fn config(&self) -> &OllamaConfig {
+        &self.config
+    }
+
+    fn url(&self, path: &str) -> String {
+        format!("{}{}", self.config.base_url.trim_end_matches('/'), path)
+    }
+
+    /// Check if the Ollama server is reachable
+    pub async fn health_check(&self) -> Result<OllamaHealth> {
+        let response = self.client.get(self.url("/api/version")).send().await?;
+
+        if !response.status().is_success() {
+            return Err(Error::config(format!(
+                "Ollama health check failed: {}",
+                response.status()
+            )));
+        }
+
+        let version: OllamaVersion = response.json().await?;
+        Ok(OllamaHealth {
+            status: "ok".to_string(),
+            version: version.version,
+        })
+    }
+
+    /// List models available locally
+    pub async fn list_models(&self) -> Result<Vec<OllamaModel>> {
+        let response = self.client.get(self.url("/api/tags")).send().await?;
+
+        if !response.status().is_success() {
+            return Err(Error::config(format!(
+                "Failed to list models: {}",
+                response.status()
+            )));
+        }
+
+        let tags: OllamaTags = response.json().await?;
+        Ok(tags.models)
+    }
+
+    /// Download a model, waiting until it is ready
+    pub async fn pull_model(&self, model: &str) -> Result<()> {
+        let response = self
+            .client
+            .post(self.url("/api/pull"))
+            .timeout(self.config.pull_timeout)
+            .json(&serde_json::json!({ "model": model, "stream": false }))
+            .send()
+            .await?;
+
+        if !response.status().is_success() {
+            let status = response.status();
+            let error_text = response.text().await.unwrap_or_default();
+            return Err(Error::config(format!(
+                "Failed to pull model '{}': {} {}",
+                model, status, error_text
+            )));
+        }
+
+        let progress: PullStatus = response.json().await?;
+        if progress.status != "success" {
+            return Err(Error::config(format!(
+                "Failed to pull model '{}': {}",
+                model, progress.status
+            )));
+        }
+        Ok(())
+    }
+
+    /// Get details of a local model
+    pub async fn show_model(&self, model: &str) -> Result<OllamaModelInfo> {
+        let response = self
+            .client
+            .post(self.url("/api/show"))
+            .json(&serde_json::json!({ "model": model }))
+            .send()
+            .await?;
+
+        if !response.status().is_success() {
+            return Err(Error::config(format!(
+                "Failed to show model '{}': {}",
+                model,
+                response.status()
+            )));
+        }
+
+        Ok(response.json().await?)
+    }
+
+    async fn send(
+        &self,
+        request: CompletionRequest,
+        stream: bool,
+        what: &str,
+    ) -> Result<reqwest::Response> {
+        let body = self.chat_request(request, stream);
+        let response = self
+            .client
+            .post(self.url("/api/chat"))
+            .json(&body)
+            .send()
+            .await?;
+
+        if !response.status().is_success() {
+            let status = response.status();
+            let error_text = response
+                .text()
+                .await
+                .unwrap_or_else(|_| "Unknown error".to_string());
+            return Err(Error::api(
+                status.as_u16(),
+                format!("Ollama {} failed: {}", what, error_text),
+            ));
+        }
+
+        Ok(response)
+    }
+
+    fn chat_request(&self, request: CompletionRequest, stream: bool) -> ChatRequest {
+        // Ollama names the tool a result belongs to rather than the call
+        let mut tool_names = HashMap::new();
+        let messages = request
+            .messages
+            .into_iter()
+            .map(|message| {
+                let tool_calls = message.tool_calls.map(|calls| {
+                    calls
+                        .into_iter()
+                        .map(|call| {
+                            tool_names.insert(call.id, call.function.name.clone());
+                            OllamaToolCall {
+                                function: OllamaFunctionCall {
+                                    arguments: serde_json::from_str(&call.function.arguments)
+                                        .unwrap_or_else(|_| serde_json::json!({})),
+                                    name: call.function.name,
+                                },
+                            }
+                        })
+                        .collect()
+                });
+                let tool_name = message
+                    .tool_call_id
+                    .as_ref()
+                    .and_then(|id| tool_names.get(id).cloned());
+
+                ChatMessage {
+                    role: message.role,
+                    content: message.content.text().into_owned(),
+                    thinking: None,
+                    images: images(&message.content),
+                    tool_calls,
+                    tool_name,
+                }
+            })
+            .collect();
+
+        ChatRequest {
+            model: request.model,
+            messages,
+            tools: request.tools,
+            stream,
+            options: ChatOptions {
+                temperature: request.temperature,
+                top_p: request.top_p,
+                num_predict: request.max_tokens,
+                stop: request.stop,
+                frequency_penalty: request.frequency_penalty,
+                presence_penalty: request.presence_penalty,
+            },
+            format: request.response_format.and_then(|format| match format {
+                ResponseFormat::Text => None,
+                ResponseFormat::JsonObject => Some(serde_json::json!("json")),
+                ResponseFormat::JsonSchema { json_schema } => Some(json_schema.schema),
+            }),
+            keep_alive: self.config.keep_alive.clone(),
+            think: self.config.think.then_some(true),
+        }
+    }
+}
+
+/// Base64 images of a message
+///
+/// Ollama only takes inline image data, so image URLs and files are dropped.
+fn images(content: &MessageContent) -> Vec<String> {
+    let MessageContent::Parts(parts) = content else {
+        return Vec::new();
+    };
+    parts
+        .iter()
+        .filter_map(|part| match part {
+            ContentPart::ImageUrl { image_url } => {
+                image_url.as_base64().map(|(_, data)| data.to_string())
+            }
+            _ => None,
+        })
+        .collect()
+}
+
+/// OpenAI-style tool calls; Ollama sends arguments as an object and no IDs
+fn tool_calls(calls: Vec<OllamaToolCall>, first_index: usize) -> Vec<ToolCall> {
+    calls
+        .into_iter()
+        .enumerate()
+        .map(|(i, call)| ToolCall {
+            id: format!("call_{}", first_index + i),
+            tool_type: "function".to_string(),
+            function: FunctionCall {
+                name: call.function.name,
+                arguments: call.function.arguments.to_string(),
+            },
+        })
+        .collect()
+}
+
+fn usage(response: &ChatResponse) -> Usage {
+    Usage {
+        prompt_tokens: response.prompt_eval_count,
+        completion_tokens: response.eval_count,
+        total_tokens: response.prompt_eval_count + response.eval_count,
+    }
+}
+
+#[async_trait]
+impl LlmClient for OllamaClient {
+    async fn complete(&self, request: CompletionRequest) -> Result<CompletionResponse> {
+        let response: ChatResponse = self.send(request, false, "request").await?.json().await?;
+        let usage = usage(&response);
+
+        let calls = tool_calls(response.message.tool_calls.unwrap_or_default(), 0);
+        let finish_reason = if calls.is_empty() {
+            response.done_reason
+        } else {
+            Some("tool_calls".to_string())
+        };
+        let mut message = if calls.is_empty() {
+            Message::assistant(response.message.content)
+        } else {
+            Message::assistant_with_tool_calls(response.message.content, calls)
+        };
+        if let Some(thinking) = response.message.thinking.filter(|t| !t.is_empty()) {
+            message = message.with_reasoning(thinking);
+        }
+
+        Ok(CompletionResponse {
+            id: format!("ollama-{}", response.created_at),
+            model: response.model,
+            provider: None,
+            choices: vec![Choice {
+                index: 0,
+                message,
+                finish_reason,
+            }],
+            usage,
+        })
+    }
+
+    async fn stream(&self, request: CompletionRequest) -> Result<CompletionStream> {
+        let mut bytes = self
+            .send(request, true, "stream request")
+            .await?
+            .bytes_stream();
+
+        let chunks = async_stream::stream! {
+            let mut buffer = Vec::new();
+            let mut tool_count = 0;
+
+            loop {
+                while let Some(end) = buffer.iter().position(|&b| b == b'\n') {
+                    let line: Vec<u8> = buffer.drain(..=end).collect();
+                    let line = String::from_utf8_lossy(&line);
+                    if line.trim().is_empty() {
+                        continue;
+                    }
+
+                    match stream_chunk(&line, &mut tool_count) {
+                        Ok((chunk, done)) => {
+                            yield Ok(chunk);
+                            if done {
+                                return;
+                            }
+                        }
+                        Err(e) => {
+                            yield Err(e);
+                            return;
+                        }
+                    }
+                }
+
+                match bytes.next().await {
+                    Some(Ok(data)) => buffer.extend_from_slice(&data),
+                    Some(Err(e)) => {
+                        yield Err(e.into());
+                        return;
+                    }
+                    None if buffer.iter().all(u8::is_ascii_whitespace) => return,
+                    // Treat a final line without a newline like any other
+                    None => buffer.push(b'\n'),
+                }
+            }
+        };
+
+        Ok(CompletionStream::from_chunks(chunks))
+    }
+
+    fn client_type(&self) -> &str {
+        "ollama"
+    }
+
+    fn endpoint(&self) -> &str {
+        &self.config.base_url
+    }
+}
+
+/// Translate one line of a streamed chat into a chunk, and whether it was the last
+fn stream_chunk(line: &str, tool_count: &mut usize) -> Result<(StreamChunk, bool)> {
+    let value: serde_json::Value = serde_json::from_str(line)?;
+    if let Some(error) = value.get("error") {
+        let message = error
+            .as_str()
+            .map(str::to_string)
+            .unwrap_or_else(|| error.to_string());
+        return Err(Error::openrouter(format!("Stream error: {}", message)));
+    }
+    let response: ChatResponse = serde_json::from_value(value)?;
+
+    let calls = response.message.tool_calls.clone().unwrap_or_default();
+    let tool_calls: Vec<ToolCallDelta> = tool_calls(calls, *tool_count)
+        .into_iter()
+        .enumerate()
+        .map(|(i, call)| ToolCallDelta {
+            index: (*tool_count + i) as u32,
+            id: Some(call.id),
+            tool_type: Some(call.tool_type),
+            function: Some(FunctionCallDelta {
+                name: Some(call.function.name),
+                arguments: Some(call.function.arguments),
+            }),
+        })
+        .collect();
+    *tool_count += tool_calls.len();
+
+    let finish_reason = match response.done {
+        true if *tool_count > 0 => Some("tool_calls".to_string()),
+        true => response.done_reason.clone(),
+        false => None,
+    };
+
+    let chunk = StreamChunk {
+        id: format!("ollama-{}", response.created_at),
+        model: response.model.clone(),
+        choices: vec![StreamChoice {
+            index: 0,
+            delta: Delta {
+                role: Some(Role::Assistant),
+                content: Some(response.message.content.clone()).filter(|c| !c.is_empty()),
+                tool_calls: Some(tool_calls).filter(|calls| !calls.is_empty()),
+                reasoning: response.message.thinking.clone().filter(|t| !t.is_empty()),
+                reasoning_details: None,
+            },
+            finish_reason,
+        }],
+        usage: response.done.then(|| usage(&response)),
+    };
+    Ok((chunk, response.done))
+}
+
+#[derive(Debug, Serialize)]
+struct ChatRequest {
+    model: String,
+    messages: Vec<ChatMessage>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    tools: Option<Vec<ToolDefinition>>,
+    stream: bool,
+    options: ChatOptions,
+    /// `"json"` or a JSON Schema the reply must match
+    #[serde(skip_serializing_if = "Option::is_none")]
+    format: Option<serde_json::Value>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    keep_alive: Option<String>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    think: Option<bool>,
+}
+
+#[derive(Debug, Serialize)]
+struct ChatOptions {
+    #[serde(skip_serializing_if = "Option::is_none")]
+    temperature: Option<f32>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    top_p: Option<f32>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    num_predict: Option<u32>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    stop: Option<Vec<String>>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    frequency_penalty: Option<f32>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    presence_penalty: Option<f32>,
+}
+
+#[derive(Debug, Serialize, Deserialize)]
+struct ChatMessage {
+    role: Role,
+    #[serde(default)]
+    content: String,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    thinking: Option<String>,
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    images: Vec<String>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    tool_calls: Option<Vec<OllamaToolCall>>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    tool_name: Option<String>,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+struct OllamaToolCall {
+    function: OllamaFunctionCall,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+struct OllamaFunctionCall {
+    name: String,
+    #[serde(default)]
+    arguments: serde_json::Value,
+}
+
+#[derive(Debug, Deserialize)]
+struct ChatResponse {
+    model: String,
+    #[serde(default)]
+    created_at: String,
+    message: ChatMessage,
+    #[serde(default)]
+    done: bool,
+    #[serde(default)]
+    done_reason: Option<String>,
+    #[serde(default)]
+    prompt_eval_count: u64,
+    #[serde(default)]
+    eval_count: u64,
+}
+
+#[derive(Debug, Deserialize)]
+struct OllamaVersion {
+    version: String,
+}
+
+#[derive(Debug, Deserialize)]
+struct OllamaTags {
+    models: Vec<OllamaModel>,
+}
+
+#[derive(Debug, Deserialize)]
+struct PullStatus {
+    status: String,
+}
+
+/// Ollama health check response
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct OllamaHealth {
+    /// Server status
+    pub status: String,
+    /// Ollama version
+    pub version: String,
+}
+
+/// A model available on the Ollama server
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct OllamaModel {
+    /// Model name with tag (e.g., "llama3.2:latest")
+    pub name: String,
+    /// When the model was last modified
+    #[serde(default)]
+    pub modified_at: String,
+    /// Size on disk in bytes
+    #[serde(default)]
+    pub size: u64,
+    /// Content digest
+    #[serde(default)]
+    pub digest: String,
+    /// Format, family, size and quantization
+    #[serde(default)]
+    pub details: OllamaModelDetails,
+}
+
+/// Model format, family, size and quantization
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+pub struct OllamaModelDetails {
+    /// File format (e.g., "gguf")
+    #[serde(default)]
+    pub format: String,
+    /// Model family (e.g., "llama")
+    #[serde(default)]
+    pub family: String,
+    /// Parameter count (e.g., "3.2B")
+    #[serde(default)]
+    pub parameter_size: String,
+    /// Quantization level (e.g., "Q4_K_M")
+    #[serde(default)]
+    pub quantization_level: String,
+}
+
+/// Details of a local model
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct OllamaModelInfo {
+    /// Modelfile the model was built from
+    #[serde(default)]
+    pub modelfile: String,
+    /// Default parameters
+    #[serde(default)]
+    pub parameters: String,
+    /// Prompt template
+    #[serde(default)]
+    pub template: String,
+    /// Format, family, size and quantization
+    #[serde(default)]
+    pub details: OllamaModelDetails,
+    /// Architecture metadata such as context length
+    #[serde(default)]
+    pub model_info: serde_json::Map<String, serde_json::Value>,
+    /// Capabilities such as "completion", "tools" and "thinking"
+    #[serde(default)]
+    pub capabilities: Vec<String>,
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_host_url() {
+        assert_eq!(host_url("127.0.0.1:11434"), "http://127.0.0.1:11434");
+        assert_eq!(host_url("gpu-box"), "http://gpu-box:11434");
+        assert_eq!(
+            host_url("https://ollama.internal/"),
+            "https://ollama.internal"
+        );
+    }
+
+    #[tokio::test]
+    async fn test_chat_translates_tool_calls() {
+        let mut server = mockito::Server::new_async().await;
+        let mock = server
+            .mock("POST", "/api/chat")
+            .match_body(mockito::Matcher::PartialJson(serde_json::json!({
+                "model": "llama3.2",
+                "stream": false,
+                "options": { "num_predict": 50 },
+                "messages": [
+                    { "role": "user", "content": "Weather in Paris?" },
+                    { "role": "assistant", "content": "", "tool_calls": [
+                        { "function": { "name": "weather", "arguments": { "city": "Paris" } } }
+                    ] },
+                    { "role": "tool", "content": "Sunny", "tool_name": "weather" }
+                ]
+            })))
+            .with_body(
+                serde_json::json!({
+                    "model": "llama3.2",
+                    "created_at": "2025-01-01T00:00:00Z",
+                    "message": { "role": "assistant", "content": "", "tool_calls": [
+                        { "function": { "name": "weather", "arguments": { "city": "Lyon" } } }
+                    ] },
+                    "done": true,
+                    "done_reason": "stop",
+                    "prompt_eval_count": 20,
+                    "eval_count": 5
+                })
+                .to_string(),
+            )
+            .create_async()
+            .await;
+
+        let previous = ToolCall {
+            id: "call_0".to_string(),
+            tool_type: "function".to_string(),
+            function: FunctionCall {
+                name: "weather".to_string(),
+                arguments: r#"{"city":"Paris"}"#.to_string(),
+            },
+        };
+        let request = CompletionRequest::new(
+            "llama3.2",
+            vec![
+                Message::user("Weather in Paris?"),
+                Message::assistant_with_tool_calls("", vec![previous]),
+                Message::tool("Sunny", "call_0"),
+            ],
+        )
+        .with_max_tokens(50);
+
+        let client = OllamaClient::new(OllamaConfig::new(server.url())).unwrap();
+        let response = client.complete(request).await.unwrap();
+
+        mock.assert_async().await;
+        let choice = &response.choices[0];
+        assert_eq!(choice.finish_reason.as_deref(), Some("tool_calls"));
+        let call = &choice.message.tool_calls.as_ref().unwrap()[0];
+        assert_eq!(call.function.name, "weather");
+        assert_eq!(call.function.arguments, r#"{"city":"Lyon"}"#);
+        assert_eq!(response.usage.total_tokens, 25);
+    }
+
+    #[tokio::test]
+    async fn test_stream_ndjson() {
+        let mut server = mockito::Server::new_async().await;
+        let lines = [
+            r#"{"model":"qwen3","created_at":"t","message":{"role":"assistant","content":"","thinking":"Simple sum."},"done":false}"#,
+            r#"{"model":"qwen3","created_at":"t","message":{"role":"assistant","content":"2 + 2 "},"done":false}"#,
+            r#"{"model":"qwen3","created_at":"t","message":{"role":"assistant","content":"= 4"},"done":false}"#,
+            r#"{"model":"qwen3","created_at":"t","message":{"role":"assistant","content":""},"done":true,"done_reason":"stop","prompt_eval_count":8,"eval_count":6}"#,
+        ];
+        server
+            .mock("POST", "/api/chat")
+            .match_body(mockito::Matcher::PartialJson(
+                serde_json::json!({ "stream": true, "think": true }),
+            ))
+            .with_header("content-type", "application/x-ndjson")
+            .with_body(lines.join("\n"))
+            .create_async()
+            .await;
+
+        let client = OllamaClient::new(OllamaConfig::new(server.url()).with_think(true)).unwrap();
+        let response = client
+            .stream(CompletionRequest::new(
+                "qwen3",
+                vec![Message::user("2 + 2?")],
+            ))
+            .await
+            .unwrap()
+            .collect_response()
+            .await
+            .unwrap();
+
+        let message = &response.choices[0].message;
+        assert_eq!(message.content, "2 + 2 = 4");
+        assert_eq!(message.reasoning.as_deref(), Some("Simple sum."));
+        assert_eq!(response.choices[0].finish_reason.as_deref(), Some("stop"));
+        assert_eq!(response.usage.total_tokens, 14);
+    }
+
+    #[tokio::test]
+    async fn test_model_management() {
+        let mut server = mockito::Server::new_async().await;
+        server
+            .mock("GET", "/api/version")
+            .with_body(r#"{"version":"0.6.2"}"#)
+            .create_async()
+            .await;
+        server
+            .mock("GET", "/api/tags")
+            .with_body(r#"{"models":[{"name":"llama3.2:latest","model":"llama3.2:latest","size":2019393189,"digest":"a80c","details":{"family":"llama","parameter_size":"3.2B","quantization_level":"Q4_K_M"}}]}"#)
+            .create_async()
+            .await;
+        let pull = server
+            .mock("POST", "/api/pull")
+            .match_body(mockito::Matcher::Json(
+                serde_json::json!({ "model": "llama3.2", "stream": false }),
+            ))
+            .with_body(r#"{"status":"success"}"#)
+            .create_async()
+            .await;
+        server
+            .mock("POST", "/api/show")
+            .with_body(r#"{"modelfile":"FROM llama3.2","details":{"family":"llama"},"model_info":{"llama.context_length":131072},"capabilities":["completion","tools"]}"#)
+            .create_async()
+            .await;
+
+        let client = OllamaClient::new(OllamaConfig::new(server.url())).unwrap();
+        assert_eq!(client.health_check().await.unwrap().version, "0.6.2");
+
+        let models = client.list_models().await.unwrap();
+        assert_eq!(models[0].details.parameter_size, "3.2B");
+
+        client.pull_model("llama3.2").await.unwrap();
+        pull.assert_async().await;
+
+        let info = client.show_model("llama3.2").await.unwrap();
+        assert!(info.capabilities.contains(&"tools".to_string()));
+        assert_eq!(info.model_info["llama.context_length"], 131072);
+    }
+}
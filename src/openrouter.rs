@@ -1,16 +1,23 @@
 //! OpenRouter API client implementation with streaming support
 
-use crate::config::OpenRouterConfig;
+use crate::config::{OpenRouterConfig, OptimizationTarget, ProviderPreferences};
+use crate::embeddings::{self, EmbeddingClient, EmbeddingRequest};
 use crate::error::{Error, Result};
 use crate::llm_client::LlmClient;
 use crate::types::TokenUsage;
 use async_trait::async_trait;
 use bytes::Bytes;
-use futures::stream::Stream;
+use chrono::{DateTime, NaiveDateTime, Utc};
+use futures::stream::{Stream, StreamExt};
+use rand_core::{OsRng, RngCore};
 use reqwest::Client;
 use serde::{Deserialize, Serialize};
+use std::borrow::Cow;
+use std::collections::BTreeMap;
 use std::pin::Pin;
+use std::sync::OnceLock;
 use std::task::{Context, Poll};
+use std::time::Duration;
 
 /// OpenRouter API client
 pub struct OpenRouterClient {
@@ -18,6 +25,8 @@ pub struct OpenRouterClient {
     client: Client,
     /// Configuration
     config: OpenRouterConfig,
+    /// Embedding length reported by the API
+    embedding_dimensions: OnceLock<usize>,
 }
 
 impl OpenRouterClient {
@@ -29,70 +38,282 @@ impl OpenRouterClient {
 
     /// Create a new OpenRouter client with the given configuration
     pub fn new(config: OpenRouterConfig) -> Result<Self> {
-        let client = Client::builder()
-            .timeout(config.timeout)
-            .build()?;
+        let client = Client::builder().timeout(config.timeout).build()?;
 
-        Ok(Self { client, config })
+        Ok(Self {
+            client,
+            config,
+            embedding_dimensions: OnceLock::new(),
+        })
     }
 
     /// Send a completion request
+    ///
+    /// Retries and fallback models are applied as described on
+    /// [`OpenRouterClient::send`]; the response's `model` names the model
+    /// that answered.
     pub async fn complete(&self, request: CompletionRequest) -> Result<CompletionResponse> {
-        let url = format!("{}/chat/completions", self.config.base_url);
-
-        let response = self
-            .client
-            .post(&url)
-            .header("Authorization", format!("Bearer {}", self.config.api_key()))
-            .header("X-Title", &self.config.app_name)
-            .json(&request)
-            .send()
-            .await?;
+        let (model, response) = self.send(request, "Request").await?;
 
-        if !response.status().is_success() {
-            let status = response.status();
-            let error_text = response.text().await.unwrap_or_else(|_| "Unknown error".to_string());
-            return Err(Error::openrouter(format!(
-                "Request failed with status {}: {}",
-                status, error_text
-            )));
+        let mut completion: CompletionResponse = response.json().await?;
+        if completion.model.is_empty() {
+            completion.model = model;
         }
-
-        let completion: CompletionResponse = response.json().await?;
         Ok(completion)
     }
 
     /// Stream a completion request
     pub async fn stream(&self, request: CompletionRequest) -> Result<CompletionStream> {
-        let url = format!("{}/chat/completions", self.config.base_url);
-
         let mut request_with_stream = request;
         request_with_stream.stream = true;
+        request_with_stream.stream_options = Some(StreamOptions {
+            include_usage: true,
+        });
+
+        let (_, response) = self.send(request_with_stream, "Stream request").await?;
+        Ok(CompletionStream::new(response.bytes_stream()))
+    }
+
+    /// Post a request, retrying and falling back to other models
+    ///
+    /// Rate limits (429), server errors (5xx), timeouts and connection
+    /// failures are retried up to `max_retries` times with jittered
+    /// exponential backoff, waiting for `Retry-After` when the server sends
+    /// it. Once retries are exhausted, or the server asks to wait longer than
+    /// [`MAX_BACKOFF`], the request moves on to the next of `fallback_models`, and once every model has failed the last error is
+    /// returned as [`Error::RetriesExhausted`]. Other errors are returned
+    /// immediately. Provider preferences from the config are sent unless the
+    /// request sets its own.
+    async fn send(
+        &self,
+        mut request: CompletionRequest,
+        what: &str,
+    ) -> Result<(String, reqwest::Response)> {
+        let url = format!("{}/chat/completions", self.config.base_url);
+        if request.provider.is_none() {
+            let routing = ProviderRouting::from(&self.config.provider_preferences);
+            request.provider = (!routing.is_empty()).then_some(routing);
+        }
+
+        let mut models = vec![request.model.clone()];
+        for model in &self.config.fallback_models {
+            if !models.contains(model) {
+                models.push(model.clone());
+            }
+        }
 
-        let response = self
+        let mut last_error = None;
+        let mut attempts = 0;
+        for model in models {
+            request.model = model.clone();
+
+            for attempt in 0..=self.config.max_retries {
+                attempts += 1;
+                let sent = self
+                    .client
+                    .post(&url)
+                    .header("Authorization", format!("Bearer {}", self.config.api_key()))
+                    .header("X-Title", &self.config.app_name)
+                    .json(&request)
+                    .send()
+                    .await;
+
+                let (error, retry_after) = match sent {
+                    Ok(response) if response.status().is_success() => return Ok((model, response)),
+                    Ok(response) => {
+                        let status = response.status();
+                        let retry_after = retry_after(&response);
+                        let error_text = response
+                            .text()
+                            .await
+                            .unwrap_or_else(|_| "Unknown error".to_string());
+                        let error =
+                            Error::api(status.as_u16(), format!("{} failed: {}", what, error_text));
+                        if status != reqwest::StatusCode::TOO_MANY_REQUESTS
+                            && !status.is_server_error()
+                        {
+                            return Err(error);
+                        }
+                        (error, retry_after)
+                    }
+                    Err(e) if e.is_timeout() || e.is_connect() => (e.into(), None),
+                    Err(e) => return Err(e.into()),
+                };
+
+                if let Some(delay) = retry_after.filter(|delay| *delay > MAX_BACKOFF) {
+                    tracing::warn!(
+                        "{} to {} failed: {}; server asked to wait {:?}, trying the next model",
+                        what,
+                        model,
+                        error,
+                        delay
+                    );
+                    last_error = Some(error);
+                    break;
+                }
+                if attempt < self.config.max_retries {
+                    let delay = retry_after.unwrap_or_else(|| self.backoff(attempt));
+                    tracing::warn!(
+                        "{} to {} failed (attempt {}): {}; retrying in {:?}",
+                        what,
+                        model,
+                        attempt + 1,
+                        error,
+                        delay
+                    );
+                    tokio::time::sleep(delay).await;
+                }
+                last_error = Some(error);
+            }
+
+            tracing::warn!(
+                "Model {} unavailable after {} retries",
+                model,
+                self.config.max_retries
+            );
+        }
+
+        Err(match last_error {
+            Some(last) if attempts > 1 => Error::RetriesExhausted {
+                attempts,
+                last: Box::new(last),
+            },
+            Some(last) => last,
+            None => Error::openrouter(format!("{} was never sent", what)),
+        })
+    }
+
+    /// Jittered delay before retry number `attempt + 1`
+    fn backoff(&self, attempt: u32) -> Duration {
+        let delay = self
+            .config
+            .retry_backoff
+            .saturating_mul(1 << attempt.min(16))
+            .min(MAX_BACKOFF);
+        // Wait somewhere between half and all of the delay
+        let jitter = OsRng.next_u32() as f64 / u32::MAX as f64;
+        delay.mul_f64(0.5 + jitter / 2.0)
+    }
+
+    /// Get the configuration
+    pub fn config(&self) -> &OpenRouterConfig {
+        &self.config
+    }
+}
+
+#[async_trait]
+impl EmbeddingClient for OpenRouterClient {
+    async fn embed_batch(&self, texts: &[String]) -> Result<Vec<Vec<f32>>> {
+        let url = format!(
+            "{}/embeddings",
+            self.config.base_url.as_str().trim_end_matches('/')
+        );
+        let body = EmbeddingRequest {
+            model: &self.config.embedding_model,
+            input: texts,
+            dimensions: self.config.embedding_dimensions,
+            encoding_format: "float",
+        };
+
+        let http_request = self
             .client
             .post(&url)
             .header("Authorization", format!("Bearer {}", self.config.api_key()))
             .header("X-Title", &self.config.app_name)
-            .json(&request_with_stream)
-            .send()
-            .await?;
+            .json(&body);
 
-        if !response.status().is_success() {
-            let status = response.status();
-            let error_text = response.text().await.unwrap_or_else(|_| "Unknown error".to_string());
-            return Err(Error::openrouter(format!(
-                "Stream request failed with status {}: {}",
-                status, error_text
-            )));
+        let vectors = embeddings::send(http_request, "OpenRouter", texts.len()).await?;
+        if let Some(vector) = vectors.first() {
+            let _ = self.embedding_dimensions.set(vector.len());
         }
+        Ok(vectors)
+    }
 
-        Ok(CompletionStream::new(response.bytes_stream()))
+    fn dimensions(&self) -> Option<usize> {
+        self.config
+            .embedding_dimensions
+            .or_else(|| self.embedding_dimensions.get().copied())
     }
 
-    /// Get the configuration
-    pub fn config(&self) -> &OpenRouterConfig {
-        &self.config
+    fn model_id(&self) -> &str {
+        &self.config.embedding_model
+    }
+}
+
+/// Longest delay between retries of one model
+///
+/// Computed backoff is capped to it, and a longer `Retry-After` is not
+/// waited for at all.
+const MAX_BACKOFF: Duration = Duration::from_secs(30);
+
+/// Delay requested by a `Retry-After` header
+fn retry_after(response: &reqwest::Response) -> Option<Duration> {
+    let value = response
+        .headers()
+        .get(reqwest::header::RETRY_AFTER)?
+        .to_str()
+        .ok()?;
+    parse_retry_after(value, Utc::now())
+}
+
+/// Parse `Retry-After` as delta-seconds or as an HTTP-date relative to `now`
+///
+/// HTTP-dates may use the IMF-fixdate, RFC 850 or asctime forms. A date in
+/// the past asks for no delay.
+fn parse_retry_after(value: &str, now: DateTime<Utc>) -> Option<Duration> {
+    let value = value.trim();
+    if let Ok(secs) = value.parse::<f64>() {
+        return Duration::try_from_secs_f64(secs).ok();
+    }
+
+    let date = DateTime::parse_from_rfc2822(value)
+        .map(|date| date.with_timezone(&Utc))
+        .or_else(|_| {
+            NaiveDateTime::parse_from_str(value, "%A, %d-%b-%y %H:%M:%S GMT")
+                .or_else(|_| NaiveDateTime::parse_from_str(value, "%a %b %e %H:%M:%S %Y"))
+                .map(|date| date.and_utc())
+        })
+        .ok()?;
+    Some((date - now).to_std().unwrap_or(Duration::ZERO))
+}
+
+/// OpenRouter's `provider` routing object
+///
+/// See <https://openrouter.ai/docs/features/provider-routing>.
+#[derive(Debug, Clone, Default, PartialEq, Serialize, Deserialize)]
+pub struct ProviderRouting {
+    /// Providers to try first, in order
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub order: Vec<String>,
+    /// Providers never to use
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub ignore: Vec<String>,
+    /// Sort providers by `price`, `throughput` or `latency`
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub sort: Option<String>,
+}
+
+impl ProviderRouting {
+    /// Whether the routing leaves everything to OpenRouter's defaults
+    pub fn is_empty(&self) -> bool {
+        self.order.is_empty() && self.ignore.is_empty() && self.sort.is_none()
+    }
+}
+
+impl From<&ProviderPreferences> for ProviderRouting {
+    fn from(preferences: &ProviderPreferences) -> Self {
+        let sort = match preferences.optimization {
+            OptimizationTarget::LowerCost => Some("price".to_string()),
+            OptimizationTarget::Performance => Some("throughput".to_string()),
+            // OpenRouter balances price and uptime by default
+            OptimizationTarget::Balanced => None,
+        };
+
+        Self {
+            order: preferences.preferred.clone(),
+            ignore: preferences.excluded.clone(),
+            sort,
+        }
     }
 }
 
@@ -118,15 +339,27 @@ pub struct CompletionRequest {
     /// Presence penalty
     #[serde(skip_serializing_if = "Option::is_none")]
     pub presence_penalty: Option<f32>,
+    /// Stop sequences
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub stop: Option<Vec<String>>,
     /// Whether to stream the response
     #[serde(default)]
     pub stream: bool,
+    /// Streaming options
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub stream_options: Option<StreamOptions>,
     /// Tools available to the model
     #[serde(skip_serializing_if = "Option::is_none")]
     pub tools: Option<Vec<ToolDefinition>>,
     /// Tool choice behavior
     #[serde(skip_serializing_if = "Option::is_none")]
     pub tool_choice: Option<ToolChoice>,
+    /// Provider routing (OpenRouter only)
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub provider: Option<ProviderRouting>,
+    /// Format the reply must follow
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub response_format: Option<ResponseFormat>,
 }
 
 impl CompletionRequest {
@@ -140,9 +373,13 @@ impl CompletionRequest {
             top_p: None,
             frequency_penalty: None,
             presence_penalty: None,
+            stop: None,
             stream: false,
+            stream_options: None,
             tools: None,
             tool_choice: None,
+            provider: None,
+            response_format: None,
         }
     }
 
@@ -158,6 +395,12 @@ impl CompletionRequest {
         self
     }
 
+    /// Set the stop sequences
+    pub fn with_stop(mut self, stop: Vec<String>) -> Self {
+        self.stop = Some(stop);
+        self
+    }
+
     /// Enable streaming
     pub fn with_stream(mut self, stream: bool) -> Self {
         self.stream = stream;
@@ -175,6 +418,18 @@ impl CompletionRequest {
         self.tool_choice = Some(tool_choice);
         self
     }
+
+    /// Set the provider routing, overriding the client's preferences
+    pub fn with_provider(mut self, provider: ProviderRouting) -> Self {
+        self.provider = Some(provider);
+        self
+    }
+
+    /// Set the response format
+    pub fn with_response_format(mut self, response_format: ResponseFormat) -> Self {
+        self.response_format = Some(response_format);
+        self
+    }
 }
 
 /// Message in a conversation
@@ -183,7 +438,8 @@ pub struct Message {
     /// Role of the message sender
     pub role: Role,
     /// Content of the message
-    pub content: String,
+    #[serde(default, deserialize_with = "deserialize_nullable_content")]
+    pub content: MessageContent,
     /// Optional name of the sender
     #[serde(skip_serializing_if = "Option::is_none")]
     pub name: Option<String>,
@@ -193,6 +449,40 @@ pub struct Message {
     /// Optional tool call ID (for tool messages)
     #[serde(skip_serializing_if = "Option::is_none")]
     pub tool_call_id: Option<String>,
+    /// Reasoning the model produced before its answer (for assistant messages)
+    #[serde(
+        default,
+        alias = "reasoning_content",
+        skip_serializing_if = "Option::is_none"
+    )]
+    pub reasoning: Option<String>,
+    /// Reasoning blocks as the provider returned them, to send back unchanged
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub reasoning_details: Vec<ReasoningDetail>,
+}
+
+/// One block of provider reasoning
+///
+/// Providers such as Anthropic only accept earlier reasoning back if every
+/// block is returned as it was produced, each with its own signature.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(tag = "type")]
+pub enum ReasoningDetail {
+    /// Reasoning text
+    #[serde(rename = "reasoning.text")]
+    Text {
+        /// Reasoning text
+        text: String,
+        /// Signature authenticating the text
+        #[serde(default, skip_serializing_if = "Option::is_none")]
+        signature: Option<String>,
+    },
+    /// Reasoning the provider withheld, as opaque data
+    #[serde(rename = "reasoning.encrypted")]
+    Encrypted {
+        /// Encrypted reasoning
+        data: String,
+    },
 }
 
 impl Message {
@@ -200,10 +490,12 @@ impl Message {
     pub fn system(content: impl Into<String>) -> Self {
         Self {
             role: Role::System,
-            content: content.into(),
+            content: MessageContent::Text(content.into()),
             name: None,
             tool_calls: None,
             tool_call_id: None,
+            reasoning: None,
+            reasoning_details: Vec::new(),
         }
     }
 
@@ -211,10 +503,12 @@ impl Message {
     pub fn user(content: impl Into<String>) -> Self {
         Self {
             role: Role::User,
-            content: content.into(),
+            content: MessageContent::Text(content.into()),
             name: None,
             tool_calls: None,
             tool_call_id: None,
+            reasoning: None,
+            reasoning_details: Vec::new(),
         }
     }
 
@@ -222,10 +516,28 @@ impl Message {
     pub fn assistant(content: impl Into<String>) -> Self {
         Self {
             role: Role::Assistant,
-            content: content.into(),
+            content: MessageContent::Text(content.into()),
             name: None,
             tool_calls: None,
             tool_call_id: None,
+            reasoning: None,
+            reasoning_details: Vec::new(),
+        }
+    }
+
+    /// Create an assistant message that requests tool calls
+    pub fn assistant_with_tool_calls(
+        content: impl Into<String>,
+        tool_calls: Vec<ToolCall>,
+    ) -> Self {
+        Self {
+            role: Role::Assistant,
+            content: MessageContent::Text(content.into()),
+            name: None,
+            tool_calls: Some(tool_calls),
+            tool_call_id: None,
+            reasoning: None,
+            reasoning_details: Vec::new(),
         }
     }
 
@@ -233,12 +545,264 @@ impl Message {
     pub fn tool(content: impl Into<String>, tool_call_id: impl Into<String>) -> Self {
         Self {
             role: Role::Tool,
-            content: content.into(),
+            content: MessageContent::Text(content.into()),
             name: None,
             tool_calls: None,
             tool_call_id: Some(tool_call_id.into()),
+            reasoning: None,
+            reasoning_details: Vec::new(),
+        }
+    }
+
+    /// Create a user message from content parts, such as text and images
+    pub fn user_parts(parts: Vec<ContentPart>) -> Self {
+        Self {
+            content: MessageContent::Parts(parts),
+            ..Self::user("")
         }
     }
+
+    /// Append a content part, such as an image, to the message
+    pub fn with_part(mut self, part: ContentPart) -> Self {
+        self.content.push(part);
+        self
+    }
+
+    /// Attach the reasoning behind an assistant message
+    pub fn with_reasoning(mut self, reasoning: impl Into<String>) -> Self {
+        self.reasoning = Some(reasoning.into());
+        self
+    }
+
+    /// Attach the provider's reasoning blocks, kept to be sent back unchanged
+    pub fn with_reasoning_details(mut self, details: Vec<ReasoningDetail>) -> Self {
+        self.reasoning_details = details;
+        self
+    }
+}
+
+/// Assistant messages that only carry tool calls have `"content": null`
+fn deserialize_nullable_content<'de, D>(
+    deserializer: D,
+) -> std::result::Result<MessageContent, D::Error>
+where
+    D: serde::Deserializer<'de>,
+{
+    Ok(Option::<MessageContent>::deserialize(deserializer)?.unwrap_or_default())
+}
+
+/// Content of a message: plain text, or typed parts for multimodal models
+///
+/// Text-only content serializes as a plain string, which every
+/// OpenAI-compatible server accepts.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(untagged)]
+pub enum MessageContent {
+    /// Plain text
+    Text(String),
+    /// Text, image and file parts in order
+    Parts(Vec<ContentPart>),
+}
+
+impl MessageContent {
+    /// Text of the message, with text parts joined by newlines
+    pub fn text(&self) -> Cow<'_, str> {
+        match self {
+            Self::Text(text) => Cow::Borrowed(text),
+            Self::Parts(parts) => {
+                let texts: Vec<&str> = parts
+                    .iter()
+                    .filter_map(|part| match part {
+                        ContentPart::Text { text } => Some(text.as_str()),
+                        _ => None,
+                    })
+                    .collect();
+                match texts.as_slice() {
+                    [text] => Cow::Borrowed(text),
+                    _ => Cow::Owned(texts.join("\n")),
+                }
+            }
+        }
+    }
+
+    /// Whether there is no text and no other parts
+    pub fn is_empty(&self) -> bool {
+        match self {
+            Self::Text(text) => text.is_empty(),
+            Self::Parts(parts) => parts.is_empty(),
+        }
+    }
+
+    /// Content as parts; plain text becomes a single text part
+    pub fn parts(&self) -> Vec<ContentPart> {
+        match self {
+            Self::Text(text) if text.is_empty() => Vec::new(),
+            Self::Text(text) => vec![ContentPart::text(text.clone())],
+            Self::Parts(parts) => parts.clone(),
+        }
+    }
+
+    /// Append a part, turning plain text into parts
+    pub fn push(&mut self, part: ContentPart) {
+        let mut parts = self.parts();
+        parts.push(part);
+        *self = Self::Parts(parts);
+    }
+}
+
+impl Default for MessageContent {
+    fn default() -> Self {
+        Self::Text(String::new())
+    }
+}
+
+impl std::fmt::Display for MessageContent {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.write_str(&self.text())
+    }
+}
+
+impl From<String> for MessageContent {
+    fn from(text: String) -> Self {
+        Self::Text(text)
+    }
+}
+
+impl From<&str> for MessageContent {
+    fn from(text: &str) -> Self {
+        Self::Text(text.to_string())
+    }
+}
+
+impl From<Vec<ContentPart>> for MessageContent {
+    fn from(parts: Vec<ContentPart>) -> Self {
+        Self::Parts(parts)
+    }
+}
+
+impl PartialEq<str> for MessageContent {
+    fn eq(&self, other: &str) -> bool {
+        self.text() == other
+    }
+}
+
+impl PartialEq<&str> for MessageContent {
+    fn eq(&self, other: &&str) -> bool {
+        self.text() == *other
+    }
+}
+
+/// One part of a multimodal message, in the OpenAI content-part format
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(tag = "type", rename_all = "snake_case")]
+pub enum ContentPart {
+    /// Text
+    Text {
+        /// The text
+        text: String,
+    },
+    /// Image by URL or base64 `data:` URL
+    ImageUrl {
+        /// Image location
+        image_url: ImageUrl,
+    },
+    /// File such as a PDF, as a base64 `data:` URL (OpenRouter)
+    File {
+        /// File name and contents
+        file: FileData,
+    },
+}
+
+impl ContentPart {
+    /// Create a text part
+    pub fn text(text: impl Into<String>) -> Self {
+        Self::Text { text: text.into() }
+    }
+
+    /// Create an image part from a URL
+    pub fn image_url(url: impl Into<String>) -> Self {
+        Self::ImageUrl {
+            image_url: ImageUrl::new(url),
+        }
+    }
+
+    /// Create an image part from base64 data, e.g. `("image/png", data)`
+    pub fn image_base64(media_type: &str, data: &str) -> Self {
+        Self::ImageUrl {
+            image_url: ImageUrl::base64(media_type, data),
+        }
+    }
+
+    /// Create a file part from base64 data, e.g. `("report.pdf", "application/pdf", data)`
+    pub fn file_base64(filename: impl Into<String>, media_type: &str, data: &str) -> Self {
+        Self::File {
+            file: FileData {
+                filename: filename.into(),
+                file_data: data_url(media_type, data),
+            },
+        }
+    }
+}
+
+/// Image location for an image part
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct ImageUrl {
+    /// HTTP(S) URL or base64 `data:` URL
+    pub url: String,
+    /// Resolution hint: "low", "high" or "auto"
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub detail: Option<String>,
+}
+
+impl ImageUrl {
+    /// Image at a URL
+    pub fn new(url: impl Into<String>) -> Self {
+        Self {
+            url: url.into(),
+            detail: None,
+        }
+    }
+
+    /// Image from base64 data with its media type
+    pub fn base64(media_type: &str, data: &str) -> Self {
+        Self::new(data_url(media_type, data))
+    }
+
+    /// Set the resolution hint
+    pub fn with_detail(mut self, detail: impl Into<String>) -> Self {
+        self.detail = Some(detail.into());
+        self
+    }
+
+    /// Media type and base64 data, if this is a `data:` URL
+    pub fn as_base64(&self) -> Option<(&str, &str)> {
+        parse_data_url(&self.url)
+    }
+}
+
+/// File contents for a file part
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct FileData {
+    /// File name shown to the model
+    pub filename: String,
+    /// Base64 `data:` URL
+    pub file_data: String,
+}
+
+impl FileData {
+    /// Media type and base64 data, if the contents are a `data:` URL
+    pub fn as_base64(&self) -> Option<(&str, &str)> {
+        parse_data_url(&self.file_data)
+    }
+}
+
+fn data_url(media_type: &str, data: &str) -> String {
+    format!("data:{};base64,{}", media_type, data)
+}
+
+/// Split a base64 `data:` URL into media type and data
+fn parse_data_url(url: &str) -> Option<(&str, &str)> {
+    url.strip_prefix("data:")?.split_once(";base64,")
 }
 
 /// Role of a message sender
@@ -265,6 +829,24 @@ pub struct ToolDefinition {
     pub function: FunctionDefinition,
 }
 
+impl ToolDefinition {
+    /// Create a function tool definition
+    pub fn function(
+        name: impl Into<String>,
+        description: impl Into<String>,
+        parameters: serde_json::Value,
+    ) -> Self {
+        Self {
+            tool_type: "function".to_string(),
+            function: FunctionDefinition {
+                name: name.into(),
+                description: description.into(),
+                parameters,
+            },
+        }
+    }
+}
+
 /// Function definition for tool calling
 #[derive(Debug, Clone, Serialize, Deserialize)]
 pub struct FunctionDefinition {
@@ -297,6 +879,58 @@ pub struct FunctionChoice {
     pub name: String,
 }
 
+/// Format the model's reply must follow
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(tag = "type", rename_all = "snake_case")]
+pub enum ResponseFormat {
+    /// Free-form text
+    Text,
+    /// Any JSON object
+    JsonObject,
+    /// JSON matching a schema
+    JsonSchema {
+        /// Schema the reply must match
+        json_schema: JsonSchemaFormat,
+    },
+}
+
+impl ResponseFormat {
+    /// JSON matching `schema`
+    pub fn json_schema(name: impl Into<String>, schema: serde_json::Value) -> Self {
+        Self::JsonSchema {
+            json_schema: JsonSchemaFormat {
+                name: name.into(),
+                description: None,
+                schema,
+                strict: None,
+            },
+        }
+    }
+
+    /// Schema the reply must match, if any
+    pub fn schema(&self) -> Option<&serde_json::Value> {
+        match self {
+            Self::JsonSchema { json_schema } => Some(&json_schema.schema),
+            _ => None,
+        }
+    }
+}
+
+/// Named JSON Schema for structured output
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct JsonSchemaFormat {
+    /// Schema name (letters, digits, `_` and `-`)
+    pub name: String,
+    /// What the output represents
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub description: Option<String>,
+    /// JSON Schema of the output
+    pub schema: serde_json::Value,
+    /// Whether the provider must follow the schema exactly
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub strict: Option<bool>,
+}
+
 /// Tool call from the assistant
 #[derive(Debug, Clone, Serialize, Deserialize)]
 pub struct ToolCall {
@@ -323,8 +957,11 @@ pub struct FunctionCall {
 pub struct CompletionResponse {
     /// Unique identifier
     pub id: String,
-    /// Model used
+    /// Model that answered
     pub model: String,
+    /// Provider that served the model, if reported
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub provider: Option<String>,
     /// Choices
     pub choices: Vec<Choice>,
     /// Token usage
@@ -343,7 +980,7 @@ pub struct Choice {
 }
 
 /// Token usage information
-#[derive(Debug, Clone, Serialize, Deserialize)]
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
 pub struct Usage {
     /// Prompt tokens
     pub prompt_tokens: u64,
@@ -363,11 +1000,17 @@ impl From<Usage> for TokenUsage {
 #[derive(Debug, Clone, Serialize, Deserialize)]
 pub struct StreamChunk {
     /// Unique identifier
+    #[serde(default)]
     pub id: String,
     /// Model used
+    #[serde(default)]
     pub model: String,
     /// Choices
+    #[serde(default)]
     pub choices: Vec<StreamChoice>,
+    /// Token usage (only on the final chunk)
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub usage: Option<Usage>,
 }
 
 /// Choice in stream chunk
@@ -382,7 +1025,7 @@ pub struct StreamChoice {
 }
 
 /// Delta (incremental content) in stream chunk
-#[derive(Debug, Clone, Serialize, Deserialize)]
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
 pub struct Delta {
     /// Role (only in first chunk)
     #[serde(skip_serializing_if = "Option::is_none")]
@@ -392,27 +1035,406 @@ pub struct Delta {
     pub content: Option<String>,
     /// Tool calls delta
     #[serde(skip_serializing_if = "Option::is_none")]
-    pub tool_calls: Option<Vec<ToolCall>>,
+    pub tool_calls: Option<Vec<ToolCallDelta>>,
+    /// Reasoning delta
+    #[serde(
+        default,
+        alias = "reasoning_content",
+        skip_serializing_if = "Option::is_none"
+    )]
+    pub reasoning: Option<String>,
+    /// Reasoning blocks; a text block without a signature is continued by
+    /// the next text block
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub reasoning_details: Option<Vec<ReasoningDetail>>,
+}
+
+/// Incremental tool call in a stream chunk
+///
+/// The ID and function name arrive with the first fragment for a given
+/// `index`; later fragments only carry more of the argument string.
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+pub struct ToolCallDelta {
+    /// Position of the tool call in the assistant message
+    #[serde(default)]
+    pub index: u32,
+    /// Tool call ID
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub id: Option<String>,
+    /// Type (always "function")
+    #[serde(rename = "type", skip_serializing_if = "Option::is_none")]
+    pub tool_type: Option<String>,
+    /// Function name and argument fragment
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub function: Option<FunctionCallDelta>,
+}
+
+/// Incremental function call details
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+pub struct FunctionCallDelta {
+    /// Function name
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub name: Option<String>,
+    /// Fragment of the JSON argument string
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub arguments: Option<String>,
+}
+
+/// Streaming options sent with streamed requests
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct StreamOptions {
+    /// Ask the server to send token usage on the final chunk
+    pub include_usage: bool,
+}
+
+/// Assembles streamed chunks back into a complete response
+#[derive(Debug, Clone, Default)]
+pub struct StreamAccumulator {
+    id: String,
+    model: String,
+    content: String,
+    reasoning: String,
+    reasoning_details: Vec<ReasoningDetail>,
+    tool_calls: BTreeMap<u32, ToolCall>,
+    finish_reason: Option<String>,
+    usage: Option<Usage>,
+}
+
+impl StreamAccumulator {
+    /// Create an empty accumulator
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Fold a chunk into the accumulated response
+    pub fn push(&mut self, chunk: &StreamChunk) {
+        if self.id.is_empty() {
+            self.id = chunk.id.clone();
+        }
+        if self.model.is_empty() {
+            self.model = chunk.model.clone();
+        }
+        if let Some(usage) = &chunk.usage {
+            self.usage = Some(usage.clone());
+        }
+
+        for choice in chunk.choices.iter().filter(|c| c.index == 0) {
+            if let Some(content) = &choice.delta.content {
+                self.content.push_str(content);
+            }
+            if let Some(reasoning) = &choice.delta.reasoning {
+                self.reasoning.push_str(reasoning);
+            }
+            for detail in choice.delta.reasoning_details.iter().flatten() {
+                self.push_reasoning_detail(detail.clone());
+            }
+            if let Some(reason) = &choice.finish_reason {
+                self.finish_reason = Some(reason.clone());
+            }
+
+            for delta in choice.delta.tool_calls.iter().flatten() {
+                let call = self
+                    .tool_calls
+                    .entry(delta.index)
+                    .or_insert_with(|| ToolCall {
+                        id: String::new(),
+                        tool_type: "function".to_string(),
+                        function: FunctionCall {
+                            name: String::new(),
+                            arguments: String::new(),
+                        },
+                    });
+
+                if let Some(id) = &delta.id {
+                    call.id = id.clone();
+                }
+                if let Some(tool_type) = &delta.tool_type {
+                    call.tool_type = tool_type.clone();
+                }
+                if let Some(function) = &delta.function {
+                    if let Some(name) = &function.name {
+                        call.function.name.push_str(name);
+                    }
+                    if let Some(arguments) = &function.arguments {
+                        call.function.arguments.push_str(arguments);
+                    }
+                }
+            }
+        }
+    }
+
+    /// Append a reasoning block, continuing an unsigned text block
+    fn push_reasoning_detail(&mut self, detail: ReasoningDetail) {
+        if let (
+            Some(ReasoningDetail::Text {
+                text,
+                signature: signature @ None,
+            }),
+            ReasoningDetail::Text {
+                text: more,
+                signature: more_signature,
+            },
+        ) = (self.reasoning_details.last_mut(), &detail)
+        {
+            text.push_str(more);
+            *signature = more_signature.clone();
+            return;
+        }
+        self.reasoning_details.push(detail);
+    }
+
+    /// Content received so far
+    pub fn content(&self) -> &str {
+        &self.content
+    }
+
+    /// Token usage, once the final chunk has arrived
+    pub fn usage(&self) -> Option<&Usage> {
+        self.usage.as_ref()
+    }
+
+    /// Build the complete response
+    pub fn finish(self) -> CompletionResponse {
+        let tool_calls: Vec<ToolCall> = self.tool_calls.into_values().collect();
+        let mut message = if tool_calls.is_empty() {
+            Message::assistant(self.content)
+        } else {
+            Message::assistant_with_tool_calls(self.content, tool_calls)
+        };
+        if !self.reasoning.is_empty() {
+            message = message.with_reasoning(self.reasoning);
+        }
+        message = message.with_reasoning_details(self.reasoning_details);
+
+        CompletionResponse {
+            id: self.id,
+            model: self.model,
+            provider: None,
+            choices: vec![Choice {
+                index: 0,
+                message,
+                finish_reason: self.finish_reason,
+            }],
+            usage: self.usage.unwrap_or_default(),
+        }
+    }
+}
+
+/// Incremental decoder for `text/event-stream` bodies
+#[derive(Debug, Default)]
+pub(crate) struct SseDecoder {
+    buffer: Vec<u8>,
+}
+
+impl SseDecoder {
+    pub(crate) fn push(&mut self, bytes: &[u8]) {
+        self.buffer.extend_from_slice(bytes);
+    }
+
+    /// Pop the data payload of the next complete event, if any
+    pub(crate) fn next_event(&mut self) -> Option<String> {
+        loop {
+            let (end, separator_len) = find_event_boundary(&self.buffer)?;
+            let raw: Vec<u8> = self.buffer.drain(..end + separator_len).take(end).collect();
+            if let Some(data) = event_data(&raw) {
+                return Some(data);
+            }
+        }
+    }
+
+    /// Treat whatever remains once the body ends as a final event
+    pub(crate) fn finish(&mut self) -> Option<String> {
+        let raw = std::mem::take(&mut self.buffer);
+        event_data(&raw)
+    }
+}
+
+fn find_event_boundary(buffer: &[u8]) -> Option<(usize, usize)> {
+    (0..buffer.len()).find_map(|i| {
+        if buffer[i..].starts_with(b"\r\n\r\n") {
+            Some((i, 4))
+        } else if buffer[i..].starts_with(b"\n\n") || buffer[i..].starts_with(b"\r\r") {
+            Some((i, 2))
+        } else {
+            None
+        }
+    })
+}
+
+/// Join the `data:` lines of an event, skipping comments and other fields
+fn event_data(raw: &[u8]) -> Option<String> {
+    let text = String::from_utf8_lossy(raw);
+    let lines: Vec<&str> = text
+        .lines()
+        .filter_map(|line| line.strip_prefix("data:"))
+        .map(|data| data.strip_prefix(' ').unwrap_or(data))
+        .collect();
+
+    if lines.is_empty() {
+        None
+    } else {
+        Some(lines.join("\n"))
+    }
+}
+
+/// Decode one SSE data payload into a chunk, `None` for the `[DONE]` sentinel
+fn decode_event(data: &str) -> Option<Result<StreamChunk>> {
+    let data = data.trim();
+    if data == "[DONE]" {
+        return None;
+    }
+
+    let value: serde_json::Value = match serde_json::from_str(data) {
+        Ok(value) => value,
+        Err(e) => return Some(Err(e.into())),
+    };
+
+    if let Some(error) = value.get("error") {
+        let message = error
+            .get("message")
+            .and_then(|m| m.as_str())
+            .map(str::to_string)
+            .unwrap_or_else(|| error.to_string());
+        return Some(Err(Error::openrouter(format!("Stream error: {}", message))));
+    }
+
+    Some(serde_json::from_value(value).map_err(Error::from))
+}
+
+/// Where a [`CompletionStream`] reads its chunks from
+enum Source {
+    /// OpenAI-compatible `text/event-stream` body
+    Sse {
+        inner: Pin<Box<dyn Stream<Item = reqwest::Result<Bytes>> + Send>>,
+        decoder: SseDecoder,
+    },
+    /// Chunks already translated from a provider's native format
+    Chunks(Pin<Box<dyn Stream<Item = Result<StreamChunk>> + Send>>),
 }
 
 /// Streaming completion response
 pub struct CompletionStream {
-    inner: Pin<Box<dyn Stream<Item = reqwest::Result<Bytes>> + Send>>,
-    buffer: String,
+    source: Source,
+    usage: Option<Usage>,
+    done: bool,
 }
 
 impl CompletionStream {
     pub(crate) fn new(stream: impl Stream<Item = reqwest::Result<Bytes>> + Send + 'static) -> Self {
         Self {
-            inner: Box::pin(stream),
-            buffer: String::new(),
+            source: Source::Sse {
+                inner: Box::pin(stream),
+                decoder: SseDecoder::default(),
+            },
+            usage: None,
+            done: false,
+        }
+    }
+
+    /// Stream chunks produced by a client with its own wire format
+    pub(crate) fn from_chunks(
+        chunks: impl Stream<Item = Result<StreamChunk>> + Send + 'static,
+    ) -> Self {
+        Self {
+            source: Source::Chunks(Box::pin(chunks)),
+            usage: None,
+            done: false,
         }
     }
 
+    /// Replay a complete response as a stream of chunks
+    ///
+    /// Useful for clients that cannot stream natively and for tests.
+    pub fn from_response(response: CompletionResponse) -> Self {
+        let chunks: Vec<StreamChunk> = response
+            .choices
+            .into_iter()
+            .map(|choice| StreamChunk {
+                id: response.id.clone(),
+                model: response.model.clone(),
+                choices: vec![StreamChoice {
+                    index: choice.index,
+                    delta: Delta {
+                        role: Some(choice.message.role),
+                        content: Some(choice.message.content.to_string()),
+                        tool_calls: choice.message.tool_calls.map(|calls| {
+                            calls
+                                .into_iter()
+                                .enumerate()
+                                .map(|(index, call)| ToolCallDelta {
+                                    index: index as u32,
+                                    id: Some(call.id),
+                                    tool_type: Some(call.tool_type),
+                                    function: Some(FunctionCallDelta {
+                                        name: Some(call.function.name),
+                                        arguments: Some(call.function.arguments),
+                                    }),
+                                })
+                                .collect()
+                        }),
+                        reasoning: choice.message.reasoning,
+                        reasoning_details: Some(choice.message.reasoning_details)
+                            .filter(|details| !details.is_empty()),
+                    },
+                    finish_reason: choice.finish_reason,
+                }],
+                usage: None,
+            })
+            .chain(std::iter::once(StreamChunk {
+                id: response.id.clone(),
+                model: response.model.clone(),
+                choices: Vec::new(),
+                usage: Some(response.usage),
+            }))
+            .collect();
+
+        let mut body = String::new();
+        for chunk in &chunks {
+            if let Ok(json) = serde_json::to_string(chunk) {
+                body.push_str(&format!("data: {}\n\n", json));
+            }
+        }
+        body.push_str("data: [DONE]\n\n");
+
+        Self::new(futures::stream::iter(vec![Ok(Bytes::from(body))]))
+    }
+
     /// Get the next chunk from the stream
     pub async fn next_chunk(&mut self) -> Option<Result<StreamChunk>> {
-        // Simplified streaming implementation - in production would need proper SSE parsing
-        None
+        self.next().await
+    }
+
+    /// Token usage reported by the final chunk, once it has been received
+    pub fn usage(&self) -> Option<&Usage> {
+        self.usage.as_ref()
+    }
+
+    /// Drain the stream and assemble the complete response
+    pub async fn collect_response(mut self) -> Result<CompletionResponse> {
+        let mut accumulator = StreamAccumulator::new();
+        while let Some(chunk) = self.next_chunk().await {
+            accumulator.push(&chunk?);
+        }
+        Ok(accumulator.finish())
+    }
+
+    fn handle_event(&mut self, data: &str) -> Option<Result<StreamChunk>> {
+        match decode_event(data) {
+            Some(Ok(chunk)) => {
+                if let Some(usage) = &chunk.usage {
+                    self.usage = Some(usage.clone());
+                }
+                Some(Ok(chunk))
+            }
+            Some(Err(e)) => {
+                self.done = true;
+                Some(Err(e))
+            }
+            None => {
+                self.done = true;
+                None
+            }
+        }
     }
 }
 
@@ -420,8 +1442,45 @@ impl Stream for CompletionStream {
     type Item = Result<StreamChunk>;
 
     fn poll_next(mut self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<Option<Self::Item>> {
-        // Simplified implementation - in production would parse SSE events
-        Poll::Ready(None)
+        loop {
+            if self.done {
+                return Poll::Ready(None);
+            }
+
+            let (inner, decoder) = match &mut self.source {
+                Source::Sse { inner, decoder } => (inner, decoder),
+                Source::Chunks(chunks) => {
+                    let item = futures::ready!(chunks.as_mut().poll_next(cx));
+                    match &item {
+                        Some(Ok(chunk)) => {
+                            if let Some(usage) = &chunk.usage {
+                                self.usage = Some(usage.clone());
+                            }
+                        }
+                        Some(Err(_)) | None => self.done = true,
+                    }
+                    return Poll::Ready(item);
+                }
+            };
+
+            if let Some(data) = decoder.next_event() {
+                return Poll::Ready(self.handle_event(&data));
+            }
+
+            match inner.as_mut().poll_next(cx) {
+                Poll::Ready(Some(Ok(bytes))) => decoder.push(&bytes),
+                Poll::Ready(Some(Err(e))) => {
+                    self.done = true;
+                    return Poll::Ready(Some(Err(e.into())));
+                }
+                Poll::Ready(None) => {
+                    let remaining = decoder.finish();
+                    self.done = true;
+                    return Poll::Ready(remaining.and_then(|data| self.handle_event(&data)));
+                }
+                Poll::Pending => return Poll::Pending,
+            }
+        }
     }
 }
 
@@ -445,3 +1504,343 @@ impl LlmClient for OpenRouterClient {
         self.config.base_url.as_str()
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::config::{OpenRouterConfig, OptimizationTarget, ProviderPreferences};
+    use crate::vllm::{VllmClient, VllmConfig};
+
+    fn sse(events: &[&str]) -> String {
+        events.iter().map(|e| format!("data: {}\n\n", e)).collect()
+    }
+
+    async fn openrouter_client(server: &mockito::ServerGuard) -> OpenRouterClient {
+        let base_url = url::Url::parse(&format!("{}/api/v1", server.url())).unwrap();
+        OpenRouterClient::new(OpenRouterConfig::new("test-key").with_base_url(base_url)).unwrap()
+    }
+
+    #[tokio::test]
+    async fn test_stream_text_and_usage() {
+        let mut server = mockito::Server::new_async().await;
+        let body = format!(
+            ": OPENROUTER PROCESSING\n\n{}",
+            sse(&[
+                r#"{"id":"gen-1","model":"m","choices":[{"index":0,"delta":{"role":"assistant","content":"Hel"},"finish_reason":null}]}"#,
+                r#"{"id":"gen-1","model":"m","choices":[{"index":0,"delta":{"content":"lo"},"finish_reason":"stop"}]}"#,
+                r#"{"id":"gen-1","model":"m","choices":[],"usage":{"prompt_tokens":3,"completion_tokens":2,"total_tokens":5}}"#,
+                "[DONE]",
+            ])
+        );
+        let mock = server
+            .mock("POST", "/api/v1/chat/completions")
+            .match_body(mockito::Matcher::PartialJson(serde_json::json!({
+                "stream": true,
+                "stream_options": { "include_usage": true }
+            })))
+            .with_header("content-type", "text/event-stream")
+            .with_body(body)
+            .create_async()
+            .await;
+
+        let client = openrouter_client(&server).await;
+        let mut stream = client
+            .stream(CompletionRequest::new("m", vec![Message::user("hi")]))
+            .await
+            .unwrap();
+
+        let mut text = String::new();
+        while let Some(chunk) = stream.next_chunk().await {
+            for choice in chunk.unwrap().choices {
+                text.push_str(choice.delta.content.as_deref().unwrap_or_default());
+            }
+        }
+
+        mock.assert_async().await;
+        assert_eq!(text, "Hello");
+        assert_eq!(stream.usage().unwrap().total_tokens, 5);
+    }
+
+    #[tokio::test]
+    async fn test_stream_assembles_tool_call_fragments() {
+        let mut server = mockito::Server::new_async().await;
+        server
+            .mock("POST", "/v1/chat/completions")
+            .with_header("content-type", "text/event-stream")
+            .with_body(sse(&[
+                r#"{"id":"c","model":"m","choices":[{"index":0,"delta":{"role":"assistant","tool_calls":[{"index":0,"id":"call_1","type":"function","function":{"name":"calculator","arguments":""}}]},"finish_reason":null}]}"#,
+                r#"{"id":"c","model":"m","choices":[{"index":0,"delta":{"tool_calls":[{"index":0,"function":{"arguments":"{\"operation\":\"add\","}}]},"finish_reason":null}]}"#,
+                r#"{"id":"c","model":"m","choices":[{"index":0,"delta":{"tool_calls":[{"index":0,"function":{"arguments":"\"a\":1,\"b\":2}"}}]},"finish_reason":"tool_calls"}]}"#,
+                "[DONE]",
+            ]))
+            .create_async()
+            .await;
+
+        let client = VllmClient::new(VllmConfig::new(server.url())).unwrap();
+        let response = LlmClient::stream(&client, CompletionRequest::new("m", vec![]))
+            .await
+            .unwrap()
+            .collect_response()
+            .await
+            .unwrap();
+
+        let choice = &response.choices[0];
+        assert_eq!(choice.finish_reason.as_deref(), Some("tool_calls"));
+        let call = &choice.message.tool_calls.as_ref().unwrap()[0];
+        assert_eq!(call.id, "call_1");
+        assert_eq!(call.function.name, "calculator");
+        let args: serde_json::Value = serde_json::from_str(&call.function.arguments).unwrap();
+        assert_eq!(
+            args,
+            serde_json::json!({ "operation": "add", "a": 1, "b": 2 })
+        );
+    }
+
+    #[tokio::test]
+    async fn test_stream_mid_stream_error() {
+        let mut server = mockito::Server::new_async().await;
+        server
+            .mock("POST", "/api/v1/chat/completions")
+            .with_header("content-type", "text/event-stream")
+            .with_body(sse(&[
+                r#"{"id":"gen-1","model":"m","choices":[{"index":0,"delta":{"content":"par"},"finish_reason":null}]}"#,
+                r#"{"error":{"code":502,"message":"Provider disconnected"}}"#,
+                r#"{"id":"gen-1","model":"m","choices":[{"index":0,"delta":{"content":"never"},"finish_reason":null}]}"#,
+            ]))
+            .create_async()
+            .await;
+
+        let client = openrouter_client(&server).await;
+        let mut stream = client
+            .stream(CompletionRequest::new("m", vec![]))
+            .await
+            .unwrap();
+
+        assert!(stream.next_chunk().await.unwrap().is_ok());
+        let err = stream.next_chunk().await.unwrap().unwrap_err();
+        assert!(err.to_string().contains("Provider disconnected"));
+        assert!(stream.next_chunk().await.is_none());
+    }
+
+    #[tokio::test]
+    async fn test_stream_events_split_across_reads() {
+        let payload = sse(&[
+            r#"{"id":"x","model":"m","choices":[{"index":0,"delta":{"content":"héllo"},"finish_reason":null}]}"#,
+        ]);
+        let bytes = payload.into_bytes();
+        // Split inside both the multi-byte character and the event terminator
+        let split = bytes.iter().position(|&b| b == 0xc3).unwrap() + 1;
+        let parts = vec![
+            Ok(Bytes::copy_from_slice(&bytes[..split])),
+            Ok(Bytes::copy_from_slice(&bytes[split..bytes.len() - 1])),
+            Ok(Bytes::copy_from_slice(&bytes[bytes.len() - 1..])),
+        ];
+
+        let response = CompletionStream::new(futures::stream::iter(parts))
+            .collect_response()
+            .await
+            .unwrap();
+        assert_eq!(response.choices[0].message.content, "héllo");
+    }
+
+    fn completion_body(model: &str) -> String {
+        serde_json::json!({
+            "id": "gen-1",
+            "model": model,
+            "provider": "Together",
+            "choices": [{
+                "index": 0,
+                "message": { "role": "assistant", "content": "ok" },
+                "finish_reason": "stop"
+            }],
+            "usage": { "prompt_tokens": 1, "completion_tokens": 1, "total_tokens": 2 }
+        })
+        .to_string()
+    }
+
+    fn resilient_client(server: &mockito::ServerGuard) -> OpenRouterClient {
+        let base_url = url::Url::parse(&format!("{}/api/v1", server.url())).unwrap();
+        let preferences = ProviderPreferences {
+            preferred: vec!["Together".to_string()],
+            excluded: vec!["Azure".to_string()],
+            optimization: OptimizationTarget::LowerCost,
+        };
+        let config = OpenRouterConfig::new("test-key")
+            .with_base_url(base_url)
+            .with_provider_preferences(preferences)
+            .with_fallback_models(vec!["backup".to_string()])
+            .with_max_retries(2)
+            .with_retry_backoff(Duration::from_millis(1));
+        OpenRouterClient::new(config).unwrap()
+    }
+
+    #[tokio::test]
+    async fn test_retries_then_falls_back_with_provider_routing() {
+        let mut server = mockito::Server::new_async().await;
+        let primary = server
+            .mock("POST", "/api/v1/chat/completions")
+            .match_body(mockito::Matcher::PartialJson(
+                serde_json::json!({ "model": "primary" }),
+            ))
+            .with_status(429)
+            .with_header("retry-after", "0")
+            .with_body("rate limited")
+            .expect(3)
+            .create_async()
+            .await;
+        let backup = server
+            .mock("POST", "/api/v1/chat/completions")
+            .match_body(mockito::Matcher::PartialJson(serde_json::json!({
+                "model": "backup",
+                "provider": { "order": ["Together"], "ignore": ["Azure"], "sort": "price" }
+            })))
+            .with_header("content-type", "application/json")
+            .with_body(completion_body("backup"))
+            .expect(1)
+            .create_async()
+            .await;
+
+        let client = resilient_client(&server);
+        let response = client
+            .complete(CompletionRequest::new("primary", vec![Message::user("hi")]))
+            .await
+            .unwrap();
+
+        primary.assert_async().await;
+        backup.assert_async().await;
+        assert_eq!(response.model, "backup");
+        assert_eq!(response.provider.as_deref(), Some("Together"));
+    }
+
+    #[test]
+    fn test_retry_after_seconds_and_http_dates() {
+        let now = DateTime::parse_from_rfc3339("1994-11-06T08:49:00Z")
+            .unwrap()
+            .with_timezone(&Utc);
+
+        assert_eq!(parse_retry_after("2", now), Some(Duration::from_secs(2)));
+        assert_eq!(parse_retry_after("-1", now), None);
+        for date in [
+            "Sun, 06 Nov 1994 08:49:37 GMT",
+            "Sunday, 06-Nov-94 08:49:37 GMT",
+            "Sun Nov  6 08:49:37 1994",
+        ] {
+            assert_eq!(
+                parse_retry_after(date, now),
+                Some(Duration::from_secs(37)),
+                "{}",
+                date
+            );
+        }
+        assert_eq!(
+            parse_retry_after("Sat, 05 Nov 1994 08:49:37 GMT", now),
+            Some(Duration::ZERO)
+        );
+        assert_eq!(parse_retry_after("soon", now), None);
+        assert_eq!(parse_retry_after("1e300", now), None);
+    }
+
+    #[tokio::test]
+    async fn test_long_retry_after_moves_to_the_fallback_model() {
+        let mut server = mockito::Server::new_async().await;
+        let primary = server
+            .mock("POST", "/api/v1/chat/completions")
+            .match_body(mockito::Matcher::PartialJson(
+                serde_json::json!({ "model": "primary" }),
+            ))
+            .with_status(429)
+            .with_header("retry-after", "86400")
+            .with_body("rate limited")
+            .expect(1)
+            .create_async()
+            .await;
+        let backup = server
+            .mock("POST", "/api/v1/chat/completions")
+            .match_body(mockito::Matcher::PartialJson(
+                serde_json::json!({ "model": "backup" }),
+            ))
+            .with_header("content-type", "application/json")
+            .with_body(completion_body("backup"))
+            .expect(1)
+            .create_async()
+            .await;
+
+        let client = resilient_client(&server);
+        let request = CompletionRequest::new("primary", vec![Message::user("hi")]);
+        let response = tokio::time::timeout(Duration::from_secs(5), client.complete(request))
+            .await
+            .expect("a day-long Retry-After is not waited for")
+            .unwrap();
+
+        primary.assert_async().await;
+        backup.assert_async().await;
+        assert_eq!(response.model, "backup");
+    }
+
+    #[tokio::test]
+    async fn test_client_errors_are_not_retried() {
+        let mut server = mockito::Server::new_async().await;
+        let primary = server
+            .mock("POST", "/api/v1/chat/completions")
+            .with_status(400)
+            .with_body("bad request")
+            .expect(1)
+            .create_async()
+            .await;
+
+        let client = resilient_client(&server);
+        let err = client
+            .complete(CompletionRequest::new("primary", vec![]))
+            .await
+            .unwrap_err();
+
+        primary.assert_async().await;
+        assert!(err.to_string().contains("400"));
+    }
+
+    #[test]
+    fn test_multimodal_content_serialization() {
+        let text = serde_json::to_value(Message::user("hi")).unwrap();
+        assert_eq!(text["content"], "hi");
+
+        let message = Message::user_parts(vec![
+            ContentPart::text("Describe these"),
+            ContentPart::image_url("https://example.com/cat.png"),
+            ContentPart::image_base64("image/png", "iVBORw0KGgo="),
+            ContentPart::file_base64("report.pdf", "application/pdf", "JVBERi0="),
+        ]);
+        let value = serde_json::to_value(&message).unwrap();
+        assert_eq!(
+            value["content"],
+            serde_json::json!([
+                { "type": "text", "text": "Describe these" },
+                { "type": "image_url", "image_url": { "url": "https://example.com/cat.png" } },
+                { "type": "image_url", "image_url": { "url": "data:image/png;base64,iVBORw0KGgo=" } },
+                { "type": "file", "file": { "filename": "report.pdf", "file_data": "data:application/pdf;base64,JVBERi0=" } },
+            ])
+        );
+
+        let parsed: Message = serde_json::from_value(value).unwrap();
+        assert_eq!(parsed.content.text(), "Describe these");
+        let parts = parsed.content.parts();
+        let ContentPart::ImageUrl { image_url } = &parts[2] else {
+            panic!("expected an image part");
+        };
+        assert_eq!(image_url.as_base64(), Some(("image/png", "iVBORw0KGgo=")));
+    }
+
+    #[test]
+    fn test_provider_routing_from_preferences() {
+        let balanced = ProviderRouting::from(&ProviderPreferences::default());
+        assert!(balanced.is_empty());
+
+        let fast = ProviderPreferences {
+            optimization: OptimizationTarget::Performance,
+            ..Default::default()
+        };
+        assert_eq!(
+            serde_json::to_value(ProviderRouting::from(&fast)).unwrap(),
+            serde_json::json!({ "sort": "throughput" })
+        );
+    }
+}
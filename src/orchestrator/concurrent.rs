@@ -3,18 +3,23 @@
 //! All agents execute in parallel, with results aggregated according
 //! to the specified strategy.
 
-use crate::error::Result;
+use crate::budget::BudgetLedger;
+use crate::error::{Error, Result};
+use crate::tracing_ext::PatternConfig;
 use crate::Agent;
 use crate::orchestrator::config::AggregationStrategy;
-use crate::orchestrator::pattern::{OrchestratorPattern, OrchestratorResult, AgentOutput};
+use crate::orchestrator::pattern::{OrchestratorPattern, OrchestratorResult, AgentOutput, Steps, execute_with};
 use async_trait::async_trait;
+use std::sync::Arc;
 use std::time::Instant;
-use futures::future::join_all;
+use futures::future::try_join_all;
 
 /// Concurrent orchestrator - parallel execution with aggregation
 pub struct ConcurrentOrchestrator {
     agents: Vec<Agent>,
     aggregation: AggregationStrategy,
+    config: PatternConfig,
+    budget: Option<Arc<BudgetLedger>>,
 }
 
 impl ConcurrentOrchestrator {
@@ -23,9 +28,23 @@ impl ConcurrentOrchestrator {
         Self {
             agents,
             aggregation: AggregationStrategy::Concatenate,
+            config: PatternConfig::default(),
+            budget: None,
         }
     }
 
+    /// Set the timeout, failure mode and token budget for each execution
+    pub fn with_config(mut self, config: PatternConfig) -> Self {
+        self.config = config;
+        self
+    }
+
+    /// Charge every LLM call in an execution to a shared budget
+    pub fn with_budget(mut self, budget: Arc<BudgetLedger>) -> Self {
+        self.budget = Some(budget);
+        self
+    }
+
     /// Set aggregation strategy
     pub fn with_aggregation(mut self, strategy: AggregationStrategy) -> Self {
         self.aggregation = strategy;
@@ -69,11 +88,9 @@ impl ConcurrentOrchestrator {
             }
         }
     }
-}
 
-#[async_trait]
-impl OrchestratorPattern for ConcurrentOrchestrator {
-    async fn execute(&self, input: &str) -> Result<OrchestratorResult> {
+    /// Run the pattern's steps
+    async fn run(&self, input: &str, steps: &Steps<'_>) -> Result<OrchestratorResult> {
         let start = Instant::now();
         
         // Create futures for all agents
@@ -82,35 +99,29 @@ impl OrchestratorPattern for ConcurrentOrchestrator {
                 let input = input.to_string();
                 async move {
                     let agent_start = Instant::now();
-                    let result = agent.react_loop(&input).await;
-                    (agent.name.clone(), result, agent_start.elapsed().as_millis() as u64)
+                    let output = steps.run(&agent.name, agent, &input).await?;
+                    let time_ms = agent_start.elapsed().as_millis() as u64;
+                    Ok::<_, Error>(output.map(|output| (agent.name.clone(), output, time_ms)))
                 }
             })
             .collect();
 
         // Execute all in parallel
-        let results = join_all(futures).await;
+        let results = try_join_all(futures).await?;
 
         // Collect outputs
         let mut agent_outputs = Vec::new();
         let mut result = OrchestratorResult::new("", "concurrent");
 
-        for (name, output_result, time_ms) in results {
-            match output_result {
-                Ok(output) => {
-                    let agent_output = AgentOutput {
-                        agent_name: name,
-                        content: output.content,
-                        loops_executed: output.trace.iteration_count(),
-                        execution_time_ms: time_ms,
-                    };
-                    agent_outputs.push(agent_output.clone());
-                    result = result.with_agent_output(agent_output);
-                }
-                Err(e) => {
-                    tracing::warn!("Agent {} failed: {}", name, e);
-                }
-            }
+        for (name, output, time_ms) in results.into_iter().flatten() {
+            let agent_output = AgentOutput {
+                agent_name: name,
+                content: output.content,
+                loops_executed: output.trace.iteration_count(),
+                execution_time_ms: time_ms,
+            };
+            agent_outputs.push(agent_output.clone());
+            result = result.with_agent_output(agent_output);
         }
 
         // Aggregate results
@@ -119,7 +130,15 @@ impl OrchestratorPattern for ConcurrentOrchestrator {
             .with_time(start.elapsed().as_millis() as u64)
             .with_extra("aggregation", serde_json::json!(format!("{:?}", self.aggregation)));
 
-        Ok(result)
+        steps.finish(result)
+    }
+}
+
+#[async_trait]
+impl OrchestratorPattern for ConcurrentOrchestrator {
+    async fn execute(&self, input: &str) -> Result<OrchestratorResult> {
+        let steps = Steps::new("concurrent", &self.config);
+        execute_with(&steps, self.budget.as_ref(), self.run(input, &steps)).await
     }
 
     fn pattern_type(&self) -> &str {
@@ -130,3 +149,66 @@ impl OrchestratorPattern for ConcurrentOrchestrator {
         self.agents.len()
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::llm_client::testing::ScriptedClient;
+    use crate::openrouter::Message;
+    use crate::orchestrator::pattern::testing::scripted_agent;
+    use crate::tracing_ext::FailureMode;
+    use std::time::Duration;
+
+    #[tokio::test]
+    async fn test_orchestrator_failure_modes() {
+        let run = |failure_mode: FailureMode| async move {
+            let steady = ScriptedClient::new(vec![Message::assistant("steady answer")]);
+            let flaky = ScriptedClient::new(vec![Message::assistant("flaky answer")]).failing_first(1);
+            ConcurrentOrchestrator::new(vec![scripted_agent("Steady", steady), scripted_agent("Flaky", flaky)])
+                .with_config(PatternConfig::new().with_failure_mode(failure_mode))
+                .execute("question")
+                .await
+        };
+
+        assert!(run(FailureMode::FailFast).await.is_err());
+
+        let partial = run(FailureMode::FailSafe).await.unwrap();
+        assert_eq!(partial.agent_outputs.len(), 1);
+        assert!(partial.agent_outputs.contains_key("Steady"));
+        let failed = &partial.metadata.failed_agents;
+        assert_eq!(failed.len(), 1);
+        assert_eq!(failed[0].agent_name, "Flaky");
+        assert_eq!(failed[0].attempts, 1);
+
+        let retried = run(FailureMode::Retry {
+            max_attempts: 2,
+            backoff: Duration::from_millis(1),
+        })
+        .await
+        .unwrap();
+        assert_eq!(retried.agent_outputs.len(), 2);
+        assert!(retried.metadata.failed_agents.is_empty());
+
+        // A single attempt means no retry at all
+        assert!(run(FailureMode::Retry {
+            max_attempts: 1,
+            backoff: Duration::from_millis(1),
+        })
+        .await
+        .is_err());
+
+        assert!(matches!(PatternConfig::new().failure_mode, FailureMode::FailSafe));
+
+        // Fail-safe still fails when no agent produced anything
+        let broken = ScriptedClient::new(vec![Message::assistant("unused")]).failing_first(1);
+        let err = ConcurrentOrchestrator::new(vec![scripted_agent("Broken", broken)])
+            .execute("question")
+            .await
+            .unwrap_err();
+        let Error::NoAgentOutput { pattern, failed_agents } = err else {
+            panic!("unexpected error: {err}");
+        };
+        assert_eq!(pattern, "concurrent");
+        assert_eq!(failed_agents[0].agent_name, "Broken");
+    }
+}
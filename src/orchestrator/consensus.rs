@@ -3,18 +3,23 @@
 //! Multiple agents vote/respond independently, and a majority
 //! voting mechanism determines the final consensus.
 
-use crate::error::Result;
+use crate::budget::BudgetLedger;
+use crate::error::{Error, Result};
+use crate::tracing_ext::PatternConfig;
 use crate::Agent;
-use crate::orchestrator::pattern::{OrchestratorPattern, OrchestratorResult, AgentOutput};
+use crate::orchestrator::pattern::{OrchestratorPattern, OrchestratorResult, AgentOutput, Steps, execute_with};
 use async_trait::async_trait;
+use std::sync::Arc;
 use std::collections::HashMap;
 use std::time::Instant;
-use futures::future::join_all;
+use futures::future::try_join_all;
 
 /// Consensus orchestrator - majority voting
 pub struct ConsensusOrchestrator {
     agents: Vec<Agent>,
     threshold: f64,
+    config: PatternConfig,
+    budget: Option<Arc<BudgetLedger>>,
 }
 
 impl ConsensusOrchestrator {
@@ -23,9 +28,23 @@ impl ConsensusOrchestrator {
         Self {
             agents,
             threshold: 0.66, // 2/3 majority by default
+            config: PatternConfig::default(),
+            budget: None,
         }
     }
 
+    /// Set the timeout, failure mode and token budget for each execution
+    pub fn with_config(mut self, config: PatternConfig) -> Self {
+        self.config = config;
+        self
+    }
+
+    /// Charge every LLM call in an execution to a shared budget
+    pub fn with_budget(mut self, budget: Arc<BudgetLedger>) -> Self {
+        self.budget = Some(budget);
+        self
+    }
+
     /// Set consensus threshold (0.0 to 1.0)
     pub fn with_threshold(mut self, threshold: f64) -> Self {
         self.threshold = threshold.clamp(0.0, 1.0);
@@ -82,11 +101,9 @@ impl ConsensusOrchestrator {
     fn consensus_reached(&self, percentage: f64) -> bool {
         percentage >= self.threshold
     }
-}
 
-#[async_trait]
-impl OrchestratorPattern for ConsensusOrchestrator {
-    async fn execute(&self, input: &str) -> Result<OrchestratorResult> {
+    /// Run the pattern's steps
+    async fn run(&self, input: &str, steps: &Steps<'_>) -> Result<OrchestratorResult> {
         let start = Instant::now();
         
         // All agents respond independently in parallel
@@ -95,32 +112,26 @@ impl OrchestratorPattern for ConsensusOrchestrator {
                 let input = input.to_string();
                 async move {
                     let agent_start = Instant::now();
-                    let result = agent.react_loop(&input).await;
-                    (agent.name.clone(), result, agent_start.elapsed().as_millis() as u64)
+                    let output = steps.run(&agent.name, agent, &input).await?;
+                    let time_ms = agent_start.elapsed().as_millis() as u64;
+                    Ok::<_, Error>(output.map(|output| (agent.name.clone(), output, time_ms)))
                 }
             })
             .collect();
 
-        let results = join_all(futures).await;
+        let results = try_join_all(futures).await?;
 
         let mut result = OrchestratorResult::new("", "consensus");
         let mut responses = Vec::new();
 
-        for (name, output_result, time_ms) in results {
-            match output_result {
-                Ok(output) => {
-                    responses.push(output.content.clone());
-                    result = result.with_agent_output(AgentOutput {
-                        agent_name: name,
-                        content: output.content,
-                        loops_executed: output.trace.iteration_count(),
-                        execution_time_ms: time_ms,
-                    });
-                }
-                Err(e) => {
-                    tracing::warn!("Agent {} failed: {}", name, e);
-                }
-            }
+        for (name, output, time_ms) in results.into_iter().flatten() {
+            responses.push(output.content.clone());
+            result = result.with_agent_output(AgentOutput {
+                agent_name: name,
+                content: output.content,
+                loops_executed: output.trace.iteration_count(),
+                execution_time_ms: time_ms,
+            });
         }
 
         // Perform majority vote
@@ -162,7 +173,15 @@ impl OrchestratorPattern for ConsensusOrchestrator {
             .with_extra("agreement_percentage", serde_json::json!(percentage))
             .with_extra("threshold", serde_json::json!(self.threshold));
 
-        Ok(result)
+        steps.finish(result)
+    }
+}
+
+#[async_trait]
+impl OrchestratorPattern for ConsensusOrchestrator {
+    async fn execute(&self, input: &str) -> Result<OrchestratorResult> {
+        let steps = Steps::new("consensus", &self.config);
+        execute_with(&steps, self.budget.as_ref(), self.run(input, &steps)).await
     }
 
     fn pattern_type(&self) -> &str {
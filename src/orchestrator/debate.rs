@@ -3,10 +3,13 @@
 //! Pro and con agents argue positions for multiple rounds,
 //! with a synthesizer agent producing the final balanced conclusion.
 
+use crate::budget::BudgetLedger;
 use crate::error::Result;
+use crate::tracing_ext::PatternConfig;
 use crate::Agent;
-use crate::orchestrator::pattern::{OrchestratorPattern, OrchestratorResult, AgentOutput};
+use crate::orchestrator::pattern::{OrchestratorPattern, OrchestratorResult, AgentOutput, Steps, execute_with};
 use async_trait::async_trait;
+use std::sync::Arc;
 use std::time::Instant;
 
 /// Debate orchestrator - pro/con with synthesis
@@ -15,6 +18,8 @@ pub struct DebateOrchestrator {
     con_agent: Agent,
     synthesizer: Agent,
     rounds: usize,
+    config: PatternConfig,
+    budget: Option<Arc<BudgetLedger>>,
 }
 
 impl DebateOrchestrator {
@@ -25,9 +30,23 @@ impl DebateOrchestrator {
             con_agent,
             synthesizer,
             rounds: 2,
+            config: PatternConfig::default(),
+            budget: None,
         }
     }
 
+    /// Set the timeout, failure mode and token budget for each execution
+    pub fn with_config(mut self, config: PatternConfig) -> Self {
+        self.config = config;
+        self
+    }
+
+    /// Charge every LLM call in an execution to a shared budget
+    pub fn with_budget(mut self, budget: Arc<BudgetLedger>) -> Self {
+        self.budget = Some(budget);
+        self
+    }
+
     /// Set number of debate rounds
     pub fn with_rounds(mut self, rounds: usize) -> Self {
         self.rounds = rounds;
@@ -51,11 +70,9 @@ impl DebateOrchestrator {
         
         synthesis
     }
-}
 
-#[async_trait]
-impl OrchestratorPattern for DebateOrchestrator {
-    async fn execute(&self, input: &str) -> Result<OrchestratorResult> {
+    /// Run the pattern's steps
+    async fn run(&self, input: &str, steps: &Steps<'_>) -> Result<OrchestratorResult> {
         let start = Instant::now();
         let mut result = OrchestratorResult::new("", "debate");
         
@@ -87,41 +104,43 @@ impl OrchestratorPattern for DebateOrchestrator {
             };
 
             let pro_start = Instant::now();
-            let pro_output = self.pro_agent.react_loop(&pro_prompt).await?;
-            pro_arguments.push(pro_output.content.clone());
+            let pro_output = steps.run(format!("pro round {}", round + 1), &self.pro_agent, &pro_prompt).await?;
             
-            all_outputs.push(AgentOutput {
-                agent_name: format!("{} (Round {})", self.pro_agent.name, round + 1),
-                content: pro_output.content.clone(),
-                loops_executed: pro_output.trace.iteration_count(),
-                execution_time_ms: pro_start.elapsed().as_millis() as u64,
-            });
-
-            // Con agent's turn
-            let con_prompt = if round == 0 {
-                format!(
+            if let Some(pro_output) = &pro_output {
+                pro_arguments.push(pro_output.content.clone());
+                all_outputs.push(AgentOutput {
+                    agent_name: format!("{} (Round {})", self.pro_agent.name, round + 1),
+                    content: pro_output.content.clone(),
+                    loops_executed: pro_output.trace.iteration_count(),
+                    execution_time_ms: pro_start.elapsed().as_millis() as u64,
+                });
+            }
+
+            // Con agent's turn; without a pro argument it makes its own case
+            let con_prompt = match &pro_output {
+                Some(pro_output) if round == 0 => format!(
                     "Pro has argued:\n{}\n\nPresent your counter-arguments AGAINST:\n{}",
                     pro_output.content,
                     input
-                )
-            } else {
-                format!(
+                ),
+                Some(pro_output) => format!(
                     "Pro has responded:\n{}\n\nCounter their points and strengthen your position AGAINST:\n{}",
                     pro_output.content,
                     input
-                )
+                ),
+                None => con_opening.clone(),
             };
 
             let con_start = Instant::now();
-            let con_output = self.con_agent.react_loop(&con_prompt).await?;
-            con_arguments.push(con_output.content.clone());
-            
-            all_outputs.push(AgentOutput {
-                agent_name: format!("{} (Round {})", self.con_agent.name, round + 1),
-                content: con_output.content.clone(),
-                loops_executed: con_output.trace.iteration_count(),
-                execution_time_ms: con_start.elapsed().as_millis() as u64,
-            });
+            if let Some(con_output) = steps.run(format!("con round {}", round + 1), &self.con_agent, &con_prompt).await? {
+                con_arguments.push(con_output.content.clone());
+                all_outputs.push(AgentOutput {
+                    agent_name: format!("{} (Round {})", self.con_agent.name, round + 1),
+                    content: con_output.content.clone(),
+                    loops_executed: con_output.trace.iteration_count(),
+                    execution_time_ms: con_start.elapsed().as_millis() as u64,
+                });
+            }
         }
 
         // Store all outputs
@@ -142,22 +161,35 @@ impl OrchestratorPattern for DebateOrchestrator {
         );
 
         let synth_start = Instant::now();
-        let synth_output = self.synthesizer.react_loop(&synthesis_prompt).await?;
-        
-        result = result.with_agent_output(AgentOutput {
-            agent_name: format!("{} (Synthesis)", self.synthesizer.name),
-            content: synth_output.content.clone(),
-            loops_executed: synth_output.trace.iteration_count(),
-            execution_time_ms: synth_start.elapsed().as_millis() as u64,
-        });
-
-        result.content = synth_output.content;
+        // Without a synthesis the debate transcript is the answer
+        let content = match steps.run("synthesis", &self.synthesizer, &synthesis_prompt).await? {
+            Some(synth_output) => {
+                result = result.with_agent_output(AgentOutput {
+                    agent_name: format!("{} (Synthesis)", self.synthesizer.name),
+                    content: synth_output.content.clone(),
+                    loops_executed: synth_output.trace.iteration_count(),
+                    execution_time_ms: synth_start.elapsed().as_millis() as u64,
+                });
+                synth_output.content
+            }
+            None => debate_summary,
+        };
+
+        result.content = content;
         result = result
             .with_time(start.elapsed().as_millis() as u64)
             .with_handoffs(self.rounds * 2) // Each round has pro->con handoff
             .with_extra("rounds", serde_json::json!(self.rounds));
 
-        Ok(result)
+        steps.finish(result)
+    }
+}
+
+#[async_trait]
+impl OrchestratorPattern for DebateOrchestrator {
+    async fn execute(&self, input: &str) -> Result<OrchestratorResult> {
+        let steps = Steps::new("debate", &self.config);
+        execute_with(&steps, self.budget.as_ref(), self.run(input, &steps)).await
     }
 
     fn pattern_type(&self) -> &str {
@@ -3,25 +3,47 @@
 //! A lead agent decomposes tasks and delegates to subagents,
 //! then synthesizes their outputs into a final result.
 
-use crate::error::Result;
+use crate::budget::BudgetLedger;
+use crate::error::{Error, Result};
+use crate::tracing_ext::PatternConfig;
 use crate::Agent;
 use crate::handoffs::{Handoff, HandoffContext};
-use crate::orchestrator::pattern::{OrchestratorPattern, OrchestratorResult, AgentOutput};
+use crate::orchestrator::pattern::{OrchestratorPattern, OrchestratorResult, AgentOutput, Steps, execute_with};
 use crate::types::AgentId;
 use async_trait::async_trait;
+use std::sync::Arc;
 use std::time::Instant;
-use futures::future::join_all;
+use futures::future::try_join_all;
 
 /// Hierarchical orchestrator - lead agent with subagent delegation
 pub struct HierarchicalOrchestrator {
     lead_agent: Agent,
     subagents: Vec<Agent>,
+    config: PatternConfig,
+    budget: Option<Arc<BudgetLedger>>,
 }
 
 impl HierarchicalOrchestrator {
     /// Create a new hierarchical orchestrator
     pub fn new(lead_agent: Agent, subagents: Vec<Agent>) -> Self {
-        Self { lead_agent, subagents }
+        Self {
+            lead_agent,
+            subagents,
+            config: PatternConfig::default(),
+            budget: None,
+        }
+    }
+
+    /// Set the timeout, failure mode and token budget for each execution
+    pub fn with_config(mut self, config: PatternConfig) -> Self {
+        self.config = config;
+        self
+    }
+
+    /// Charge every LLM call in an execution to a shared budget
+    pub fn with_budget(mut self, budget: Arc<BudgetLedger>) -> Self {
+        self.budget = Some(budget);
+        self
     }
 
     /// Create handoff to a subagent
@@ -57,11 +79,9 @@ impl HierarchicalOrchestrator {
             .filter(|s| !s.is_empty())
             .collect()
     }
-}
 
-#[async_trait]
-impl OrchestratorPattern for HierarchicalOrchestrator {
-    async fn execute(&self, input: &str) -> Result<OrchestratorResult> {
+    /// Run the pattern's steps
+    async fn run(&self, input: &str, steps: &Steps<'_>) -> Result<OrchestratorResult> {
         let start = Instant::now();
         let mut result = OrchestratorResult::new("", "hierarchical");
         let mut handoff_count = 0;
@@ -74,17 +94,22 @@ impl OrchestratorPattern for HierarchicalOrchestrator {
         );
 
         let lead_start = Instant::now();
-        let lead_output = self.lead_agent.react_loop(&decomposition_prompt).await?;
+        let lead_output = steps.run("decomposition", &self.lead_agent, &decomposition_prompt).await?;
         
-        result = result.with_agent_output(AgentOutput {
-            agent_name: format!("{} (decomposition)", self.lead_agent.name),
-            content: lead_output.content.clone(),
-            loops_executed: lead_output.trace.iteration_count(),
-            execution_time_ms: lead_start.elapsed().as_millis() as u64,
-        });
-
-        // Phase 2: Parse subtasks and delegate to subagents
-        let subtasks = self.parse_subtasks(&lead_output.content);
+        // Phase 2: Parse subtasks and delegate to subagents; without a
+        // decomposition every subagent works on the whole task
+        let subtasks = match lead_output {
+            Some(lead_output) => {
+                result = result.with_agent_output(AgentOutput {
+                    agent_name: format!("{} (decomposition)", self.lead_agent.name),
+                    content: lead_output.content.clone(),
+                    loops_executed: lead_output.trace.iteration_count(),
+                    execution_time_ms: lead_start.elapsed().as_millis() as u64,
+                });
+                self.parse_subtasks(&lead_output.content)
+            }
+            None => vec![input.to_string()],
+        };
         
         let futures: Vec<_> = self.subagents.iter()
             .zip(subtasks.iter().cycle()) // Cycle if fewer subtasks than subagents
@@ -99,55 +124,70 @@ impl OrchestratorPattern for HierarchicalOrchestrator {
                 
                 async move {
                     let agent_start = Instant::now();
-                    let result = subagent.react_loop(&subtask_prompt).await;
-                    (subagent.name.clone(), result, agent_start.elapsed().as_millis() as u64)
+                    let output = steps.run(&subagent.name, subagent, &subtask_prompt).await?;
+                    let time_ms = agent_start.elapsed().as_millis() as u64;
+                    Ok::<_, Error>(output.map(|output| (subagent.name.clone(), output, time_ms)))
                 }
             })
             .collect();
 
-        let subagent_results = join_all(futures).await;
+        let subagent_results = try_join_all(futures).await?;
         
         let mut subagent_outputs = Vec::new();
-        for (name, output_result, time_ms) in subagent_results {
-            if let Ok(output) = output_result {
-                let agent_output = AgentOutput {
-                    agent_name: name,
-                    content: output.content.clone(),
-                    loops_executed: output.trace.iteration_count(),
-                    execution_time_ms: time_ms,
-                };
-                subagent_outputs.push(agent_output.clone());
-                result = result.with_agent_output(agent_output);
-            }
+        for (name, output, time_ms) in subagent_results.into_iter().flatten() {
+            let agent_output = AgentOutput {
+                agent_name: name,
+                content: output.content.clone(),
+                loops_executed: output.trace.iteration_count(),
+                execution_time_ms: time_ms,
+            };
+            subagent_outputs.push(agent_output.clone());
+            result = result.with_agent_output(agent_output);
         }
 
         // Phase 3: Lead agent synthesizes results
+        let combined = subagent_outputs.iter()
+            .map(|o| format!("### {}\n{}", o.agent_name, o.content))
+            .collect::<Vec<_>>()
+            .join("\n\n");
         let synthesis_prompt = format!(
             "Original task: {}\n\nSubagent outputs:\n{}\n\nSynthesize these into a comprehensive final answer:",
             input,
-            subagent_outputs.iter()
-                .map(|o| format!("### {}\n{}", o.agent_name, o.content))
-                .collect::<Vec<_>>()
-                .join("\n\n")
+            combined
         );
 
         let synthesis_start = Instant::now();
-        let synthesis_output = self.lead_agent.react_loop(&synthesis_prompt).await?;
+        let synthesis_output = steps.run("synthesis", &self.lead_agent, &synthesis_prompt).await?;
         
-        result = result.with_agent_output(AgentOutput {
-            agent_name: format!("{} (synthesis)", self.lead_agent.name),
-            content: synthesis_output.content.clone(),
-            loops_executed: synthesis_output.trace.iteration_count(),
-            execution_time_ms: synthesis_start.elapsed().as_millis() as u64,
-        });
-
-        result.content = synthesis_output.content;
+        // Without a synthesis the subagent outputs are the answer
+        let content = match synthesis_output {
+            Some(synthesis_output) => {
+                result = result.with_agent_output(AgentOutput {
+                    agent_name: format!("{} (synthesis)", self.lead_agent.name),
+                    content: synthesis_output.content.clone(),
+                    loops_executed: synthesis_output.trace.iteration_count(),
+                    execution_time_ms: synthesis_start.elapsed().as_millis() as u64,
+                });
+                synthesis_output.content
+            }
+            None => combined,
+        };
+
+        result.content = content;
         result = result
             .with_time(start.elapsed().as_millis() as u64)
             .with_handoffs(handoff_count)
             .with_extra("subtasks", serde_json::json!(subtasks));
 
-        Ok(result)
+        steps.finish(result)
+    }
+}
+
+#[async_trait]
+impl OrchestratorPattern for HierarchicalOrchestrator {
+    async fn execute(&self, input: &str) -> Result<OrchestratorResult> {
+        let steps = Steps::new("hierarchical", &self.config);
+        execute_with(&steps, self.budget.as_ref(), self.run(input, &steps)).await
     }
 
     fn pattern_type(&self) -> &str {
@@ -45,6 +45,7 @@ pub use pattern::{
     OrchestratorResult, 
     AgentOutput,
     OrchestratorMetadata,
+    FailedAgent,
     OrchestratorBuilder,
 };
 pub use sequential::SequentialOrchestrator;
@@ -1,10 +1,16 @@
 //! Orchestrator pattern trait and result types
 
-use crate::error::Result;
+use crate::budget::{self, BudgetLedger, UsageReport};
+use crate::error::{Error, Result};
+use crate::tracing_ext::{self, FailureMode, PatternConfig, SpanType};
 use crate::Agent;
 use async_trait::async_trait;
+use parking_lot::Mutex;
 use serde::{Deserialize, Serialize};
 use std::collections::HashMap;
+use std::future::Future;
+use std::sync::Arc;
+use std::time::Duration;
 
 /// Output from an orchestrator pattern execution
 #[derive(Debug, Clone, Serialize, Deserialize)]
@@ -44,6 +50,25 @@ pub struct OrchestratorMetadata {
     /// Pattern-specific data
     #[serde(default)]
     pub extra: HashMap<String, serde_json::Value>,
+    /// Token usage and estimated cost per agent for this execution
+    #[serde(default)]
+    pub usage: UsageReport,
+    /// Agent steps that failed and were left out of the result
+    #[serde(default)]
+    pub failed_agents: Vec<FailedAgent>,
+}
+
+/// Agent step that failed during an execution
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct FailedAgent {
+    /// Agent name
+    pub agent_name: String,
+    /// Pattern step the agent was running
+    pub step: String,
+    /// Error from the last attempt
+    pub error: String,
+    /// Number of attempts made
+    pub attempts: u32,
 }
 
 impl OrchestratorResult {
@@ -58,6 +83,8 @@ impl OrchestratorResult {
                 agent_count: 0,
                 handoff_count: 0,
                 extra: HashMap::new(),
+                usage: UsageReport::default(),
+                failed_agents: Vec::new(),
             },
         }
     }
@@ -81,6 +108,12 @@ impl OrchestratorResult {
         self
     }
 
+    /// Set the usage report
+    pub fn with_usage(mut self, usage: UsageReport) -> Self {
+        self.metadata.usage = usage;
+        self
+    }
+
     /// Add extra metadata
     pub fn with_extra(mut self, key: impl Into<String>, value: serde_json::Value) -> Self {
         self.metadata.extra.insert(key.into(), value);
@@ -101,6 +134,157 @@ pub trait OrchestratorPattern: Send + Sync {
     fn agent_count(&self) -> usize;
 }
 
+/// Runs the agent steps of one pattern execution under its failure mode
+pub(crate) struct Steps<'a> {
+    pattern: &'a str,
+    config: &'a PatternConfig,
+    failures: Mutex<Vec<FailedAgent>>,
+    running: Mutex<Vec<String>>,
+    completed: Mutex<Vec<String>>,
+}
+
+impl<'a> Steps<'a> {
+    /// Create a runner for a pattern
+    pub(crate) fn new(pattern: &'a str, config: &'a PatternConfig) -> Self {
+        Self {
+            pattern,
+            config,
+            failures: Mutex::new(Vec::new()),
+            running: Mutex::new(Vec::new()),
+            completed: Mutex::new(Vec::new()),
+        }
+    }
+
+    /// Run one agent step, retrying it under [`FailureMode::Retry`]
+    ///
+    /// A step that still fails is recorded. Under [`FailureMode::FailSafe`] it
+    /// yields `None` so the pattern can carry on without it; otherwise the
+    /// error is returned.
+    pub(crate) async fn run(
+        &self,
+        step: impl Into<String>,
+        agent: &Agent,
+        input: &str,
+    ) -> Result<Option<crate::agent::AgentOutput>> {
+        let step = step.into();
+        let (max_attempts, backoff) = match &self.config.failure_mode {
+            FailureMode::Retry { max_attempts, backoff } => ((*max_attempts).max(1), *backoff),
+            _ => (1, Duration::ZERO),
+        };
+
+        self.running.lock().push(agent.name.clone());
+        let mut attempts = 0;
+        let error = loop {
+            attempts += 1;
+            match self.attempt(&step, agent, input).await {
+                Ok(output) => {
+                    self.settle(&agent.name);
+                    self.completed.lock().push(agent.name.clone());
+                    return Ok(Some(output));
+                }
+                // Retrying cannot help once the token budget is used up
+                Err(e) if attempts >= max_attempts || matches!(e, Error::BudgetExceeded { .. }) => break e,
+                Err(e) => {
+                    let delay = backoff.saturating_mul(1 << (attempts - 1).min(16));
+                    tracing::warn!(
+                        "Agent {} failed {} step (attempt {}): {}; retrying in {:?}",
+                        agent.name, step, attempts, e, delay
+                    );
+                    tokio::time::sleep(delay).await;
+                }
+            }
+        };
+
+        tracing::warn!("Agent {} failed {} step: {}", agent.name, step, error);
+        self.settle(&agent.name);
+        self.failures.lock().push(FailedAgent {
+            agent_name: agent.name.clone(),
+            step,
+            error: error.to_string(),
+            attempts,
+        });
+
+        match self.config.failure_mode {
+            FailureMode::FailSafe => Ok(None),
+            _ => Err(error),
+        }
+    }
+
+    /// Mark one running step of an agent as finished
+    fn settle(&self, agent_name: &str) {
+        let mut running = self.running.lock();
+        if let Some(i) = running.iter().position(|name| name == agent_name) {
+            running.remove(i);
+        }
+    }
+
+    async fn attempt(&self, step: &str, agent: &Agent, input: &str) -> Result<crate::agent::AgentOutput> {
+        tracing_ext::in_span(SpanType::OrchestratorStep, step, async {
+            tracing_ext::record("pattern", serde_json::json!(self.pattern));
+            tracing_ext::record("agent", serde_json::json!(agent.name));
+            agent.react_loop(input).await
+        })
+        .await
+    }
+
+    /// Attach the recorded failures to a result
+    ///
+    /// Fails with [`Error::NoAgentOutput`] if steps failed and none of them
+    /// produced an output, rather than returning an empty result.
+    pub(crate) fn finish(&self, mut result: OrchestratorResult) -> Result<OrchestratorResult> {
+        let failed_agents = std::mem::take(&mut *self.failures.lock());
+        if result.agent_outputs.is_empty() && !failed_agents.is_empty() {
+            return Err(Error::NoAgentOutput {
+                pattern: self.pattern.to_string(),
+                failed_agents,
+            });
+        }
+        result.metadata.failed_agents = failed_agents;
+        Ok(result)
+    }
+
+    /// Error for an execution that ran past the timeout
+    ///
+    /// Reports the steps that were still running, those that completed and
+    /// those that had already failed.
+    fn timed_out(&self) -> Error {
+        Error::PatternTimeout {
+            timeout: self.config.timeout,
+            running: std::mem::take(&mut *self.running.lock()),
+            completed: std::mem::take(&mut *self.completed.lock()),
+            failed_agents: std::mem::take(&mut *self.failures.lock()),
+        }
+    }
+}
+
+/// Run a pattern execution under its config with usage accounting
+///
+/// The execution fails with [`Error::PatternTimeout`] once the timeout of the
+/// `steps` config elapses. Every LLM call inside `fut` is charged to `budget`,
+/// if given, and to a fresh ledger, limited to `config.token_budget`, whose
+/// report becomes the result's `metadata.usage`.
+pub(crate) async fn execute_with<F>(
+    steps: &Steps<'_>,
+    budget: Option<&Arc<BudgetLedger>>,
+    fut: F,
+) -> Result<OrchestratorResult>
+where
+    F: Future<Output = Result<OrchestratorResult>>,
+{
+    let config = steps.config;
+    let prices = budget.map(|b| b.prices().clone()).unwrap_or_default();
+    let execution = Arc::new(config.budget_ledger().unwrap_or_default().with_prices(prices));
+
+    let fut = budget::scope(execution.clone(), tokio::time::timeout(config.timeout, fut));
+    let result = match budget {
+        Some(budget) => budget::scope(budget.clone(), fut).await,
+        None => fut.await,
+    };
+    let result = result.map_err(|_| steps.timed_out())??;
+
+    Ok(result.with_usage(execution.report()))
+}
+
 /// Builder for orchestrator patterns
 pub struct OrchestratorBuilder {
     agents: Vec<Agent>,
@@ -135,3 +319,100 @@ impl Default for OrchestratorBuilder {
         Self::new()
     }
 }
+
+/// Agent builder shared by the orchestrator tests
+#[cfg(test)]
+pub(crate) mod testing {
+    use crate::llm_client::testing::ScriptedClient;
+    use crate::Agent;
+    use std::sync::Arc;
+
+    /// Agent without tools that answers from `client`
+    pub(crate) fn scripted_agent(name: &str, client: ScriptedClient) -> Agent {
+        Agent::builder()
+            .name(name)
+            .system_prompt("You are a test agent.")
+            .model("test")
+            .client(Arc::new(client))
+            .build()
+            .unwrap()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::testing::scripted_agent;
+    use super::*;
+    use crate::llm_client::testing::ScriptedClient;
+    use crate::openrouter::Message;
+    use crate::orchestrator::{ConcurrentOrchestrator, SequentialOrchestrator};
+    use crate::tracing_ext::testing::CollectingExporter;
+    use crate::tracing_ext::Tracer;
+
+    #[tokio::test]
+    async fn test_orchestrator_steps_share_a_trace() {
+        let exporter = Arc::new(CollectingExporter::default());
+        let tracer = Tracer::new().with_exporter(exporter.clone());
+        let orchestrator = SequentialOrchestrator::new(vec![
+            scripted_agent("Writer", ScriptedClient::new(vec![Message::assistant("draft")])),
+            scripted_agent("Editor", ScriptedClient::new(vec![Message::assistant("final")])),
+        ]);
+
+        tracer
+            .trace(SpanType::Custom("workflow".into()), "pipeline", orchestrator.execute("topic"))
+            .await
+            .unwrap();
+
+        let traces = exporter.traces.lock();
+        let steps = &traces[0].root_span.children;
+        assert_eq!(steps.len(), 2);
+        assert!(matches!(steps[0].span_type, SpanType::OrchestratorStep));
+        assert_eq!(steps[1].data.data["agent"], "Editor");
+        assert!(matches!(steps[1].children[0].span_type, SpanType::AgentRun));
+    }
+
+    #[tokio::test]
+    async fn test_orchestrator_reports_usage_per_agent() {
+        use crate::budget::{ModelPrice, PriceTable};
+
+        let prices = PriceTable::new().with_price("test", ModelPrice::new(1_000.0, 2_000.0));
+        let shared = Arc::new(BudgetLedger::new().with_prices(prices));
+        let orchestrator = SequentialOrchestrator::new(vec![
+            scripted_agent("Writer", ScriptedClient::new(vec![Message::assistant("draft")])),
+            scripted_agent("Editor", ScriptedClient::new(vec![Message::assistant("final")])),
+        ])
+        .with_budget(shared.clone());
+
+        let result = orchestrator.execute("topic").await.unwrap();
+        let usage = &result.metadata.usage;
+        assert_eq!(usage.total.total_tokens, 30);
+        assert_eq!(usage.by_name("Writer").unwrap().usage.total_tokens, 15);
+        assert!((usage.by_name("Editor").unwrap().cost_usd - 0.02).abs() < 1e-9);
+        assert_eq!(shared.used(), 30);
+
+        // Each execution reports only its own usage
+        let second = orchestrator.execute("topic").await.unwrap();
+        assert_eq!(second.metadata.usage.total.total_tokens, 30);
+        assert_eq!(shared.used(), 60);
+    }
+
+    #[tokio::test]
+    async fn test_orchestrator_timeout() {
+        let slow = ScriptedClient::new(vec![Message::assistant("late")]).with_delay(Duration::from_secs(5));
+        let orchestrator = ConcurrentOrchestrator::new(vec![
+            scripted_agent("Fast", ScriptedClient::new(vec![Message::assistant("early")])),
+            scripted_agent("Broken", ScriptedClient::new(vec![Message::assistant("unused")]).failing_first(1)),
+            scripted_agent("Slow", slow),
+        ])
+        .with_config(PatternConfig::new().with_timeout(Duration::from_millis(50)));
+
+        let err = orchestrator.execute("topic").await.unwrap_err();
+        assert!(err.is_transient());
+        let Error::PatternTimeout { running, completed, failed_agents, .. } = err else {
+            panic!("unexpected error: {err}");
+        };
+        assert_eq!(running, ["Slow"]);
+        assert_eq!(completed, ["Fast"]);
+        assert_eq!(failed_agents[0].agent_name, "Broken");
+    }
+}
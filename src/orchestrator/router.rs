@@ -3,12 +3,15 @@
 //! A router agent triages requests and routes them
 //! to specialized agents based on domain expertise.
 
+use crate::budget::BudgetLedger;
 use crate::error::Result;
+use crate::tracing_ext::PatternConfig;
 use crate::Agent;
 use crate::handoffs::{Handoff, HandoffContext};
-use crate::orchestrator::pattern::{OrchestratorPattern, OrchestratorResult, AgentOutput};
+use crate::orchestrator::pattern::{OrchestratorPattern, OrchestratorResult, AgentOutput, Steps, execute_with};
 use crate::types::AgentId;
 use async_trait::async_trait;
+use std::sync::Arc;
 use std::collections::HashMap;
 use std::time::Instant;
 
@@ -16,6 +19,8 @@ use std::time::Instant;
 pub struct RouterOrchestrator {
     router_agent: Agent,
     specialists: HashMap<String, Agent>,
+    config: PatternConfig,
+    budget: Option<Arc<BudgetLedger>>,
 }
 
 impl RouterOrchestrator {
@@ -24,9 +29,23 @@ impl RouterOrchestrator {
         Self {
             router_agent,
             specialists: HashMap::new(),
+            config: PatternConfig::default(),
+            budget: None,
         }
     }
 
+    /// Set the timeout, failure mode and token budget for each execution
+    pub fn with_config(mut self, config: PatternConfig) -> Self {
+        self.config = config;
+        self
+    }
+
+    /// Charge every LLM call in an execution to a shared budget
+    pub fn with_budget(mut self, budget: Arc<BudgetLedger>) -> Self {
+        self.budget = Some(budget);
+        self
+    }
+
     /// Add a specialist agent
     pub fn with_specialist(mut self, domain: impl Into<String>, agent: Agent) -> Self {
         self.specialists.insert(domain.into(), agent);
@@ -75,11 +94,9 @@ impl RouterOrchestrator {
         
         None
     }
-}
 
-#[async_trait]
-impl OrchestratorPattern for RouterOrchestrator {
-    async fn execute(&self, input: &str) -> Result<OrchestratorResult> {
+    /// Run the pattern's steps
+    async fn run(&self, input: &str, steps: &Steps<'_>) -> Result<OrchestratorResult> {
         let start = Instant::now();
         let mut result = OrchestratorResult::new("", "router");
 
@@ -96,7 +113,11 @@ impl OrchestratorPattern for RouterOrchestrator {
 
         // Router agent makes decision
         let router_start = Instant::now();
-        let router_output = self.router_agent.react_loop(&routing_prompt).await?;
+        let Some(router_output) = steps.run("routing", &self.router_agent, &routing_prompt).await? else {
+            result.content = "Routing failed; no specialist handled the request".to_string();
+            result = result.with_time(start.elapsed().as_millis() as u64);
+            return steps.finish(result);
+        };
         
         result = result.with_agent_output(AgentOutput {
             agent_name: format!("{} (Routing)", self.router_agent.name),
@@ -107,6 +128,10 @@ impl OrchestratorPattern for RouterOrchestrator {
 
         // Parse routing decision
         let routed_domain = self.parse_routing(&router_output.content);
+        result.content = format!(
+            "No specialist matched. Router response:\n\n{}",
+            router_output.content
+        );
 
         if let Some(domain) = &routed_domain {
             if let Some(specialist) = self.specialists.get(domain) {
@@ -119,31 +144,42 @@ impl OrchestratorPattern for RouterOrchestrator {
                 );
 
                 let spec_start = Instant::now();
-                let spec_output = specialist.react_loop(&specialist_prompt).await?;
-                
-                result = result.with_agent_output(AgentOutput {
-                    agent_name: format!("{} ({})", specialist.name, domain),
-                    content: spec_output.content.clone(),
-                    loops_executed: spec_output.trace.iteration_count(),
-                    execution_time_ms: spec_start.elapsed().as_millis() as u64,
-                });
-
-                result.content = spec_output.content;
                 result = result.with_handoffs(1);
+                
+                match steps.run(domain.as_str(), specialist, &specialist_prompt).await? {
+                    Some(spec_output) => {
+                        result = result.with_agent_output(AgentOutput {
+                            agent_name: format!("{} ({})", specialist.name, domain),
+                            content: spec_output.content.clone(),
+                            loops_executed: spec_output.trace.iteration_count(),
+                            execution_time_ms: spec_start.elapsed().as_millis() as u64,
+                        });
+
+                        result.content = spec_output.content;
+                    }
+                    None => {
+                        result.content = format!(
+                            "The {} specialist failed. Router response:\n\n{}",
+                            domain, router_output.content
+                        );
+                    }
+                }
             }
-        } else {
-            // No specialist found, router handles directly
-            result.content = format!(
-                "No specialist matched. Router response:\n\n{}",
-                router_output.content
-            );
         }
 
         result = result
             .with_time(start.elapsed().as_millis() as u64)
             .with_extra("routed_to", serde_json::json!(routed_domain));
 
-        Ok(result)
+        steps.finish(result)
+    }
+}
+
+#[async_trait]
+impl OrchestratorPattern for RouterOrchestrator {
+    async fn execute(&self, input: &str) -> Result<OrchestratorResult> {
+        let steps = Steps::new("router", &self.config);
+        execute_with(&steps, self.budget.as_ref(), self.run(input, &steps)).await
     }
 
     fn pattern_type(&self) -> &str {
@@ -154,3 +190,25 @@ impl OrchestratorPattern for RouterOrchestrator {
         1 + self.specialists.len()
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::llm_client::testing::ScriptedClient;
+    use crate::openrouter::Message;
+    use crate::orchestrator::pattern::testing::scripted_agent;
+
+    #[tokio::test]
+    async fn test_router_reports_failed_specialist() {
+        let router = ScriptedClient::new(vec![Message::assistant("Route to billing")]);
+        let billing = ScriptedClient::new(vec![Message::assistant("refund issued")]).failing_first(1);
+        let result = RouterOrchestrator::new(scripted_agent("Router", router))
+            .with_specialist("billing", scripted_agent("Billing", billing))
+            .execute("I was charged twice")
+            .await
+            .unwrap();
+
+        assert!(result.content.starts_with("The billing specialist failed"));
+        assert_eq!(result.metadata.failed_agents[0].agent_name, "Billing");
+    }
+}
@@ -3,32 +3,55 @@
 //! Agents execute in order, with the output of each agent becoming
 //! the input for the next agent in the sequence.
 
+use crate::budget::BudgetLedger;
 use crate::error::Result;
+use crate::tracing_ext::PatternConfig;
 use crate::Agent;
-use crate::orchestrator::pattern::{OrchestratorPattern, OrchestratorResult, AgentOutput};
+use crate::orchestrator::pattern::{OrchestratorPattern, OrchestratorResult, AgentOutput, Steps, execute_with};
 use async_trait::async_trait;
+use std::sync::Arc;
 use std::time::Instant;
 
 /// Sequential orchestrator - agents execute in order
 pub struct SequentialOrchestrator {
     agents: Vec<Agent>,
+    config: PatternConfig,
+    budget: Option<Arc<BudgetLedger>>,
 }
 
 impl SequentialOrchestrator {
     /// Create a new sequential orchestrator with given agents
     pub fn new(agents: Vec<Agent>) -> Self {
-        Self { agents }
+        Self {
+            agents,
+            config: PatternConfig::default(),
+            budget: None,
+        }
+    }
+
+    /// Set the timeout, failure mode and token budget for each execution
+    pub fn with_config(mut self, config: PatternConfig) -> Self {
+        self.config = config;
+        self
+    }
+
+    /// Charge every LLM call in an execution to a shared budget
+    pub fn with_budget(mut self, budget: Arc<BudgetLedger>) -> Self {
+        self.budget = Some(budget);
+        self
     }
 
     /// Create from a single agent (for simple chains)
     pub fn single(agent: Agent) -> Self {
-        Self { agents: vec![agent] }
+        Self {
+            agents: vec![agent],
+            config: PatternConfig::default(),
+            budget: None,
+        }
     }
-}
 
-#[async_trait]
-impl OrchestratorPattern for SequentialOrchestrator {
-    async fn execute(&self, input: &str) -> Result<OrchestratorResult> {
+    /// Run the pattern's steps
+    async fn run(&self, input: &str, steps: &Steps<'_>) -> Result<OrchestratorResult> {
         let start = Instant::now();
         let mut result = OrchestratorResult::new("", "sequential");
         let mut current_input = input.to_string();
@@ -36,7 +59,10 @@ impl OrchestratorPattern for SequentialOrchestrator {
         for agent in &self.agents {
             let agent_start = Instant::now();
             
-            let output = agent.react_loop(&current_input).await?;
+            // A skipped agent passes its input on to the next one
+            let Some(output) = steps.run(&agent.name, agent, &current_input).await? else {
+                continue;
+            };
             
             let agent_output = AgentOutput {
                 agent_name: agent.name.clone(),
@@ -52,7 +78,15 @@ impl OrchestratorPattern for SequentialOrchestrator {
         result.content = current_input;
         result = result.with_time(start.elapsed().as_millis() as u64);
         
-        Ok(result)
+        steps.finish(result)
+    }
+}
+
+#[async_trait]
+impl OrchestratorPattern for SequentialOrchestrator {
+    async fn execute(&self, input: &str) -> Result<OrchestratorResult> {
+        let steps = Steps::new("sequential", &self.config);
+        execute_with(&steps, self.budget.as_ref(), self.run(input, &steps)).await
     }
 
     fn pattern_type(&self) -> &str {
@@ -1,7 +1,7 @@
 //! Workflow patterns for multi-agent orchestration
 
 use crate::error::Result;
-pub use crate::tracing_ext::PatternConfig;
+pub use crate::tracing_ext::{FailureMode, PatternConfig};
 use async_trait::async_trait;
 use serde::{Deserialize, Serialize};
 
@@ -0,0 +1,277 @@
+//! Client-side rate limiting for LLM clients
+//!
+//! A [`RateLimiter`] holds request and token buckets per endpoint and model.
+//! Wrapping clients in [`RateLimitedClient`] with the same `Arc<RateLimiter>`
+//! makes every agent in the process draw from the same buckets, so a wide
+//! fan-out queues instead of tripping provider limits.
+//!
+//! ```rust,ignore
+//! let limiter = Arc::new(RateLimiter::new(
+//!     RateLimit::new().with_requests_per_minute(60).with_tokens_per_minute(100_000),
+//! ));
+//! let client: Arc<dyn LlmClient> = Arc::new(RateLimitedClient::new(client, limiter.clone()));
+//! ```
+
+use crate::error::Result;
+use crate::llm_client::{Layer, LlmClient};
+use crate::openrouter::{CompletionRequest, CompletionResponse, CompletionStream};
+use crate::turns::estimate_tokens;
+use async_trait::async_trait;
+use dashmap::DashMap;
+use governor::{DefaultDirectRateLimiter, Jitter, Quota};
+use std::collections::HashMap;
+use std::num::NonZeroU32;
+use std::sync::atomic::{AtomicU64, Ordering};
+use std::sync::Arc;
+use std::time::Duration;
+
+/// Requests and tokens allowed per minute
+///
+/// A limit of zero means unlimited.
+#[derive(Debug, Clone, Copy, Default)]
+pub struct RateLimit {
+    /// Requests allowed per minute
+    pub requests_per_minute: u32,
+    /// Prompt and completion tokens allowed per minute
+    pub tokens_per_minute: u32,
+}
+
+impl RateLimit {
+    /// Create an unlimited rate limit
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Limit requests per minute
+    pub fn with_requests_per_minute(mut self, requests: u32) -> Self {
+        self.requests_per_minute = requests;
+        self
+    }
+
+    /// Limit tokens per minute
+    pub fn with_tokens_per_minute(mut self, tokens: u32) -> Self {
+        self.tokens_per_minute = tokens;
+        self
+    }
+}
+
+/// Buckets for one endpoint and model
+struct Buckets {
+    requests: Option<DefaultDirectRateLimiter>,
+    tokens: Option<(DefaultDirectRateLimiter, NonZeroU32)>,
+    /// Tokens used beyond earlier reservations, paid by the next request
+    debt: AtomicU64,
+}
+
+impl Buckets {
+    fn new(limit: RateLimit) -> Self {
+        let bucket = |per_minute| {
+            NonZeroU32::new(per_minute).map(|n| (governor::RateLimiter::direct(Quota::per_minute(n)), n))
+        };
+        Self {
+            requests: bucket(limit.requests_per_minute).map(|(bucket, _)| bucket),
+            tokens: bucket(limit.tokens_per_minute),
+            debt: AtomicU64::new(0),
+        }
+    }
+
+    async fn take_tokens(&self, tokens: u64) {
+        let Some((bucket, capacity)) = &self.tokens else {
+            return;
+        };
+        // Debt is paid in full, one bucket at a time
+        let mut debt = self.debt.swap(0, Ordering::Relaxed);
+        while debt > 0 {
+            let n = debt.min(capacity.get() as u64);
+            let _ = bucket
+                .until_n_ready_with_jitter(NonZeroU32::new(n as u32).unwrap_or(*capacity), jitter())
+                .await;
+            debt -= n;
+        }
+        // More tokens than a whole minute's worth can only wait for a full bucket
+        let n = NonZeroU32::new(tokens.min(capacity.get() as u64) as u32);
+        if let Some(n) = n {
+            // Cannot fail: `n` never exceeds the bucket's capacity
+            let _ = bucket.until_n_ready_with_jitter(n, jitter()).await;
+        }
+    }
+}
+
+fn jitter() -> Jitter {
+    Jitter::up_to(Duration::from_millis(50))
+}
+
+/// Shared request and token buckets keyed by endpoint and model
+pub struct RateLimiter {
+    default: RateLimit,
+    models: HashMap<String, RateLimit>,
+    buckets: DashMap<(String, String), Arc<Buckets>>,
+}
+
+impl RateLimiter {
+    /// Create a limiter applying `default` to every endpoint and model
+    pub fn new(default: RateLimit) -> Self {
+        Self {
+            default,
+            models: HashMap::new(),
+            buckets: DashMap::new(),
+        }
+    }
+
+    /// Use a different limit for a model on every endpoint
+    pub fn with_model_limit(mut self, model: impl Into<String>, limit: RateLimit) -> Self {
+        self.models.insert(model.into(), limit);
+        self
+    }
+
+    fn buckets(&self, endpoint: &str, model: &str) -> Arc<Buckets> {
+        self.buckets
+            .entry((endpoint.to_string(), model.to_string()))
+            .or_insert_with(|| {
+                let limit = self.models.get(model).copied().unwrap_or(self.default);
+                Arc::new(Buckets::new(limit))
+            })
+            .clone()
+    }
+
+    /// Wait until a request expected to use `tokens` may be sent
+    pub async fn acquire(&self, endpoint: &str, model: &str, tokens: u64) {
+        let buckets = self.buckets(endpoint, model);
+        if let Some(requests) = &buckets.requests {
+            requests.until_ready_with_jitter(jitter()).await;
+        }
+        buckets.take_tokens(tokens).await;
+    }
+
+    /// Record `tokens` used beyond the acquired estimate without waiting
+    ///
+    /// The bucket goes into deficit and the next [`acquire`](Self::acquire)
+    /// for the same endpoint and model waits for it to be paid back.
+    pub fn debit(&self, endpoint: &str, model: &str, tokens: u64) {
+        let buckets = self.buckets(endpoint, model);
+        if buckets.tokens.is_some() {
+            buckets.debt.fetch_add(tokens, Ordering::Relaxed);
+        }
+    }
+}
+
+/// [`LlmClient`] that waits for a [`RateLimiter`] before each request
+///
+/// Tokens are reserved up front from the prompt length plus `max_tokens`.
+/// Completions that use more are returned at once and the difference is
+/// debited from the bucket, so the next request waits for it; streamed
+/// completions are only charged the reservation.
+pub struct RateLimitedClient {
+    inner: Arc<dyn LlmClient>,
+    limiter: Arc<RateLimiter>,
+}
+
+impl RateLimitedClient {
+    /// Wrap a client with a shared limiter
+    pub fn new(inner: Arc<dyn LlmClient>, limiter: Arc<RateLimiter>) -> Self {
+        Self { inner, limiter }
+    }
+
+    /// The shared limiter
+    pub fn limiter(&self) -> &Arc<RateLimiter> {
+        &self.limiter
+    }
+}
+
+/// Wrapping a client in the shared limiter makes a [`RateLimitedClient`]
+impl Layer for Arc<RateLimiter> {
+    fn layer(&self, inner: Arc<dyn LlmClient>) -> Arc<dyn LlmClient> {
+        Arc::new(RateLimitedClient::new(inner, self.clone()))
+    }
+}
+
+/// Tokens reserved for a request before it is sent
+fn reserved_tokens(request: &CompletionRequest) -> u64 {
+    let prompt: u64 = request.messages.iter().map(|m| estimate_tokens(&m.content.text())).sum();
+    prompt + request.max_tokens.unwrap_or(0) as u64
+}
+
+#[async_trait]
+impl LlmClient for RateLimitedClient {
+    async fn complete(&self, request: CompletionRequest) -> Result<CompletionResponse> {
+        let endpoint = self.inner.endpoint();
+        let model = request.model.clone();
+        let reserved = reserved_tokens(&request);
+
+        self.limiter.acquire(endpoint, &model, reserved).await;
+        let response = self.inner.complete(request).await?;
+
+        let used = response.usage.total_tokens;
+        if used > reserved {
+            self.limiter.debit(endpoint, &model, used - reserved);
+        }
+        Ok(response)
+    }
+
+    async fn stream(&self, request: CompletionRequest) -> Result<CompletionStream> {
+        self.limiter
+            .acquire(self.inner.endpoint(), &request.model, reserved_tokens(&request))
+            .await;
+        self.inner.stream(request).await
+    }
+
+    fn client_type(&self) -> &str {
+        self.inner.client_type()
+    }
+
+    fn endpoint(&self) -> &str {
+        self.inner.endpoint()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::llm_client::testing::ScriptedClient;
+    use crate::openrouter::Message;
+
+    async fn finishes_quickly(client: &RateLimitedClient, request: CompletionRequest) -> bool {
+        tokio::time::timeout(Duration::from_millis(200), client.complete(request))
+            .await
+            .is_ok()
+    }
+
+    #[tokio::test]
+    async fn test_requests_queue_per_model_across_clients() {
+        let limiter = Arc::new(RateLimiter::new(RateLimit::new().with_requests_per_minute(1)));
+        let first = RateLimitedClient::new(Arc::new(ScriptedClient::repeating("ok")), limiter.clone());
+        let second = RateLimitedClient::new(Arc::new(ScriptedClient::repeating("ok")), limiter);
+
+        assert!(finishes_quickly(&first, CompletionRequest::new("a", vec![])).await);
+        // The shared bucket for model "a" is empty, so the call waits rather than failing
+        assert!(!finishes_quickly(&second, CompletionRequest::new("a", vec![])).await);
+        assert!(finishes_quickly(&second, CompletionRequest::new("b", vec![])).await);
+    }
+
+    #[tokio::test]
+    async fn test_tokens_are_limited_with_model_overrides() {
+        let limiter = Arc::new(
+            RateLimiter::new(RateLimit::new().with_tokens_per_minute(100))
+                .with_model_limit("big", RateLimit::new().with_tokens_per_minute(1_000_000)),
+        );
+        let client = RateLimitedClient::new(Arc::new(ScriptedClient::repeating("ok")), limiter);
+        let request = |model: &str| CompletionRequest::new(model, vec![Message::user("hi")]).with_max_tokens(80);
+
+        assert!(finishes_quickly(&client, request("small")).await);
+        assert!(!finishes_quickly(&client, request("small")).await);
+        assert!(finishes_quickly(&client, request("big")).await);
+        assert!(finishes_quickly(&client, request("big")).await);
+    }
+
+    #[tokio::test]
+    async fn test_overage_is_paid_by_the_next_request() {
+        let limiter = Arc::new(RateLimiter::new(RateLimit::new().with_tokens_per_minute(100)));
+        let inner = ScriptedClient::repeating("ok").with_usage(150);
+        let client = RateLimitedClient::new(Arc::new(inner), limiter);
+        let request = || CompletionRequest::new("a", vec![Message::user("hi")]);
+
+        // The completion is returned before the bucket refills for the overage
+        assert!(finishes_quickly(&client, request()).await);
+        assert!(!finishes_quickly(&client, request()).await);
+    }
+}
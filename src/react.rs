@@ -1,5 +1,6 @@
 //! ReAct (Reasoning and Acting) paradigm implementation
 
+use crate::openrouter::ImageUrl;
 use crate::types::{SpanId, TokenUsage};
 use chrono::{DateTime, Utc};
 use serde::{Deserialize, Serialize};
@@ -40,6 +41,17 @@ pub enum ReasoningFormat {
     JsonStructured,
 }
 
+/// How the agent asks the model to invoke tools
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum ToolCallingMode {
+    /// OpenAI-style function calling via `tools` / `tool_calls`
+    #[default]
+    Native,
+    /// Plain-text Thought/Action/Observation protocol shaped by [`ReasoningFormat`]
+    TextProtocol,
+}
+
 /// A trace of ReAct loop execution
 #[derive(Debug, Clone, Serialize, Deserialize)]
 pub struct ReActTrace {
@@ -173,6 +185,9 @@ pub enum Action {
         tool_id: String,
         /// Tool parameters
         params: serde_json::Value,
+        /// Provider-assigned tool call ID, echoed back on the tool result message
+        #[serde(default, skip_serializing_if = "Option::is_none")]
+        call_id: Option<String>,
         /// When this action occurred
         timestamp: DateTime<Utc>,
     },
@@ -182,6 +197,9 @@ pub enum Action {
         target_agent: String,
         /// Handoff reason
         reason: String,
+        /// Provider-assigned tool call ID, echoed back if control returns
+        #[serde(default, skip_serializing_if = "Option::is_none")]
+        call_id: Option<String>,
         /// When this action occurred
         timestamp: DateTime<Utc>,
     },
@@ -200,6 +218,21 @@ impl Action {
         Self::ToolCall {
             tool_id: tool_id.into(),
             params,
+            call_id: None,
+            timestamp: Utc::now(),
+        }
+    }
+
+    /// Create a tool call action carrying the provider's tool call ID
+    pub fn tool_call_with_id(
+        call_id: impl Into<String>,
+        tool_id: impl Into<String>,
+        params: serde_json::Value,
+    ) -> Self {
+        Self::ToolCall {
+            tool_id: tool_id.into(),
+            params,
+            call_id: Some(call_id.into()),
             timestamp: Utc::now(),
         }
     }
@@ -209,6 +242,21 @@ impl Action {
         Self::Handoff {
             target_agent: target_agent.into(),
             reason: reason.into(),
+            call_id: None,
+            timestamp: Utc::now(),
+        }
+    }
+
+    /// Create a handoff action carrying the provider's tool call ID
+    pub fn handoff_with_id(
+        call_id: impl Into<String>,
+        target_agent: impl Into<String>,
+        reason: impl Into<String>,
+    ) -> Self {
+        Self::Handoff {
+            target_agent: target_agent.into(),
+            reason: reason.into(),
+            call_id: Some(call_id.into()),
             timestamp: Utc::now(),
         }
     }
@@ -248,6 +296,9 @@ pub struct Observation {
     pub is_error: bool,
     /// Span ID for tracing
     pub span_id: Option<SpanId>,
+    /// Images returned by the tool
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub images: Vec<ImageUrl>,
 }
 
 impl Observation {
@@ -258,6 +309,7 @@ impl Observation {
             timestamp: Utc::now(),
             is_error: false,
             span_id: None,
+            images: Vec::new(),
         }
     }
 
@@ -268,6 +320,7 @@ impl Observation {
             timestamp: Utc::now(),
             is_error: true,
             span_id: None,
+            images: Vec::new(),
         }
     }
 
@@ -276,4 +329,10 @@ impl Observation {
         self.span_id = Some(span_id);
         self
     }
+
+    /// Attach images returned by the tool
+    pub fn with_images(mut self, images: Vec<ImageUrl>) -> Self {
+        self.images = images;
+        self
+    }
 }
@@ -0,0 +1,379 @@
+//! Text-protocol ReAct parsing for models without native tool calling
+//!
+//! Models served without OpenAI-style function calling are prompted to emit
+//! their reasoning as plain text. The layout follows
+//! [`ReActConfig::reasoning_format`](crate::react::ReActConfig):
+//!
+//! - [`ReasoningFormat::ThoughtAction`]: `Thought:` / `Action:` / `Action Input:` lines,
+//!   ending in `Final Answer:` once the task is done
+//! - [`ReasoningFormat::XmlThinking`]: the same lines, with the thought wrapped
+//!   in `<thinking>...</thinking>`
+//! - [`ReasoningFormat::JsonStructured`]: a single JSON object per step
+//!
+//! Tool results are returned to the model as `Observation:` messages.
+
+use crate::error::{Error, Result};
+use crate::openrouter::ToolDefinition;
+use crate::react::ReasoningFormat;
+use serde_json::Value;
+
+/// Stop sequence that keeps the model from inventing its own observations
+pub const OBSERVATION_STOP: &str = "\nObservation:";
+
+/// A single parsed step of text-protocol output
+#[derive(Debug, Clone, PartialEq)]
+pub enum ParsedStep {
+    /// The model wants to call a tool
+    ToolCall {
+        /// Reasoning preceding the action
+        thought: String,
+        /// Tool identifier named in the action
+        tool: String,
+        /// Parsed action input
+        input: Value,
+    },
+    /// The model produced its final answer
+    FinalAnswer {
+        /// Reasoning preceding the answer
+        thought: String,
+        /// The answer content
+        answer: String,
+    },
+}
+
+impl ParsedStep {
+    /// Reasoning text for this step
+    pub fn thought(&self) -> &str {
+        match self {
+            Self::ToolCall { thought, .. } | Self::FinalAnswer { thought, .. } => thought,
+        }
+    }
+}
+
+/// Build the protocol instructions appended to the system prompt
+pub fn instructions(format: ReasoningFormat, tools: &[ToolDefinition]) -> String {
+    let mut output = String::from("## Tools\n\n");
+
+    if tools.is_empty() {
+        output.push_str("No tools are available; answer directly.\n");
+    } else {
+        output.push_str("You can use the following tools:\n\n");
+        for tool in tools {
+            output.push_str(&format!(
+                "- {}: {}\n  Input schema: {}\n",
+                tool.function.name, tool.function.description, tool.function.parameters
+            ));
+        }
+    }
+
+    output.push_str("\n## Response format\n\n");
+    output.push_str(match format {
+        ReasoningFormat::ThoughtAction => {
+            "Respond using exactly this format:\n\n\
+             Thought: your reasoning about what to do next\n\
+             Action: the tool id to use\n\
+             Action Input: the tool input as a JSON object\n\n\
+             After each action you will receive an Observation with the result. \
+             When you know the answer, respond with:\n\n\
+             Thought: your final reasoning\n\
+             Final Answer: the answer to the original question\n"
+        }
+        ReasoningFormat::XmlThinking => {
+            "Respond using exactly this format:\n\n\
+             <thinking>your reasoning about what to do next</thinking>\n\
+             Action: the tool id to use\n\
+             Action Input: the tool input as a JSON object\n\n\
+             After each action you will receive an Observation with the result. \
+             When you know the answer, respond with:\n\n\
+             <thinking>your final reasoning</thinking>\n\
+             Final Answer: the answer to the original question\n"
+        }
+        ReasoningFormat::JsonStructured => {
+            "Respond with a single JSON object and nothing else. To use a tool:\n\n\
+             {\"thought\": \"your reasoning\", \"action\": \"tool id\", \"action_input\": {...}}\n\n\
+             After each action you will receive an Observation with the result. \
+             When you know the answer:\n\n\
+             {\"thought\": \"your final reasoning\", \"final_answer\": \"the answer\"}\n"
+        }
+    });
+
+    output
+}
+
+/// Build the re-prompt sent when the model's output could not be parsed
+pub fn corrective_prompt(format: ReasoningFormat, error: &Error) -> String {
+    let reminder = match format {
+        ReasoningFormat::ThoughtAction | ReasoningFormat::XmlThinking => {
+            "Reply with either an 'Action:' line followed by an 'Action Input:' JSON object, \
+             or a 'Final Answer:' line."
+        }
+        ReasoningFormat::JsonStructured => {
+            "Reply with a single JSON object containing \"thought\" and either \
+             \"action\" with \"action_input\", or \"final_answer\"."
+        }
+    };
+
+    format!(
+        "Your previous response could not be processed: {}\n{}",
+        error_detail(error),
+        reminder
+    )
+}
+
+/// Format a tool result as an observation message
+pub fn observation(content: &str) -> String {
+    format!("Observation: {}", content)
+}
+
+/// Drop anything the model wrote after its action, such as an invented observation
+pub fn truncate_at_observation(text: &str) -> &str {
+    match find_ignore_case(text, "observation:") {
+        Some(idx) if idx > 0 => text[..idx].trim_end(),
+        _ => text,
+    }
+}
+
+/// Parse one step of model output according to the reasoning format
+pub fn parse(format: ReasoningFormat, text: &str) -> Result<ParsedStep> {
+    let text = truncate_at_observation(text.trim());
+
+    match format {
+        ReasoningFormat::ThoughtAction => parse_lines(text, labelled_thought(text)),
+        ReasoningFormat::XmlThinking => {
+            let (thought, rest) = split_thinking(text);
+            parse_lines(rest, thought)
+        }
+        ReasoningFormat::JsonStructured => parse_json(text),
+    }
+}
+
+fn parse_lines(text: &str, thought: String) -> Result<ParsedStep> {
+    if let Some(idx) = find_ignore_case(text, "final answer:") {
+        let answer = text[idx + "final answer:".len()..].trim();
+        if answer.is_empty() {
+            return Err(parse_error("'Final Answer:' was empty"));
+        }
+        return Ok(ParsedStep::FinalAnswer {
+            thought,
+            answer: answer.to_string(),
+        });
+    }
+
+    let action_idx = find_action_label(text)
+        .ok_or_else(|| parse_error("expected an 'Action:' line or a 'Final Answer:' line"))?;
+    let after_action = &text[action_idx + "action:".len()..];
+    let tool = after_action
+        .lines()
+        .next()
+        .unwrap_or_default()
+        .trim()
+        .trim_matches(|c: char| c == '`' || c == '"' || c == '\'' || c == '[' || c == ']')
+        .to_string();
+    if tool.is_empty() {
+        return Err(parse_error("'Action:' did not name a tool"));
+    }
+
+    let input = match find_ignore_case(after_action, "action input:") {
+        Some(idx) => parse_input(&after_action[idx + "action input:".len()..])?,
+        None => Value::Object(Default::default()),
+    };
+
+    Ok(ParsedStep::ToolCall { thought, tool, input })
+}
+
+fn parse_json(text: &str) -> Result<ParsedStep> {
+    let value = parse_input(text)?;
+    let object = value
+        .as_object()
+        .ok_or_else(|| parse_error("expected a JSON object"))?;
+
+    let thought = object
+        .get("thought")
+        .and_then(Value::as_str)
+        .unwrap_or_default()
+        .to_string();
+
+    if let Some(answer) = object.get("final_answer") {
+        let answer = match answer {
+            Value::String(s) => s.clone(),
+            other => other.to_string(),
+        };
+        return Ok(ParsedStep::FinalAnswer { thought, answer });
+    }
+
+    let tool = object
+        .get("action")
+        .and_then(Value::as_str)
+        .filter(|s| !s.trim().is_empty())
+        .ok_or_else(|| parse_error("expected an \"action\" or \"final_answer\" field"))?
+        .trim()
+        .to_string();
+
+    let input = match object.get("action_input") {
+        Some(Value::String(raw)) => parse_input(raw)?,
+        Some(other) => other.clone(),
+        None => Value::Object(Default::default()),
+    };
+
+    Ok(ParsedStep::ToolCall { thought, tool, input })
+}
+
+/// Parse a JSON payload, tolerating code fences and surrounding prose
+fn parse_input(raw: &str) -> Result<Value> {
+    let raw = strip_code_fence(raw.trim());
+    if raw.is_empty() {
+        return Ok(Value::Object(Default::default()));
+    }
+
+    if let Ok(value) = serde_json::from_str(raw) {
+        return Ok(value);
+    }
+
+    // Fall back to the outermost braces if the model wrapped the JSON in prose
+    match (raw.find('{'), raw.rfind('}')) {
+        (Some(start), Some(end)) if start < end => serde_json::from_str(&raw[start..=end])
+            .map_err(|e| parse_error(format!("action input is not valid JSON: {}", e))),
+        _ => Err(parse_error("action input is not valid JSON")),
+    }
+}
+
+fn strip_code_fence(text: &str) -> &str {
+    let Some(rest) = text.strip_prefix("```") else {
+        return text;
+    };
+    let rest = rest.strip_prefix("json").unwrap_or(rest);
+    rest.trim_end().strip_suffix("```").unwrap_or(rest).trim()
+}
+
+fn labelled_thought(text: &str) -> String {
+    let end = find_action_label(text)
+        .into_iter()
+        .chain(find_ignore_case(text, "final answer:"))
+        .min()
+        .unwrap_or(text.len());
+    let head = &text[..end];
+
+    match find_ignore_case(head, "thought:") {
+        Some(idx) => head[idx + "thought:".len()..].trim().to_string(),
+        None => head.trim().to_string(),
+    }
+}
+
+fn split_thinking(text: &str) -> (String, &str) {
+    let open = find_ignore_case(text, "<thinking>");
+    let close = find_ignore_case(text, "</thinking>");
+
+    match (open, close) {
+        (Some(start), Some(end)) if start < end => (
+            text[start + "<thinking>".len()..end].trim().to_string(),
+            &text[end + "</thinking>".len()..],
+        ),
+        _ => (labelled_thought(text), text),
+    }
+}
+
+/// Locate an `Action:` label that is not part of `Action Input:`
+fn find_action_label(text: &str) -> Option<usize> {
+    let mut offset = 0;
+    while let Some(idx) = find_ignore_case(&text[offset..], "action:") {
+        let absolute = offset + idx;
+        let preceded_by_word = text[..absolute]
+            .chars()
+            .next_back()
+            .is_some_and(|c| c.is_alphanumeric());
+        if !preceded_by_word {
+            return Some(absolute);
+        }
+        offset = absolute + "action:".len();
+    }
+    None
+}
+
+fn find_ignore_case(haystack: &str, needle: &str) -> Option<usize> {
+    haystack.char_indices().map(|(idx, _)| idx).find(|&idx| {
+        haystack
+            .get(idx..idx + needle.len())
+            .is_some_and(|candidate| candidate.eq_ignore_ascii_case(needle))
+    })
+}
+
+fn parse_error(message: impl Into<String>) -> Error {
+    Error::InvalidInput(message.into())
+}
+
+fn error_detail(error: &Error) -> String {
+    match error {
+        Error::InvalidInput(message) | Error::JsonSchema(message) => message.clone(),
+        other => other.to_string(),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_parse_thought_action() {
+        let step = parse(
+            ReasoningFormat::ThoughtAction,
+            "Thought: I should add the numbers\nAction: calculator\nAction Input: {\"operation\": \"add\", \"a\": 1, \"b\": 2}\nObservation: 3",
+        )
+        .unwrap();
+
+        assert_eq!(
+            step,
+            ParsedStep::ToolCall {
+                thought: "I should add the numbers".to_string(),
+                tool: "calculator".to_string(),
+                input: serde_json::json!({ "operation": "add", "a": 1, "b": 2 }),
+            }
+        );
+    }
+
+    #[test]
+    fn test_parse_final_answer_and_fenced_input() {
+        let step = parse(
+            ReasoningFormat::ThoughtAction,
+            "Thought: done\nFinal Answer: 42",
+        )
+        .unwrap();
+        assert_eq!(step.thought(), "done");
+        assert!(matches!(step, ParsedStep::FinalAnswer { ref answer, .. } if answer == "42"));
+
+        let step = parse(
+            ReasoningFormat::XmlThinking,
+            "<thinking>echo it</thinking>\nAction: `echo`\nAction Input: ```json\n{\"message\": \"hi\"}\n```",
+        )
+        .unwrap();
+        assert_eq!(step.thought(), "echo it");
+        assert!(matches!(step, ParsedStep::ToolCall { ref input, .. } if input["message"] == "hi"));
+    }
+
+    #[test]
+    fn test_parse_json_structured() {
+        let step = parse(
+            ReasoningFormat::JsonStructured,
+            "```json\n{\"thought\": \"look it up\", \"action\": \"echo\", \"action_input\": {\"message\": \"x\"}}\n```",
+        )
+        .unwrap();
+        assert!(matches!(step, ParsedStep::ToolCall { ref tool, .. } if tool == "echo"));
+
+        let step = parse(
+            ReasoningFormat::JsonStructured,
+            "{\"thought\": \"ok\", \"final_answer\": \"yes\"}",
+        )
+        .unwrap();
+        assert!(matches!(step, ParsedStep::FinalAnswer { ref answer, .. } if answer == "yes"));
+    }
+
+    #[test]
+    fn test_parse_malformed_output() {
+        assert!(parse(ReasoningFormat::ThoughtAction, "I am not sure what to do").is_err());
+        assert!(parse(
+            ReasoningFormat::ThoughtAction,
+            "Action: echo\nAction Input: {message: hi}"
+        )
+        .is_err());
+        assert!(parse(ReasoningFormat::JsonStructured, "{\"thought\": \"hmm\"}").is_err());
+    }
+}
@@ -0,0 +1,381 @@
+//! Record and replay LLM traffic for offline tests
+//!
+//! A [`ReplayClient`] in record mode forwards requests to a live client and
+//! saves each request/response pair to a cassette file. In replay mode it
+//! answers from the cassette, so agents, orchestrators and background runs
+//! can be exercised with no network:
+//!
+//! ```rust,ignore
+//! // Once, against a live endpoint
+//! let client = ReplayClient::record(Arc::new(OpenRouterClient::from_env()?), "tests/cassettes/triage.json");
+//!
+//! // In CI
+//! let client = ReplayClient::replay("tests/cassettes/triage.json")?;
+//! let agent = Agent::builder().client(Arc::new(client)) /* ... */;
+//! ```
+//!
+//! Requests are matched by a hash of the request with stream settings
+//! removed and object keys sorted. Identical requests are answered in the
+//! order they were recorded, repeating the last answer once they run out.
+
+use crate::error::{Error, Result};
+use crate::llm_client::LlmClient;
+use crate::openrouter::{
+    Choice, CompletionRequest, CompletionResponse, CompletionStream, Message, Usage,
+};
+use async_trait::async_trait;
+use parking_lot::Mutex;
+use serde::{Deserialize, Serialize};
+use std::collections::{HashMap, VecDeque};
+use std::path::{Path, PathBuf};
+use std::sync::Arc;
+
+/// One recorded request and its response
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct Interaction {
+    /// Normalised request hash
+    pub key: String,
+    /// Request as sent
+    pub request: CompletionRequest,
+    /// Response received
+    pub response: CompletionResponse,
+}
+
+/// Recorded interactions, stored as JSON
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+pub struct Cassette {
+    /// Interactions in the order they happened
+    pub interactions: Vec<Interaction>,
+}
+
+impl Cassette {
+    /// Load a cassette file
+    pub async fn load(path: impl AsRef<Path>) -> Result<Self> {
+        let data = tokio::fs::read(path.as_ref()).await?;
+        Ok(serde_json::from_slice(&data)?)
+    }
+
+    /// Write the cassette to a file, replacing it
+    pub async fn save(&self, path: impl AsRef<Path>) -> Result<()> {
+        if let Some(parent) = path.as_ref().parent().filter(|p| !p.as_os_str().is_empty()) {
+            tokio::fs::create_dir_all(parent).await?;
+        }
+        tokio::fs::write(path.as_ref(), serde_json::to_vec_pretty(self)?).await?;
+        Ok(())
+    }
+}
+
+/// Hash identifying a request regardless of how its response is delivered
+pub fn request_key(request: &CompletionRequest) -> Result<String> {
+    let mut request = request.clone();
+    request.stream = false;
+    request.stream_options = None;
+
+    let mut canonical = String::new();
+    write_canonical(&serde_json::to_value(&request)?, &mut canonical);
+    Ok(format!("{:016x}", fnv1a(canonical.as_bytes())))
+}
+
+/// JSON with object keys sorted, so the hash does not depend on field order
+fn write_canonical(value: &serde_json::Value, out: &mut String) {
+    match value {
+        serde_json::Value::Object(map) => {
+            let mut keys: Vec<_> = map.keys().collect();
+            keys.sort();
+            out.push('{');
+            for (i, key) in keys.into_iter().enumerate() {
+                if i > 0 {
+                    out.push(',');
+                }
+                out.push_str(&serde_json::Value::from(key.as_str()).to_string());
+                out.push(':');
+                write_canonical(&map[key], out);
+            }
+            out.push('}');
+        }
+        serde_json::Value::Array(items) => {
+            out.push('[');
+            for (i, item) in items.iter().enumerate() {
+                if i > 0 {
+                    out.push(',');
+                }
+                write_canonical(item, out);
+            }
+            out.push(']');
+        }
+        other => out.push_str(&other.to_string()),
+    }
+}
+
+/// 64-bit FNV-1a, stable across platforms and compiler versions
+pub(crate) fn fnv1a(bytes: &[u8]) -> u64 {
+    bytes.iter().fold(0xcbf29ce484222325, |hash, &b| {
+        (hash ^ b as u64).wrapping_mul(0x100000001b3)
+    })
+}
+
+enum Mode {
+    Record {
+        inner: Arc<dyn LlmClient>,
+        path: PathBuf,
+        cassette: tokio::sync::Mutex<Cassette>,
+    },
+    Replay {
+        recorded: Mutex<HashMap<String, VecDeque<CompletionResponse>>>,
+    },
+}
+
+/// [`LlmClient`] that records live traffic or replays it from a cassette
+///
+/// Scripted responses added with [`ReplayClient::with_response`] are served
+/// in order. In replay mode they answer requests missing from the cassette;
+/// in record mode they answer requests before the live client does and are
+/// saved to the cassette like live responses.
+pub struct ReplayClient {
+    mode: Mode,
+    scripted: Mutex<VecDeque<CompletionResponse>>,
+    endpoint: String,
+}
+
+impl ReplayClient {
+    /// Forward requests to `inner`, saving every interaction to `path`
+    ///
+    /// The cassette is rewritten after each response, replacing any
+    /// previous recording.
+    pub fn record(inner: Arc<dyn LlmClient>, path: impl Into<PathBuf>) -> Self {
+        let path = path.into();
+        Self {
+            endpoint: format!("replay://{}", path.display()),
+            mode: Mode::Record {
+                inner,
+                path,
+                cassette: tokio::sync::Mutex::new(Cassette::default()),
+            },
+            scripted: Mutex::new(VecDeque::new()),
+        }
+    }
+
+    /// Answer requests from the cassette at `path`
+    pub async fn replay(path: impl Into<PathBuf>) -> Result<Self> {
+        let path = path.into();
+        let cassette = Cassette::load(&path).await?;
+        let mut client = Self::from_cassette(cassette);
+        client.endpoint = format!("replay://{}", path.display());
+        Ok(client)
+    }
+
+    /// Answer requests from an in-memory cassette
+    pub fn from_cassette(cassette: Cassette) -> Self {
+        let mut recorded: HashMap<String, VecDeque<CompletionResponse>> = HashMap::new();
+        for interaction in cassette.interactions {
+            recorded
+                .entry(interaction.key)
+                .or_default()
+                .push_back(interaction.response);
+        }
+
+        Self {
+            mode: Mode::Replay {
+                recorded: Mutex::new(recorded),
+            },
+            scripted: Mutex::new(VecDeque::new()),
+            endpoint: "replay://memory".to_string(),
+        }
+    }
+
+    /// Answer every request with scripted responses, in order
+    pub fn scripted() -> Self {
+        Self::from_cassette(Cassette::default())
+    }
+
+    /// Queue a scripted response
+    ///
+    /// See [`ReplayClient`] for when scripted responses are served.
+    pub fn with_response(self, response: CompletionResponse) -> Self {
+        self.scripted.lock().push_back(response);
+        self
+    }
+
+    /// Queue a scripted assistant reply with no token usage
+    pub fn with_reply(self, content: impl Into<String>) -> Self {
+        self.with_response(CompletionResponse {
+            id: "scripted".to_string(),
+            model: "scripted".to_string(),
+            provider: None,
+            choices: vec![Choice {
+                index: 0,
+                message: Message::assistant(content),
+                finish_reason: Some("stop".to_string()),
+            }],
+            usage: Usage::default(),
+        })
+    }
+
+    fn replayed(&self, key: &str) -> Option<CompletionResponse> {
+        if let Mode::Replay { recorded } = &self.mode {
+            let mut recorded = recorded.lock();
+            if let Some(responses) = recorded.get_mut(key) {
+                // Keep the last answer for any further identical requests
+                return if responses.len() > 1 {
+                    responses.pop_front()
+                } else {
+                    responses.front().cloned()
+                };
+            }
+        }
+        None
+    }
+}
+
+#[async_trait]
+impl LlmClient for ReplayClient {
+    async fn complete(&self, request: CompletionRequest) -> Result<CompletionResponse> {
+        let key = request_key(&request)?;
+
+        match &self.mode {
+            Mode::Record {
+                inner,
+                path,
+                cassette,
+            } => {
+                let scripted = self.scripted.lock().pop_front();
+                let response = match scripted {
+                    Some(response) => response,
+                    None => inner.complete(request.clone()).await?,
+                };
+                let mut cassette = cassette.lock().await;
+                cassette.interactions.push(Interaction {
+                    key,
+                    request,
+                    response: response.clone(),
+                });
+                cassette.save(path).await?;
+                Ok(response)
+            }
+            Mode::Replay { .. } => self
+                .replayed(&key)
+                .or_else(|| self.scripted.lock().pop_front())
+                .ok_or_else(|| Error::other(format!("No recorded response for request {}", key))),
+        }
+    }
+
+    async fn stream(&self, request: CompletionRequest) -> Result<CompletionStream> {
+        // Streams are recorded and replayed as whole responses
+        Ok(CompletionStream::from_response(
+            self.complete(request).await?,
+        ))
+    }
+
+    fn client_type(&self) -> &str {
+        "replay"
+    }
+
+    fn endpoint(&self) -> &str {
+        &self.endpoint
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::Agent;
+
+    #[tokio::test]
+    async fn test_record_then_replay() {
+        let dir = tempfile::tempdir().unwrap();
+        let path = dir.path().join("cassettes/run.json");
+        let live = Arc::new(
+            ReplayClient::scripted()
+                .with_reply("first")
+                .with_reply("second"),
+        );
+
+        let recorder = ReplayClient::record(live, &path);
+        let request = CompletionRequest::new("m", vec![Message::user("hi")]);
+        recorder.complete(request.clone()).await.unwrap();
+        recorder.complete(request.clone()).await.unwrap();
+
+        let replay = ReplayClient::replay(&path).await.unwrap();
+        let streamed = replay
+            .stream(request.clone().with_stream(true))
+            .await
+            .unwrap();
+        assert_eq!(
+            streamed.collect_response().await.unwrap().choices[0]
+                .message
+                .content,
+            "first"
+        );
+        for _ in 0..2 {
+            let response = replay.complete(request.clone()).await.unwrap();
+            assert_eq!(response.choices[0].message.content, "second");
+        }
+
+        let unknown = CompletionRequest::new("m", vec![Message::user("bye")]);
+        assert!(replay.complete(unknown).await.is_err());
+    }
+
+    #[tokio::test]
+    async fn test_record_serves_scripted_responses_first() {
+        let dir = tempfile::tempdir().unwrap();
+        let path = dir.path().join("run.json");
+        let live = Arc::new(ReplayClient::scripted().with_reply("live"));
+
+        let recorder = ReplayClient::record(live, &path).with_reply("stub");
+        let stubbed = CompletionRequest::new("m", vec![Message::user("stubbed")]);
+        let forwarded = CompletionRequest::new("m", vec![Message::user("forwarded")]);
+        assert_eq!(
+            recorder.complete(stubbed.clone()).await.unwrap().choices[0]
+                .message
+                .content,
+            "stub"
+        );
+        assert_eq!(
+            recorder.complete(forwarded.clone()).await.unwrap().choices[0]
+                .message
+                .content,
+            "live"
+        );
+
+        let replay = ReplayClient::replay(&path).await.unwrap();
+        assert_eq!(
+            replay.complete(stubbed).await.unwrap().choices[0]
+                .message
+                .content,
+            "stub"
+        );
+        assert_eq!(
+            replay.complete(forwarded).await.unwrap().choices[0]
+                .message
+                .content,
+            "live"
+        );
+    }
+
+    #[test]
+    fn test_request_key_ignores_stream_settings() {
+        let request = CompletionRequest::new("m", vec![Message::user("hi")]).with_temperature(0.0);
+        let key = request_key(&request).unwrap();
+
+        assert_eq!(
+            key,
+            request_key(&request.clone().with_stream(true)).unwrap()
+        );
+        assert_ne!(key, request_key(&request.with_max_tokens(10)).unwrap());
+    }
+
+    #[tokio::test]
+    async fn test_scripted_agent_run() {
+        let client = ReplayClient::scripted().with_reply("Thought: I know this\nFinal Answer: 4");
+        let agent = Agent::builder()
+            .name("Calculator")
+            .model("m")
+            .system_prompt("Answer arithmetic questions.")
+            .client(Arc::new(client))
+            .build()
+            .unwrap();
+
+        let output = agent.react_loop("What is 2 + 2?").await.unwrap();
+        assert_eq!(output.content, "4");
+    }
+}
@@ -6,14 +6,36 @@
 //! - Context window optimization
 //! - Automatic archival of old memories
 //! - Pattern detection across conversation history
-
+//!
+//! Given an [`LlmClient`] with [`SleepTimeAgent::with_llm`], consolidation
+//! summarizes new messages with the model, rewrites core memory blocks
+//! within their `max_size`, resolves contradictions between blocks and
+//! saves durable facts to archival memory. Without one it falls back to
+//! simple counting heuristics. Every change is recorded as a [`MemoryEdit`]
+//! with the values before and after, available from
+//! [`SleepTimeAgent::edits`] and kept across restarts with
+//! [`SleepTimeAgent::persist_to_storage`] (`storage` feature).
+
+use crate::archival::PassageSource;
+use crate::budget::{self, BudgetLedger};
 use crate::error::{Error, Result};
-use crate::memory::{AgentMemory, MemoryBlock};
+use crate::llm_client::LlmClient;
+use crate::memory::{AgentMemory, EditAuthor, MemoryBlock, MemoryBlockId, MessageEntry};
+use crate::openrouter::{CompletionRequest, CompletionResponse, Message, ResponseFormat};
+#[cfg(feature = "storage")]
+use crate::storage::MemoryStorage;
 use crate::types::AgentId;
+use chrono::{DateTime, Utc};
+use serde::{Deserialize, Serialize};
+use std::collections::HashMap;
 use std::sync::Arc;
 use std::time::Duration;
 use tokio::sync::{watch, RwLock};
 use tokio::time;
+use uuid::Uuid;
+
+/// Name consolidation calls are charged under in budget reports
+const SLEEP_TIME_AGENT_NAME: &str = "sleep-time";
 
 /// Configuration for sleep-time agent behavior
 #[derive(Debug, Clone)]
@@ -32,6 +54,12 @@ pub struct SleepTimeConfig {
 
     /// Enable pattern detection
     pub enable_pattern_detection: bool,
+
+    /// Let the LLM rewrite blocks, resolve contradictions and extract facts
+    pub enable_reflection: bool,
+
+    /// Size limit for a new `conversation_summary` block
+    pub summary_max_chars: usize,
 }
 
 impl Default for SleepTimeConfig {
@@ -42,12 +70,196 @@ impl Default for SleepTimeConfig {
             context_warning_threshold: 6000, // 75% of default 8K context
             enable_summarization: true,
             enable_pattern_detection: true,
+            enable_reflection: true,
+            summary_max_chars: 2000,
+        }
+    }
+}
+
+/// Kind of change the sleep-time agent made to memory
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum MemoryEditKind {
+    /// Conversation summary written or extended
+    Summary,
+    /// Block rewritten to reflect what the agent has learned
+    Rewrite,
+    /// Block rewritten to resolve a contradiction with another block
+    ContradictionResolved,
+    /// Fact saved to archival memory
+    FactArchived,
+    /// Block moved out of context
+    MovedOutOfContext,
+    /// Repeated questions recorded
+    PatternsDetected,
+}
+
+/// One change made by the sleep-time agent, kept for auditing
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct MemoryEdit {
+    /// Unique edit ID
+    pub id: Uuid,
+    /// When the edit was made
+    pub timestamp: DateTime<Utc>,
+    /// What kind of change this was
+    pub kind: MemoryEditKind,
+    /// Block that changed, or `None` for archival inserts
+    pub block_id: Option<MemoryBlockId>,
+    /// Block label, or `archival` for archival inserts
+    pub label: String,
+    /// Value before the edit, `None` if the block was created
+    pub before: Option<String>,
+    /// Value after the edit
+    pub after: Option<String>,
+    /// Why the edit was made
+    pub reason: String,
+}
+
+impl MemoryEdit {
+    fn new(kind: MemoryEditKind, label: impl Into<String>, reason: impl Into<String>) -> Self {
+        Self {
+            id: Uuid::new_v4(),
+            timestamp: Utc::now(),
+            kind,
+            block_id: None,
+            label: label.into(),
+            before: None,
+            after: None,
+            reason: reason.into(),
+        }
+    }
+
+    fn for_block(kind: MemoryEditKind, block: &MemoryBlock, reason: impl Into<String>) -> Self {
+        Self {
+            block_id: Some(block.id),
+            before: Some(block.value.clone()),
+            ..Self::new(kind, &block.label, reason)
+        }
+    }
+
+    fn with_after(mut self, after: impl Into<String>) -> Self {
+        self.after = Some(after.into());
+        self
+    }
+}
+
+/// Append-only record of edits
+#[derive(Default)]
+struct EditLog {
+    edits: RwLock<Vec<MemoryEdit>>,
+    /// Number of leading edits already written to storage
+    #[cfg(feature = "storage")]
+    persisted: RwLock<usize>,
+}
+
+impl EditLog {
+    async fn record(&self, edit: MemoryEdit) {
+        tracing::info!(
+            kind = ?edit.kind,
+            label = %edit.label,
+            reason = %edit.reason,
+            "Sleep-time memory edit"
+        );
+        self.edits.write().await.push(edit);
+    }
+
+    async fn len(&self) -> usize {
+        self.edits.read().await.len()
+    }
+
+    async fn since(&self, start: usize) -> Vec<MemoryEdit> {
+        self.edits.read().await[start..].to_vec()
+    }
+
+    /// Put stored edits ahead of any made since startup
+    #[cfg(feature = "storage")]
+    async fn load(&self, storage: &dyn MemoryStorage, agent_id: AgentId) -> Result<()> {
+        let stored = storage.load_memory_edits(agent_id).await?;
+        let mut persisted = self.persisted.write().await;
+        let mut edits = self.edits.write().await;
+
+        *persisted = stored.len();
+        let ids: std::collections::HashSet<Uuid> = stored.iter().map(|e| e.id).collect();
+        let unsaved: Vec<MemoryEdit> = edits.drain(..).filter(|e| !ids.contains(&e.id)).collect();
+        *edits = stored;
+        edits.extend(unsaved);
+        Ok(())
+    }
+
+    /// Write edits not yet in storage
+    #[cfg(feature = "storage")]
+    async fn persist(&self, storage: &dyn MemoryStorage, agent_id: AgentId) -> Result<()> {
+        let mut persisted = self.persisted.write().await;
+        let pending = self.edits.read().await[*persisted..].to_vec();
+        for edit in &pending {
+            storage.save_memory_edit(agent_id, edit).await?;
+            *persisted += 1;
         }
+        Ok(())
     }
 }
 
+const SUMMARY_PROMPT: &str = "You maintain the long-term conversation summary of an AI agent. \
+Merge the new messages into the current summary. Keep decisions, facts about the user, \
+commitments and open questions; drop small talk. Reply with the summary text only.";
+
+const REFLECTION_PROMPT: &str = "You maintain the core memory of an AI agent. Given its memory \
+blocks and recent messages, reply with JSON containing:
+- rewrites: blocks whose value should change to reflect what was learned. Give the full new \
+value, which must fit within the block's limit.
+- contradictions: blocks that disagree with each other or with the messages, with an \
+explanation and the rewrites that resolve them. Prefer the most recent information.
+- facts: durable, self-contained facts from the messages worth recalling in later \
+conversations, e.g. preferences, names, dates and decisions.
+Leave a list empty when there is nothing to do; do not rewrite blocks that are already accurate.";
+
+/// Block change proposed by the LLM
+#[derive(Debug, Deserialize, schemars::JsonSchema)]
+struct BlockRewrite {
+    /// Label of the block to change
+    label: String,
+    /// Complete new value
+    value: String,
+    /// Why the change is needed
+    reason: String,
+}
+
+/// Blocks that disagree and how to reconcile them
+#[derive(Debug, Deserialize, schemars::JsonSchema)]
+struct Contradiction {
+    /// What disagrees
+    explanation: String,
+    /// Rewrites that resolve it
+    rewrites: Vec<BlockRewrite>,
+}
+
+/// Consolidation plan returned by the LLM
+#[derive(Debug, Deserialize, schemars::JsonSchema)]
+struct ReflectionPlan {
+    #[serde(default)]
+    rewrites: Vec<BlockRewrite>,
+    #[serde(default)]
+    contradictions: Vec<Contradiction>,
+    #[serde(default)]
+    facts: Vec<String>,
+}
+
+/// One consolidation pass, runnable from the agent or its background task
+struct Consolidator {
+    id: AgentId,
+    ledgers: Vec<Arc<BudgetLedger>>,
+    memory: Arc<AgentMemory>,
+    config: SleepTimeConfig,
+    llm: Option<(Arc<dyn LlmClient>, String)>,
+    log: Arc<EditLog>,
+    processed_through: Arc<RwLock<Option<Uuid>>>,
+}
+
 /// Sleep-time agent that processes memory in the background
 pub struct SleepTimeAgent {
+    /// ID this agent's LLM usage is charged under
+    id: AgentId,
+
     /// ID of the primary agent this sleep-time agent serves
     primary_agent_id: AgentId,
 
@@ -57,6 +269,18 @@ pub struct SleepTimeAgent {
     /// Configuration
     config: SleepTimeConfig,
 
+    /// Model used for summaries and reflection, with its model name
+    llm: Option<(Arc<dyn LlmClient>, String)>,
+
+    /// Budget charged for consolidation calls
+    budget: Option<Arc<BudgetLedger>>,
+
+    /// Every edit made to memory
+    log: Arc<EditLog>,
+
+    /// Last message already consolidated by the LLM
+    processed_through: Arc<RwLock<Option<Uuid>>>,
+
     /// Flag to control the background task
     running: Arc<RwLock<bool>>,
 
@@ -78,9 +302,14 @@ impl SleepTimeAgent {
         let (shutdown_tx, shutdown_rx) = watch::channel(false);
 
         Self {
+            id: AgentId::new(),
             primary_agent_id,
             shared_memory,
             config,
+            llm: None,
+            budget: None,
+            log: Arc::new(EditLog::default()),
+            processed_through: Arc::new(RwLock::new(None)),
             running: Arc::new(RwLock::new(false)),
             shutdown_tx,
             shutdown_rx,
@@ -88,6 +317,80 @@ impl SleepTimeAgent {
         }
     }
 
+    /// Consolidate with `model` on `llm` instead of heuristics
+    pub fn with_llm(mut self, llm: Arc<dyn LlmClient>, model: impl Into<String>) -> Self {
+        self.llm = Some((llm, model.into()));
+        self
+    }
+
+    /// Charge consolidation calls to a budget
+    ///
+    /// Calls are also charged to any ledger active where the agent is started
+    /// or [`consolidate`](Self::consolidate) is called.
+    pub fn with_budget(mut self, budget: Arc<BudgetLedger>) -> Self {
+        self.budget = Some(budget);
+        self
+    }
+
+    /// ID this agent's LLM usage is charged under
+    pub fn id(&self) -> AgentId {
+        self.id
+    }
+
+    /// Every edit made to memory so far, oldest first
+    pub async fn edits(&self) -> Vec<MemoryEdit> {
+        self.log.since(0).await
+    }
+
+    /// Run one consolidation pass now, returning the edits it made
+    pub async fn consolidate(&self) -> Result<Vec<MemoryEdit>> {
+        self.consolidator().run().await
+    }
+
+    /// Load the edit log of the primary agent from a storage backend
+    ///
+    /// Stored edits come first in [`edits`](Self::edits), followed by any
+    /// made since this agent was created. Consolidation resumes after the
+    /// last stored message already processed, unless this agent has
+    /// processed messages since it was created.
+    #[cfg(feature = "storage")]
+    pub async fn load_from_storage(&self, storage: &dyn MemoryStorage) -> Result<()> {
+        self.log.load(storage, self.primary_agent_id).await?;
+        let stored = storage
+            .load_processed_through(self.primary_agent_id)
+            .await?;
+        let mut processed_through = self.processed_through.write().await;
+        if processed_through.is_none() {
+            *processed_through = stored;
+        }
+        Ok(())
+    }
+
+    /// Persist edits not yet written, and the last processed message, to a
+    /// storage backend
+    #[cfg(feature = "storage")]
+    pub async fn persist_to_storage(&self, storage: &dyn MemoryStorage) -> Result<()> {
+        self.log.persist(storage, self.primary_agent_id).await?;
+        if let Some(message_id) = *self.processed_through.read().await {
+            storage
+                .save_processed_through(self.primary_agent_id, message_id)
+                .await?;
+        }
+        Ok(())
+    }
+
+    fn consolidator(&self) -> Consolidator {
+        Consolidator {
+            id: self.id,
+            ledgers: budget::active_with(self.budget.as_ref()),
+            memory: self.shared_memory.clone(),
+            config: self.config.clone(),
+            llm: self.llm.clone(),
+            log: self.log.clone(),
+            processed_through: self.processed_through.clone(),
+        }
+    }
+
     /// Start the background processing loop
     pub async fn start(&self) -> Result<()> {
         let mut running = self.running.write().await;
@@ -101,14 +404,14 @@ impl SleepTimeAgent {
         let _ = self.shutdown_tx.send(false);
 
         // Spawn background task
-        let memory = self.shared_memory.clone();
-        let config = self.config.clone();
+        let consolidator = self.consolidator();
+        let interval_duration = self.config.consolidation_interval;
         let running_flag = self.running.clone();
         let mut shutdown_rx = self.shutdown_rx.clone();
         let agent_id = self.primary_agent_id;
 
         let handle = tokio::spawn(async move {
-            let mut interval = time::interval(config.consolidation_interval);
+            let mut interval = time::interval(interval_duration);
 
             loop {
                 tokio::select! {
@@ -125,8 +428,8 @@ impl SleepTimeAgent {
                         }
 
                         // Perform consolidation
-                        if let Err(e) = Self::consolidate_memory(&memory, &config, agent_id).await {
-                            eprintln!("Sleep-time agent error during consolidation: {}", e);
+                        if let Err(e) = consolidator.run().await {
+                            tracing::error!("Sleep-time agent error during consolidation for {}: {}", agent_id, e);
                         }
                     }
                 }
@@ -148,47 +451,16 @@ impl SleepTimeAgent {
         // Wait for task to complete
         let mut task_handle = self.task_handle.write().await;
         if let Some(handle) = task_handle.take() {
-            handle.await.map_err(|e| {
-                Error::config(format!("Failed to stop sleep-time agent: {}", e))
-            })?;
-        }
-
-        Ok(())
-    }
-
-    /// Perform memory consolidation
-    async fn consolidate_memory(
-        memory: &Arc<AgentMemory>,
-        config: &SleepTimeConfig,
-        _agent_id: AgentId,
-    ) -> Result<()> {
-        // Check message count
-        let recent_messages = memory.get_recent_messages(1000).await;
-        if recent_messages.len() < config.min_messages_for_consolidation {
-            return Ok(());
-        }
-
-        // Check context size
-        let context_size = memory.context_size().await;
-        let needs_archival = context_size > config.context_warning_threshold;
-
-        if needs_archival {
-            Self::perform_archival(memory).await?;
-        }
-
-        if config.enable_summarization {
-            Self::perform_summarization(memory, &recent_messages).await?;
-        }
-
-        if config.enable_pattern_detection {
-            Self::detect_patterns(memory, &recent_messages).await?;
+            handle
+                .await
+                .map_err(|e| Error::config(format!("Failed to stop sleep-time agent: {}", e)))?;
         }
 
         Ok(())
     }
 
     /// Archive old or low-priority memory blocks
-    async fn perform_archival(memory: &Arc<AgentMemory>) -> Result<()> {
+    async fn perform_archival(memory: &Arc<AgentMemory>, log: &EditLog) -> Result<()> {
         let in_context = memory.in_context_blocks().await;
 
         // Simple heuristic: archive blocks that haven't been updated recently
@@ -203,6 +475,15 @@ impl SleepTimeAgent {
                 && block.label != "system"
             {
                 memory.move_out_of_context(block.id).await?;
+                log.record(
+                    MemoryEdit::for_block(
+                        MemoryEditKind::MovedOutOfContext,
+                        &block,
+                        "Context over warning threshold and block not updated for an hour",
+                    )
+                    .with_after(&block.value),
+                )
+                .await;
             }
         }
 
@@ -212,7 +493,8 @@ impl SleepTimeAgent {
     /// Summarize old messages and create summary memory block
     async fn perform_summarization(
         memory: &Arc<AgentMemory>,
-        messages: &[crate::memory::MessageEntry],
+        messages: &[MessageEntry],
+        log: &EditLog,
     ) -> Result<()> {
         if messages.len() < 50 {
             return Ok(());
@@ -242,9 +524,7 @@ impl SleepTimeAgent {
         for msg in to_summarize {
             let words: Vec<&str> = msg.content.split_whitespace().collect();
             for word in words {
-                if word.len() > 5
-                    && !keywords.contains(&word.to_lowercase())
-                    && keywords.len() < 10
+                if word.len() > 5 && !keywords.contains(&word.to_lowercase()) && keywords.len() < 10
                 {
                     keywords.push(word.to_lowercase());
                 }
@@ -253,23 +533,58 @@ impl SleepTimeAgent {
 
         summary.push_str(&format!("- Key topics: {}\n", keywords.join(", ")));
 
-        // Check if we already have a conversation_summary block
+        Self::write_block(
+            memory,
+            log,
+            MemoryBlock::with_description(
+                "conversation_summary",
+                "Automatically generated summary of conversation history",
+                "",
+            ),
+            |existing| {
+                if existing.is_empty() {
+                    summary
+                } else {
+                    format!("{}\n{}", existing, summary)
+                }
+            },
+            MemoryEditKind::Summary,
+            "Message counts and keywords of older messages",
+        )
+        .await
+    }
+
+    /// Replace the value of the block labelled like `template` with
+    /// `update(old_value)`, or add `template` with value `update("")`
+    async fn write_block(
+        memory: &Arc<AgentMemory>,
+        log: &EditLog,
+        mut template: MemoryBlock,
+        update: impl FnOnce(&str) -> String,
+        kind: MemoryEditKind,
+        reason: &str,
+    ) -> Result<()> {
         let blocks = memory.in_context_blocks().await;
-        let summary_block = blocks.iter().find(|b| b.label == "conversation_summary");
 
-        if let Some(existing) = summary_block {
-            // Append to existing summary
-            let mut updated = existing.clone();
-            updated.append(summary)?;
-            memory.update_block(existing.id, updated.value).await?;
+        let author = EditAuthor::SleepTime {
+            agent_id: memory.agent_id,
+        };
+
+        if let Some(existing) = blocks.iter().find(|b| b.label == template.label) {
+            let value = update(&existing.value);
+            memory
+                .update_block_as(existing.id, value.clone(), author, reason)
+                .await?;
+            log.record(MemoryEdit::for_block(kind, existing, reason).with_after(value))
+                .await;
         } else {
-            // Create new summary block
-            let block = MemoryBlock::with_description(
-                "conversation_summary",
-                "Automatically generated summary of conversation history",
-                summary,
-            );
-            memory.add_block(block).await?;
+            template.update_value(update(""))?;
+            let edit = MemoryEdit {
+                block_id: Some(template.id),
+                ..MemoryEdit::new(kind, &template.label, reason).with_after(&template.value)
+            };
+            memory.add_block_as(template, author, reason).await?;
+            log.record(edit).await;
         }
 
         Ok(())
@@ -278,15 +593,15 @@ impl SleepTimeAgent {
     /// Detect patterns in conversation history
     async fn detect_patterns(
         memory: &Arc<AgentMemory>,
-        messages: &[crate::memory::MessageEntry],
+        messages: &[MessageEntry],
+        log: &EditLog,
     ) -> Result<()> {
         if messages.is_empty() {
             return Ok(());
         }
 
         // Simple pattern detection: repeated questions
-        let mut question_patterns: std::collections::HashMap<String, usize> =
-            std::collections::HashMap::new();
+        let mut question_patterns: HashMap<String, usize> = HashMap::new();
 
         for msg in messages.iter().filter(|m| m.role == "user") {
             // Extract first 50 chars as pattern key
@@ -301,10 +616,11 @@ impl SleepTimeAgent {
         }
 
         // Find repeated patterns (asked 3+ times)
-        let repeated: Vec<String> = question_patterns
+        let mut repeated: Vec<String> = question_patterns
             .into_iter()
             .filter_map(|(pattern, count)| if count >= 3 { Some(pattern) } else { None })
             .collect();
+        repeated.sort();
 
         if !repeated.is_empty() {
             // Store detected patterns in a memory block
@@ -317,18 +633,25 @@ impl SleepTimeAgent {
                     .join("\n")
             );
 
-            let blocks = memory.in_context_blocks().await;
-            let pattern_block = blocks.iter().find(|b| b.label == "detected_patterns");
-
-            if let Some(existing) = pattern_block {
-                memory.update_block(existing.id, patterns_text).await?;
-            } else {
-                let block = MemoryBlock::with_description(
-                    "detected_patterns",
-                    "Patterns detected in conversation by sleep-time agent",
-                    patterns_text,
-                );
-                memory.add_block(block).await?;
+            let unchanged = memory
+                .in_context_blocks()
+                .await
+                .iter()
+                .any(|b| b.label == "detected_patterns" && b.value == patterns_text);
+            if !unchanged {
+                Self::write_block(
+                    memory,
+                    log,
+                    MemoryBlock::with_description(
+                        "detected_patterns",
+                        "Patterns detected in conversation by sleep-time agent",
+                        "",
+                    ),
+                    |_| patterns_text,
+                    MemoryEditKind::PatternsDetected,
+                    "Questions asked three or more times",
+                )
+                .await?;
             }
         }
 
@@ -336,6 +659,310 @@ impl SleepTimeAgent {
     }
 }
 
+impl Consolidator {
+    /// Perform memory consolidation
+    async fn run(&self) -> Result<Vec<MemoryEdit>> {
+        let start = self.log.len().await;
+
+        // Check message count
+        let recent_messages = self.memory.get_recent_messages(1000).await;
+        if recent_messages.len() < self.config.min_messages_for_consolidation {
+            return Ok(Vec::new());
+        }
+
+        // Check context size
+        let context_size = self.memory.context_size().await;
+        let needs_archival = context_size > self.config.context_warning_threshold;
+
+        if needs_archival {
+            SleepTimeAgent::perform_archival(&self.memory, &self.log).await?;
+        }
+
+        match &self.llm {
+            Some((llm, model)) => {
+                self.consolidate_with_llm(llm.as_ref(), model, &recent_messages)
+                    .await?
+            }
+            None if self.config.enable_summarization => {
+                SleepTimeAgent::perform_summarization(&self.memory, &recent_messages, &self.log)
+                    .await?;
+            }
+            None => {}
+        }
+
+        if self.config.enable_pattern_detection {
+            SleepTimeAgent::detect_patterns(&self.memory, &recent_messages, &self.log).await?;
+        }
+
+        Ok(self.log.since(start).await)
+    }
+
+    /// Summarize and reflect on messages not yet consolidated
+    async fn consolidate_with_llm(
+        &self,
+        llm: &dyn LlmClient,
+        model: &str,
+        messages: &[MessageEntry],
+    ) -> Result<()> {
+        let processed_through = *self.processed_through.read().await;
+        let new_messages =
+            match processed_through.and_then(|id| messages.iter().position(|m| m.id == id)) {
+                Some(index) => &messages[index + 1..],
+                None => messages,
+            };
+        if new_messages.len() < self.config.min_messages_for_consolidation.max(1) {
+            return Ok(());
+        }
+
+        let transcript = new_messages
+            .iter()
+            .map(|m| format!("[{}] {}", m.role, m.content))
+            .collect::<Vec<_>>()
+            .join("\n");
+
+        let start = self.log.len().await;
+        let mut result = Ok(());
+        if self.config.enable_summarization {
+            result = self.summarize(llm, model, &transcript).await;
+        }
+        if result.is_ok() && self.config.enable_reflection {
+            result = self.reflect(llm, model, &transcript).await;
+        }
+
+        // Once an edit has been applied, retrying these messages would apply
+        // it again, so a failed pass only retries if it changed nothing
+        if result.is_ok() || self.log.len().await > start {
+            *self.processed_through.write().await = new_messages.last().map(|m| m.id);
+        }
+        result
+    }
+
+    /// Merge new messages into the `conversation_summary` block
+    async fn summarize(&self, llm: &dyn LlmClient, model: &str, transcript: &str) -> Result<()> {
+        let existing = self
+            .memory
+            .in_context_blocks()
+            .await
+            .into_iter()
+            .find(|b| b.label == "conversation_summary");
+        let limit = existing
+            .as_ref()
+            .and_then(|b| b.max_size)
+            .unwrap_or(self.config.summary_max_chars);
+
+        let prompt = format!(
+            "Current summary:\n{}\n\nNew messages:\n{}\n\nWrite the updated summary in at most {} characters.",
+            existing.as_ref().map(|b| b.value.as_str()).unwrap_or("(none)"),
+            transcript,
+            limit
+        );
+        let Some(summary) = self
+            .complete_within(llm, model, SUMMARY_PROMPT, prompt, limit)
+            .await?
+        else {
+            tracing::warn!(
+                "Sleep-time summary did not fit in {} characters; keeping the old one",
+                limit
+            );
+            return Ok(());
+        };
+
+        let mut template = MemoryBlock::with_description(
+            "conversation_summary",
+            "Summary of conversation history, maintained by the sleep-time agent",
+            "",
+        );
+        template.max_size = Some(limit);
+
+        SleepTimeAgent::write_block(
+            &self.memory,
+            &self.log,
+            template,
+            |_| summary,
+            MemoryEditKind::Summary,
+            "Merged new messages into the summary",
+        )
+        .await
+    }
+
+    /// Ask the LLM for block rewrites, contradictions and facts, and apply them
+    async fn reflect(&self, llm: &dyn LlmClient, model: &str, transcript: &str) -> Result<()> {
+        let mut blocks = self.memory.in_context_blocks().await;
+        blocks.sort_by(|a, b| {
+            a.created_at
+                .cmp(&b.created_at)
+                .then_with(|| a.label.cmp(&b.label))
+        });
+        let rendered = blocks
+            .iter()
+            .map(|b| {
+                format!(
+                    "<block label=\"{}\" limit=\"{}\">\n{}\n{}\n</block>",
+                    b.label,
+                    b.max_size
+                        .map(|m| m.to_string())
+                        .unwrap_or_else(|| "none".to_string()),
+                    b.description,
+                    b.value
+                )
+            })
+            .collect::<Vec<_>>()
+            .join("\n");
+
+        let schema = serde_json::to_value(schemars::schema_for!(ReflectionPlan))?;
+        let request = CompletionRequest::new(
+            model,
+            vec![
+                Message::system(REFLECTION_PROMPT),
+                Message::user(format!(
+                    "Memory blocks:\n{}\n\nRecent messages:\n{}",
+                    rendered, transcript
+                )),
+            ],
+        )
+        .with_response_format(ResponseFormat::json_schema("memory_consolidation", schema));
+
+        let response = self.complete(llm, request).await?;
+        let content = response
+            .choices
+            .first()
+            .map(|c| c.message.content.text().into_owned())
+            .unwrap_or_default();
+        let plan: ReflectionPlan = crate::agent::parse_json_answer(&content)
+            .and_then(serde_json::from_value)
+            .map_err(|e| Error::JsonSchema(format!("invalid consolidation plan: {}", e)))?;
+
+        for rewrite in plan.rewrites {
+            let reason = rewrite.reason.clone();
+            self.apply_rewrite(rewrite, MemoryEditKind::Rewrite, reason)
+                .await?;
+        }
+        for contradiction in plan.contradictions {
+            for rewrite in contradiction.rewrites {
+                let reason = format!("{} {}", contradiction.explanation, rewrite.reason);
+                self.apply_rewrite(
+                    rewrite,
+                    MemoryEditKind::ContradictionResolved,
+                    reason.trim().to_string(),
+                )
+                .await?;
+            }
+        }
+
+        if plan.facts.is_empty() {
+            return Ok(());
+        }
+        let Some(archival) = self.memory.archival() else {
+            tracing::debug!(
+                "Skipping {} extracted facts: no archival memory",
+                plan.facts.len()
+            );
+            return Ok(());
+        };
+        for fact in plan.facts {
+            let metadata = HashMap::from([("source".to_string(), "sleeptime".to_string())]);
+            archival
+                .insert(&fact, PassageSource::Insert, metadata)
+                .await?;
+            self.log
+                .record(
+                    MemoryEdit::new(
+                        MemoryEditKind::FactArchived,
+                        "archival",
+                        "Durable fact from recent messages",
+                    )
+                    .with_after(fact),
+                )
+                .await;
+        }
+
+        Ok(())
+    }
+
+    /// Apply a proposed rewrite if the block exists, changes and fits its limit
+    async fn apply_rewrite(
+        &self,
+        rewrite: BlockRewrite,
+        kind: MemoryEditKind,
+        reason: String,
+    ) -> Result<()> {
+        let blocks = self.memory.in_context_blocks().await;
+        let Some(block) = blocks.iter().find(|b| b.label == rewrite.label) else {
+            tracing::warn!("Sleep-time rewrite names unknown block '{}'", rewrite.label);
+            return Ok(());
+        };
+        if block.value == rewrite.value {
+            return Ok(());
+        }
+        if block.max_size.is_some_and(|max| rewrite.value.len() > max) {
+            tracing::warn!(
+                "Sleep-time rewrite of '{}' exceeds its limit ({} > {}); skipped",
+                block.label,
+                rewrite.value.len(),
+                block.max_size.unwrap_or_default()
+            );
+            return Ok(());
+        }
+
+        let author = EditAuthor::SleepTime {
+            agent_id: self.memory.agent_id,
+        };
+        self.memory
+            .update_block_as(block.id, rewrite.value.clone(), author, &reason)
+            .await?;
+        self.log
+            .record(MemoryEdit::for_block(kind, block, reason).with_after(rewrite.value))
+            .await;
+        Ok(())
+    }
+}
+
+impl Consolidator {
+    /// Complete a request, charging this agent's ledgers
+    async fn complete(
+        &self,
+        llm: &dyn LlmClient,
+        request: CompletionRequest,
+    ) -> Result<CompletionResponse> {
+        budget::complete_charged(llm, &self.ledgers, self.id, SLEEP_TIME_AGENT_NAME, request).await
+    }
+
+    /// Complete a prompt, asking once more for a shorter reply if it exceeds `limit` bytes
+    async fn complete_within(
+        &self,
+        llm: &dyn LlmClient,
+        model: &str,
+        system: &str,
+        prompt: String,
+        limit: usize,
+    ) -> Result<Option<String>> {
+        let mut messages = vec![Message::system(system), Message::user(prompt)];
+
+        for _ in 0..2 {
+            let response = self
+                .complete(llm, CompletionRequest::new(model, messages.clone()))
+                .await?;
+            let text = response
+                .choices
+                .first()
+                .map(|c| c.message.content.text().trim().to_string())
+                .unwrap_or_default();
+            if !text.is_empty() && text.len() <= limit {
+                return Ok(Some(text));
+            }
+
+            messages.push(Message::assistant(&text));
+            messages.push(Message::user(format!(
+                "That is {} characters; the limit is {}. Shorten it.",
+                text.len(),
+                limit
+            )));
+        }
+
+        Ok(None)
+    }
+}
+
 impl Drop for SleepTimeAgent {
     fn drop(&mut self) {
         // Best effort stop on drop (can't await in Drop)
@@ -352,6 +979,9 @@ impl Drop for SleepTimeAgent {
 #[cfg(test)]
 mod tests {
     use super::*;
+    use crate::archival::{ArchivalMemory, FlatIndex};
+    use crate::embeddings::HashingEmbedder;
+    use crate::llm_client::testing::ScriptedClient;
     use crate::memory::MemoryConfig;
     use crate::types::AgentId;
 
@@ -393,6 +1023,244 @@ mod tests {
             .unwrap();
     }
 
+    #[tokio::test]
+    async fn test_llm_consolidation_is_audited() {
+        let agent_id = AgentId::new();
+        let archival = Arc::new(ArchivalMemory::new(
+            agent_id,
+            Arc::new(HashingEmbedder::new(64)),
+            Arc::new(FlatIndex::new()),
+        ));
+        let memory = Arc::new(
+            AgentMemory::new(agent_id, MemoryConfig::default()).with_archival(archival.clone()),
+        );
+
+        let mut human = MemoryBlock::new("human", "Name: Sam. Lives in Berlin.");
+        human.max_size = Some(100);
+        let human_id = memory.add_block(human).await.unwrap();
+        memory
+            .add_block(MemoryBlock::new("travel", "Sam is moving to Munich"))
+            .await
+            .unwrap();
+        let mut mood = MemoryBlock::new("mood", "calm");
+        mood.max_size = Some(10);
+        let mood_id = memory.add_block(mood).await.unwrap();
+
+        memory
+            .add_message(
+                "user".to_string(),
+                "I moved to Lisbon last week".to_string(),
+            )
+            .await;
+        memory
+            .add_message("assistant".to_string(), "Congrats on Lisbon!".to_string())
+            .await;
+        memory
+            .add_message("user".to_string(), "I'm vegetarian, by the way".to_string())
+            .await;
+
+        let plan = serde_json::json!({
+            "rewrites": [
+                {"label": "human", "value": "Name: Sam. Lives in Lisbon.", "reason": "Sam moved."},
+                {"label": "mood", "value": "far too long for this block", "reason": "Too big."}
+            ],
+            "contradictions": [{
+                "explanation": "travel says Munich but Sam moved to Lisbon.",
+                "rewrites": [{"label": "travel", "value": "Sam moved to Lisbon", "reason": "Latest message wins."}]
+            }],
+            "facts": ["Sam is vegetarian."]
+        });
+        let client = Arc::new(
+            ScriptedClient::new(vec![
+                Message::assistant(
+                    "Sam told the assistant about moving from Berlin to Lisbon and about being vegetarian.",
+                ),
+                Message::assistant("Sam moved to Lisbon; vegetarian."),
+                Message::assistant(format!("```json\n{}\n```", plan)),
+            ])
+            .with_fallback(""),
+        );
+        let config = SleepTimeConfig {
+            min_messages_for_consolidation: 2,
+            summary_max_chars: 60,
+            enable_pattern_detection: false,
+            ..Default::default()
+        };
+        let ledger = Arc::new(BudgetLedger::new());
+        let sleeptime = SleepTimeAgent::new(agent_id, memory.clone(), config)
+            .with_llm(client.clone(), "test-model")
+            .with_budget(ledger.clone());
+
+        let edits = sleeptime.consolidate().await.unwrap();
+        let kinds: Vec<MemoryEditKind> = edits.iter().map(|e| e.kind).collect();
+        assert_eq!(
+            kinds,
+            vec![
+                MemoryEditKind::Summary,
+                MemoryEditKind::Rewrite,
+                MemoryEditKind::ContradictionResolved,
+                MemoryEditKind::FactArchived,
+            ]
+        );
+        assert_eq!(edits[0].before, None);
+        assert_eq!(
+            edits[0].after.as_deref(),
+            Some("Sam moved to Lisbon; vegetarian.")
+        );
+        assert_eq!(edits[1].block_id, Some(human_id));
+        assert_eq!(
+            edits[1].before.as_deref(),
+            Some("Name: Sam. Lives in Berlin.")
+        );
+        assert_eq!(
+            edits[1].after.as_deref(),
+            Some("Name: Sam. Lives in Lisbon.")
+        );
+        assert!(edits[2].reason.starts_with("travel says Munich"));
+        let history = memory.block_history(human_id).await.unwrap();
+        assert_eq!(
+            history.last().unwrap().author,
+            EditAuthor::SleepTime { agent_id }
+        );
+        assert_eq!(history.last().unwrap().reason, "Sam moved.");
+        assert_eq!(memory.get_block(mood_id).await.unwrap().value, "calm");
+
+        let hits = archival.search("is Sam vegetarian", 1).await.unwrap();
+        assert_eq!(hits[0].passage.text, "Sam is vegetarian.");
+
+        {
+            let requests = client.requests();
+            assert_eq!(requests.len(), 3);
+            assert!(requests[1]
+                .messages
+                .last()
+                .unwrap()
+                .content
+                .text()
+                .contains("Shorten it"));
+            assert!(requests[2].response_format.is_some());
+        }
+
+        let usage = &ledger.report().by_agent[&sleeptime.id()];
+        assert_eq!((usage.agent_name.as_str(), usage.calls), ("sleep-time", 3));
+
+        // Nothing new to consolidate
+        assert!(sleeptime.consolidate().await.unwrap().is_empty());
+        assert_eq!(client.requests().len(), 3);
+        assert_eq!(sleeptime.edits().await.len(), 4);
+    }
+
+    #[tokio::test]
+    async fn test_partial_failure_is_not_reapplied() {
+        let agent_id = AgentId::new();
+        let memory = Arc::new(AgentMemory::new(agent_id, MemoryConfig::default()));
+        memory
+            .add_message("user".to_string(), "I moved to Lisbon".to_string())
+            .await;
+        memory
+            .add_message("assistant".to_string(), "Welcome to Lisbon!".to_string())
+            .await;
+
+        // The summary is written, then the reflection plan is unreadable
+        let client = Arc::new(
+            ScriptedClient::new(vec![
+                Message::assistant("Sam moved to Lisbon."),
+                Message::assistant("not a plan"),
+            ])
+            .with_fallback(""),
+        );
+        let config = SleepTimeConfig {
+            min_messages_for_consolidation: 2,
+            enable_pattern_detection: false,
+            ..Default::default()
+        };
+        let sleeptime = SleepTimeAgent::new(agent_id, memory.clone(), config)
+            .with_llm(client.clone(), "test-model");
+
+        let err = sleeptime.consolidate().await.unwrap_err();
+        assert!(matches!(err, Error::JsonSchema(_)), "{:?}", err);
+        assert_eq!(sleeptime.edits().await.len(), 1);
+
+        // The same messages are not summarized a second time
+        assert!(sleeptime.consolidate().await.unwrap().is_empty());
+        assert_eq!(client.requests().len(), 2);
+    }
+
+    #[cfg(feature = "storage")]
+    #[tokio::test]
+    async fn test_edits_persist_to_storage() {
+        use crate::storage::SqliteStorage;
+
+        let storage = SqliteStorage::new("sqlite::memory:").await.unwrap();
+        let agent_id = AgentId::new();
+        let memory = Arc::new(AgentMemory::new(agent_id, MemoryConfig::default()));
+        let mut block = MemoryBlock::new("test_old", "old data");
+        block.updated_at = chrono::Utc::now() - chrono::Duration::hours(2);
+        memory.add_block(block).await.unwrap();
+
+        let config = SleepTimeConfig {
+            min_messages_for_consolidation: 0,
+            context_warning_threshold: 0,
+            enable_summarization: false,
+            enable_pattern_detection: false,
+            ..Default::default()
+        };
+        let sleeptime = SleepTimeAgent::new(agent_id, memory.clone(), config.clone());
+        sleeptime.consolidate().await.unwrap();
+        sleeptime.persist_to_storage(&storage).await.unwrap();
+        sleeptime.persist_to_storage(&storage).await.unwrap();
+
+        let restarted = SleepTimeAgent::new(agent_id, memory, config);
+        restarted.load_from_storage(&storage).await.unwrap();
+        let edits = restarted.edits().await;
+        assert_eq!(edits.len(), 1);
+        assert_eq!(edits[0].id, sleeptime.edits().await[0].id);
+        assert_eq!(edits[0].kind, MemoryEditKind::MovedOutOfContext);
+        assert!(storage
+            .load_memory_edits(AgentId::new())
+            .await
+            .unwrap()
+            .is_empty());
+    }
+
+    #[cfg(feature = "storage")]
+    #[tokio::test]
+    async fn test_processed_messages_survive_a_restart() {
+        use crate::storage::SqliteStorage;
+
+        let storage = SqliteStorage::new("sqlite::memory:").await.unwrap();
+        let agent_id = AgentId::new();
+        let memory = Arc::new(AgentMemory::new(agent_id, MemoryConfig::default()));
+        memory
+            .add_message("user".to_string(), "I moved to Lisbon".to_string())
+            .await;
+        memory
+            .add_message("assistant".to_string(), "Welcome to Lisbon!".to_string())
+            .await;
+        memory.persist_to_storage(&storage).await.unwrap();
+
+        let config = SleepTimeConfig {
+            min_messages_for_consolidation: 2,
+            enable_pattern_detection: false,
+            enable_reflection: false,
+            ..Default::default()
+        };
+        let client = Arc::new(ScriptedClient::repeating("Sam moved to Lisbon."));
+        let sleeptime = SleepTimeAgent::new(agent_id, memory.clone(), config.clone())
+            .with_llm(client.clone(), "test-model");
+        assert_eq!(sleeptime.consolidate().await.unwrap().len(), 1);
+        sleeptime.persist_to_storage(&storage).await.unwrap();
+
+        // A new process reloads the same messages but does not consolidate them again
+        let memory = Arc::new(AgentMemory::new(agent_id, MemoryConfig::default()));
+        memory.load_from_storage(&storage, 1000).await.unwrap();
+        let restarted =
+            SleepTimeAgent::new(agent_id, memory, config).with_llm(client.clone(), "test-model");
+        restarted.load_from_storage(&storage).await.unwrap();
+        assert!(restarted.consolidate().await.unwrap().is_empty());
+        assert_eq!(client.requests().len(), 1);
+    }
+
     #[tokio::test]
     async fn test_archival() {
         let agent_id = AgentId::new();
@@ -404,10 +1272,16 @@ mod tests {
         memory.add_block(block).await.unwrap();
 
         // Perform archival
-        SleepTimeAgent::perform_archival(&memory).await.unwrap();
+        let log = EditLog::default();
+        SleepTimeAgent::perform_archival(&memory, &log)
+            .await
+            .unwrap();
 
         // Check it was moved out of context
         assert_eq!(memory.in_context_blocks().await.len(), 0);
         assert_eq!(memory.out_of_context_blocks().await.len(), 1);
+        let edits = log.since(0).await;
+        assert_eq!(edits[0].kind, MemoryEditKind::MovedOutOfContext);
+        assert_eq!(edits[0].label, "test_old");
     }
 }
@@ -4,19 +4,36 @@
 //! - Storage trait for abstracting backend implementations
 //! - SQLite backend for local persistence
 //! - PostgreSQL backend for distributed deployments
+//! - Embedded sled backend for conversation state
 //! - Automatic migrations
-//! - Memory block and message history persistence
+//! - Memory block, block version, shared block and message history persistence
+//! - Sleep-time [`MemoryEdit`] audit log and consolidation progress persistence
+//! - Session and turn persistence for [`TurnStorage`]
+//! - Response cache entries for [`CacheStore`]
+//! - Archival passages in pgvector for [`VectorIndex`]
 
+#[cfg(feature = "storage")]
+use crate::archival::{ArchivalPassage, PassageSource, ScoredPassage, VectorIndex};
+#[cfg(feature = "storage")]
+use crate::cache::{CacheEntry, CacheStore};
 #[cfg(feature = "storage")]
 use crate::error::{Error, Result};
 #[cfg(feature = "storage")]
-use crate::memory::{MemoryBlock, MemoryBlockId, MessageEntry};
+use crate::memory::{
+    BlockAccess, BlockVersion, MemoryBlock, MemoryBlockId, MessageEntry, SharedBlock,
+};
+#[cfg(feature = "storage")]
+use crate::sleeptime::MemoryEdit;
+#[cfg(feature = "storage")]
+use crate::turns::{Session, Turn, TurnStorage};
 #[cfg(feature = "storage")]
-use crate::types::AgentId;
+use crate::types::{AgentId, SessionId};
 #[cfg(feature = "storage")]
 use async_trait::async_trait;
 #[cfg(feature = "storage")]
 use chrono::{DateTime, Utc};
+#[cfg(feature = "storage")]
+use std::collections::HashMap;
 
 #[cfg(feature = "storage")]
 use sqlx::{Pool, Postgres, Row, Sqlite};
@@ -37,6 +54,58 @@ pub trait MemoryStorage: Send + Sync {
     /// Delete a memory block
     async fn delete_block(&self, block_id: MemoryBlockId) -> Result<()>;
 
+    /// Save a block version; versions already stored are left unchanged
+    async fn save_block_version(&self, agent_id: AgentId, version: &BlockVersion) -> Result<()>;
+
+    /// Load every stored version of a block, oldest first
+    async fn load_block_versions(&self, block_id: MemoryBlockId) -> Result<Vec<BlockVersion>>;
+
+    /// Load every stored version of an agent's blocks, including deleted
+    /// ones, oldest first within each block
+    async fn load_agent_block_versions(&self, agent_id: AgentId) -> Result<Vec<BlockVersion>>;
+
+    /// Save a shared block's value if storage still holds `expected_revision`
+    ///
+    /// `None` stores a block that is not stored yet. Fails with
+    /// [`Error::MemoryConflict`] when the stored revision differs, so
+    /// concurrent writers never overwrite each other.
+    async fn save_shared_block(
+        &self,
+        block: &MemoryBlock,
+        expected_revision: Option<u64>,
+    ) -> Result<()>;
+
+    /// Replace the access list of a shared block
+    async fn save_shared_block_acl(
+        &self,
+        block_id: MemoryBlockId,
+        acl: &HashMap<AgentId, BlockAccess>,
+    ) -> Result<()>;
+
+    /// Load all shared blocks
+    async fn load_shared_blocks(&self) -> Result<Vec<SharedBlock>>;
+
+    /// Save a shared block version; versions already stored are left unchanged
+    async fn save_shared_block_version(&self, version: &BlockVersion) -> Result<()>;
+
+    /// Load every stored version of every shared block, oldest first within
+    /// each block
+    async fn load_shared_block_versions(&self) -> Result<Vec<BlockVersion>>;
+
+    /// Save a sleep-time edit; edits already stored are left unchanged
+    async fn save_memory_edit(&self, agent_id: AgentId, edit: &MemoryEdit) -> Result<()>;
+
+    /// Load every stored sleep-time edit of an agent, oldest first
+    async fn load_memory_edits(&self, agent_id: AgentId) -> Result<Vec<MemoryEdit>>;
+
+    /// Save the last message of an agent already consolidated by its
+    /// sleep-time agent
+    async fn save_processed_through(&self, agent_id: AgentId, message_id: uuid::Uuid)
+        -> Result<()>;
+
+    /// Load the last consolidated message of an agent, if any
+    async fn load_processed_through(&self, agent_id: AgentId) -> Result<Option<uuid::Uuid>>;
+
     /// Save a message to history
     async fn save_message(&self, agent_id: AgentId, message: &MessageEntry) -> Result<()>;
 
@@ -93,6 +162,136 @@ impl SqliteStorage {
         .await
         .map_err(|e| Error::config(format!("Failed to create memory_blocks table: {}", e)))?;
 
+        // Added with block versioning; SQLite has no ADD COLUMN IF NOT EXISTS
+        let has_version: bool = sqlx::query_scalar(
+            "SELECT COUNT(*) > 0 FROM pragma_table_info('memory_blocks') WHERE name = 'version'",
+        )
+        .fetch_one(&self.pool)
+        .await
+        .map_err(|e| Error::config(format!("Failed to inspect memory_blocks: {}", e)))?;
+        if !has_version {
+            sqlx::query("ALTER TABLE memory_blocks ADD COLUMN version INTEGER NOT NULL DEFAULT 1")
+                .execute(&self.pool)
+                .await
+                .map_err(|e| {
+                    Error::config(format!("Failed to add memory_blocks version: {}", e))
+                })?;
+        }
+
+        sqlx::query(
+            r#"
+            CREATE TABLE IF NOT EXISTS memory_block_versions (
+                block_id TEXT NOT NULL,
+                version INTEGER NOT NULL,
+                agent_id TEXT NOT NULL,
+                value TEXT NOT NULL,
+                author TEXT NOT NULL,
+                reason TEXT NOT NULL,
+                timestamp TEXT NOT NULL,
+                PRIMARY KEY (block_id, version)
+            )
+            "#,
+        )
+        .execute(&self.pool)
+        .await
+        .map_err(|e| {
+            Error::config(format!(
+                "Failed to create memory_block_versions table: {}",
+                e
+            ))
+        })?;
+
+        sqlx::query(
+            r#"
+            CREATE TABLE IF NOT EXISTS shared_memory_blocks (
+                id TEXT PRIMARY KEY,
+                revision INTEGER NOT NULL,
+                updated_at TEXT NOT NULL,
+                data TEXT NOT NULL
+            )
+            "#,
+        )
+        .execute(&self.pool)
+        .await
+        .map_err(|e| {
+            Error::config(format!(
+                "Failed to create shared_memory_blocks table: {}",
+                e
+            ))
+        })?;
+
+        sqlx::query(
+            r#"
+            CREATE TABLE IF NOT EXISTS shared_block_access (
+                block_id TEXT NOT NULL,
+                agent_id TEXT NOT NULL,
+                access TEXT NOT NULL,
+                PRIMARY KEY (block_id, agent_id)
+            )
+            "#,
+        )
+        .execute(&self.pool)
+        .await
+        .map_err(|e| Error::config(format!("Failed to create shared_block_access table: {}", e)))?;
+
+        sqlx::query(
+            r#"
+            CREATE TABLE IF NOT EXISTS shared_block_versions (
+                block_id TEXT NOT NULL,
+                version INTEGER NOT NULL,
+                value TEXT NOT NULL,
+                author TEXT NOT NULL,
+                reason TEXT NOT NULL,
+                timestamp TEXT NOT NULL,
+                PRIMARY KEY (block_id, version)
+            )
+            "#,
+        )
+        .execute(&self.pool)
+        .await
+        .map_err(|e| {
+            Error::config(format!(
+                "Failed to create shared_block_versions table: {}",
+                e
+            ))
+        })?;
+
+        sqlx::query(
+            r#"
+            CREATE TABLE IF NOT EXISTS memory_edits (
+                id TEXT PRIMARY KEY,
+                agent_id TEXT NOT NULL,
+                timestamp TEXT NOT NULL,
+                data TEXT NOT NULL
+            )
+            "#,
+        )
+        .execute(&self.pool)
+        .await
+        .map_err(|e| Error::config(format!("Failed to create memory_edits table: {}", e)))?;
+
+        sqlx::query("CREATE INDEX IF NOT EXISTS idx_memory_edits_agent ON memory_edits(agent_id, timestamp)")
+            .execute(&self.pool)
+            .await
+            .map_err(|e| Error::config(format!("Failed to create index: {}", e)))?;
+
+        sqlx::query(
+            r#"
+            CREATE TABLE IF NOT EXISTS memory_consolidation (
+                agent_id TEXT PRIMARY KEY,
+                processed_through TEXT NOT NULL
+            )
+            "#,
+        )
+        .execute(&self.pool)
+        .await
+        .map_err(|e| {
+            Error::config(format!(
+                "Failed to create memory_consolidation table: {}",
+                e
+            ))
+        })?;
+
         // Create messages table
         sqlx::query(
             r#"
@@ -112,18 +311,195 @@ impl SqliteStorage {
         .map_err(|e| Error::config(format!("Failed to create messages table: {}", e)))?;
 
         // Create indices
-        sqlx::query("CREATE INDEX IF NOT EXISTS idx_memory_blocks_agent ON memory_blocks(agent_id)")
+        sqlx::query(
+            "CREATE INDEX IF NOT EXISTS idx_memory_blocks_agent ON memory_blocks(agent_id)",
+        )
+        .execute(&self.pool)
+        .await
+        .map_err(|e| Error::config(format!("Failed to create index: {}", e)))?;
+
+        sqlx::query("CREATE INDEX IF NOT EXISTS idx_messages_agent ON messages(agent_id)")
             .execute(&self.pool)
             .await
             .map_err(|e| Error::config(format!("Failed to create index: {}", e)))?;
 
-        sqlx::query("CREATE INDEX IF NOT EXISTS idx_messages_agent ON messages(agent_id)")
+        // Create sessions and turns tables
+        sqlx::query(
+            r#"
+            CREATE TABLE IF NOT EXISTS sessions (
+                id TEXT PRIMARY KEY,
+                user_id TEXT,
+                current_agent TEXT NOT NULL,
+                updated_at TEXT NOT NULL,
+                data TEXT NOT NULL
+            )
+            "#,
+        )
+        .execute(&self.pool)
+        .await
+        .map_err(|e| Error::config(format!("Failed to create sessions table: {}", e)))?;
+
+        sqlx::query(
+            r#"
+            CREATE TABLE IF NOT EXISTS turns (
+                id TEXT PRIMARY KEY,
+                session_id TEXT NOT NULL,
+                agent_id TEXT NOT NULL,
+                timestamp TEXT NOT NULL,
+                data TEXT NOT NULL
+            )
+            "#,
+        )
+        .execute(&self.pool)
+        .await
+        .map_err(|e| Error::config(format!("Failed to create turns table: {}", e)))?;
+
+        sqlx::query("CREATE INDEX IF NOT EXISTS idx_turns_session ON turns(session_id, timestamp)")
             .execute(&self.pool)
             .await
             .map_err(|e| Error::config(format!("Failed to create index: {}", e)))?;
 
+        // Create response cache table
+        sqlx::query(
+            r#"
+            CREATE TABLE IF NOT EXISTS llm_cache (
+                key TEXT PRIMARY KEY,
+                scope TEXT NOT NULL,
+                created_at TEXT NOT NULL,
+                data TEXT NOT NULL
+            )
+            "#,
+        )
+        .execute(&self.pool)
+        .await
+        .map_err(|e| Error::config(format!("Failed to create llm_cache table: {}", e)))?;
+
+        sqlx::query("CREATE INDEX IF NOT EXISTS idx_llm_cache_scope ON llm_cache(scope)")
+            .execute(&self.pool)
+            .await
+            .map_err(|e| Error::config(format!("Failed to create index: {}", e)))?;
+
+        Ok(())
+    }
+}
+
+#[cfg(feature = "storage")]
+#[async_trait]
+impl CacheStore for SqliteStorage {
+    async fn get(&self, key: &str) -> Result<Option<CacheEntry>> {
+        let data: Option<String> = sqlx::query_scalar("SELECT data FROM llm_cache WHERE key = ?")
+            .bind(key)
+            .fetch_optional(&self.pool)
+            .await
+            .map_err(|e| Error::storage(format!("Failed to load cache entry: {}", e)))?;
+
+        data.map(|data| from_json(&data)).transpose()
+    }
+
+    async fn put(&self, entry: &CacheEntry) -> Result<()> {
+        sqlx::query(
+            r#"
+            INSERT OR REPLACE INTO llm_cache (key, scope, created_at, data)
+            VALUES (?, ?, ?, ?)
+            "#,
+        )
+        .bind(&entry.key)
+        .bind(&entry.scope)
+        .bind(entry.created_at.to_rfc3339())
+        .bind(to_json(entry)?)
+        .execute(&self.pool)
+        .await
+        .map_err(|e| Error::storage(format!("Failed to save cache entry: {}", e)))?;
+
+        Ok(())
+    }
+
+    async fn remove(&self, key: &str) -> Result<()> {
+        sqlx::query("DELETE FROM llm_cache WHERE key = ?")
+            .bind(key)
+            .execute(&self.pool)
+            .await
+            .map_err(|e| Error::storage(format!("Failed to delete cache entry: {}", e)))?;
+
+        Ok(())
+    }
+
+    async fn entries(&self, scope: &str) -> Result<Vec<CacheEntry>> {
+        let rows: Vec<String> = sqlx::query_scalar("SELECT data FROM llm_cache WHERE scope = ?")
+            .bind(scope)
+            .fetch_all(&self.pool)
+            .await
+            .map_err(|e| Error::storage(format!("Failed to load cache entries: {}", e)))?;
+
+        rows.iter().map(|data| from_json(data)).collect()
+    }
+}
+
+#[cfg(feature = "storage")]
+#[async_trait]
+impl TurnStorage for SqliteStorage {
+    async fn store_session(&self, session: &Session) -> Result<()> {
+        sqlx::query(
+            r#"
+            INSERT OR REPLACE INTO sessions (id, user_id, current_agent, updated_at, data)
+            VALUES (?, ?, ?, ?, ?)
+            "#,
+        )
+        .bind(session.id.to_string())
+        .bind(session.user_id.as_ref().map(|id| id.to_string()))
+        .bind(session.current_agent.to_string())
+        .bind(session.metadata.updated_at.to_rfc3339())
+        .bind(to_json(session)?)
+        .execute(&self.pool)
+        .await
+        .map_err(|e| Error::storage(format!("Failed to save session: {}", e)))?;
+
+        Ok(())
+    }
+
+    async fn load_session(&self, id: SessionId) -> Result<Option<Session>> {
+        let data: Option<String> = sqlx::query_scalar("SELECT data FROM sessions WHERE id = ?")
+            .bind(id.to_string())
+            .fetch_optional(&self.pool)
+            .await
+            .map_err(|e| Error::storage(format!("Failed to load session: {}", e)))?;
+
+        data.map(|data| from_json(&data)).transpose()
+    }
+
+    async fn store_turn(&self, turn: &Turn) -> Result<()> {
+        sqlx::query(
+            r#"
+            INSERT OR REPLACE INTO turns (id, session_id, agent_id, timestamp, data)
+            VALUES (?, ?, ?, ?, ?)
+            "#,
+        )
+        .bind(turn.id.to_string())
+        .bind(turn.session_id.to_string())
+        .bind(turn.agent_id.to_string())
+        .bind(
+            turn.timestamp
+                .to_rfc3339_opts(chrono::SecondsFormat::Nanos, true),
+        )
+        .bind(to_json(turn)?)
+        .execute(&self.pool)
+        .await
+        .map_err(|e| Error::storage(format!("Failed to save turn: {}", e)))?;
+
         Ok(())
     }
+
+    async fn load_turns(&self, session_id: SessionId) -> Result<Vec<Turn>> {
+        let rows: Vec<String> = sqlx::query_scalar(
+            "SELECT data FROM turns WHERE session_id = ? ORDER BY timestamp ASC, rowid ASC",
+        )
+        .bind(session_id.to_string())
+        .fetch_all(&self.pool)
+        .await
+        .map_err(|e| Error::storage(format!("Failed to load turns: {}", e)))?;
+
+        rows.iter().map(|data| from_json(data)).collect()
+    }
 }
 
 #[cfg(feature = "storage")]
@@ -136,8 +512,8 @@ impl MemoryStorage for SqliteStorage {
         sqlx::query(
             r#"
             INSERT OR REPLACE INTO memory_blocks
-            (id, agent_id, label, description, value, max_size, in_context, created_at, updated_at, metadata)
-            VALUES (?, ?, ?, ?, ?, ?, ?, ?, ?, ?)
+            (id, agent_id, label, description, value, max_size, in_context, created_at, updated_at, metadata, version)
+            VALUES (?, ?, ?, ?, ?, ?, ?, ?, ?, ?, ?)
             "#,
         )
         .bind(block.id.to_string())
@@ -150,6 +526,7 @@ impl MemoryStorage for SqliteStorage {
         .bind(block.created_at.to_rfc3339())
         .bind(block.updated_at.to_rfc3339())
         .bind(metadata_json)
+        .bind(block.version as i64)
         .execute(&self.pool)
         .await
         .map_err(|e| Error::config(format!("Failed to save memory block: {}", e)))?;
@@ -160,7 +537,7 @@ impl MemoryStorage for SqliteStorage {
     async fn load_block(&self, block_id: MemoryBlockId) -> Result<Option<MemoryBlock>> {
         let row = sqlx::query(
             r#"
-            SELECT id, label, description, value, max_size, in_context, created_at, updated_at, metadata
+            SELECT id, label, description, value, max_size, in_context, created_at, updated_at, metadata, version
             FROM memory_blocks WHERE id = ?
             "#,
         )
@@ -179,6 +556,7 @@ impl MemoryStorage for SqliteStorage {
             let created_str: String = row.get(6);
             let updated_str: String = row.get(7);
             let metadata_json: String = row.get(8);
+            let version: i64 = row.get(9);
 
             Ok(Some(MemoryBlock {
                 id,
@@ -195,6 +573,7 @@ impl MemoryStorage for SqliteStorage {
                     .with_timezone(&Utc),
                 metadata: serde_json::from_str(&metadata_json)
                     .map_err(|e| Error::config(format!("Invalid metadata JSON: {}", e)))?,
+                version: version as u64,
             }))
         } else {
             Ok(None)
@@ -204,7 +583,7 @@ impl MemoryStorage for SqliteStorage {
     async fn load_agent_blocks(&self, agent_id: AgentId) -> Result<Vec<MemoryBlock>> {
         let rows = sqlx::query(
             r#"
-            SELECT id, label, description, value, max_size, in_context, created_at, updated_at, metadata
+            SELECT id, label, description, value, max_size, in_context, created_at, updated_at, metadata, version
             FROM memory_blocks WHERE agent_id = ?
             ORDER BY created_at DESC
             "#,
@@ -225,6 +604,7 @@ impl MemoryStorage for SqliteStorage {
             let created_str: String = row.get(6);
             let updated_str: String = row.get(7);
             let metadata_json: String = row.get(8);
+            let version: i64 = row.get(9);
 
             blocks.push(MemoryBlock {
                 id,
@@ -241,6 +621,7 @@ impl MemoryStorage for SqliteStorage {
                     .with_timezone(&Utc),
                 metadata: serde_json::from_str(&metadata_json)
                     .map_err(|e| Error::config(format!("Invalid metadata JSON: {}", e)))?,
+                version: version as u64,
             });
         }
 
@@ -257,100 +638,393 @@ impl MemoryStorage for SqliteStorage {
         Ok(())
     }
 
-    async fn save_message(&self, agent_id: AgentId, message: &MessageEntry) -> Result<()> {
-        let tool_calls_json = message
-            .tool_calls
-            .as_ref()
-            .map(|tc| serde_json::to_string(tc))
-            .transpose()
-            .map_err(|e| Error::config(format!("Failed to serialize tool_calls: {}", e)))?;
-
-        let metadata_json = serde_json::to_string(&message.metadata)
-            .map_err(|e| Error::config(format!("Failed to serialize metadata: {}", e)))?;
-
+    async fn save_block_version(&self, agent_id: AgentId, version: &BlockVersion) -> Result<()> {
         sqlx::query(
             r#"
-            INSERT OR REPLACE INTO messages
-            (id, agent_id, timestamp, role, content, tool_calls, metadata)
+            INSERT OR IGNORE INTO memory_block_versions
+            (block_id, version, agent_id, value, author, reason, timestamp)
             VALUES (?, ?, ?, ?, ?, ?, ?)
             "#,
         )
-        .bind(message.id.to_string())
+        .bind(version.block_id.to_string())
+        .bind(version.version as i64)
         .bind(agent_id.to_string())
-        .bind(message.timestamp.to_rfc3339())
-        .bind(&message.role)
-        .bind(&message.content)
-        .bind(tool_calls_json)
-        .bind(metadata_json)
+        .bind(&version.value)
+        .bind(to_json(&version.author)?)
+        .bind(&version.reason)
+        .bind(version.timestamp.to_rfc3339())
         .execute(&self.pool)
         .await
-        .map_err(|e| Error::config(format!("Failed to save message: {}", e)))?;
+        .map_err(|e| Error::storage(format!("Failed to save block version: {}", e)))?;
 
         Ok(())
     }
 
-    async fn load_messages(&self, agent_id: AgentId, limit: usize) -> Result<Vec<MessageEntry>> {
-        let rows = sqlx::query(
+    async fn load_block_versions(&self, block_id: MemoryBlockId) -> Result<Vec<BlockVersion>> {
+        let rows = sqlx::query_as::<_, (i64, String, String, String, String)>(
             r#"
-            SELECT id, timestamp, role, content, tool_calls, metadata
-            FROM messages WHERE agent_id = ?
-            ORDER BY timestamp DESC
-            LIMIT ?
+            SELECT version, value, author, reason, timestamp
+            FROM memory_block_versions WHERE block_id = ?
+            ORDER BY version
             "#,
         )
-        .bind(agent_id.to_string())
-        .bind(limit as i64)
+        .bind(block_id.to_string())
         .fetch_all(&self.pool)
         .await
-        .map_err(|e| Error::config(format!("Failed to load messages: {}", e)))?;
-
-        let mut messages = Vec::new();
-        for row in rows {
-            let id_str: String = row.get(0);
-            let timestamp_str: String = row.get(1);
-            let tool_calls_json: Option<String> = row.get(4);
-            let metadata_json: String = row.get(5);
-
-            messages.push(MessageEntry {
-                id: uuid::Uuid::parse_str(&id_str)
-                    .map_err(|e| Error::config(format!("Invalid message ID: {}", e)))?,
-                timestamp: DateTime::parse_from_rfc3339(&timestamp_str)
-                    .map_err(|e| Error::config(format!("Invalid timestamp: {}", e)))?
-                    .with_timezone(&Utc),
-                role: row.get(2),
-                content: row.get(3),
-                tool_calls: tool_calls_json
-                    .map(|json| serde_json::from_str(&json))
-                    .transpose()
-                    .map_err(|e| Error::config(format!("Invalid tool_calls JSON: {}", e)))?,
-                metadata: serde_json::from_str(&metadata_json)
-                    .map_err(|e| Error::config(format!("Invalid metadata JSON: {}", e)))?,
-            });
-        }
-
-        messages.reverse(); // Return in chronological order
-        Ok(messages)
+        .map_err(|e| Error::storage(format!("Failed to load block versions: {}", e)))?;
+
+        rows.into_iter()
+            .map(|(version, value, author, reason, timestamp)| {
+                Ok(BlockVersion {
+                    block_id,
+                    version: version as u64,
+                    value,
+                    author: from_json(&author)?,
+                    reason,
+                    timestamp: DateTime::parse_from_rfc3339(&timestamp)
+                        .map_err(|e| Error::storage(format!("Invalid timestamp: {}", e)))?
+                        .with_timezone(&Utc),
+                })
+            })
+            .collect()
     }
 
-    async fn search_messages(&self, agent_id: AgentId, query: &str) -> Result<Vec<MessageEntry>> {
-        let rows = sqlx::query(
+    async fn load_agent_block_versions(&self, agent_id: AgentId) -> Result<Vec<BlockVersion>> {
+        let rows = sqlx::query_as::<_, (String, i64, String, String, String, String)>(
             r#"
-            SELECT id, timestamp, role, content, tool_calls, metadata
-            FROM messages WHERE agent_id = ? AND content LIKE ?
-            ORDER BY timestamp DESC
+            SELECT block_id, version, value, author, reason, timestamp
+            FROM memory_block_versions WHERE agent_id = ?
+            ORDER BY block_id, version
             "#,
         )
         .bind(agent_id.to_string())
-        .bind(format!("%{}%", query))
         .fetch_all(&self.pool)
         .await
-        .map_err(|e| Error::config(format!("Failed to search messages: {}", e)))?;
+        .map_err(|e| Error::storage(format!("Failed to load block versions: {}", e)))?;
+
+        rows.into_iter()
+            .map(|(block_id, version, value, author, reason, timestamp)| {
+                Ok(BlockVersion {
+                    block_id: from_json(&format!("\"{}\"", block_id))?,
+                    version: version as u64,
+                    value,
+                    author: from_json(&author)?,
+                    reason,
+                    timestamp: DateTime::parse_from_rfc3339(&timestamp)
+                        .map_err(|e| Error::storage(format!("Invalid timestamp: {}", e)))?
+                        .with_timezone(&Utc),
+                })
+            })
+            .collect()
+    }
 
-        let mut messages = Vec::new();
-        for row in rows {
-            let id_str: String = row.get(0);
-            let timestamp_str: String = row.get(1);
-            let tool_calls_json: Option<String> = row.get(4);
+    async fn save_shared_block(
+        &self,
+        block: &MemoryBlock,
+        expected_revision: Option<u64>,
+    ) -> Result<()> {
+        // The revision check happens in the statement, so concurrent writers
+        // cannot both succeed
+        let saved = match expected_revision {
+            None => sqlx::query(
+                r#"
+                INSERT INTO shared_memory_blocks (id, revision, updated_at, data)
+                VALUES (?, ?, ?, ?)
+                ON CONFLICT(id) DO NOTHING
+                "#,
+            )
+            .bind(block.id.to_string())
+            .bind(block.version as i64)
+            .bind(block.updated_at.to_rfc3339())
+            .bind(to_json(block)?),
+            Some(expected) => sqlx::query(
+                r#"
+                UPDATE shared_memory_blocks SET revision = ?, updated_at = ?, data = ?
+                WHERE id = ? AND revision = ?
+                "#,
+            )
+            .bind(block.version as i64)
+            .bind(block.updated_at.to_rfc3339())
+            .bind(to_json(block)?)
+            .bind(block.id.to_string())
+            .bind(expected as i64),
+        }
+        .execute(&self.pool)
+        .await
+        .map_err(|e| Error::storage(format!("Failed to save shared block: {}", e)))?;
+
+        if saved.rows_affected() == 0 {
+            let actual: Option<i64> =
+                sqlx::query_scalar("SELECT revision FROM shared_memory_blocks WHERE id = ?")
+                    .bind(block.id.to_string())
+                    .fetch_optional(&self.pool)
+                    .await
+                    .map_err(|e| {
+                        Error::storage(format!("Failed to load shared block revision: {}", e))
+                    })?;
+            return Err(Error::MemoryConflict {
+                block_id: block.id.to_string(),
+                expected: expected_revision.unwrap_or_default(),
+                actual: actual.unwrap_or_default() as u64,
+            });
+        }
+
+        Ok(())
+    }
+
+    async fn save_shared_block_acl(
+        &self,
+        block_id: MemoryBlockId,
+        acl: &HashMap<AgentId, BlockAccess>,
+    ) -> Result<()> {
+        let mut tx = self
+            .pool
+            .begin()
+            .await
+            .map_err(|e| Error::storage(format!("Failed to start transaction: {}", e)))?;
+
+        sqlx::query("DELETE FROM shared_block_access WHERE block_id = ?")
+            .bind(block_id.to_string())
+            .execute(&mut *tx)
+            .await
+            .map_err(|e| Error::storage(format!("Failed to save shared block access: {}", e)))?;
+        for (agent_id, access) in acl {
+            sqlx::query(
+                "INSERT INTO shared_block_access (block_id, agent_id, access) VALUES (?, ?, ?)",
+            )
+            .bind(block_id.to_string())
+            .bind(agent_id.to_string())
+            .bind(access.to_string())
+            .execute(&mut *tx)
+            .await
+            .map_err(|e| Error::storage(format!("Failed to save shared block access: {}", e)))?;
+        }
+
+        tx.commit()
+            .await
+            .map_err(|e| Error::storage(format!("Failed to commit shared block access: {}", e)))
+    }
+
+    async fn load_shared_blocks(&self) -> Result<Vec<SharedBlock>> {
+        let rows: Vec<String> =
+            sqlx::query_scalar("SELECT data FROM shared_memory_blocks ORDER BY id")
+                .fetch_all(&self.pool)
+                .await
+                .map_err(|e| Error::storage(format!("Failed to load shared blocks: {}", e)))?;
+        let grants = sqlx::query_as::<_, (String, String, String)>(
+            "SELECT block_id, agent_id, access FROM shared_block_access",
+        )
+        .fetch_all(&self.pool)
+        .await
+        .map_err(|e| Error::storage(format!("Failed to load shared block access: {}", e)))?;
+
+        shared_blocks_from_rows(
+            rows.iter()
+                .map(|data| from_json(data))
+                .collect::<Result<_>>()?,
+            grants,
+        )
+    }
+
+    async fn save_shared_block_version(&self, version: &BlockVersion) -> Result<()> {
+        sqlx::query(
+            r#"
+            INSERT OR IGNORE INTO shared_block_versions
+            (block_id, version, value, author, reason, timestamp)
+            VALUES (?, ?, ?, ?, ?, ?)
+            "#,
+        )
+        .bind(version.block_id.to_string())
+        .bind(version.version as i64)
+        .bind(&version.value)
+        .bind(to_json(&version.author)?)
+        .bind(&version.reason)
+        .bind(version.timestamp.to_rfc3339())
+        .execute(&self.pool)
+        .await
+        .map_err(|e| Error::storage(format!("Failed to save shared block version: {}", e)))?;
+
+        Ok(())
+    }
+
+    async fn load_shared_block_versions(&self) -> Result<Vec<BlockVersion>> {
+        let rows = sqlx::query_as::<_, (String, i64, String, String, String, String)>(
+            r#"
+            SELECT block_id, version, value, author, reason, timestamp
+            FROM shared_block_versions
+            ORDER BY block_id, version
+            "#,
+        )
+        .fetch_all(&self.pool)
+        .await
+        .map_err(|e| Error::storage(format!("Failed to load shared block versions: {}", e)))?;
+
+        rows.into_iter()
+            .map(|(block_id, version, value, author, reason, timestamp)| {
+                Ok(BlockVersion {
+                    block_id: from_json(&format!("\"{}\"", block_id))?,
+                    version: version as u64,
+                    value,
+                    author: from_json(&author)?,
+                    reason,
+                    timestamp: DateTime::parse_from_rfc3339(&timestamp)
+                        .map_err(|e| Error::storage(format!("Invalid timestamp: {}", e)))?
+                        .with_timezone(&Utc),
+                })
+            })
+            .collect()
+    }
+
+    async fn save_memory_edit(&self, agent_id: AgentId, edit: &MemoryEdit) -> Result<()> {
+        sqlx::query("INSERT OR IGNORE INTO memory_edits (id, agent_id, timestamp, data) VALUES (?, ?, ?, ?)")
+            .bind(edit.id.to_string())
+            .bind(agent_id.to_string())
+            .bind(edit.timestamp.to_rfc3339())
+            .bind(to_json(edit)?)
+            .execute(&self.pool)
+            .await
+            .map_err(|e| Error::storage(format!("Failed to save memory edit: {}", e)))?;
+
+        Ok(())
+    }
+
+    async fn load_memory_edits(&self, agent_id: AgentId) -> Result<Vec<MemoryEdit>> {
+        let rows: Vec<String> = sqlx::query_scalar(
+            "SELECT data FROM memory_edits WHERE agent_id = ? ORDER BY timestamp, rowid",
+        )
+        .bind(agent_id.to_string())
+        .fetch_all(&self.pool)
+        .await
+        .map_err(|e| Error::storage(format!("Failed to load memory edits: {}", e)))?;
+
+        rows.iter().map(|data| from_json(data)).collect()
+    }
+
+    async fn save_processed_through(
+        &self,
+        agent_id: AgentId,
+        message_id: uuid::Uuid,
+    ) -> Result<()> {
+        sqlx::query("INSERT OR REPLACE INTO memory_consolidation (agent_id, processed_through) VALUES (?, ?)")
+            .bind(agent_id.to_string())
+            .bind(message_id.to_string())
+            .execute(&self.pool)
+            .await
+            .map_err(|e| Error::storage(format!("Failed to save consolidation progress: {}", e)))?;
+
+        Ok(())
+    }
+
+    async fn load_processed_through(&self, agent_id: AgentId) -> Result<Option<uuid::Uuid>> {
+        let id: Option<String> = sqlx::query_scalar(
+            "SELECT processed_through FROM memory_consolidation WHERE agent_id = ?",
+        )
+        .bind(agent_id.to_string())
+        .fetch_optional(&self.pool)
+        .await
+        .map_err(|e| Error::storage(format!("Failed to load consolidation progress: {}", e)))?;
+
+        id.map(|id| {
+            uuid::Uuid::parse_str(&id)
+                .map_err(|e| Error::storage(format!("Invalid message id: {}", e)))
+        })
+        .transpose()
+    }
+
+    async fn save_message(&self, agent_id: AgentId, message: &MessageEntry) -> Result<()> {
+        let tool_calls_json = message
+            .tool_calls
+            .as_ref()
+            .map(|tc| serde_json::to_string(tc))
+            .transpose()
+            .map_err(|e| Error::config(format!("Failed to serialize tool_calls: {}", e)))?;
+
+        let metadata_json = serde_json::to_string(&message.metadata)
+            .map_err(|e| Error::config(format!("Failed to serialize metadata: {}", e)))?;
+
+        sqlx::query(
+            r#"
+            INSERT OR REPLACE INTO messages
+            (id, agent_id, timestamp, role, content, tool_calls, metadata)
+            VALUES (?, ?, ?, ?, ?, ?, ?)
+            "#,
+        )
+        .bind(message.id.to_string())
+        .bind(agent_id.to_string())
+        .bind(message.timestamp.to_rfc3339())
+        .bind(&message.role)
+        .bind(&message.content)
+        .bind(tool_calls_json)
+        .bind(metadata_json)
+        .execute(&self.pool)
+        .await
+        .map_err(|e| Error::config(format!("Failed to save message: {}", e)))?;
+
+        Ok(())
+    }
+
+    async fn load_messages(&self, agent_id: AgentId, limit: usize) -> Result<Vec<MessageEntry>> {
+        let rows = sqlx::query(
+            r#"
+            SELECT id, timestamp, role, content, tool_calls, metadata
+            FROM messages WHERE agent_id = ?
+            ORDER BY timestamp DESC
+            LIMIT ?
+            "#,
+        )
+        .bind(agent_id.to_string())
+        .bind(limit as i64)
+        .fetch_all(&self.pool)
+        .await
+        .map_err(|e| Error::config(format!("Failed to load messages: {}", e)))?;
+
+        let mut messages = Vec::new();
+        for row in rows {
+            let id_str: String = row.get(0);
+            let timestamp_str: String = row.get(1);
+            let tool_calls_json: Option<String> = row.get(4);
+            let metadata_json: String = row.get(5);
+
+            messages.push(MessageEntry {
+                id: uuid::Uuid::parse_str(&id_str)
+                    .map_err(|e| Error::config(format!("Invalid message ID: {}", e)))?,
+                timestamp: DateTime::parse_from_rfc3339(&timestamp_str)
+                    .map_err(|e| Error::config(format!("Invalid timestamp: {}", e)))?
+                    .with_timezone(&Utc),
+                role: row.get(2),
+                content: row.get(3),
+                tool_calls: tool_calls_json
+                    .map(|json| serde_json::from_str(&json))
+                    .transpose()
+                    .map_err(|e| Error::config(format!("Invalid tool_calls JSON: {}", e)))?,
+                metadata: serde_json::from_str(&metadata_json)
+                    .map_err(|e| Error::config(format!("Invalid metadata JSON: {}", e)))?,
+            });
+        }
+
+        messages.reverse(); // Return in chronological order
+        Ok(messages)
+    }
+
+    async fn search_messages(&self, agent_id: AgentId, query: &str) -> Result<Vec<MessageEntry>> {
+        let rows = sqlx::query(
+            r#"
+            SELECT id, timestamp, role, content, tool_calls, metadata
+            FROM messages WHERE agent_id = ? AND content LIKE ?
+            ORDER BY timestamp DESC
+            "#,
+        )
+        .bind(agent_id.to_string())
+        .bind(format!("%{}%", query))
+        .fetch_all(&self.pool)
+        .await
+        .map_err(|e| Error::config(format!("Failed to search messages: {}", e)))?;
+
+        let mut messages = Vec::new();
+        for row in rows {
+            let id_str: String = row.get(0);
+            let timestamp_str: String = row.get(1);
+            let tool_calls_json: Option<String> = row.get(4);
             let metadata_json: String = row.get(5);
 
             messages.push(MessageEntry {
@@ -380,12 +1054,32 @@ impl MemoryStorage for SqliteStorage {
             .await
             .map_err(|e| Error::config(format!("Failed to delete agent blocks: {}", e)))?;
 
+        sqlx::query("DELETE FROM memory_block_versions WHERE agent_id = ?")
+            .bind(agent_id.to_string())
+            .execute(&self.pool)
+            .await
+            .map_err(|e| Error::config(format!("Failed to delete block versions: {}", e)))?;
+
         sqlx::query("DELETE FROM messages WHERE agent_id = ?")
             .bind(agent_id.to_string())
             .execute(&self.pool)
             .await
             .map_err(|e| Error::config(format!("Failed to delete agent messages: {}", e)))?;
 
+        sqlx::query("DELETE FROM memory_edits WHERE agent_id = ?")
+            .bind(agent_id.to_string())
+            .execute(&self.pool)
+            .await
+            .map_err(|e| Error::config(format!("Failed to delete memory edits: {}", e)))?;
+
+        sqlx::query("DELETE FROM memory_consolidation WHERE agent_id = ?")
+            .bind(agent_id.to_string())
+            .execute(&self.pool)
+            .await
+            .map_err(|e| {
+                Error::config(format!("Failed to delete consolidation progress: {}", e))
+            })?;
+
         Ok(())
     }
 }
@@ -433,6 +1127,128 @@ impl PostgresStorage {
         .await
         .map_err(|e| Error::config(format!("Failed to create memory_blocks table: {}", e)))?;
 
+        // Added with block versioning
+        sqlx::query(
+            "ALTER TABLE memory_blocks ADD COLUMN IF NOT EXISTS version BIGINT NOT NULL DEFAULT 1",
+        )
+        .execute(&self.pool)
+        .await
+        .map_err(|e| Error::config(format!("Failed to add memory_blocks version: {}", e)))?;
+
+        sqlx::query(
+            r#"
+            CREATE TABLE IF NOT EXISTS memory_block_versions (
+                block_id TEXT NOT NULL,
+                version BIGINT NOT NULL,
+                agent_id TEXT NOT NULL,
+                value TEXT NOT NULL,
+                author JSONB NOT NULL,
+                reason TEXT NOT NULL,
+                timestamp TIMESTAMPTZ NOT NULL,
+                PRIMARY KEY (block_id, version)
+            )
+            "#,
+        )
+        .execute(&self.pool)
+        .await
+        .map_err(|e| {
+            Error::config(format!(
+                "Failed to create memory_block_versions table: {}",
+                e
+            ))
+        })?;
+
+        sqlx::query(
+            r#"
+            CREATE TABLE IF NOT EXISTS shared_memory_blocks (
+                id TEXT PRIMARY KEY,
+                revision BIGINT NOT NULL,
+                updated_at TIMESTAMPTZ NOT NULL,
+                data JSONB NOT NULL
+            )
+            "#,
+        )
+        .execute(&self.pool)
+        .await
+        .map_err(|e| {
+            Error::config(format!(
+                "Failed to create shared_memory_blocks table: {}",
+                e
+            ))
+        })?;
+
+        sqlx::query(
+            r#"
+            CREATE TABLE IF NOT EXISTS shared_block_access (
+                block_id TEXT NOT NULL,
+                agent_id TEXT NOT NULL,
+                access TEXT NOT NULL,
+                PRIMARY KEY (block_id, agent_id)
+            )
+            "#,
+        )
+        .execute(&self.pool)
+        .await
+        .map_err(|e| Error::config(format!("Failed to create shared_block_access table: {}", e)))?;
+
+        sqlx::query(
+            r#"
+            CREATE TABLE IF NOT EXISTS shared_block_versions (
+                block_id TEXT NOT NULL,
+                version BIGINT NOT NULL,
+                value TEXT NOT NULL,
+                author JSONB NOT NULL,
+                reason TEXT NOT NULL,
+                timestamp TIMESTAMPTZ NOT NULL,
+                PRIMARY KEY (block_id, version)
+            )
+            "#,
+        )
+        .execute(&self.pool)
+        .await
+        .map_err(|e| {
+            Error::config(format!(
+                "Failed to create shared_block_versions table: {}",
+                e
+            ))
+        })?;
+
+        sqlx::query(
+            r#"
+            CREATE TABLE IF NOT EXISTS memory_edits (
+                id UUID PRIMARY KEY,
+                agent_id TEXT NOT NULL,
+                timestamp TIMESTAMPTZ NOT NULL,
+                data JSONB NOT NULL
+            )
+            "#,
+        )
+        .execute(&self.pool)
+        .await
+        .map_err(|e| Error::config(format!("Failed to create memory_edits table: {}", e)))?;
+
+        sqlx::query("CREATE INDEX IF NOT EXISTS idx_memory_edits_agent ON memory_edits(agent_id, timestamp)")
+            .execute(&self.pool)
+            .await
+            .map_err(|e| Error::config(format!("Failed to create index: {}", e)))?;
+
+        sqlx::query(
+            r#"
+            CREATE TABLE IF NOT EXISTS memory_consolidation (
+                agent_id TEXT PRIMARY KEY,
+                processed_through UUID NOT NULL
+            )
+            "#,
+        )
+        .execute(&self.pool)
+        .await
+        .map_err(|e| {
+            Error::config(format!(
+                "Failed to create memory_consolidation table: {}",
+                e
+            ))
+        })?;
+
         // Create messages table
         sqlx::query(
             r#"
@@ -451,24 +1267,606 @@ impl PostgresStorage {
         .await
         .map_err(|e| Error::config(format!("Failed to create messages table: {}", e)))?;
 
-        // Create indices
-        sqlx::query("CREATE INDEX IF NOT EXISTS idx_memory_blocks_agent ON memory_blocks(agent_id)")
-            .execute(&self.pool)
+        // Create indices
+        sqlx::query(
+            "CREATE INDEX IF NOT EXISTS idx_memory_blocks_agent ON memory_blocks(agent_id)",
+        )
+        .execute(&self.pool)
+        .await
+        .map_err(|e| Error::config(format!("Failed to create index: {}", e)))?;
+
+        sqlx::query("CREATE INDEX IF NOT EXISTS idx_messages_agent ON messages(agent_id)")
+            .execute(&self.pool)
+            .await
+            .map_err(|e| Error::config(format!("Failed to create index: {}", e)))?;
+
+        sqlx::query("CREATE INDEX IF NOT EXISTS idx_messages_content ON messages USING gin(to_tsvector('english', content))")
+            .execute(&self.pool)
+            .await
+            .ok(); // Ignore error if GIN extension not available
+
+        // Create sessions and turns tables
+        sqlx::query(
+            r#"
+            CREATE TABLE IF NOT EXISTS sessions (
+                id UUID PRIMARY KEY,
+                user_id TEXT,
+                current_agent TEXT NOT NULL,
+                updated_at TIMESTAMPTZ NOT NULL,
+                data JSONB NOT NULL
+            )
+            "#,
+        )
+        .execute(&self.pool)
+        .await
+        .map_err(|e| Error::config(format!("Failed to create sessions table: {}", e)))?;
+
+        sqlx::query(
+            r#"
+            CREATE TABLE IF NOT EXISTS turns (
+                id UUID PRIMARY KEY,
+                session_id UUID NOT NULL,
+                agent_id TEXT NOT NULL,
+                timestamp TIMESTAMPTZ NOT NULL,
+                seq BIGSERIAL,
+                data JSONB NOT NULL
+            )
+            "#,
+        )
+        .execute(&self.pool)
+        .await
+        .map_err(|e| Error::config(format!("Failed to create turns table: {}", e)))?;
+
+        sqlx::query("CREATE INDEX IF NOT EXISTS idx_turns_session ON turns(session_id, timestamp)")
+            .execute(&self.pool)
+            .await
+            .map_err(|e| Error::config(format!("Failed to create index: {}", e)))?;
+
+        // Archival passages need the pgvector extension; without it the
+        // table is missing and archival calls fail with a storage error
+        if let Err(e) = sqlx::query("CREATE EXTENSION IF NOT EXISTS vector")
+            .execute(&self.pool)
+            .await
+        {
+            if is_missing_extension(&e) {
+                tracing::warn!(
+                    "pgvector is not installed; archival memory is unavailable: {}",
+                    e
+                );
+                return Ok(());
+            }
+            return Err(Error::config(format!(
+                "Failed to create vector extension: {}",
+                e
+            )));
+        }
+
+        sqlx::query(
+            r#"
+            CREATE TABLE IF NOT EXISTS archival_passages (
+                id UUID PRIMARY KEY,
+                agent_id TEXT NOT NULL,
+                source JSONB NOT NULL,
+                text TEXT NOT NULL,
+                created_at TIMESTAMPTZ NOT NULL,
+                metadata JSONB NOT NULL DEFAULT '{}',
+                embedding vector NOT NULL,
+                text_search tsvector GENERATED ALWAYS AS (to_tsvector('english', text)) STORED
+            )
+            "#,
+        )
+        .execute(&self.pool)
+        .await
+        .map_err(|e| Error::config(format!("Failed to create archival_passages table: {}", e)))?;
+
+        sqlx::query(
+            "CREATE INDEX IF NOT EXISTS idx_archival_passages_agent ON archival_passages(agent_id)",
+        )
+        .execute(&self.pool)
+        .await
+        .map_err(|e| Error::config(format!("Failed to create index: {}", e)))?;
+
+        sqlx::query("CREATE INDEX IF NOT EXISTS idx_archival_passages_text ON archival_passages USING gin(text_search)")
+            .execute(&self.pool)
+            .await
+            .map_err(|e| Error::config(format!("Failed to create index: {}", e)))?;
+
+        Ok(())
+    }
+}
+
+/// Whether `CREATE EXTENSION` failed because the extension is not installed
+///
+/// PostgreSQL 15 and later report `feature_not_supported`; older servers
+/// report the missing control file as `undefined_file`.
+#[cfg(feature = "storage")]
+fn is_missing_extension(e: &sqlx::Error) -> bool {
+    e.as_database_error()
+        .and_then(|e| e.code())
+        .is_some_and(|code| code == "0A000" || code == "58P01")
+}
+
+/// pgvector text literal, e.g. `[0.1,0.2]`
+#[cfg(feature = "storage")]
+fn to_pgvector(vector: &[f32]) -> String {
+    let values: Vec<String> = vector.iter().map(|v| v.to_string()).collect();
+    format!("[{}]", values.join(","))
+}
+
+#[cfg(feature = "storage")]
+fn from_pgvector(text: &str) -> Result<Vec<f32>> {
+    text.trim_matches(|c| c == '[' || c == ']')
+        .split(',')
+        .filter(|v| !v.is_empty())
+        .map(|v| {
+            v.trim()
+                .parse()
+                .map_err(|e| Error::storage(format!("Invalid stored embedding: {}", e)))
+        })
+        .collect()
+}
+
+#[cfg(feature = "storage")]
+type PassageRow = (
+    uuid::Uuid,
+    String,
+    serde_json::Value,
+    String,
+    DateTime<Utc>,
+    serde_json::Value,
+    String,
+);
+
+#[cfg(feature = "storage")]
+fn passage_from_row(row: PassageRow) -> Result<ArchivalPassage> {
+    let (id, agent_id, source, text, created_at, metadata, embedding) = row;
+    Ok(ArchivalPassage {
+        id,
+        agent_id: from_json(&format!("\"{}\"", agent_id))?,
+        source: serde_json::from_value(source)
+            .map_err(|e| Error::storage(format!("Invalid passage source: {}", e)))?,
+        text,
+        created_at,
+        metadata: serde_json::from_value(metadata)
+            .map_err(|e| Error::storage(format!("Invalid metadata: {}", e)))?,
+        embedding: from_pgvector(&embedding)?,
+    })
+}
+
+/// Nearest-neighbour search runs in PostgreSQL with pgvector's cosine
+/// distance, and keyword search with `ts_rank` over a GIN-indexed tsvector.
+///
+/// The embedding column has no fixed dimension, so searches scan the
+/// agent's passages; for large archives add an index on a cast, e.g.
+/// `USING hnsw ((embedding::vector(1536)) vector_cosine_ops)`.
+#[cfg(feature = "storage")]
+#[async_trait]
+impl VectorIndex for PostgresStorage {
+    async fn insert(&self, passages: Vec<ArchivalPassage>) -> Result<()> {
+        let mut tx = self
+            .pool
+            .begin()
+            .await
+            .map_err(|e| Error::storage(format!("Failed to start transaction: {}", e)))?;
+
+        for passage in &passages {
+            sqlx::query(
+                r#"
+                INSERT INTO archival_passages (id, agent_id, source, text, created_at, metadata, embedding)
+                VALUES ($1, $2, $3, $4, $5, $6, $7::vector)
+                "#,
+            )
+            .bind(passage.id)
+            .bind(passage.agent_id.to_string())
+            .bind(serde_json::to_value(&passage.source)?)
+            .bind(&passage.text)
+            .bind(passage.created_at)
+            .bind(serde_json::to_value(&passage.metadata)?)
+            .bind(to_pgvector(&passage.embedding))
+            .execute(&mut *tx)
+            .await
+            .map_err(|e| Error::storage(format!("Failed to save archival passage: {}", e)))?;
+        }
+
+        tx.commit()
+            .await
+            .map_err(|e| Error::storage(format!("Failed to commit archival passages: {}", e)))
+    }
+
+    async fn remove_source(&self, agent_id: AgentId, source: &PassageSource) -> Result<()> {
+        sqlx::query("DELETE FROM archival_passages WHERE agent_id = $1 AND source = $2")
+            .bind(agent_id.to_string())
+            .bind(serde_json::to_value(source)?)
+            .execute(&self.pool)
+            .await
+            .map_err(|e| Error::storage(format!("Failed to delete archival passages: {}", e)))?;
+
+        Ok(())
+    }
+
+    async fn nearest(
+        &self,
+        agent_id: AgentId,
+        vector: &[f32],
+        limit: usize,
+    ) -> Result<Vec<ScoredPassage>> {
+        let rows = sqlx::query(
+            r#"
+            SELECT id, agent_id, source, text, created_at, metadata, embedding::text AS embedding,
+                   1 - (embedding <=> $2::vector) AS score
+            FROM archival_passages WHERE agent_id = $1
+            ORDER BY embedding <=> $2::vector
+            LIMIT $3
+            "#,
+        )
+        .bind(agent_id.to_string())
+        .bind(to_pgvector(vector))
+        .bind(limit as i64)
+        .fetch_all(&self.pool)
+        .await
+        .map_err(|e| Error::storage(format!("Failed to search archival passages: {}", e)))?;
+
+        rows.into_iter()
+            .map(|row| {
+                let score: f64 = row.get("score");
+                let passage = passage_from_row((
+                    row.get("id"),
+                    row.get("agent_id"),
+                    row.get("source"),
+                    row.get("text"),
+                    row.get("created_at"),
+                    row.get("metadata"),
+                    row.get("embedding"),
+                ))?;
+                Ok(ScoredPassage {
+                    passage,
+                    score: score as f32,
+                })
+            })
+            .collect()
+    }
+
+    async fn keyword(
+        &self,
+        agent_id: AgentId,
+        query: &str,
+        limit: usize,
+    ) -> Result<Vec<ScoredPassage>> {
+        // Match any query term, like BM25, rather than all of them
+        let rows = sqlx::query(
+            r#"
+            SELECT id, agent_id, source, text, created_at, metadata, embedding::text AS embedding,
+                   ts_rank(text_search, query) AS score
+            FROM archival_passages,
+                 replace(plainto_tsquery('english', $2)::text, ' & ', ' | ')::tsquery AS query
+            WHERE agent_id = $1 AND text_search @@ query
+            ORDER BY score DESC
+            LIMIT $3
+            "#,
+        )
+        .bind(agent_id.to_string())
+        .bind(query)
+        .bind(limit as i64)
+        .fetch_all(&self.pool)
+        .await
+        .map_err(|e| Error::storage(format!("Failed to search archival passages: {}", e)))?;
+
+        rows.into_iter()
+            .map(|row| {
+                let score: f32 = row.get("score");
+                let passage = passage_from_row((
+                    row.get("id"),
+                    row.get("agent_id"),
+                    row.get("source"),
+                    row.get("text"),
+                    row.get("created_at"),
+                    row.get("metadata"),
+                    row.get("embedding"),
+                ))?;
+                Ok(ScoredPassage { passage, score })
+            })
+            .collect()
+    }
+
+    async fn passages(&self, agent_id: AgentId) -> Result<Vec<ArchivalPassage>> {
+        let rows = sqlx::query_as::<_, PassageRow>(
+            r#"
+            SELECT id, agent_id, source, text, created_at, metadata, embedding::text
+            FROM archival_passages WHERE agent_id = $1
+            ORDER BY created_at
+            "#,
+        )
+        .bind(agent_id.to_string())
+        .fetch_all(&self.pool)
+        .await
+        .map_err(|e| Error::storage(format!("Failed to load archival passages: {}", e)))?;
+
+        rows.into_iter().map(passage_from_row).collect()
+    }
+}
+
+#[cfg(feature = "storage")]
+#[async_trait]
+impl TurnStorage for PostgresStorage {
+    async fn store_session(&self, session: &Session) -> Result<()> {
+        let data = serde_json::to_value(session)
+            .map_err(|e| Error::storage(format!("Failed to serialize session: {}", e)))?;
+
+        sqlx::query(
+            r#"
+            INSERT INTO sessions (id, user_id, current_agent, updated_at, data)
+            VALUES ($1, $2, $3, $4, $5)
+            ON CONFLICT (id) DO UPDATE SET
+                user_id = EXCLUDED.user_id,
+                current_agent = EXCLUDED.current_agent,
+                updated_at = EXCLUDED.updated_at,
+                data = EXCLUDED.data
+            "#,
+        )
+        .bind(session.id.as_uuid())
+        .bind(session.user_id.as_ref().map(|id| id.to_string()))
+        .bind(session.current_agent.to_string())
+        .bind(session.metadata.updated_at)
+        .bind(data)
+        .execute(&self.pool)
+        .await
+        .map_err(|e| Error::storage(format!("Failed to save session: {}", e)))?;
+
+        Ok(())
+    }
+
+    async fn load_session(&self, id: SessionId) -> Result<Option<Session>> {
+        let data: Option<serde_json::Value> =
+            sqlx::query_scalar("SELECT data FROM sessions WHERE id = $1")
+                .bind(id.as_uuid())
+                .fetch_optional(&self.pool)
+                .await
+                .map_err(|e| Error::storage(format!("Failed to load session: {}", e)))?;
+
+        data.map(|data| {
+            serde_json::from_value(data)
+                .map_err(|e| Error::storage(format!("Invalid session data: {}", e)))
+        })
+        .transpose()
+    }
+
+    async fn store_turn(&self, turn: &Turn) -> Result<()> {
+        let data = serde_json::to_value(turn)
+            .map_err(|e| Error::storage(format!("Failed to serialize turn: {}", e)))?;
+
+        sqlx::query(
+            r#"
+            INSERT INTO turns (id, session_id, agent_id, timestamp, data)
+            VALUES ($1, $2, $3, $4, $5)
+            ON CONFLICT (id) DO UPDATE SET data = EXCLUDED.data
+            "#,
+        )
+        .bind(turn.id.as_uuid())
+        .bind(turn.session_id.as_uuid())
+        .bind(turn.agent_id.to_string())
+        .bind(turn.timestamp)
+        .bind(data)
+        .execute(&self.pool)
+        .await
+        .map_err(|e| Error::storage(format!("Failed to save turn: {}", e)))?;
+
+        Ok(())
+    }
+
+    async fn load_turns(&self, session_id: SessionId) -> Result<Vec<Turn>> {
+        let rows: Vec<serde_json::Value> = sqlx::query_scalar(
+            "SELECT data FROM turns WHERE session_id = $1 ORDER BY timestamp ASC, seq ASC",
+        )
+        .bind(session_id.as_uuid())
+        .fetch_all(&self.pool)
+        .await
+        .map_err(|e| Error::storage(format!("Failed to load turns: {}", e)))?;
+
+        rows.into_iter()
+            .map(|data| {
+                serde_json::from_value(data)
+                    .map_err(|e| Error::storage(format!("Invalid turn data: {}", e)))
+            })
+            .collect()
+    }
+}
+
+/// Embedded turn storage on sled
+///
+/// Sessions live in a `sessions` tree keyed by session ID. Turns live in a
+/// `turns` tree keyed by session ID and timestamp, so a prefix scan returns
+/// them in order. Writes are flushed before returning.
+#[cfg(feature = "storage")]
+pub struct SledTurnStorage {
+    sessions: sled::Tree,
+    turns: sled::Tree,
+}
+
+#[cfg(feature = "storage")]
+impl SledTurnStorage {
+    /// Open or create a database at `path`
+    pub fn open(path: impl AsRef<std::path::Path>) -> Result<Self> {
+        let db = sled::open(path)
+            .map_err(|e| Error::config(format!("Failed to open sled database: {}", e)))?;
+        Self::from_db(&db)
+    }
+
+    /// Use trees within an existing database
+    pub fn from_db(db: &sled::Db) -> Result<Self> {
+        let open_tree = |name: &str| {
+            db.open_tree(name)
+                .map_err(|e| Error::config(format!("Failed to open sled tree '{}': {}", name, e)))
+        };
+
+        Ok(Self {
+            sessions: open_tree("sessions")?,
+            turns: open_tree("turns")?,
+        })
+    }
+
+    fn turn_key(turn: &Turn) -> Vec<u8> {
+        let nanos = turn.timestamp.timestamp_nanos_opt().unwrap_or_default();
+        let mut key = format!("{}/", turn.session_id).into_bytes();
+        key.extend_from_slice(&nanos.to_be_bytes());
+        key.extend_from_slice(turn.id.to_string().as_bytes());
+        key
+    }
+}
+
+#[cfg(feature = "storage")]
+#[async_trait]
+impl TurnStorage for SledTurnStorage {
+    async fn store_session(&self, session: &Session) -> Result<()> {
+        self.sessions
+            .insert(session.id.to_string(), to_json(session)?.into_bytes())
+            .map_err(|e| Error::storage(format!("Failed to save session: {}", e)))?;
+        self.sessions
+            .flush_async()
+            .await
+            .map_err(|e| Error::storage(format!("Failed to flush sessions: {}", e)))?;
+
+        Ok(())
+    }
+
+    async fn load_session(&self, id: SessionId) -> Result<Option<Session>> {
+        let data = self
+            .sessions
+            .get(id.to_string())
+            .map_err(|e| Error::storage(format!("Failed to load session: {}", e)))?;
+
+        data.map(|data| from_json(&String::from_utf8_lossy(&data)))
+            .transpose()
+    }
+
+    async fn store_turn(&self, turn: &Turn) -> Result<()> {
+        self.turns
+            .insert(Self::turn_key(turn), to_json(turn)?.into_bytes())
+            .map_err(|e| Error::storage(format!("Failed to save turn: {}", e)))?;
+        self.turns
+            .flush_async()
             .await
-            .map_err(|e| Error::config(format!("Failed to create index: {}", e)))?;
+            .map_err(|e| Error::storage(format!("Failed to flush turns: {}", e)))?;
 
-        sqlx::query("CREATE INDEX IF NOT EXISTS idx_messages_agent ON messages(agent_id)")
-            .execute(&self.pool)
-            .await
-            .map_err(|e| Error::config(format!("Failed to create index: {}", e)))?;
+        Ok(())
+    }
 
-        sqlx::query("CREATE INDEX IF NOT EXISTS idx_messages_content ON messages USING gin(to_tsvector('english', content))")
-            .execute(&self.pool)
+    async fn load_turns(&self, session_id: SessionId) -> Result<Vec<Turn>> {
+        self.turns
+            .scan_prefix(format!("{}/", session_id))
+            .values()
+            .map(|data| {
+                let data =
+                    data.map_err(|e| Error::storage(format!("Failed to load turns: {}", e)))?;
+                from_json(&String::from_utf8_lossy(&data))
+            })
+            .collect()
+    }
+}
+
+/// Embedded response cache on sled
+///
+/// Entries live in an `llm_cache` tree keyed by `<scope>/<request hash>`, so
+/// a prefix scan returns one scope.
+#[cfg(feature = "storage")]
+pub struct SledCacheStore {
+    entries: sled::Tree,
+}
+
+#[cfg(feature = "storage")]
+impl SledCacheStore {
+    /// Open or create a database at `path`
+    pub fn open(path: impl AsRef<std::path::Path>) -> Result<Self> {
+        let db = sled::open(path)
+            .map_err(|e| Error::config(format!("Failed to open sled database: {}", e)))?;
+        Self::from_db(&db)
+    }
+
+    /// Use a tree within an existing database
+    pub fn from_db(db: &sled::Db) -> Result<Self> {
+        let entries = db
+            .open_tree("llm_cache")
+            .map_err(|e| Error::config(format!("Failed to open sled tree 'llm_cache': {}", e)))?;
+        Ok(Self { entries })
+    }
+}
+
+#[cfg(feature = "storage")]
+#[async_trait]
+impl CacheStore for SledCacheStore {
+    async fn get(&self, key: &str) -> Result<Option<CacheEntry>> {
+        let data = self
+            .entries
+            .get(key)
+            .map_err(|e| Error::storage(format!("Failed to load cache entry: {}", e)))?;
+
+        data.map(|data| from_json(&String::from_utf8_lossy(&data)))
+            .transpose()
+    }
+
+    async fn put(&self, entry: &CacheEntry) -> Result<()> {
+        self.entries
+            .insert(entry.key.as_bytes(), to_json(entry)?.into_bytes())
+            .map_err(|e| Error::storage(format!("Failed to save cache entry: {}", e)))?;
+        self.entries
+            .flush_async()
             .await
-            .ok(); // Ignore error if GIN extension not available
+            .map_err(|e| Error::storage(format!("Failed to flush cache: {}", e)))?;
+
+        Ok(())
+    }
+
+    async fn remove(&self, key: &str) -> Result<()> {
+        self.entries
+            .remove(key)
+            .map_err(|e| Error::storage(format!("Failed to delete cache entry: {}", e)))?;
 
         Ok(())
     }
+
+    async fn entries(&self, scope: &str) -> Result<Vec<CacheEntry>> {
+        self.entries
+            .scan_prefix(format!("{}/", scope))
+            .values()
+            .map(|data| {
+                let data = data
+                    .map_err(|e| Error::storage(format!("Failed to load cache entries: {}", e)))?;
+                from_json(&String::from_utf8_lossy(&data))
+            })
+            .collect()
+    }
+}
+
+#[cfg(feature = "storage")]
+fn to_json<T: serde::Serialize>(value: &T) -> Result<String> {
+    serde_json::to_string(value).map_err(|e| Error::storage(format!("Failed to serialize: {}", e)))
+}
+
+#[cfg(feature = "storage")]
+fn from_json<T: serde::de::DeserializeOwned>(data: &str) -> Result<T> {
+    serde_json::from_str(data).map_err(|e| Error::storage(format!("Invalid stored data: {}", e)))
+}
+
+/// Pair stored shared blocks with their `(block_id, agent_id, access)` grants
+#[cfg(feature = "storage")]
+fn shared_blocks_from_rows(
+    blocks: Vec<MemoryBlock>,
+    grants: Vec<(String, String, String)>,
+) -> Result<Vec<SharedBlock>> {
+    let mut acls: HashMap<String, HashMap<AgentId, BlockAccess>> = HashMap::new();
+    for (block_id, agent_id, access) in grants {
+        acls.entry(block_id).or_default().insert(
+            from_json(&format!("\"{}\"", agent_id))?,
+            from_json(&format!("\"{}\"", access))?,
+        );
+    }
+
+    Ok(blocks
+        .into_iter()
+        .map(|block| SharedBlock {
+            acl: acls.remove(&block.id.to_string()).unwrap_or_default(),
+            block,
+        })
+        .collect())
 }
 
 #[cfg(feature = "storage")]
@@ -478,8 +1876,8 @@ impl MemoryStorage for PostgresStorage {
         sqlx::query(
             r#"
             INSERT INTO memory_blocks
-            (id, agent_id, label, description, value, max_size, in_context, created_at, updated_at, metadata)
-            VALUES ($1, $2, $3, $4, $5, $6, $7, $8, $9, $10)
+            (id, agent_id, label, description, value, max_size, in_context, created_at, updated_at, metadata, version)
+            VALUES ($1, $2, $3, $4, $5, $6, $7, $8, $9, $10, $11)
             ON CONFLICT (id) DO UPDATE SET
                 label = EXCLUDED.label,
                 description = EXCLUDED.description,
@@ -487,7 +1885,8 @@ impl MemoryStorage for PostgresStorage {
                 max_size = EXCLUDED.max_size,
                 in_context = EXCLUDED.in_context,
                 updated_at = EXCLUDED.updated_at,
-                metadata = EXCLUDED.metadata
+                metadata = EXCLUDED.metadata,
+                version = EXCLUDED.version
             "#,
         )
         .bind(block.id.to_string())
@@ -500,6 +1899,7 @@ impl MemoryStorage for PostgresStorage {
         .bind(block.created_at)
         .bind(block.updated_at)
         .bind(serde_json::to_value(&block.metadata).unwrap())
+        .bind(block.version as i64)
         .execute(&self.pool)
         .await
         .map_err(|e| Error::config(format!("Failed to save memory block: {}", e)))?;
@@ -508,9 +1908,9 @@ impl MemoryStorage for PostgresStorage {
     }
 
     async fn load_block(&self, block_id: MemoryBlockId) -> Result<Option<MemoryBlock>> {
-        let row = sqlx::query_as::<_, (String, String, String, String, Option<i64>, bool, DateTime<Utc>, DateTime<Utc>, serde_json::Value)>(
+        let row = sqlx::query_as::<_, (String, String, String, String, Option<i64>, bool, DateTime<Utc>, DateTime<Utc>, serde_json::Value, i64)>(
             r#"
-            SELECT id, label, description, value, max_size, in_context, created_at, updated_at, metadata
+            SELECT id, label, description, value, max_size, in_context, created_at, updated_at, metadata, version
             FROM memory_blocks WHERE id = $1
             "#,
         )
@@ -519,7 +1919,19 @@ impl MemoryStorage for PostgresStorage {
         .await
         .map_err(|e| Error::config(format!("Failed to load memory block: {}", e)))?;
 
-        if let Some((id_str, label, description, value, max_size, in_context, created_at, updated_at, metadata)) = row {
+        if let Some((
+            id_str,
+            label,
+            description,
+            value,
+            max_size,
+            in_context,
+            created_at,
+            updated_at,
+            metadata,
+            version,
+        )) = row
+        {
             let id = serde_json::from_str(&format!("\"{}\"", id_str))
                 .map_err(|e| Error::config(format!("Invalid block ID: {}", e)))?;
 
@@ -534,6 +1946,7 @@ impl MemoryStorage for PostgresStorage {
                 updated_at,
                 metadata: serde_json::from_value(metadata)
                     .map_err(|e| Error::config(format!("Invalid metadata: {}", e)))?,
+                version: version as u64,
             }))
         } else {
             Ok(None)
@@ -541,9 +1954,9 @@ impl MemoryStorage for PostgresStorage {
     }
 
     async fn load_agent_blocks(&self, agent_id: AgentId) -> Result<Vec<MemoryBlock>> {
-        let rows = sqlx::query_as::<_, (String, String, String, String, Option<i64>, bool, DateTime<Utc>, DateTime<Utc>, serde_json::Value)>(
+        let rows = sqlx::query_as::<_, (String, String, String, String, Option<i64>, bool, DateTime<Utc>, DateTime<Utc>, serde_json::Value, i64)>(
             r#"
-            SELECT id, label, description, value, max_size, in_context, created_at, updated_at, metadata
+            SELECT id, label, description, value, max_size, in_context, created_at, updated_at, metadata, version
             FROM memory_blocks WHERE agent_id = $1
             ORDER BY created_at DESC
             "#,
@@ -554,7 +1967,19 @@ impl MemoryStorage for PostgresStorage {
         .map_err(|e| Error::config(format!("Failed to load agent blocks: {}", e)))?;
 
         let mut blocks = Vec::new();
-        for (id_str, label, description, value, max_size, in_context, created_at, updated_at, metadata) in rows {
+        for (
+            id_str,
+            label,
+            description,
+            value,
+            max_size,
+            in_context,
+            created_at,
+            updated_at,
+            metadata,
+            version,
+        ) in rows
+        {
             let id = serde_json::from_str(&format!("\"{}\"", id_str))
                 .map_err(|e| Error::config(format!("Invalid block ID: {}", e)))?;
 
@@ -569,6 +1994,7 @@ impl MemoryStorage for PostgresStorage {
                 updated_at,
                 metadata: serde_json::from_value(metadata)
                     .map_err(|e| Error::config(format!("Invalid metadata: {}", e)))?,
+                version: version as u64,
             });
         }
 
@@ -585,6 +2011,329 @@ impl MemoryStorage for PostgresStorage {
         Ok(())
     }
 
+    async fn save_block_version(&self, agent_id: AgentId, version: &BlockVersion) -> Result<()> {
+        sqlx::query(
+            r#"
+            INSERT INTO memory_block_versions
+            (block_id, version, agent_id, value, author, reason, timestamp)
+            VALUES ($1, $2, $3, $4, $5, $6, $7)
+            ON CONFLICT (block_id, version) DO NOTHING
+            "#,
+        )
+        .bind(version.block_id.to_string())
+        .bind(version.version as i64)
+        .bind(agent_id.to_string())
+        .bind(&version.value)
+        .bind(serde_json::to_value(&version.author)?)
+        .bind(&version.reason)
+        .bind(version.timestamp)
+        .execute(&self.pool)
+        .await
+        .map_err(|e| Error::storage(format!("Failed to save block version: {}", e)))?;
+
+        Ok(())
+    }
+
+    async fn load_block_versions(&self, block_id: MemoryBlockId) -> Result<Vec<BlockVersion>> {
+        let rows = sqlx::query_as::<_, (i64, String, serde_json::Value, String, DateTime<Utc>)>(
+            r#"
+            SELECT version, value, author, reason, timestamp
+            FROM memory_block_versions WHERE block_id = $1
+            ORDER BY version
+            "#,
+        )
+        .bind(block_id.to_string())
+        .fetch_all(&self.pool)
+        .await
+        .map_err(|e| Error::storage(format!("Failed to load block versions: {}", e)))?;
+
+        rows.into_iter()
+            .map(|(version, value, author, reason, timestamp)| {
+                Ok(BlockVersion {
+                    block_id,
+                    version: version as u64,
+                    value,
+                    author: serde_json::from_value(author)
+                        .map_err(|e| Error::storage(format!("Invalid version author: {}", e)))?,
+                    reason,
+                    timestamp,
+                })
+            })
+            .collect()
+    }
+
+    async fn load_agent_block_versions(&self, agent_id: AgentId) -> Result<Vec<BlockVersion>> {
+        let rows = sqlx::query_as::<
+            _,
+            (
+                String,
+                i64,
+                String,
+                serde_json::Value,
+                String,
+                DateTime<Utc>,
+            ),
+        >(
+            r#"
+            SELECT block_id, version, value, author, reason, timestamp
+            FROM memory_block_versions WHERE agent_id = $1
+            ORDER BY block_id, version
+            "#,
+        )
+        .bind(agent_id.to_string())
+        .fetch_all(&self.pool)
+        .await
+        .map_err(|e| Error::storage(format!("Failed to load block versions: {}", e)))?;
+
+        rows.into_iter()
+            .map(|(block_id, version, value, author, reason, timestamp)| {
+                Ok(BlockVersion {
+                    block_id: from_json(&format!("\"{}\"", block_id))?,
+                    version: version as u64,
+                    value,
+                    author: serde_json::from_value(author)
+                        .map_err(|e| Error::storage(format!("Invalid version author: {}", e)))?,
+                    reason,
+                    timestamp,
+                })
+            })
+            .collect()
+    }
+
+    async fn save_shared_block(
+        &self,
+        block: &MemoryBlock,
+        expected_revision: Option<u64>,
+    ) -> Result<()> {
+        // The revision check happens in the statement, so concurrent writers
+        // cannot both succeed
+        let saved = match expected_revision {
+            None => sqlx::query(
+                r#"
+                INSERT INTO shared_memory_blocks (id, revision, updated_at, data)
+                VALUES ($1, $2, $3, $4)
+                ON CONFLICT (id) DO NOTHING
+                "#,
+            )
+            .bind(block.id.to_string())
+            .bind(block.version as i64)
+            .bind(block.updated_at)
+            .bind(serde_json::to_value(block)?),
+            Some(expected) => sqlx::query(
+                r#"
+                UPDATE shared_memory_blocks SET revision = $2, updated_at = $3, data = $4
+                WHERE id = $1 AND revision = $5
+                "#,
+            )
+            .bind(block.id.to_string())
+            .bind(block.version as i64)
+            .bind(block.updated_at)
+            .bind(serde_json::to_value(block)?)
+            .bind(expected as i64),
+        }
+        .execute(&self.pool)
+        .await
+        .map_err(|e| Error::storage(format!("Failed to save shared block: {}", e)))?;
+
+        if saved.rows_affected() == 0 {
+            let actual: Option<i64> =
+                sqlx::query_scalar("SELECT revision FROM shared_memory_blocks WHERE id = $1")
+                    .bind(block.id.to_string())
+                    .fetch_optional(&self.pool)
+                    .await
+                    .map_err(|e| {
+                        Error::storage(format!("Failed to load shared block revision: {}", e))
+                    })?;
+            return Err(Error::MemoryConflict {
+                block_id: block.id.to_string(),
+                expected: expected_revision.unwrap_or_default(),
+                actual: actual.unwrap_or_default() as u64,
+            });
+        }
+
+        Ok(())
+    }
+
+    async fn save_shared_block_acl(
+        &self,
+        block_id: MemoryBlockId,
+        acl: &HashMap<AgentId, BlockAccess>,
+    ) -> Result<()> {
+        let mut tx = self
+            .pool
+            .begin()
+            .await
+            .map_err(|e| Error::storage(format!("Failed to start transaction: {}", e)))?;
+
+        sqlx::query("DELETE FROM shared_block_access WHERE block_id = $1")
+            .bind(block_id.to_string())
+            .execute(&mut *tx)
+            .await
+            .map_err(|e| Error::storage(format!("Failed to save shared block access: {}", e)))?;
+        for (agent_id, access) in acl {
+            sqlx::query(
+                "INSERT INTO shared_block_access (block_id, agent_id, access) VALUES ($1, $2, $3)",
+            )
+            .bind(block_id.to_string())
+            .bind(agent_id.to_string())
+            .bind(access.to_string())
+            .execute(&mut *tx)
+            .await
+            .map_err(|e| Error::storage(format!("Failed to save shared block access: {}", e)))?;
+        }
+
+        tx.commit()
+            .await
+            .map_err(|e| Error::storage(format!("Failed to commit shared block access: {}", e)))
+    }
+
+    async fn load_shared_blocks(&self) -> Result<Vec<SharedBlock>> {
+        let rows: Vec<serde_json::Value> =
+            sqlx::query_scalar("SELECT data FROM shared_memory_blocks ORDER BY id")
+                .fetch_all(&self.pool)
+                .await
+                .map_err(|e| Error::storage(format!("Failed to load shared blocks: {}", e)))?;
+        let grants = sqlx::query_as::<_, (String, String, String)>(
+            "SELECT block_id, agent_id, access FROM shared_block_access",
+        )
+        .fetch_all(&self.pool)
+        .await
+        .map_err(|e| Error::storage(format!("Failed to load shared block access: {}", e)))?;
+
+        let blocks = rows
+            .into_iter()
+            .map(|data| {
+                serde_json::from_value(data)
+                    .map_err(|e| Error::storage(format!("Invalid stored data: {}", e)))
+            })
+            .collect::<Result<_>>()?;
+        shared_blocks_from_rows(blocks, grants)
+    }
+
+    async fn save_shared_block_version(&self, version: &BlockVersion) -> Result<()> {
+        sqlx::query(
+            r#"
+            INSERT INTO shared_block_versions
+            (block_id, version, value, author, reason, timestamp)
+            VALUES ($1, $2, $3, $4, $5, $6)
+            ON CONFLICT (block_id, version) DO NOTHING
+            "#,
+        )
+        .bind(version.block_id.to_string())
+        .bind(version.version as i64)
+        .bind(&version.value)
+        .bind(serde_json::to_value(&version.author)?)
+        .bind(&version.reason)
+        .bind(version.timestamp)
+        .execute(&self.pool)
+        .await
+        .map_err(|e| Error::storage(format!("Failed to save shared block version: {}", e)))?;
+
+        Ok(())
+    }
+
+    async fn load_shared_block_versions(&self) -> Result<Vec<BlockVersion>> {
+        let rows = sqlx::query_as::<
+            _,
+            (
+                String,
+                i64,
+                String,
+                serde_json::Value,
+                String,
+                DateTime<Utc>,
+            ),
+        >(
+            r#"
+            SELECT block_id, version, value, author, reason, timestamp
+            FROM shared_block_versions
+            ORDER BY block_id, version
+            "#,
+        )
+        .fetch_all(&self.pool)
+        .await
+        .map_err(|e| Error::storage(format!("Failed to load shared block versions: {}", e)))?;
+
+        rows.into_iter()
+            .map(|(block_id, version, value, author, reason, timestamp)| {
+                Ok(BlockVersion {
+                    block_id: from_json(&format!("\"{}\"", block_id))?,
+                    version: version as u64,
+                    value,
+                    author: serde_json::from_value(author)
+                        .map_err(|e| Error::storage(format!("Invalid version author: {}", e)))?,
+                    reason,
+                    timestamp,
+                })
+            })
+            .collect()
+    }
+
+    async fn save_memory_edit(&self, agent_id: AgentId, edit: &MemoryEdit) -> Result<()> {
+        sqlx::query(
+            r#"
+            INSERT INTO memory_edits (id, agent_id, timestamp, data)
+            VALUES ($1, $2, $3, $4)
+            ON CONFLICT (id) DO NOTHING
+            "#,
+        )
+        .bind(edit.id)
+        .bind(agent_id.to_string())
+        .bind(edit.timestamp)
+        .bind(serde_json::to_value(edit)?)
+        .execute(&self.pool)
+        .await
+        .map_err(|e| Error::storage(format!("Failed to save memory edit: {}", e)))?;
+
+        Ok(())
+    }
+
+    async fn load_memory_edits(&self, agent_id: AgentId) -> Result<Vec<MemoryEdit>> {
+        let rows: Vec<serde_json::Value> = sqlx::query_scalar(
+            "SELECT data FROM memory_edits WHERE agent_id = $1 ORDER BY timestamp",
+        )
+        .bind(agent_id.to_string())
+        .fetch_all(&self.pool)
+        .await
+        .map_err(|e| Error::storage(format!("Failed to load memory edits: {}", e)))?;
+
+        rows.into_iter()
+            .map(|data| {
+                serde_json::from_value(data)
+                    .map_err(|e| Error::storage(format!("Invalid stored data: {}", e)))
+            })
+            .collect()
+    }
+
+    async fn save_processed_through(
+        &self,
+        agent_id: AgentId,
+        message_id: uuid::Uuid,
+    ) -> Result<()> {
+        sqlx::query(
+            r#"
+            INSERT INTO memory_consolidation (agent_id, processed_through)
+            VALUES ($1, $2)
+            ON CONFLICT (agent_id) DO UPDATE SET processed_through = EXCLUDED.processed_through
+            "#,
+        )
+        .bind(agent_id.to_string())
+        .bind(message_id)
+        .execute(&self.pool)
+        .await
+        .map_err(|e| Error::storage(format!("Failed to save consolidation progress: {}", e)))?;
+
+        Ok(())
+    }
+
+    async fn load_processed_through(&self, agent_id: AgentId) -> Result<Option<uuid::Uuid>> {
+        sqlx::query_scalar("SELECT processed_through FROM memory_consolidation WHERE agent_id = $1")
+            .bind(agent_id.to_string())
+            .fetch_optional(&self.pool)
+            .await
+            .map_err(|e| Error::storage(format!("Failed to load consolidation progress: {}", e)))
+    }
+
     async fn save_message(&self, agent_id: AgentId, message: &MessageEntry) -> Result<()> {
         let tool_calls_json = message
             .tool_calls
@@ -616,7 +2365,17 @@ impl MemoryStorage for PostgresStorage {
     }
 
     async fn load_messages(&self, agent_id: AgentId, limit: usize) -> Result<Vec<MessageEntry>> {
-        let rows = sqlx::query_as::<_, (uuid::Uuid, DateTime<Utc>, String, String, Option<serde_json::Value>, serde_json::Value)>(
+        let rows = sqlx::query_as::<
+            _,
+            (
+                uuid::Uuid,
+                DateTime<Utc>,
+                String,
+                String,
+                Option<serde_json::Value>,
+                serde_json::Value,
+            ),
+        >(
             r#"
             SELECT id, timestamp, role, content, tool_calls, metadata
             FROM messages WHERE agent_id = $1
@@ -651,7 +2410,17 @@ impl MemoryStorage for PostgresStorage {
     }
 
     async fn search_messages(&self, agent_id: AgentId, query: &str) -> Result<Vec<MessageEntry>> {
-        let rows = sqlx::query_as::<_, (uuid::Uuid, DateTime<Utc>, String, String, Option<serde_json::Value>, serde_json::Value)>(
+        let rows = sqlx::query_as::<
+            _,
+            (
+                uuid::Uuid,
+                DateTime<Utc>,
+                String,
+                String,
+                Option<serde_json::Value>,
+                serde_json::Value,
+            ),
+        >(
             r#"
             SELECT id, timestamp, role, content, tool_calls, metadata
             FROM messages WHERE agent_id = $1 AND content ILIKE $2
@@ -690,12 +2459,49 @@ impl MemoryStorage for PostgresStorage {
             .await
             .map_err(|e| Error::config(format!("Failed to delete agent blocks: {}", e)))?;
 
+        sqlx::query("DELETE FROM memory_block_versions WHERE agent_id = $1")
+            .bind(agent_id.to_string())
+            .execute(&self.pool)
+            .await
+            .map_err(|e| Error::config(format!("Failed to delete block versions: {}", e)))?;
+
         sqlx::query("DELETE FROM messages WHERE agent_id = $1")
             .bind(agent_id.to_string())
             .execute(&self.pool)
             .await
             .map_err(|e| Error::config(format!("Failed to delete agent messages: {}", e)))?;
 
+        sqlx::query("DELETE FROM memory_edits WHERE agent_id = $1")
+            .bind(agent_id.to_string())
+            .execute(&self.pool)
+            .await
+            .map_err(|e| Error::config(format!("Failed to delete memory edits: {}", e)))?;
+
+        sqlx::query("DELETE FROM memory_consolidation WHERE agent_id = $1")
+            .bind(agent_id.to_string())
+            .execute(&self.pool)
+            .await
+            .map_err(|e| {
+                Error::config(format!("Failed to delete consolidation progress: {}", e))
+            })?;
+
+        let archived = sqlx::query("DELETE FROM archival_passages WHERE agent_id = $1")
+            .bind(agent_id.to_string())
+            .execute(&self.pool)
+            .await;
+        match archived {
+            // Table is absent without pgvector
+            Err(e)
+                if e.as_database_error()
+                    .and_then(|e| e.code())
+                    .is_some_and(|code| code == "42P01") => {}
+            other => {
+                other.map_err(|e| {
+                    Error::config(format!("Failed to delete archival passages: {}", e))
+                })?;
+            }
+        }
+
         Ok(())
     }
 }
@@ -704,8 +2510,274 @@ impl MemoryStorage for PostgresStorage {
 #[cfg(feature = "storage")]
 mod tests {
     use super::*;
+    use crate::agent::AgentOutput;
     use crate::memory::MemoryBlock;
-    use crate::types::AgentId;
+    use crate::react::ReActTrace;
+    use crate::turns::SessionConfig;
+    use crate::types::{AgentId, TokenUsage, TurnId};
+    use std::collections::HashMap;
+
+    fn session_with_turns(count: usize) -> (Session, Vec<Turn>) {
+        let agent_id = AgentId::new();
+        let mut session = Session::new(SessionConfig {
+            agent_id,
+            user_id: None,
+            metadata: HashMap::new(),
+        });
+
+        let turns: Vec<Turn> = (0..count)
+            .map(|i| Turn {
+                id: TurnId::new(),
+                session_id: session.id,
+                agent_id,
+                input: format!("question {}", i),
+                output: AgentOutput::new(agent_id, format!("answer {}", i), ReActTrace::new()),
+                timestamp: Utc::now() + chrono::Duration::milliseconds(i as i64),
+                token_usage: TokenUsage::new(10, 2),
+                trace: ReActTrace::new(),
+            })
+            .collect();
+        session.turns = turns.clone();
+        (session, turns)
+    }
+
+    async fn assert_round_trip(storage: &dyn TurnStorage) {
+        let (session, turns) = session_with_turns(3);
+        storage.store_session(&session).await.unwrap();
+        for turn in &turns {
+            storage.store_turn(turn).await.unwrap();
+        }
+
+        let loaded = storage.load_session(session.id).await.unwrap().unwrap();
+        assert_eq!(loaded.id, session.id);
+        assert_eq!(loaded.turns.len(), 3);
+
+        let inputs: Vec<String> = storage
+            .load_turns(session.id)
+            .await
+            .unwrap()
+            .into_iter()
+            .map(|turn| turn.input)
+            .collect();
+        assert_eq!(inputs, ["question 0", "question 1", "question 2"]);
+
+        assert!(storage
+            .load_session(SessionId::new())
+            .await
+            .unwrap()
+            .is_none());
+    }
+
+    #[tokio::test]
+    async fn test_sqlite_turn_storage() {
+        let storage = SqliteStorage::new("sqlite::memory:")
+            .await
+            .expect("Failed to create SQLite storage");
+        assert_round_trip(&storage).await;
+    }
+
+    /// PostgreSQL tests run only when `SPAI_TEST_POSTGRES_URL` is set
+    async fn postgres_storage() -> Option<PostgresStorage> {
+        let url = std::env::var("SPAI_TEST_POSTGRES_URL").ok()?;
+        Some(
+            PostgresStorage::new(&url)
+                .await
+                .expect("Failed to create PostgreSQL storage"),
+        )
+    }
+
+    #[tokio::test]
+    async fn test_postgres_turn_storage() {
+        let Some(storage) = postgres_storage().await else {
+            return;
+        };
+        assert_round_trip(&storage).await;
+
+        // Storing a turn again replaces it rather than duplicating it
+        let (session, mut turns) = session_with_turns(1);
+        storage.store_turn(&turns[0]).await.unwrap();
+        turns[0].input = "edited".to_string();
+        storage.store_turn(&turns[0]).await.unwrap();
+        let loaded = storage.load_turns(session.id).await.unwrap();
+        assert_eq!(loaded.len(), 1);
+        assert_eq!(loaded[0].input, "edited");
+    }
+
+    #[tokio::test]
+    async fn test_postgres_archival_matches_flat_index() {
+        use crate::archival::FlatIndex;
+        use std::collections::HashSet;
+
+        let Some(storage) = postgres_storage().await else {
+            return;
+        };
+        // The archival table is only created where pgvector is installed
+        let archival: bool =
+            sqlx::query_scalar("SELECT to_regclass('archival_passages') IS NOT NULL")
+                .fetch_one(&storage.pool)
+                .await
+                .unwrap();
+        if !archival {
+            return;
+        }
+
+        let agent_id = AgentId::new();
+        let texts = [
+            "the cat sat",
+            "the dog sat",
+            "the zebra",
+            "zebra crossing ahead",
+        ];
+        let passages: Vec<ArchivalPassage> = texts
+            .iter()
+            .enumerate()
+            .map(|(i, text)| ArchivalPassage {
+                id: uuid::Uuid::new_v4(),
+                agent_id,
+                source: PassageSource::Insert,
+                text: text.to_string(),
+                created_at: Utc::now(),
+                metadata: HashMap::new(),
+                embedding: (0..8)
+                    .map(|j| ((i * 7 + j * 3) % 11) as f32 - 5.0)
+                    .collect(),
+            })
+            .collect();
+        let flat = FlatIndex::new();
+        flat.insert(passages.clone()).await.unwrap();
+        storage.insert(passages.clone()).await.unwrap();
+
+        for query in &passages {
+            let expected = flat.nearest(agent_id, &query.embedding, 3).await.unwrap();
+            let found = storage
+                .nearest(agent_id, &query.embedding, 3)
+                .await
+                .unwrap();
+            assert_eq!(found[0].passage.id, query.id);
+            assert_eq!(found[0].passage.embedding, query.embedding);
+            assert_eq!(found.len(), expected.len());
+            for (found, expected) in found.iter().zip(&expected) {
+                assert!(
+                    (found.score - expected.score).abs() < 1e-4,
+                    "{}",
+                    query.text
+                );
+            }
+        }
+
+        // ts_rank scores differently from BM25 but matches the same passages
+        for query in ["zebra sat", "dog", "crossing zebra"] {
+            let ids = |hits: Vec<ScoredPassage>| {
+                hits.into_iter()
+                    .map(|s| s.passage.id)
+                    .collect::<HashSet<_>>()
+            };
+            let expected = flat.keyword(agent_id, query, 10).await.unwrap();
+            let found = storage.keyword(agent_id, query, 10).await.unwrap();
+            if query == "crossing zebra" {
+                assert_eq!(found[0].passage.id, expected[0].passage.id);
+            }
+            assert_eq!(ids(found), ids(expected), "{}", query);
+        }
+        assert!(storage
+            .keyword(agent_id, "unrelated", 10)
+            .await
+            .unwrap()
+            .is_empty());
+
+        let block = ArchivalPassage {
+            id: uuid::Uuid::new_v4(),
+            source: PassageSource::Block {
+                block_id: MemoryBlockId::new(),
+            },
+            text: "zebra block".to_string(),
+            ..passages[0].clone()
+        };
+        storage.insert(vec![block.clone()]).await.unwrap();
+        storage
+            .remove_source(agent_id, &block.source)
+            .await
+            .unwrap();
+        let stored = storage.passages(agent_id).await.unwrap();
+        assert_eq!(stored.len(), passages.len());
+        assert!(stored.iter().all(|p| p.id != block.id));
+
+        storage.delete_agent_data(agent_id).await.unwrap();
+        assert!(storage.passages(agent_id).await.unwrap().is_empty());
+    }
+
+    /// Copy every file sled wrote under `from` into `to`
+    fn copy_dir(from: &std::path::Path, to: &std::path::Path) {
+        std::fs::create_dir_all(to).unwrap();
+        for entry in std::fs::read_dir(from).unwrap() {
+            let entry = entry.unwrap();
+            let target = to.join(entry.file_name());
+            if entry.file_type().unwrap().is_dir() {
+                copy_dir(&entry.path(), &target);
+            } else {
+                std::fs::copy(entry.path(), target).unwrap();
+            }
+        }
+    }
+
+    #[tokio::test]
+    async fn test_sled_turn_storage_survives_reopen() {
+        let dir = tempfile::tempdir().unwrap();
+        let copy = tempfile::tempdir().unwrap();
+        let (session, turns) = session_with_turns(2);
+
+        // Writes are flushed before returning, so a copy of the files taken
+        // while the original is still open holds everything stored so far
+        let storage = SledTurnStorage::open(dir.path()).unwrap();
+        assert_round_trip(&storage).await;
+        storage.store_session(&session).await.unwrap();
+        storage.store_turn(&turns[0]).await.unwrap();
+        copy_dir(dir.path(), copy.path());
+
+        let storage = SledTurnStorage::open(copy.path()).unwrap();
+        let restored = storage.load_session(session.id).await.unwrap().unwrap();
+        assert_eq!(restored.turns[1].output.content, "answer 1");
+        assert_eq!(storage.load_turns(session.id).await.unwrap().len(), 1);
+    }
+
+    async fn assert_cache_round_trip(store: &dyn CacheStore) {
+        let entry = |key: &str, scope: &str| CacheEntry {
+            key: format!("{}/{}", scope, key),
+            scope: scope.to_string(),
+            embedding: Some(vec![1.0, 0.0]),
+            response: crate::openrouter::CompletionResponse {
+                id: key.to_string(),
+                model: "m".to_string(),
+                provider: None,
+                choices: vec![],
+                usage: Default::default(),
+            },
+            created_at: Utc::now(),
+        };
+        store.put(&entry("a", "s1")).await.unwrap();
+        store.put(&entry("b", "s1")).await.unwrap();
+        store.put(&entry("c", "s2")).await.unwrap();
+
+        let loaded = store.get("s1/a").await.unwrap().unwrap();
+        assert_eq!(loaded.response.id, "a");
+        assert_eq!(loaded.embedding, Some(vec![1.0, 0.0]));
+        assert_eq!(store.entries("s1").await.unwrap().len(), 2);
+
+        store.remove("s1/a").await.unwrap();
+        assert!(store.get("s1/a").await.unwrap().is_none());
+        assert_eq!(store.entries("s1").await.unwrap().len(), 1);
+    }
+
+    #[tokio::test]
+    async fn test_cache_stores() {
+        let sqlite = SqliteStorage::new("sqlite::memory:")
+            .await
+            .expect("Failed to create SQLite storage");
+        assert_cache_round_trip(&sqlite).await;
+
+        let dir = tempfile::tempdir().unwrap();
+        assert_cache_round_trip(&SledCacheStore::open(dir.path()).unwrap()).await;
+    }
 
     #[tokio::test]
     async fn test_sqlite_storage() {
@@ -730,4 +2802,181 @@ mod tests {
         assert_eq!(loaded.label, "test");
         assert_eq!(loaded.value, "test value");
     }
+
+    #[tokio::test]
+    async fn test_sqlite_block_versions() {
+        use crate::memory::{AgentMemory, EditAuthor, MemoryConfig};
+
+        let storage = SqliteStorage::new("sqlite::memory:")
+            .await
+            .expect("Failed to create SQLite storage");
+
+        let agent_id = AgentId::new();
+        let memory = AgentMemory::new(agent_id, MemoryConfig::default());
+        let id = memory
+            .add_block(MemoryBlock::new("persona", "v1"))
+            .await
+            .unwrap();
+        memory.update_block(id, "v2".to_string()).await.unwrap();
+        memory.persist_to_storage(&storage).await.unwrap();
+
+        // Saving again keeps the stored versions as they were
+        memory
+            .update_block_as(id, "v3".to_string(), EditAuthor::System, "cleanup")
+            .await
+            .unwrap();
+        memory.persist_to_storage(&storage).await.unwrap();
+
+        let restored = AgentMemory::new(agent_id, MemoryConfig::default());
+        restored.load_from_storage(&storage, 10).await.unwrap();
+        assert_eq!(restored.get_block(id).await.unwrap().version, 3);
+
+        let history = restored.block_history(id).await.unwrap();
+        let values: Vec<&str> = history.iter().map(|v| v.value.as_str()).collect();
+        assert_eq!(values, ["v1", "v2", "v3"]);
+        assert_eq!(history[2].author, EditAuthor::System);
+        assert_eq!(history[0].author, EditAuthor::Agent { agent_id });
+
+        restored
+            .rollback_block(id, 1, EditAuthor::System)
+            .await
+            .unwrap();
+        assert_eq!(restored.get_block(id).await.unwrap().value, "v1");
+
+        // History of a deleted block survives a reload
+        let scratch = memory
+            .add_block(MemoryBlock::new("scratch", "notes"))
+            .await
+            .unwrap();
+        memory.persist_to_storage(&storage).await.unwrap();
+        let operator = EditAuthor::Human {
+            name: "ops".to_string(),
+        };
+        memory
+            .delete_block_as(scratch, operator.clone(), "stale notes")
+            .await
+            .unwrap();
+        storage.delete_block(scratch).await.unwrap();
+        memory.persist_to_storage(&storage).await.unwrap();
+        let restored = AgentMemory::new(agent_id, MemoryConfig::default());
+        restored.load_from_storage(&storage, 10).await.unwrap();
+        assert!(restored.get_block(scratch).await.is_none());
+        let history = restored.block_history(scratch).await.unwrap();
+        assert_eq!(history[0].value, "notes");
+        // The deletion itself is the final, empty version
+        assert_eq!((history[1].version, history[1].value.as_str()), (2, ""));
+        assert_eq!(
+            (&history[1].author, history[1].reason.as_str()),
+            (&operator, "stale notes")
+        );
+    }
+
+    #[tokio::test]
+    async fn test_sqlite_shared_blocks() {
+        let storage = SqliteStorage::new("sqlite::memory:")
+            .await
+            .expect("Failed to create SQLite storage");
+        assert!(storage.load_shared_blocks().await.unwrap().is_empty());
+        assert_shared_blocks(&storage).await;
+    }
+
+    #[tokio::test]
+    async fn test_postgres_shared_blocks() {
+        let Some(storage) = postgres_storage().await else {
+            return;
+        };
+        assert_shared_blocks(&storage).await;
+    }
+
+    /// Revision checks and access lists behave the same on every backend
+    async fn assert_shared_blocks(storage: &dyn MemoryStorage) {
+        use crate::memory::{BlockAccess, SharedMemoryManager};
+
+        let agent_id = AgentId::new();
+        let manager = SharedMemoryManager::new();
+        let id = manager.create_block("plan", "Team plan", "draft").await;
+        manager
+            .grant(id, agent_id, BlockAccess::ReadOnly)
+            .await
+            .unwrap();
+        manager.persist_to_storage(storage).await.unwrap();
+        manager.update_block(id, "final".to_string()).await.unwrap();
+        manager.persist_to_storage(storage).await.unwrap();
+
+        // A writer that started from an older revision is rejected
+        let mut stale = storage
+            .load_shared_blocks()
+            .await
+            .unwrap()
+            .into_iter()
+            .find(|shared| shared.block.id == id)
+            .unwrap()
+            .block;
+        stale.update_value("overwritten".to_string()).unwrap();
+        let conflict = storage.save_shared_block(&stale, Some(1)).await;
+        assert!(matches!(
+            conflict,
+            Err(Error::MemoryConflict {
+                expected: 1,
+                actual: 2,
+                ..
+            })
+        ));
+        assert!(storage.save_shared_block(&stale, None).await.is_err());
+
+        // Access changes are saved without touching the value
+        let other = AgentId::new();
+        manager
+            .grant(id, other, BlockAccess::ReadWrite)
+            .await
+            .unwrap();
+        manager.persist_to_storage(storage).await.unwrap();
+
+        let restored = SharedMemoryManager::new();
+        restored.load_from_storage(storage).await.unwrap();
+        let (block, access) = restored.read_block(id, agent_id).await.unwrap();
+        assert_eq!((block.value.as_str(), block.version), ("final", 2));
+        assert_eq!(access, BlockAccess::ReadOnly);
+        assert_eq!(
+            restored.access(id, other).await,
+            Some(BlockAccess::ReadWrite)
+        );
+        let history = restored.block_history(id).await.unwrap();
+        let values: Vec<_> = history
+            .iter()
+            .map(|v| (v.version, v.value.as_str()))
+            .collect();
+        assert_eq!(values, [(1, "draft"), (2, "final")]);
+        assert_eq!(history[0].reason, "Block created");
+
+        // Another process saved a newer revision, so this manager's copy is stale
+        restored
+            .update_block(id, "from restored".to_string())
+            .await
+            .unwrap();
+        restored.persist_to_storage(storage).await.unwrap();
+        manager
+            .update_block(id, "from manager".to_string())
+            .await
+            .unwrap();
+        let conflict = manager.persist_to_storage(storage).await;
+        assert!(matches!(
+            conflict,
+            Err(Error::MemoryConflict {
+                expected: 2,
+                actual: 3,
+                ..
+            })
+        ));
+
+        // The rejected revision never reaches the stored history
+        let reloaded = SharedMemoryManager::new();
+        reloaded.load_from_storage(storage).await.unwrap();
+        let history = reloaded.block_history(id).await.unwrap();
+        assert_eq!(
+            history.last().map(|v| v.value.as_str()),
+            Some("from restored")
+        );
+        assert_eq!(history.len(), 3);
+    }
 }
@@ -1,6 +1,7 @@
 //! Tool trait and implementations
 
-use crate::error::Result;
+use crate::error::{Error, Result};
+use crate::openrouter::{ImageUrl, ToolDefinition};
 use crate::types::AgentId;
 use async_trait::async_trait;
 use serde::{Deserialize, Serialize};
@@ -64,6 +65,9 @@ pub struct ToolOutput {
     /// Optional error message
     #[serde(skip_serializing_if = "Option::is_none")]
     pub error: Option<String>,
+    /// Images for the model to see, sent with the next turn
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub images: Vec<ImageUrl>,
 }
 
 impl ToolOutput {
@@ -74,6 +78,7 @@ impl ToolOutput {
             content: content.into(),
             data: None,
             error: None,
+            images: Vec::new(),
         }
     }
 
@@ -84,6 +89,7 @@ impl ToolOutput {
             content: content.into(),
             data: Some(data),
             error: None,
+            images: Vec::new(),
         }
     }
 
@@ -94,6 +100,7 @@ impl ToolOutput {
             content: String::new(),
             data: None,
             error: Some(error.into()),
+            images: Vec::new(),
         }
     }
 
@@ -104,8 +111,15 @@ impl ToolOutput {
             content: content.into(),
             data: None,
             error: Some(error.into()),
+            images: Vec::new(),
         }
     }
+
+    /// Attach an image for the model to see
+    pub fn with_image(mut self, image: ImageUrl) -> Self {
+        self.images.push(image);
+        self
+    }
 }
 
 /// JSON Schema for tool parameters
@@ -151,6 +165,37 @@ impl JsonSchema {
         self.required = Some(required);
         self
     }
+
+    /// Validate a parameter payload against this schema
+    pub fn validate(&self, params: &Value) -> Result<()> {
+        let schema = serde_json::to_value(self)?;
+        let validator = jsonschema::validator_for(&schema)
+            .map_err(|e| Error::JsonSchema(format!("invalid tool schema: {}", e)))?;
+
+        let violations = schema_violations(&validator, params);
+        if !violations.is_empty() {
+            return Err(Error::JsonSchema(violations.join("; ")));
+        }
+
+        Ok(())
+    }
+}
+
+/// Every way `instance` breaks the schema, prefixed with its location
+pub(crate) fn schema_violations(validator: &jsonschema::Validator, instance: &Value) -> Vec<String> {
+    match validator.validate(instance) {
+        Ok(()) => Vec::new(),
+        Err(errors) => errors
+            .map(|e| {
+                let path = e.instance_path.as_str();
+                if path.is_empty() {
+                    e.to_string()
+                } else {
+                    format!("{}: {}", path, e)
+                }
+            })
+            .collect(),
+    }
 }
 
 /// Tool trait defining the interface for agent capabilities
@@ -171,6 +216,15 @@ pub trait Tool: Send + Sync {
     /// Execute the tool with given parameters
     async fn execute(&self, params: Value, ctx: &ToolContext) -> Result<ToolOutput>;
 
+    /// Function-calling definition advertised to the LLM, keyed by [`Tool::id`]
+    fn definition(&self) -> ToolDefinition {
+        ToolDefinition::function(
+            self.id(),
+            self.description(),
+            serde_json::to_value(self.input_schema()).unwrap_or_else(|_| serde_json::json!({ "type": "object" })),
+        )
+    }
+
     /// Optional: Validate parameters before execution
     fn validate(&self, _params: &Value) -> Result<()> {
         Ok(())
@@ -438,14 +492,15 @@ impl Tool for McpSubprocessTool {
 #[cfg(feature = "mcp-tools")]
 fn convert_mcp_result(result: CallToolResult) -> ToolOutput {
     let mut text_parts: Vec<String> = Vec::new();
+    let mut images = Vec::new();
 
     for item in &result.content {
         match &item.raw {
             RawContent::Text(text) => text_parts.push(text.text.clone()),
-            RawContent::Image(image) => text_parts.push(format!(
-                "MCP tool returned an image (mime: {})",
-                image.mime_type
-            )),
+            RawContent::Image(image) => {
+                text_parts.push(format!("MCP tool returned an image (mime: {})", image.mime_type));
+                images.push(ImageUrl::base64(&image.mime_type, &image.data));
+            }
             RawContent::Resource(resource) => {
                 text_parts.push(format!("MCP tool returned a resource: {:?}", resource))
             }
@@ -472,6 +527,7 @@ fn convert_mcp_result(result: CallToolResult) -> ToolOutput {
             content,
             data,
             error: None,
+            images,
         }
     }
 }
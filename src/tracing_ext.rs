@@ -1,10 +1,34 @@
 //! Tracing and observability infrastructure
+//!
+//! Agent runs, LLM generations, tool calls, guardrail checks, handoffs and
+//! orchestrator steps record [`Span`]s while they execute. The active span is
+//! carried in a task-local, so nested operations attach to their parent
+//! without threading a handle through every call. A [`Tracer`] opens the root
+//! span, assembles the finished tree into a [`Trace`] and hands it to its
+//! [`TraceExporter`]s.
+//!
+//! Agents built with a tracer start a trace per run. To group the steps of an
+//! orchestrator into one trace, run it inside [`Tracer::trace`]:
+//!
+//! ```rust,ignore
+//! let tracer = Tracer::new().with_exporter(Arc::new(JsonLinesExporter::new("traces.jsonl")));
+//! let result = tracer
+//!     .trace(SpanType::Custom("workflow".into()), "research", orchestrator.execute(input))
+//!     .await?;
+//! ```
 
+use crate::error::{Error, Result};
 use crate::types::{SpanId, TokenUsage, TraceId};
+use async_trait::async_trait;
 use chrono::{DateTime, Utc};
+use parking_lot::Mutex;
 use serde::{Deserialize, Serialize};
 use std::collections::HashMap;
-use std::time::Duration;
+use std::future::Future;
+use std::path::PathBuf;
+use std::sync::Arc;
+use std::time::{Duration, Instant};
+use tokio::io::AsyncWriteExt;
 
 /// Trace of agent execution
 #[derive(Debug, Clone, Serialize, Deserialize)]
@@ -23,8 +47,17 @@ pub struct Trace {
     pub total_tokens: TokenUsage,
 }
 
+impl Trace {
+    /// All spans in depth-first order, starting with the root
+    pub fn spans(&self) -> Vec<&Span> {
+        let mut spans = Vec::new();
+        self.root_span.collect(&mut spans);
+        spans
+    }
+}
+
 /// Trace metadata
-#[derive(Debug, Clone, Serialize, Deserialize)]
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
 pub struct TraceMetadata {
     /// Custom metadata
     pub custom: HashMap<String, serde_json::Value>,
@@ -51,6 +84,57 @@ pub struct Span {
     pub children: Vec<Span>,
 }
 
+impl Span {
+    /// Start a new span
+    pub fn new(span_type: SpanType, name: impl Into<String>, parent_id: Option<SpanId>) -> Self {
+        Self {
+            span_id: SpanId::new(),
+            parent_id,
+            span_type,
+            name: name.into(),
+            started_at: Utc::now(),
+            ended_at: None,
+            data: SpanData::default(),
+            children: Vec::new(),
+        }
+    }
+
+    /// Mark the span as finished
+    pub fn end(&mut self) {
+        self.ended_at = Some(Utc::now());
+    }
+
+    /// Time between start and end, if the span has ended
+    pub fn duration(&self) -> Option<Duration> {
+        self.ended_at
+            .and_then(|ended| (ended - self.started_at).to_std().ok())
+    }
+
+    /// Set a data attribute
+    pub fn set(&mut self, key: impl Into<String>, value: serde_json::Value) {
+        self.data.data.insert(key.into(), value);
+    }
+
+    /// Token usage recorded on this span
+    pub fn token_usage(&self) -> TokenUsage {
+        let get = |key: &str| {
+            self.data
+                .data
+                .get(key)
+                .and_then(|v| v.as_u64())
+                .unwrap_or(0)
+        };
+        TokenUsage::new(get("prompt_tokens"), get("completion_tokens"))
+    }
+
+    fn collect<'a>(&'a self, spans: &mut Vec<&'a Span>) {
+        spans.push(self);
+        for child in &self.children {
+            child.collect(spans);
+        }
+    }
+}
+
 /// Type of span
 #[derive(Debug, Clone, Serialize, Deserialize)]
 #[serde(rename_all = "snake_case")]
@@ -71,17 +155,385 @@ pub enum SpanType {
     ReActAction,
     /// ReAct observation
     ReActObservation,
+    /// One step of an orchestrator pattern
+    OrchestratorStep,
     /// Custom span type
     Custom(String),
 }
 
 /// Span data
-#[derive(Debug, Clone, Serialize, Deserialize)]
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
 pub struct SpanData {
     /// Custom data
     pub data: HashMap<String, serde_json::Value>,
 }
 
+tokio::task_local! {
+    static CURRENT: SpanContext;
+}
+
+/// Handle to the active span and the recorder of its trace
+#[derive(Clone)]
+struct SpanContext {
+    spans: Arc<Mutex<Vec<Span>>>,
+    span_id: SpanId,
+}
+
+impl SpanContext {
+    fn open(spans: Arc<Mutex<Vec<Span>>>, span: Span) -> Self {
+        let span_id = span.span_id;
+        spans.lock().push(span);
+        Self { spans, span_id }
+    }
+
+    fn update(&self, f: impl FnOnce(&mut Span)) {
+        let mut spans = self.spans.lock();
+        if let Some(span) = spans.iter_mut().rev().find(|s| s.span_id == self.span_id) {
+            f(span);
+        }
+    }
+
+    fn close(&self, error: Option<&Error>) {
+        self.update(|span| {
+            span.end();
+            let duration = span.duration().unwrap_or_default();
+            span.set("duration_ms", serde_json::json!(duration.as_millis() as u64));
+            if let Some(error) = error {
+                span.set("error", serde_json::json!(error.to_string()));
+            }
+        });
+    }
+
+    /// Assemble the recorded spans into a tree rooted at this span
+    fn into_trace(self, name: String, duration: Duration) -> Trace {
+        let spans = std::mem::take(&mut *self.spans.lock());
+        let total_tokens = spans
+            .iter()
+            .filter(|s| matches!(s.span_type, SpanType::LlmGeneration))
+            .fold(TokenUsage::default(), |mut total, s| {
+                total.add(s.token_usage());
+                total
+            });
+
+        let mut by_parent: HashMap<Option<SpanId>, Vec<Span>> = HashMap::new();
+        for span in spans {
+            by_parent.entry(span.parent_id).or_default().push(span);
+        }
+
+        fn attach(span: &mut Span, by_parent: &mut HashMap<Option<SpanId>, Vec<Span>>) {
+            let mut children = by_parent.remove(&Some(span.span_id)).unwrap_or_default();
+            children.sort_by_key(|c| c.started_at);
+            for child in &mut children {
+                attach(child, by_parent);
+            }
+            span.children = children;
+        }
+
+        let mut root = by_parent
+            .remove(&None)
+            .and_then(|mut roots| roots.pop())
+            .unwrap_or_else(|| Span::new(SpanType::Custom("missing".to_string()), &name, None));
+        attach(&mut root, &mut by_parent);
+
+        Trace {
+            trace_id: TraceId::new(),
+            name,
+            root_span: root,
+            metadata: TraceMetadata::default(),
+            duration,
+            total_tokens,
+        }
+    }
+}
+
+/// Run `fut` inside a child span of the active span
+///
+/// Without an active trace the future runs untraced. Errors returned by the
+/// future are recorded on the span.
+pub async fn in_span<T, F>(span_type: SpanType, name: impl Into<String>, fut: F) -> Result<T>
+where
+    F: Future<Output = Result<T>>,
+{
+    let Ok(parent) = CURRENT.try_with(|ctx| ctx.clone()) else {
+        return fut.await;
+    };
+
+    let span = Span::new(span_type, name, Some(parent.span_id));
+    let ctx = SpanContext::open(parent.spans, span);
+    let result = CURRENT.scope(ctx.clone(), fut).await;
+    ctx.close(result.as_ref().err());
+    result
+}
+
+/// Set an attribute on the active span, if any
+pub fn record(key: &str, value: serde_json::Value) {
+    let _ = CURRENT.try_with(|ctx| ctx.update(|span| span.set(key, value)));
+}
+
+/// Record token usage on the active span, if any
+pub fn record_tokens(usage: TokenUsage) {
+    let _ = CURRENT.try_with(|ctx| {
+        ctx.update(|span| {
+            span.set("prompt_tokens", serde_json::json!(usage.prompt_tokens));
+            span.set("completion_tokens", serde_json::json!(usage.completion_tokens));
+            span.set("total_tokens", serde_json::json!(usage.total_tokens));
+        })
+    });
+}
+
+/// ID of the active span, if a trace is being recorded
+pub fn current_span_id() -> Option<SpanId> {
+    CURRENT.try_with(|ctx| ctx.span_id).ok()
+}
+
+/// Destination for finished traces
+#[async_trait]
+pub trait TraceExporter: Send + Sync {
+    /// Export one finished trace
+    async fn export(&self, trace: &Trace) -> Result<()>;
+}
+
+/// Records traces and sends them to exporters
+#[derive(Clone, Default)]
+pub struct Tracer {
+    exporters: Vec<Arc<dyn TraceExporter>>,
+}
+
+impl Tracer {
+    /// Create a tracer with no exporters
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Add an exporter
+    pub fn with_exporter(mut self, exporter: Arc<dyn TraceExporter>) -> Self {
+        self.exporters.push(exporter);
+        self
+    }
+
+    /// Run `fut` as the root span of a new trace, then export the trace
+    ///
+    /// Export failures are logged rather than returned, so telemetry never
+    /// fails the operation it observes.
+    pub async fn trace<T, F>(&self, span_type: SpanType, name: impl Into<String>, fut: F) -> Result<T>
+    where
+        F: Future<Output = Result<T>>,
+    {
+        let name = name.into();
+        let started = Instant::now();
+        let ctx = SpanContext::open(Arc::default(), Span::new(span_type, &name, None));
+        let result = CURRENT.scope(ctx.clone(), fut).await;
+        ctx.close(result.as_ref().err());
+
+        let trace = ctx.into_trace(name, started.elapsed());
+        for exporter in &self.exporters {
+            if let Err(e) = exporter.export(&trace).await {
+                tracing::warn!(trace_id = %trace.trace_id, "failed to export trace: {}", e);
+            }
+        }
+
+        result
+    }
+}
+
+impl std::fmt::Debug for Tracer {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.debug_struct("Tracer")
+            .field("exporters", &self.exporters.len())
+            .finish()
+    }
+}
+
+/// Appends each trace as one JSON line to a file
+pub struct JsonLinesExporter {
+    path: PathBuf,
+    lock: tokio::sync::Mutex<()>,
+}
+
+impl JsonLinesExporter {
+    /// Create an exporter writing to `path`
+    pub fn new(path: impl Into<PathBuf>) -> Self {
+        Self {
+            path: path.into(),
+            lock: tokio::sync::Mutex::new(()),
+        }
+    }
+}
+
+#[async_trait]
+impl TraceExporter for JsonLinesExporter {
+    async fn export(&self, trace: &Trace) -> Result<()> {
+        let mut line = serde_json::to_string(trace)
+            .map_err(|e| Error::Tracing(format!("Failed to serialize trace: {}", e)))?;
+        line.push('\n');
+
+        let _guard = self.lock.lock().await;
+        let mut file = tokio::fs::OpenOptions::new()
+            .create(true)
+            .append(true)
+            .open(&self.path)
+            .await?;
+        file.write_all(line.as_bytes()).await?;
+        file.flush().await?;
+
+        Ok(())
+    }
+}
+
+/// Exports traces to an OpenTelemetry collector over OTLP/HTTP
+///
+/// Spans are converted to OpenTelemetry span data and sent by the
+/// `opentelemetry-otlp` HTTP exporter, JSON encoded, to
+/// `{endpoint}/v1/traces`. Span data becomes span attributes; an `error`
+/// attribute sets an error status. The standard `OTEL_EXPORTER_OTLP_*`
+/// environment variables override the endpoint and add headers.
+#[cfg(feature = "telemetry")]
+pub struct OtlpExporter {
+    endpoint: String,
+    service_name: String,
+    headers: HashMap<String, String>,
+    exporter: tokio::sync::OnceCell<tokio::sync::Mutex<opentelemetry_otlp::SpanExporter>>,
+}
+
+#[cfg(feature = "telemetry")]
+impl OtlpExporter {
+    /// Create an exporter for a collector base URL, e.g. `http://localhost:4318`
+    pub fn new(endpoint: impl Into<String>) -> Self {
+        Self {
+            endpoint: endpoint.into().trim_end_matches('/').to_string(),
+            service_name: "spai".to_string(),
+            headers: HashMap::new(),
+            exporter: tokio::sync::OnceCell::new(),
+        }
+    }
+
+    /// Set the `service.name` resource attribute
+    pub fn with_service_name(mut self, name: impl Into<String>) -> Self {
+        self.service_name = name.into();
+        self
+    }
+
+    /// Add a header sent with every export, e.g. for collector auth
+    pub fn with_header(mut self, name: impl Into<String>, value: impl Into<String>) -> Self {
+        self.headers.insert(name.into(), value.into());
+        self
+    }
+
+    /// Build the underlying OTLP exporter on first use
+    async fn exporter(&self) -> Result<&tokio::sync::Mutex<opentelemetry_otlp::SpanExporter>> {
+        use opentelemetry_otlp::{Protocol, WithExportConfig, WithHttpConfig};
+        use opentelemetry_sdk::export::trace::SpanExporter as _;
+
+        self.exporter
+            .get_or_try_init(|| async {
+                let mut exporter = opentelemetry_otlp::SpanExporter::builder()
+                    .with_http()
+                    .with_protocol(Protocol::HttpJson)
+                    .with_endpoint(format!("{}/v1/traces", self.endpoint))
+                    .with_headers(self.headers.clone())
+                    .build()
+                    .map_err(|e| Error::Tracing(format!("Failed to build OTLP exporter: {}", e)))?;
+                exporter.set_resource(&opentelemetry_sdk::Resource::new([
+                    opentelemetry::KeyValue::new("service.name", self.service_name.clone()),
+                ]));
+                Ok(tokio::sync::Mutex::new(exporter))
+            })
+            .await
+    }
+}
+
+#[cfg(feature = "telemetry")]
+#[async_trait]
+impl TraceExporter for OtlpExporter {
+    async fn export(&self, trace: &Trace) -> Result<()> {
+        use opentelemetry_sdk::export::trace::SpanExporter as _;
+
+        let batch = otel_spans(trace);
+        let export = self.exporter().await?.lock().await.export(batch);
+        export
+            .await
+            .map_err(|e| Error::Tracing(format!("OTLP export failed: {}", e)))
+    }
+}
+
+/// Convert a finished trace into OpenTelemetry span data
+///
+/// Trace IDs keep all 16 bytes of the UUID; span IDs use the leading 8.
+#[cfg(feature = "telemetry")]
+fn otel_spans(trace: &Trace) -> Vec<opentelemetry_sdk::export::trace::SpanData> {
+    use opentelemetry::trace::{
+        SpanContext, SpanId as OtelSpanId, SpanKind, Status, TraceFlags, TraceId as OtelTraceId,
+        TraceState,
+    };
+    use opentelemetry::{InstrumentationScope, KeyValue};
+
+    let span_id = |id: SpanId| {
+        let bytes = id.as_uuid().into_bytes();
+        OtelSpanId::from_bytes(bytes[..8].try_into().expect("UUIDs have 16 bytes"))
+    };
+    let trace_id = OtelTraceId::from_bytes(trace.trace_id.as_uuid().into_bytes());
+    let scope = InstrumentationScope::builder(env!("CARGO_PKG_NAME"))
+        .with_version(env!("CARGO_PKG_VERSION"))
+        .build();
+
+    trace
+        .spans()
+        .into_iter()
+        .map(|span| {
+            let mut attributes = vec![KeyValue::new(
+                "span.type",
+                otel_value(&serde_json::to_value(&span.span_type).unwrap_or_default()),
+            )];
+            let mut keys: Vec<&String> = span.data.data.keys().collect();
+            keys.sort();
+            attributes.extend(
+                keys.into_iter()
+                    .map(|k| KeyValue::new(k.clone(), otel_value(&span.data.data[k]))),
+            );
+
+            let status = match span.data.data.get("error") {
+                Some(serde_json::Value::String(message)) => Status::error(message.clone()),
+                Some(other) => Status::error(other.to_string()),
+                None => Status::Ok,
+            };
+
+            opentelemetry_sdk::export::trace::SpanData {
+                span_context: SpanContext::new(
+                    trace_id,
+                    span_id(span.span_id),
+                    TraceFlags::SAMPLED,
+                    false,
+                    TraceState::default(),
+                ),
+                parent_span_id: span.parent_id.map(span_id).unwrap_or(OtelSpanId::INVALID),
+                span_kind: SpanKind::Internal,
+                name: span.name.clone().into(),
+                start_time: span.started_at.into(),
+                end_time: span.ended_at.unwrap_or(span.started_at).into(),
+                attributes,
+                dropped_attributes_count: 0,
+                events: Default::default(),
+                links: Default::default(),
+                status,
+                instrumentation_scope: scope.clone(),
+            }
+        })
+        .collect()
+}
+
+#[cfg(feature = "telemetry")]
+fn otel_value(value: &serde_json::Value) -> opentelemetry::Value {
+    match value {
+        serde_json::Value::String(s) => s.clone().into(),
+        serde_json::Value::Bool(b) => (*b).into(),
+        serde_json::Value::Number(n) => match n.as_i64() {
+            Some(i) => i.into(),
+            None => n.as_f64().unwrap_or_default().into(),
+        },
+        other => other.to_string().into(),
+    }
+}
+
 /// Patterns workflow configuration
 #[derive(Debug, Clone)]
 pub struct PatternConfig {
@@ -95,19 +547,212 @@ pub struct PatternConfig {
     pub params: HashMap<String, serde_json::Value>,
 }
 
+impl Default for PatternConfig {
+    fn default() -> Self {
+        Self {
+            timeout: Duration::from_secs(30 * 60),
+            failure_mode: FailureMode::default(),
+            token_budget: None,
+            params: HashMap::new(),
+        }
+    }
+}
+
+impl PatternConfig {
+    /// Create a config with a 30 minute timeout that skips failed agents
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Set the maximum total execution time
+    pub fn with_timeout(mut self, timeout: Duration) -> Self {
+        self.timeout = timeout;
+        self
+    }
+
+    /// Set how agent failures are handled
+    pub fn with_failure_mode(mut self, failure_mode: FailureMode) -> Self {
+        self.failure_mode = failure_mode;
+        self
+    }
+
+    /// Limit the tokens used across all agents in one execution
+    pub fn with_token_budget(mut self, tokens: u64) -> Self {
+        self.token_budget = Some(tokens);
+        self
+    }
+
+    /// Set a pattern-specific parameter
+    pub fn with_param(mut self, key: impl Into<String>, value: serde_json::Value) -> Self {
+        self.params.insert(key.into(), value);
+        self
+    }
+
+    /// Ledger enforcing `token_budget` as a hard limit, if one is set
+    pub fn budget_ledger(&self) -> Option<crate::budget::BudgetLedger> {
+        self.token_budget
+            .map(|limit| crate::budget::BudgetLedger::new().with_hard_limit(limit))
+    }
+}
+
 /// Failure handling mode
-#[derive(Debug, Clone, Serialize, Deserialize)]
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
 #[serde(rename_all = "snake_case")]
 pub enum FailureMode {
     /// Fail entire pattern on any agent failure
     FailFast,
     /// Continue with remaining agents
+    #[default]
     FailSafe,
-    /// Retry failed agents with backoff
+    /// Retry failed agents with backoff, then fail the pattern
     Retry {
-        /// Maximum retry attempts
+        /// Maximum attempts per agent step, including the first
         max_attempts: u32,
-        /// Backoff duration
+        /// Delay before the first retry, doubled for each further retry
         backoff: Duration,
     },
 }
+
+/// Trace exporter shared by the crate's tests
+#[cfg(test)]
+pub(crate) mod testing {
+    use super::{Trace, TraceExporter};
+    use crate::error::Result;
+    use async_trait::async_trait;
+    use parking_lot::Mutex;
+
+    /// Keeps every exported trace in memory
+    #[derive(Default)]
+    pub(crate) struct CollectingExporter {
+        pub(crate) traces: Mutex<Vec<Trace>>,
+    }
+
+    #[async_trait]
+    impl TraceExporter for CollectingExporter {
+        async fn export(&self, trace: &Trace) -> Result<()> {
+            self.traces.lock().push(trace.clone());
+            Ok(())
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    async fn sample_trace(tracer: &Tracer) -> Result<()> {
+        tracer
+            .trace(SpanType::AgentRun, "agent", async {
+                in_span(SpanType::LlmGeneration, "model", async {
+                    record_tokens(TokenUsage::new(12, 3));
+                    Ok(())
+                })
+                .await?;
+                let failed: Result<()> = in_span(SpanType::ToolCall, "search", async {
+                    Err(Error::tool_execution("search", "offline"))
+                })
+                .await;
+                assert!(failed.is_err());
+                Ok(())
+            })
+            .await
+    }
+
+    #[tokio::test]
+    async fn test_spans_nest_and_export_as_json_lines() {
+        let dir = tempfile::tempdir().unwrap();
+        let path = dir.path().join("traces.jsonl");
+        let tracer = Tracer::new().with_exporter(Arc::new(JsonLinesExporter::new(&path)));
+
+        sample_trace(&tracer).await.unwrap();
+        sample_trace(&tracer).await.unwrap();
+
+        let contents = tokio::fs::read_to_string(&path).await.unwrap();
+        let lines: Vec<&str> = contents.lines().collect();
+        assert_eq!(lines.len(), 2);
+
+        let trace: Trace = serde_json::from_str(lines[0]).unwrap();
+        assert_eq!(trace.total_tokens.total_tokens, 15);
+        assert!(matches!(trace.root_span.span_type, SpanType::AgentRun));
+        assert_eq!(trace.root_span.children.len(), 2);
+
+        let tool = &trace.root_span.children[1];
+        assert_eq!(tool.parent_id, Some(trace.root_span.span_id));
+        assert!(tool.data.data["error"].as_str().unwrap().contains("offline"));
+        assert!(tool.data.data.contains_key("duration_ms"));
+    }
+
+    #[tokio::test]
+    async fn test_in_span_without_trace_is_passthrough() {
+        let value = in_span(SpanType::ToolCall, "noop", async {
+            record("ignored", serde_json::json!(true));
+            assert!(current_span_id().is_none());
+            Ok(7)
+        })
+        .await
+        .unwrap();
+        assert_eq!(value, 7);
+    }
+
+    #[cfg(feature = "telemetry")]
+    #[tokio::test]
+    async fn test_otlp_export_to_collector() {
+        let mut server = mockito::Server::new_async().await;
+        let collector = server
+            .mock("POST", "/v1/traces")
+            .match_header("authorization", "Bearer token")
+            .match_body(mockito::Matcher::PartialJson(serde_json::json!({
+                "resourceSpans": [{
+                    "resource": {
+                        "attributes": [{ "key": "service.name", "value": { "stringValue": "chat" } }]
+                    }
+                }]
+            })))
+            .with_status(200)
+            .expect(1)
+            .create_async()
+            .await;
+
+        let exporter = OtlpExporter::new(server.url())
+            .with_service_name("chat")
+            .with_header("authorization", "Bearer token");
+        let tracer = Tracer::new().with_exporter(Arc::new(exporter));
+        sample_trace(&tracer).await.unwrap();
+
+        collector.assert_async().await;
+    }
+
+    #[cfg(feature = "telemetry")]
+    #[test]
+    fn test_otel_span_conversion() {
+        let mut root = Span::new(SpanType::AgentRun, "agent", None);
+        let mut child = Span::new(SpanType::ToolCall, "search", Some(root.span_id));
+        child.set("error", serde_json::json!("offline"));
+        child.set("results", serde_json::json!(3));
+        child.end();
+        root.children.push(child);
+        root.end();
+
+        let trace = Trace {
+            trace_id: TraceId::new(),
+            name: "agent".to_string(),
+            root_span: root,
+            metadata: TraceMetadata::default(),
+            duration: Duration::from_millis(5),
+            total_tokens: TokenUsage::default(),
+        };
+
+        let spans = otel_spans(&trace);
+        assert_eq!(spans.len(), 2);
+        assert_eq!(
+            spans[0].span_context.trace_id().to_bytes(),
+            trace.trace_id.as_uuid().into_bytes()
+        );
+        assert_eq!(spans[0].parent_span_id, opentelemetry::trace::SpanId::INVALID);
+        assert_eq!(spans[1].parent_span_id, spans[0].span_context.span_id());
+        assert_eq!(spans[1].status, opentelemetry::trace::Status::error("offline"));
+        assert!(spans[1]
+            .attributes
+            .contains(&opentelemetry::KeyValue::new("results", 3i64)));
+    }
+}
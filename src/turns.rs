@@ -1,14 +1,24 @@
 //! Turn and session management
 
-use crate::agent::AgentOutput;
-use crate::error::Result;
+use crate::agent::{Agent, AgentOutput};
+use crate::budget::{self, BudgetLedger};
+use crate::error::{Error, Result};
+use crate::handoffs::AgentRegistry;
+use crate::llm_client::LlmClient;
+use crate::openrouter::{CompletionRequest, Message};
 use crate::react::ReActTrace;
 use crate::types::{AgentId, SessionId, TokenUsage, TurnId, UserId};
+use async_trait::async_trait;
 use chrono::{DateTime, Utc};
+use parking_lot::RwLock;
 use serde::{Deserialize, Serialize};
 use std::collections::HashMap;
 use std::sync::Arc;
 
+const SUMMARY_PROMPT: &str = "You condense conversations. Summarize the conversation below in a \
+few short paragraphs, keeping facts, decisions, open questions and user preferences that later \
+turns may rely on. Reply with the summary only.";
+
 /// Turn in a conversation
 #[derive(Debug, Clone, Serialize, Deserialize)]
 pub struct Turn {
@@ -30,6 +40,13 @@ pub struct Turn {
     pub trace: ReActTrace,
 }
 
+impl Turn {
+    /// Estimated tokens this turn adds to the history
+    pub fn context_tokens(&self) -> u64 {
+        estimate_tokens(&self.input) + estimate_tokens(&self.output.content)
+    }
+}
+
 /// Session grouping related turns
 #[derive(Debug, Clone, Serialize, Deserialize)]
 pub struct Session {
@@ -45,6 +62,63 @@ pub struct Session {
     pub metadata: SessionMetadata,
     /// Session state
     pub state: SessionState,
+    /// Summary of turns removed by compaction
+    #[serde(default)]
+    pub summary: Option<String>,
+}
+
+impl Session {
+    /// Create a new active session
+    pub fn new(config: SessionConfig) -> Self {
+        let now = Utc::now();
+        Self {
+            id: SessionId::new(),
+            user_id: config.user_id,
+            current_agent: config.agent_id,
+            turns: Vec::new(),
+            metadata: SessionMetadata {
+                created_at: now,
+                updated_at: now,
+                custom: config.metadata,
+            },
+            state: SessionState::Active,
+            summary: None,
+        }
+    }
+
+    /// Conversation so far as chat messages
+    ///
+    /// The compaction summary, if any, comes first as a system message,
+    /// followed by one user/assistant pair per retained turn.
+    pub fn history(&self) -> Vec<Message> {
+        let mut messages = Vec::with_capacity(self.turns.len() * 2 + 1);
+        if let Some(summary) = &self.summary {
+            messages.push(Message::system(format!(
+                "Summary of the earlier conversation:\n{}",
+                summary
+            )));
+        }
+        for turn in &self.turns {
+            messages.push(Message::user(&turn.input));
+            messages.push(Message::assistant(&turn.output.content));
+        }
+        messages
+    }
+
+    /// Estimated tokens the history adds to a prompt
+    pub fn context_tokens(&self) -> u64 {
+        let summary = self.summary.as_deref().map(estimate_tokens).unwrap_or(0);
+        summary + self.turns.iter().map(Turn::context_tokens).sum::<u64>()
+    }
+
+    /// Total token usage across retained turns
+    pub fn token_usage(&self) -> TokenUsage {
+        let mut usage = TokenUsage::default();
+        for turn in &self.turns {
+            usage.add(turn.token_usage);
+        }
+        usage
+    }
 }
 
 /// Session metadata
@@ -80,6 +154,12 @@ pub struct TurnManager {
     compaction_strategy: CompactionStrategy,
     /// Persistent storage backend
     storage: Option<Arc<dyn TurnStorage>>,
+    /// Agents that process turns, looked up by `Session::current_agent`
+    registry: Option<Arc<AgentRegistry>>,
+    /// Client and model used for summarization, defaulting to the session's agent
+    summarizer: Option<(Arc<dyn LlmClient>, String)>,
+    /// Budget charged for turns and summaries
+    budget: Option<Arc<BudgetLedger>>,
 }
 
 impl TurnManager {
@@ -89,6 +169,9 @@ impl TurnManager {
             max_context_tokens,
             compaction_strategy: CompactionStrategy::SlidingWindow { keep_recent: 10 },
             storage: None,
+            registry: None,
+            summarizer: None,
+            budget: None,
         }
     }
 
@@ -104,24 +187,239 @@ impl TurnManager {
         self
     }
 
+    /// Set the registry used to resolve session agents
+    pub fn with_registry(mut self, registry: Arc<AgentRegistry>) -> Self {
+        self.registry = Some(registry);
+        self
+    }
+
+    /// Set the client and model used to summarize compacted turns
+    pub fn with_summarizer(mut self, client: Arc<dyn LlmClient>, model: impl Into<String>) -> Self {
+        self.summarizer = Some((client, model.into()));
+        self
+    }
+
+    /// Charge every turn and summary to a shared budget
+    pub fn with_budget(mut self, budget: Arc<BudgetLedger>) -> Self {
+        self.budget = Some(budget);
+        self
+    }
+
     /// Create a new session
-    pub async fn create_session(&self, _config: SessionConfig) -> Result<Session> {
-        todo!("Implement session creation")
+    pub async fn create_session(&self, config: SessionConfig) -> Result<Session> {
+        let session = Session::new(config);
+        if let Some(storage) = &self.storage {
+            storage.store_session(&session).await?;
+        }
+        Ok(session)
     }
 
     /// Process a new turn within a session
-    pub async fn process_turn(&self, _session: &mut Session, _input: &str) -> Result<Turn> {
-        todo!("Implement turn processing")
+    ///
+    /// Prior turns are passed to the agent as history. The session is
+    /// compacted first if the history and input would exceed
+    /// `max_context_tokens`.
+    pub async fn process_turn(&self, session: &mut Session, input: &str) -> Result<Turn> {
+        self.scoped(self.run_turn(session, input)).await
+    }
+
+    async fn run_turn(&self, session: &mut Session, input: &str) -> Result<Turn> {
+        if !matches!(session.state, SessionState::Active) {
+            return Err(Error::InvalidInput(format!(
+                "Session {} is not active",
+                session.id
+            )));
+        }
+
+        let needed = session.context_tokens() + estimate_tokens(input);
+        if needed > self.max_context_tokens && !session.turns.is_empty() {
+            self.compact(session).await?;
+        }
+
+        let needed = session.context_tokens() + estimate_tokens(input);
+        if needed > self.max_context_tokens {
+            return Err(Error::ContextWindowExceeded {
+                current: needed,
+                max: self.max_context_tokens,
+            });
+        }
+
+        let agent = self.agent(session.current_agent)?;
+        let output = agent.react_with_history(&session.history(), input).await?;
+
+        let turn = Turn {
+            id: TurnId::new(),
+            session_id: session.id,
+            agent_id: output.agent_id,
+            input: input.to_string(),
+            timestamp: Utc::now(),
+            token_usage: output.trace.total_tokens,
+            trace: output.trace.clone(),
+            output,
+        };
+
+        // A direct handoff leaves the target in charge of the conversation
+        session.current_agent = turn.agent_id;
+        session.turns.push(turn.clone());
+        session.metadata.updated_at = turn.timestamp;
+
+        if let Some(storage) = &self.storage {
+            storage.store_turn(&turn).await?;
+            storage.store_session(session).await?;
+        }
+
+        Ok(turn)
     }
 
     /// Compact session history
-    pub async fn compact(&self, _session: &mut Session) -> Result<()> {
-        todo!("Implement compaction")
+    ///
+    /// Turns removed from the session stay in the turn log of the storage
+    /// backend; only the live history shrinks.
+    pub async fn compact(&self, session: &mut Session) -> Result<()> {
+        self.scoped(self.compact_session(session)).await
+    }
+
+    async fn compact_session(&self, session: &mut Session) -> Result<()> {
+        let (summarize, keep) = match self.compaction_strategy {
+            CompactionStrategy::SlidingWindow { keep_recent } => (0, keep_recent),
+            CompactionStrategy::Summarization { summarize_after } => (usize::MAX, summarize_after),
+            CompactionStrategy::Hybrid {
+                keep_recent,
+                summarize_middle,
+            } => (summarize_middle, keep_recent),
+        };
+
+        let split = session.turns.len().saturating_sub(keep);
+        if split == 0 {
+            return Ok(());
+        }
+
+        let removed: Vec<Turn> = session.turns.drain(..split).collect();
+        let to_summarize = &removed[removed.len().saturating_sub(summarize)..];
+        if !to_summarize.is_empty() {
+            let summary = self
+                .summarize(
+                    session.current_agent,
+                    session.summary.as_deref(),
+                    to_summarize,
+                )
+                .await?;
+            session.summary = Some(summary);
+        }
+
+        let compacted = session
+            .metadata
+            .custom
+            .get("compacted_turns")
+            .and_then(|v| v.as_u64())
+            .unwrap_or(0);
+        session.metadata.custom.insert(
+            "compacted_turns".to_string(),
+            serde_json::json!(compacted + removed.len() as u64),
+        );
+        session.metadata.updated_at = Utc::now();
+
+        tracing::debug!(
+            session = %session.id,
+            removed = removed.len(),
+            summarized = to_summarize.len(),
+            "compacted session"
+        );
+
+        if let Some(storage) = &self.storage {
+            storage.store_session(session).await?;
+        }
+
+        Ok(())
     }
 
     /// Restore session from storage
-    pub async fn restore_session(&self, _id: SessionId) -> Result<Session> {
-        todo!("Implement session restoration")
+    pub async fn restore_session(&self, id: SessionId) -> Result<Session> {
+        let storage = self
+            .storage
+            .as_ref()
+            .ok_or_else(|| Error::storage("No turn storage configured"))?;
+
+        storage
+            .load_session(id)
+            .await?
+            .ok_or_else(|| Error::SessionNotFound(id.to_string()))
+    }
+
+    /// Run `fut` with this manager's budget, if any, charged for its LLM calls
+    async fn scoped<T>(&self, fut: impl std::future::Future<Output = Result<T>>) -> Result<T> {
+        match &self.budget {
+            Some(ledger) => budget::scope(ledger.clone(), fut).await,
+            None => fut.await,
+        }
+    }
+
+    fn agent(&self, id: AgentId) -> Result<Arc<Agent>> {
+        self.registry
+            .as_ref()
+            .ok_or_else(|| Error::config("TurnManager has no agent registry"))?
+            .get(id)
+            .ok_or_else(|| Error::agent(format!("Agent {} is not registered", id)))
+    }
+
+    /// Fold `turns` into the running summary with an LLM call
+    async fn summarize(
+        &self,
+        agent_id: AgentId,
+        previous: Option<&str>,
+        turns: &[Turn],
+    ) -> Result<String> {
+        // Summaries are charged to the session's agent and its own budget
+        let (client, model, ledgers, agent_name) = match &self.summarizer {
+            Some((client, model)) => {
+                let agent = self
+                    .registry
+                    .as_ref()
+                    .and_then(|registry| registry.get(agent_id));
+                let (ledgers, agent_name) = match agent {
+                    Some(agent) => (agent.ledgers(), agent.name.clone()),
+                    None => (budget::active(), "summarizer".to_string()),
+                };
+                (client.clone(), model.clone(), ledgers, agent_name)
+            }
+            None => {
+                let agent = self.agent(agent_id)?;
+                (
+                    agent.client(),
+                    agent.model.model.clone(),
+                    agent.ledgers(),
+                    agent.name.clone(),
+                )
+            }
+        };
+
+        let mut transcript = String::new();
+        if let Some(previous) = previous {
+            transcript.push_str(&format!("Earlier summary:\n{}\n\n", previous));
+        }
+        for turn in turns {
+            transcript.push_str(&format!(
+                "User: {}\nAssistant: {}\n\n",
+                turn.input, turn.output.content
+            ));
+        }
+
+        let request = CompletionRequest::new(
+            model,
+            vec![Message::system(SUMMARY_PROMPT), Message::user(transcript)],
+        )
+        .with_temperature(0.0);
+        let response =
+            budget::complete_charged(client.as_ref(), &ledgers, agent_id, &agent_name, request)
+                .await?;
+
+        response
+            .choices
+            .into_iter()
+            .next()
+            .map(|choice| choice.message.content.text().trim().to_string())
+            .filter(|summary| !summary.is_empty())
+            .ok_or_else(|| Error::agent("Summarization returned no content"))
     }
 }
 
@@ -159,17 +457,210 @@ pub enum CompactionStrategy {
     },
 }
 
+/// Rough token count for budgeting, at about four characters per token
+pub(crate) fn estimate_tokens(text: &str) -> u64 {
+    (text.chars().count() as u64).div_ceil(4)
+}
+
 /// Trait for persistent turn storage
+///
+/// Sessions are stored whole, including the turns retained after compaction.
+/// Turns are additionally appended to a per-session log that compaction never
+/// shrinks.
+#[async_trait]
 pub trait TurnStorage: Send + Sync {
-    /// Store a session
-    fn store_session(&self, session: &Session) -> Result<()>;
+    /// Store or replace a session
+    async fn store_session(&self, session: &Session) -> Result<()>;
 
     /// Load a session
-    fn load_session(&self, id: SessionId) -> Result<Option<Session>>;
+    async fn load_session(&self, id: SessionId) -> Result<Option<Session>>;
+
+    /// Append a turn to its session's log
+    async fn store_turn(&self, turn: &Turn) -> Result<()>;
+
+    /// Load all logged turns for a session, oldest first
+    async fn load_turns(&self, session_id: SessionId) -> Result<Vec<Turn>>;
+}
+
+/// In-process [`TurnStorage`], useful for tests and short-lived sessions
+#[derive(Debug, Default)]
+pub struct InMemoryTurnStorage {
+    sessions: RwLock<HashMap<SessionId, Session>>,
+    turns: RwLock<HashMap<SessionId, Vec<Turn>>>,
+}
+
+impl InMemoryTurnStorage {
+    /// Create an empty store
+    pub fn new() -> Self {
+        Self::default()
+    }
+}
+
+#[async_trait]
+impl TurnStorage for InMemoryTurnStorage {
+    async fn store_session(&self, session: &Session) -> Result<()> {
+        self.sessions.write().insert(session.id, session.clone());
+        Ok(())
+    }
+
+    async fn load_session(&self, id: SessionId) -> Result<Option<Session>> {
+        Ok(self.sessions.read().get(&id).cloned())
+    }
 
-    /// Store a turn
-    fn store_turn(&self, turn: &Turn) -> Result<()>;
+    async fn store_turn(&self, turn: &Turn) -> Result<()> {
+        self.turns
+            .write()
+            .entry(turn.session_id)
+            .or_default()
+            .push(turn.clone());
+        Ok(())
+    }
+
+    async fn load_turns(&self, session_id: SessionId) -> Result<Vec<Turn>> {
+        Ok(self
+            .turns
+            .read()
+            .get(&session_id)
+            .cloned()
+            .unwrap_or_default())
+    }
+}
 
-    /// Load turns for a session
-    fn load_turns(&self, session_id: SessionId) -> Result<Vec<Turn>>;
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::llm_client::testing::ScriptedClient;
+
+    fn manager(max_context_tokens: u64, client: Arc<ScriptedClient>) -> (TurnManager, AgentId) {
+        let registry = Arc::new(AgentRegistry::new());
+        let agent = Agent::builder()
+            .name("Assistant")
+            .system_prompt("You are helpful.")
+            .model("test")
+            .client(client)
+            .build()
+            .unwrap();
+        let agent_id = registry.register(Arc::new(agent));
+        let manager = TurnManager::new(max_context_tokens).with_registry(registry);
+        (manager, agent_id)
+    }
+
+    fn config(agent_id: AgentId) -> SessionConfig {
+        SessionConfig {
+            agent_id,
+            user_id: None,
+            metadata: HashMap::new(),
+        }
+    }
+
+    #[tokio::test]
+    async fn test_prior_turns_reach_prompt() {
+        let client = Arc::new(ScriptedClient::repeating("Noted."));
+        let (manager, agent_id) = manager(10_000, client.clone());
+        let mut session = manager.create_session(config(agent_id)).await.unwrap();
+
+        manager
+            .process_turn(&mut session, "My name is Ada.")
+            .await
+            .unwrap();
+        let turn = manager
+            .process_turn(&mut session, "What is my name?")
+            .await
+            .unwrap();
+
+        assert_eq!(session.turns.len(), 2);
+        assert_eq!(turn.token_usage.total_tokens, 15);
+        assert_eq!(session.token_usage().total_tokens, 30);
+
+        let requests = client.requests();
+        let messages = &requests[1].messages;
+        assert_eq!(messages.len(), 4);
+        assert_eq!(messages[1].content, "My name is Ada.");
+        assert_eq!(messages[2].content, "Noted.");
+        assert_eq!(messages[3].content, "What is my name?");
+    }
+
+    #[tokio::test]
+    async fn test_sliding_window_compaction() {
+        let client = Arc::new(ScriptedClient::repeating("ok"));
+        let (manager, agent_id) = manager(15, client);
+        let manager =
+            manager.with_compaction_strategy(CompactionStrategy::SlidingWindow { keep_recent: 1 });
+        let mut session = manager.create_session(config(agent_id)).await.unwrap();
+
+        for input in [
+            "first message here",
+            "second message here",
+            "third message here",
+        ] {
+            manager.process_turn(&mut session, input).await.unwrap();
+        }
+
+        assert_eq!(session.turns.len(), 2);
+        assert_eq!(session.turns.last().unwrap().input, "third message here");
+        assert!(session.summary.is_none());
+        assert_eq!(session.metadata.custom["compacted_turns"], 1);
+    }
+
+    #[tokio::test]
+    async fn test_summarization_compaction() {
+        let client = Arc::new(ScriptedClient::repeating("ok"));
+        let summarizer = Arc::new(ScriptedClient::repeating(
+            "The user introduced themselves as Ada.",
+        ));
+        let ledger = Arc::new(BudgetLedger::new());
+        let (manager, agent_id) = manager(10_000, client);
+        let manager = manager
+            .with_compaction_strategy(CompactionStrategy::Hybrid {
+                keep_recent: 1,
+                summarize_middle: 1,
+            })
+            .with_summarizer(summarizer.clone(), "summary-model")
+            .with_budget(ledger.clone());
+        let mut session = manager.create_session(config(agent_id)).await.unwrap();
+
+        for input in ["one", "two", "three"] {
+            manager.process_turn(&mut session, input).await.unwrap();
+        }
+        manager.compact(&mut session).await.unwrap();
+
+        // Three turns and one summary, all charged to the session's agent
+        let usage = &ledger.report().by_agent[&agent_id];
+        assert_eq!((usage.agent_name.as_str(), usage.calls), ("Assistant", 4));
+
+        // "one" is dropped, "two" is summarized, "three" is kept
+        assert_eq!(session.turns.len(), 1);
+        assert_eq!(
+            session.summary.as_deref(),
+            Some("The user introduced themselves as Ada.")
+        );
+
+        let requests = summarizer.requests();
+        assert_eq!(requests[0].model, "summary-model");
+        assert!(requests[0].messages[1].content.text().contains("User: two"));
+        assert!(!requests[0].messages[1].content.text().contains("User: one"));
+
+        let history = session.history();
+        assert!(history[0]
+            .content
+            .text()
+            .starts_with("Summary of the earlier conversation"));
+    }
+
+    #[tokio::test]
+    async fn test_session_round_trips_through_storage() {
+        let storage = Arc::new(InMemoryTurnStorage::new());
+        let (manager, agent_id) = manager(10_000, Arc::new(ScriptedClient::repeating("Hello!")));
+        let manager = manager.with_storage(storage.clone());
+        let mut session = manager.create_session(config(agent_id)).await.unwrap();
+        manager.process_turn(&mut session, "Hi").await.unwrap();
+
+        let restored = manager.restore_session(session.id).await.unwrap();
+        assert_eq!(restored.turns.len(), 1);
+        assert_eq!(restored.turns[0].output.content, "Hello!");
+        assert_eq!(storage.load_turns(session.id).await.unwrap().len(), 1);
+
+        let missing = manager.restore_session(SessionId::new()).await;
+        assert!(matches!(missing, Err(Error::SessionNotFound(_))));
+    }
 }
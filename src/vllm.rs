@@ -28,12 +28,14 @@
 //!        .build()?;
 //!    ```
 
+use crate::embeddings::{self, EmbeddingClient, EmbeddingRequest};
 use crate::error::{Error, Result};
 use crate::llm_client::LlmClient;
-use crate::openrouter::{CompletionRequest, CompletionResponse, CompletionStream};
+use crate::openrouter::{CompletionRequest, CompletionResponse, CompletionStream, StreamOptions};
 use async_trait::async_trait;
 use reqwest::Client;
 use serde::{Deserialize, Serialize};
+use std::sync::OnceLock;
 use std::time::Duration;
 
 /// vLLM client configuration
@@ -45,6 +47,10 @@ pub struct VllmConfig {
     pub timeout: Duration,
     /// Optional API key (for secured vLLM deployments)
     pub api_key: Option<String>,
+    /// Model for embedding requests; the served model when unset
+    pub embedding_model: Option<String>,
+    /// Embedding length to request, for models that support shortening
+    pub embedding_dimensions: Option<usize>,
 }
 
 impl VllmConfig {
@@ -54,6 +60,8 @@ impl VllmConfig {
             base_url: base_url.into(),
             timeout: Duration::from_secs(300), // 5 minutes for long reasoning
             api_key: None,
+            embedding_model: None,
+            embedding_dimensions: None,
         }
     }
 
@@ -62,11 +70,14 @@ impl VllmConfig {
         let base_url = std::env::var("VLLM_BASE_URL")
             .unwrap_or_else(|_| "http://localhost:8000".to_string());
         let api_key = std::env::var("VLLM_API_KEY").ok();
+        let embedding_model = std::env::var("VLLM_EMBEDDING_MODEL").ok();
 
         Ok(Self {
             base_url,
             timeout: Duration::from_secs(300),
             api_key,
+            embedding_model,
+            embedding_dimensions: None,
         })
     }
 
@@ -81,6 +92,18 @@ impl VllmConfig {
         self.api_key = Some(api_key.into());
         self
     }
+
+    /// Set the embedding model
+    pub fn with_embedding_model(mut self, model: impl Into<String>) -> Self {
+        self.embedding_model = Some(model.into());
+        self
+    }
+
+    /// Set the embedding length to request
+    pub fn with_embedding_dimensions(mut self, dimensions: usize) -> Self {
+        self.embedding_dimensions = Some(dimensions);
+        self
+    }
 }
 
 /// vLLM client for local model inference
@@ -89,6 +112,8 @@ pub struct VllmClient {
     client: Client,
     /// Configuration
     config: VllmConfig,
+    /// Embedding length reported by the server
+    embedding_dimensions: OnceLock<usize>,
 }
 
 impl VllmClient {
@@ -104,7 +129,11 @@ impl VllmClient {
             .timeout(config.timeout)
             .build()?;
 
-        Ok(Self { client, config })
+        Ok(Self {
+            client,
+            config,
+            embedding_dimensions: OnceLock::new(),
+        })
     }
 
     /// Get the configuration
@@ -171,10 +200,10 @@ impl LlmClient for VllmClient {
                 .text()
                 .await
                 .unwrap_or_else(|_| "Unknown error".to_string());
-            return Err(Error::openrouter(format!(
-                "vLLM request failed with status {}: {}",
-                status, error_text
-            )));
+            return Err(Error::api(
+                status.as_u16(),
+                format!("vLLM request failed: {}", error_text),
+            ));
         }
 
         let completion: CompletionResponse = response.json().await?;
@@ -186,6 +215,7 @@ impl LlmClient for VllmClient {
 
         let mut request_with_stream = request;
         request_with_stream.stream = true;
+        request_with_stream.stream_options = Some(StreamOptions { include_usage: true });
 
         let mut http_request = self.client.post(&url).json(&request_with_stream);
 
@@ -201,10 +231,10 @@ impl LlmClient for VllmClient {
                 .text()
                 .await
                 .unwrap_or_else(|_| "Unknown error".to_string());
-            return Err(Error::openrouter(format!(
-                "vLLM stream request failed with status {}: {}",
-                status, error_text
-            )));
+            return Err(Error::api(
+                status.as_u16(),
+                format!("vLLM stream request failed: {}", error_text),
+            ));
         }
 
         Ok(CompletionStream::new(response.bytes_stream()))
@@ -219,6 +249,40 @@ impl LlmClient for VllmClient {
     }
 }
 
+#[async_trait]
+impl EmbeddingClient for VllmClient {
+    async fn embed_batch(&self, texts: &[String]) -> Result<Vec<Vec<f32>>> {
+        let url = format!("{}/v1/embeddings", self.config.base_url);
+        let body = EmbeddingRequest {
+            model: self.model_id(),
+            input: texts,
+            dimensions: self.config.embedding_dimensions,
+            encoding_format: "float",
+        };
+
+        let mut http_request = self.client.post(&url).json(&body);
+        if let Some(ref api_key) = self.config.api_key {
+            http_request = http_request.header("Authorization", format!("Bearer {}", api_key));
+        }
+
+        let vectors = embeddings::send(http_request, "vLLM", texts.len()).await?;
+        if let Some(vector) = vectors.first() {
+            let _ = self.embedding_dimensions.set(vector.len());
+        }
+        Ok(vectors)
+    }
+
+    fn dimensions(&self) -> Option<usize> {
+        self.config
+            .embedding_dimensions
+            .or_else(|| self.embedding_dimensions.get().copied())
+    }
+
+    fn model_id(&self) -> &str {
+        self.config.embedding_model.as_deref().unwrap_or_default()
+    }
+}
+
 /// vLLM health check response
 #[derive(Debug, Clone, Serialize, Deserialize)]
 pub struct VllmHealth {